@@ -0,0 +1,154 @@
+// AI 响应缓存模块 - 按模型和提示词的哈希缓存模型回复
+// 调整提示词时反复分析同一页不需要重复调用接口
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 默认缓存有效期（小时）
+pub const DEFAULT_CACHE_TTL_HOURS: u64 = 168;
+
+// 缓存目录
+static CACHE_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 是否启用缓存
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// 缓存有效期（秒）
+static CACHE_TTL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_CACHE_TTL_HOURS * 3600);
+
+/// 缓存条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    created_at: u64, // Unix 时间戳（秒）
+    model: String,
+    response: String,
+    #[serde(default)]
+    reasoning: Option<String>,
+}
+
+/// 缓存统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    pub enabled: bool,
+    pub entries: u64,
+    pub total_size: u64,
+}
+
+/// 初始化缓存目录（在应用启动时调用）
+pub fn init_cache_dir(app_dir: &Path) {
+    *CACHE_DIR.write() = Some(app_dir.join("ai_cache"));
+}
+
+/// 设置是否启用缓存和有效期，ttl_hours 为 0 表示永不过期
+pub fn set_cache_settings(enabled: bool, ttl_hours: u64) {
+    CACHE_ENABLED.store(enabled, Ordering::SeqCst);
+    CACHE_TTL_SECS.store(ttl_hours.saturating_mul(3600), Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    CACHE_ENABLED.load(Ordering::SeqCst)
+}
+
+/// 计算缓存键：接口地址和完整请求体的哈希
+pub fn cache_key(api_url: &str, request_body: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(api_url.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(request_body.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 读取缓存的回复和推理内容，过期或不存在时返回 None
+pub fn get(key: &str) -> Option<(String, Option<String>)> {
+    if !is_enabled() {
+        return None;
+    }
+    let path = entry_path(key)?;
+    let content = fs::read_to_string(&path).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+    let ttl = CACHE_TTL_SECS.load(Ordering::SeqCst);
+    if ttl > 0 && now_secs().saturating_sub(entry.created_at) > ttl {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    Some((entry.response, entry.reasoning))
+}
+
+/// 写入缓存
+pub fn put(key: &str, model: &str, response: &str, reasoning: Option<&str>) {
+    use crate::logger;
+
+    if !is_enabled() {
+        return;
+    }
+    let path = match entry_path(key) {
+        Some(path) => path,
+        None => return,
+    };
+    let entry = CacheEntry {
+        created_at: now_secs(),
+        model: model.to_string(),
+        response: response.to_string(),
+        reasoning: reasoning.map(|s| s.to_string()),
+    };
+
+    let result = path
+        .parent()
+        .map(fs::create_dir_all)
+        .transpose()
+        .map_err(anyhow::Error::from)
+        .and_then(|_| Ok(serde_json::to_string(&entry)?))
+        .and_then(|content| Ok(fs::write(&path, content)?));
+    if let Err(e) = result {
+        logger::warn("ai", &format!("写入响应缓存失败: {}", e));
+    }
+}
+
+/// 统计缓存条目数和占用空间
+pub fn stats() -> CacheStats {
+    let mut stats = CacheStats {
+        enabled: is_enabled(),
+        ..Default::default()
+    };
+    if let Some(dir) = CACHE_DIR.read().as_ref() {
+        for entry in walkdir::WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+            if entry.file_type().is_file() {
+                stats.entries += 1;
+                stats.total_size += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+    }
+    stats
+}
+
+/// 清空缓存，返回删除的条目数
+pub fn clear() -> anyhow::Result<u64> {
+    let entries = stats().entries;
+    if let Some(dir) = CACHE_DIR.read().as_ref() {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+    Ok(entries)
+}
+
+/// 缓存文件路径，按哈希前两位分目录
+fn entry_path(key: &str) -> Option<PathBuf> {
+    let dir = CACHE_DIR.read().clone()?;
+    let prefix = key.get(..2).unwrap_or("00");
+    Some(dir.join(prefix).join(format!("{}.json", key)))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -2,15 +2,261 @@
 
 #![allow(dead_code)]
 
-use anyhow::{anyhow, Result};
-use reqwest::Client;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tiktoken_rs::CoreBPE;
+use tokio::sync::{Notify, Semaphore};
+
+/// 默认最大重试次数
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// 默认同时进行的 AI 请求数
+pub const DEFAULT_MAX_PARALLEL_REQUESTS: usize = 2;
+
+/// 连续失败多少次后切换到备用模型
+pub const FALLBACK_AFTER_FAILURES: u32 = 2;
+
+/// 同时进行的 AI 请求数上限
+pub const MAX_PARALLEL_REQUESTS: usize = 16;
+
+// 各服务商下一次允许发送请求的时间，按接口主机名区分
+static RATE_LIMITS: Lazy<parking_lot::Mutex<HashMap<String, Instant>>> =
+    Lazy::new(|| parking_lot::Mutex::new(HashMap::new()));
+
+/// 为模型回复预留的 token 数
+pub const RESPONSE_RESERVE_TOKENS: usize = 4096;
+
+/// 系统提示词和消息格式占用的 token 数（估计值）
+pub const PROMPT_OVERHEAD_TOKENS: usize = 1024;
+
+/// 每条消息的格式开销（角色标记等）
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// 每张图片的估算 token 数（按高清模式计）
+pub const IMAGE_TOKENS: usize = 765;
+
+/// 未知模型的默认上下文长度
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
+/// cl100k_base 分词器，与 OpenAI 兼容接口的计数方式基本一致
+/// 其他模型的分词结果会有差异，但比按字节估算准确得多
+static TOKENIZER: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::cl100k_base().ok());
+
+/// AI 接口错误类型
+#[derive(Debug, Clone, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "kebab-case")]
+pub enum AIError {
+    #[error("API 认证失败，请检查 API Key: {0}")]
+    Auth(String),
+    #[error("API 额度不足: {0}")]
+    Quota(String),
+    #[error("请求过于频繁: {0}")]
+    RateLimited(String),
+    #[error("输入内容超出模型上下文长度: {0}")]
+    ContextTooLong(String),
+    #[error("网络错误: {0}")]
+    Network(String),
+    #[error("API 服务异常: {0}")]
+    Server(String),
+    #[error("API 请求失败: {0}")]
+    Request(String),
+    #[error("API 返回空响应")]
+    EmptyResponse,
+    #[error("请求已取消")]
+    Cancelled,
+}
+
+impl AIError {
+    /// 是否值得重试（限流、网络和服务端错误通常是暂时的）
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AIError::RateLimited(_) | AIError::Network(_) | AIError::Server(_))
+    }
+
+    /// 是否需要中止整个分析（继续请求也不会成功）
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, AIError::Auth(_) | AIError::Quota(_))
+    }
+
+    /// 根据 HTTP 状态码和响应内容归类错误
+    fn from_response(status: StatusCode, body: &str) -> Self {
+        let lower = body.to_lowercase();
+        let message = format!("{} {}", status.as_u16(), body);
+        match status.as_u16() {
+            401 | 403 => AIError::Auth(message),
+            402 => AIError::Quota(message),
+            429 if lower.contains("quota") || lower.contains("insufficient") || lower.contains("billing") => {
+                AIError::Quota(message)
+            }
+            429 => AIError::RateLimited(message),
+            400 | 413 if lower.contains("context") || lower.contains("too long") || (lower.contains("maximum") && lower.contains("token")) => {
+                AIError::ContextTooLong(message)
+            }
+            500..=599 => AIError::Server(message),
+            _ => AIError::Request(message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AIError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() || e.is_connect() || e.is_request() {
+            AIError::Network(e.to_string())
+        } else {
+            AIError::Request(e.to_string())
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
-    pub content: String,
+    pub content: MessageContent,
+    // Ollama 原生接口的图片（base64，不带 data URL 前缀）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<String>>,
+}
+
+/// 消息内容：纯文本，或 OpenAI 格式的多段内容（文本 + 图片）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String, // http(s) 地址或 data:image/png;base64,...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl MessageContent {
+    /// 拼接所有文本部分
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// 所有图片地址
+    pub fn image_urls(&self) -> Vec<&str> {
+        match self {
+            MessageContent::Text(_) => Vec::new(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::ImageUrl { image_url } => Some(image_url.url.as_str()),
+                    ContentPart::Text { .. } => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: MessageContent) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+            images: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", MessageContent::Text(content.into()))
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", MessageContent::Text(content.into()))
+    }
+
+    /// 带图片的用户消息，无法读取的图片会被跳过
+    pub fn user_with_images(content: impl Into<String>, images: &[PathBuf]) -> Self {
+        use crate::logger;
+
+        let content = content.into();
+        if images.is_empty() {
+            return Self::user(content);
+        }
+
+        let mut parts = vec![ContentPart::Text { text: content }];
+        for path in images {
+            match image_data_url(path) {
+                Ok(url) => parts.push(ContentPart::ImageUrl {
+                    image_url: ImageUrl { url, detail: None },
+                }),
+                Err(e) => logger::warn("ai", &format!("读取图片失败 {}: {}", path.display(), e)),
+            }
+        }
+        Self::new("user", MessageContent::Parts(parts))
+    }
+
+    /// 去掉图片，只保留文本
+    fn without_images(self) -> Self {
+        match self.content {
+            MessageContent::Text(_) => self,
+            MessageContent::Parts(_) => Self::new(&self.role, MessageContent::Text(self.content.text())),
+        }
+    }
+
+    /// 转换为 Ollama 原生格式：文本放在 content，图片放在 images
+    fn into_ollama(self) -> Self {
+        let images: Vec<String> = self
+            .content
+            .image_urls()
+            .iter()
+            .filter_map(|url| url.split_once(";base64,").map(|(_, data)| data.to_string()))
+            .collect();
+        Self {
+            role: self.role,
+            content: MessageContent::Text(self.content.text()),
+            images: if images.is_empty() { None } else { Some(images) },
+        }
+    }
+}
+
+/// 将图片文件编码为 data URL
+pub fn image_data_url(path: &Path) -> Result<String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let mime = match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    };
+    let data = std::fs::read(path)?;
+    Ok(format!("data:{};base64,{}", mime, general_purpose::STANDARD.encode(data)))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +269,18 @@ pub struct ChatRequest {
     pub max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    // OpenAI 兼容接口的 JSON 模式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    // Ollama 原生接口的 JSON 模式（"json"）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseFormat {
+    #[serde(rename = "type")]
+    pub format_type: String, // "json_object"
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,18 +299,142 @@ pub struct ChatResponse {
     pub choices: Vec<ChatChoice>,
 }
 
+/// 取消令牌，用于中止正在进行的 AI 请求
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<(AtomicBool, Notify)>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.0.store(true, Ordering::SeqCst);
+        self.inner.1.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.0.load(Ordering::SeqCst)
+    }
+
+    /// 等待令牌被取消
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.1.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// 一次请求的结果，附带追踪所需的状态码、原始响应和用量
+struct Exchange {
+    result: std::result::Result<ChatReply, AIError>,
+    retry_after: Option<Duration>,
+    status: Option<u16>,
+    response_text: String,
+    usage: Option<crate::ai_usage::TokenUsage>,
+    streamed: bool, // 是否已经输出了部分流式内容
+}
+
+impl Default for Exchange {
+    fn default() -> Self {
+        Self {
+            result: Err(AIError::EmptyResponse),
+            retry_after: None,
+            status: None,
+            response_text: String::new(),
+            usage: None,
+            streamed: false,
+        }
+    }
+}
+
+impl Exchange {
+    fn fail(mut self, error: AIError) -> Self {
+        self.result = Err(error);
+        self
+    }
+}
+
+/// 流式响应中已收到的内容
+#[derive(Default)]
+struct StreamReply {
+    content: String,
+    reasoning: String,
+    usage_body: serde_json::Value, // 带有 token 用量的那一条数据
+}
+
+impl StreamReply {
+    /// 处理一行数据，收到结束标记时返回 true
+    fn push_line(&mut self, line: &[u8], on_delta: &mut (dyn FnMut(&str) + Send)) -> bool {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        let data = line.strip_prefix("data:").map(|s| s.trim()).unwrap_or(line);
+        if data.is_empty() {
+            return false;
+        }
+        if data == "[DONE]" {
+            return true;
+        }
+        let value: serde_json::Value = match serde_json::from_str(data) {
+            Ok(value) => value,
+            Err(_) => return false,
+        };
+
+        let text_at = |pointers: &[&str]| {
+            pointers
+                .iter()
+                .find_map(|p| value.pointer(p).and_then(|v| v.as_str()))
+                .unwrap_or("")
+                .to_string()
+        };
+        let delta = text_at(&["/choices/0/delta/content", "/message/content"]);
+        let reasoning_delta = text_at(&[
+            "/choices/0/delta/reasoning_content",
+            "/choices/0/delta/reasoning",
+            "/message/thinking",
+        ]);
+        if !delta.is_empty() {
+            self.content.push_str(&delta);
+            on_delta(&delta);
+        }
+        self.reasoning.push_str(&reasoning_delta);
+
+        if crate::ai_usage::TokenUsage::from_response(&value).is_some() {
+            self.usage_body = value.clone();
+        }
+        value.get("done").and_then(|v| v.as_bool()).unwrap_or(false)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AIService {
     client: Client,
     api_url: String,
     api_key: String,
     model_name: String,
+    max_retries: u32,
+    json_mode: bool,
+    usage_scope: Option<crate::ai_usage::UsageScope>,
+    requests_per_minute: u32,
+    vision: bool,
+    request_timeout: Option<Duration>, // 覆盖客户端的默认超时
+    cancel_token: Option<CancelToken>,
+    headers: HeaderMap, // 模型配置中的附加请求头
+    language: PromptLanguage,
 }
 
 impl AIService {
     pub fn new(api_url: &str, api_key: &str, model_name: &str) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(120))
+        // 本地模型推理较慢，使用更长的超时时间
+        let timeout = if is_local_endpoint(api_url) { 600 } else { 120 };
+        let client = crate::http_client::builder()
+            .timeout(Duration::from_secs(timeout))
             .build()
             .unwrap();
 
@@ -61,226 +443,2049 @@ impl AIService {
             api_url: api_url.to_string(),
             api_key: api_key.to_string(),
             model_name: model_name.to_string(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            json_mode: supports_json_mode(api_url),
+            usage_scope: None,
+            requests_per_minute: 0,
+            vision: supports_vision(model_name),
+            request_timeout: None,
+            cancel_token: None,
+            headers: HeaderMap::new(),
+            language: PromptLanguage::Auto,
         }
     }
 
-    /// 发送聊天请求
-    pub async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String> {
-        let request = ChatRequest {
-            model: self.model_name.clone(),
-            messages,
-            temperature: Some(0.7),
-            max_tokens: Some(4096),
-            stream: Some(false),
-        };
+    /// 设置提示词语言（默认根据页面内容自动选择）
+    pub fn with_language(mut self, language: PromptLanguage) -> Self {
+        self.language = language;
+        self
+    }
 
-        let response = self
-            .client
-            .post(&self.api_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+    /// 设置附加请求头，设置了 Authorization 时不再发送默认的 Bearer 认证
+    pub fn with_headers(mut self, headers: &HashMap<String, String>) -> Result<Self> {
+        self.headers = parse_headers(headers)?;
+        Ok(self)
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("API 请求失败: {}", error_text));
-        }
+    /// 添加认证和附加请求头
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        apply_headers(builder, &self.api_key, &self.headers)
+    }
 
-        let chat_response: ChatResponse = response.json().await?;
-        
-        if let Some(choice) = chat_response.choices.first() {
-            Ok(choice.message.content.clone())
-        } else {
-            Err(anyhow!("API 返回空响应"))
-        }
+    /// 设置是否请求 JSON 格式输出（默认根据接口地址自动判断）
+    pub fn with_json_mode(mut self, json_mode: bool) -> Self {
+        self.json_mode = json_mode;
+        self
     }
 
-    /// 分析文本中的例题
-    pub async fn analyze_examples(&self, text: &str) -> Result<String> {
-        let system_prompt = r#"你是一个专业的教育内容分析助手。请分析以下文本，识别出其中的例题（带有完整答案或解析的题目）。
+    /// 设置单次请求的超时时间，覆盖默认的 120 秒（本地模型 600 秒）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
 
-对于每道例题，请提取：
-1. 题目内容
-2. 答案或解析
-3. 涉及的知识点
-4. 所属章节（如果能识别）
+    /// 设置取消令牌，令牌取消后正在进行的请求和重试等待会立即结束
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
 
-请以 JSON 格式返回结果：
-{
-  "examples": [
-    {
-      "question": "题目内容",
-      "answer": "答案内容",
-      "analysis": "详细解析",
-      "knowledge_points": ["知识点1", "知识点2"],
-      "chapter": "章节名称",
-      "section": "小节名称"
+    /// 设置模型是否支持图片输入（默认根据模型名称判断），不支持时请求中的图片会被去掉
+    pub fn with_vision(mut self, vision: bool) -> Self {
+        self.vision = vision;
+        self
     }
-  ]
-}"#;
 
-        let messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: format!("请分析以下文本中的例题：\n\n{}", text),
-            },
-        ];
+    /// 模型是否支持图片输入
+    pub fn supports_vision(&self) -> bool {
+        self.vision
+    }
 
-        self.chat(messages).await
+    /// 设置每分钟最多请求数，同一服务商的所有请求共享该限制，0 表示不限制
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.requests_per_minute = requests_per_minute;
+        self
     }
 
-    /// 分析文本中的课后习题
-    pub async fn analyze_exercises(&self, text: &str, context: &str) -> Result<String> {
-        let system_prompt = r#"你是一个专业的教育内容分析助手。请分析以下文本，识别出其中的课后习题（没有答案的练习题）。
+    /// 设置用量统计归属的文件和分析批次
+    pub fn with_usage_scope(mut self, file_id: &str, run_id: &str) -> Self {
+        self.usage_scope = Some(crate::ai_usage::UsageScope {
+            file_id: file_id.to_string(),
+            run_id: run_id.to_string(),
+        });
+        self
+    }
 
-参考以下知识点和例题上下文来解答这些题目。
+    /// 模型名称
+    pub fn model_name(&self) -> &str {
+        &self.model_name
+    }
 
-对于每道习题，请提取并生成：
-1. 题目内容
-2. 详细答案（根据知识点和例题推理）
-3. 解题思路分析
-4. 涉及的知识点
-5. 所属章节（如果能识别）
+    /// 当前模型的上下文长度
+    pub fn context_window(&self) -> usize {
+        context_window(&self.model_name)
+    }
 
-请以 JSON 格式返回结果：
-{
-  "exercises": [
-    {
-      "question": "题目内容",
-      "answer": "生成的答案",
-      "analysis": "详细解析",
-      "knowledge_points": ["知识点1", "知识点2"],
-      "chapter": "章节名称",
-      "section": "小节名称"
+    /// 可用于页面内容和参考上下文的 token 数
+    pub fn input_budget(&self) -> usize {
+        self.context_window()
+            .saturating_sub(RESPONSE_RESERVE_TOKENS + PROMPT_OVERHEAD_TOKENS)
+            .max(1024)
     }
-  ]
-}"#;
 
-        let messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: format!(
-                    "参考上下文：\n{}\n\n请分析以下文本中的课后习题并给出答案：\n\n{}",
-                    context, text
-                ),
-            },
-        ];
+    /// 提示词占用 prompt_tokens 后剩余可用于参考上下文的 token 数
+    pub fn context_budget(&self, prompt_tokens: usize) -> usize {
+        self.context_window()
+            .saturating_sub(RESPONSE_RESERVE_TOKENS + prompt_tokens)
+    }
 
-        self.chat(messages).await
+    /// 设置失败后的最大重试次数
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
     }
 
-    /// 生成题目答案
-    pub async fn generate_answer(&self, question: &str, context: &str) -> Result<String> {
-        let system_prompt = r#"你是一个专业的教育内容分析助手。请根据提供的知识点和上下文，为给定的题目生成详细的答案和解析。
+    /// 发送聊天请求
+    pub async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String> {
+        Ok(self.chat_reply(messages).await?.content)
+    }
 
-请以 JSON 格式返回结果：
-{
-  "answer": "简洁的答案",
-  "analysis": "详细的解题步骤和思路分析",
-  "knowledge_points": ["涉及的知识点"]
-}"#;
+    /// 发送聊天请求，返回回复和推理内容
+    pub async fn chat_reply(&self, messages: Vec<ChatMessage>) -> Result<ChatReply> {
+        let request = self.build_request(messages, false);
+        Ok(self.send_with_retry(&request).await?)
+    }
 
-        let messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: format!(
-                    "参考知识点和上下文：\n{}\n\n请为以下题目生成答案：\n\n{}",
-                    context, question
-                ),
-            },
-        ];
+    /// 以流式方式发送聊天请求，每收到一段回复内容调用一次 on_delta，结束后返回完整回复
+    /// 兼容 OpenAI 的 SSE 格式（data: {...}）和 Ollama 原生接口的逐行 JSON 格式
+    pub async fn chat_stream<F>(&self, messages: Vec<ChatMessage>, mut on_delta: F) -> Result<ChatReply>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let mut request = self.build_request(messages, false);
+        request.stream = Some(true);
+        Ok(self.send_with_backoff(&request, Some(&mut on_delta)).await?)
+    }
 
-        self.chat(messages).await
+    /// 发送要求 JSON 输出的聊天请求
+    pub async fn chat_json(&self, messages: Vec<ChatMessage>) -> Result<String> {
+        Ok(self.chat_json_reply(messages).await?.content)
     }
 
-    /// 提取章节结构
-    pub async fn extract_structure(&self, text: &str) -> Result<String> {
-        let system_prompt = r#"你是一个专业的教育内容分析助手。请分析以下文本，识别出章节结构和主要知识点。
+    /// 发送要求 JSON 输出的聊天请求，返回回复和推理内容
+    /// 接口支持时开启 JSON 模式；接口拒绝该参数时自动回退为普通请求
+    pub async fn chat_json_reply(&self, messages: Vec<ChatMessage>) -> Result<ChatReply> {
+        use crate::logger;
 
-请以 JSON 格式返回结果：
-{
-  "chapters": [
-    {
-      "name": "章节名称",
-      "sections": [
-        {
-          "name": "小节名称",
-          "knowledge_points": ["知识点1", "知识点2"]
+        if !self.json_mode {
+            return self.chat_reply(messages).await;
         }
-      ]
-    }
-  ]
-}"#;
-
-        let messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: format!("请分析以下文本的章节结构：\n\n{}", text),
-            },
-        ];
 
-        self.chat(messages).await
+        let request = self.build_request(messages.clone(), true);
+        match self.send_with_retry(&request).await {
+            Err(AIError::Request(msg)) => {
+                logger::warn("ai", &format!("接口不支持 JSON 模式，改用普通请求: {}", msg));
+                self.chat_reply(messages).await
+            }
+            result => Ok(result?),
+        }
     }
-}
 
-/// 测试模型连接
-impl AIService {
-    pub async fn test_connection(&self) -> Result<String> {
-        let messages = vec![
-            ChatMessage {
-                role: "user".to_string(),
-                content: "Hello, this is a test message. Please respond with 'OK'.".to_string(),
-            },
-        ];
+    /// 构造要求 JSON 输出的请求体（用于批量任务）
+    pub fn json_request(&self, messages: Vec<ChatMessage>) -> ChatRequest {
+        self.build_request(messages, self.json_mode)
+    }
 
-        let request = ChatRequest {
+    /// 构造聊天请求
+    fn build_request(&self, messages: Vec<ChatMessage>, json: bool) -> ChatRequest {
+        let ollama_native = is_ollama_native(&self.api_url);
+        let messages = messages
+            .into_iter()
+            .map(|m| {
+                if !self.vision {
+                    m.without_images()
+                } else if ollama_native {
+                    m.into_ollama()
+                } else {
+                    m
+                }
+            })
+            .collect();
+        ChatRequest {
             model: self.model_name.clone(),
             messages,
-            temperature: Some(0.1),
-            max_tokens: Some(50),
+            temperature: Some(0.7),
+            max_tokens: Some(4096),
             stream: Some(false),
+            response_format: if json && !ollama_native {
+                Some(ResponseFormat {
+                    format_type: "json_object".to_string(),
+                })
+            } else {
+                None
+            },
+            format: if json && ollama_native {
+                Some("json".to_string())
+            } else {
+                None
+            },
+        }
+    }
+
+    /// 发送请求，启用响应缓存时相同的请求直接返回缓存的回复
+    async fn send_with_retry(&self, request: &ChatRequest) -> std::result::Result<ChatReply, AIError> {
+        use crate::{ai_cache, logger};
+
+        let cache_key = if ai_cache::is_enabled() {
+            serde_json::to_string(request)
+                .ok()
+                .map(|body| ai_cache::cache_key(&self.api_url, &body))
+        } else {
+            None
         };
+        if let Some((content, reasoning)) = cache_key.as_deref().and_then(ai_cache::get) {
+            logger::debug("ai", &format!("使用缓存的 {} 响应", self.model_name));
+            return Ok(ChatReply { content, reasoning });
+        }
 
-        let response = self
-            .client
-            .post(&self.api_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+        let result = self.send_with_backoff(request, None).await;
+        if let (Some(key), Ok(reply)) = (&cache_key, &result) {
+            ai_cache::put(key, &self.model_name, &reply.content, reply.reasoning.as_deref());
+        }
+        result
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("API 请求失败: {}", error_text));
+    /// 发送请求，遇到限流、网络或服务端错误时按指数退避重试
+    /// 服务端返回 Retry-After 时按其指定的时间等待
+    /// on_delta 不为空时以流式方式读取回复；已经输出部分内容后出错时不再重试，避免调用方收到重复的内容
+    async fn send_with_backoff(
+        &self,
+        request: &ChatRequest,
+        mut on_delta: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> std::result::Result<ChatReply, AIError> {
+        use crate::logger;
+
+        let mut attempt = 0;
+        loop {
+            let (result, retry_after, streamed) = self.send_once(request, on_delta.as_deref_mut()).await;
+            let error = match result {
+                Ok(reply) => return Ok(reply),
+                Err(e) => e,
+            };
+
+            if streamed || !error.is_retryable() || attempt >= self.max_retries {
+                return Err(error);
+            }
+
+            let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+            attempt += 1;
+            logger::warn("ai", &format!(
+                "{}，{:.1} 秒后第 {}/{} 次重试",
+                error,
+                delay.as_secs_f32(),
+                attempt,
+                self.max_retries
+            ));
+            if !self.sleep_unless_cancelled(delay).await {
+                return Err(AIError::Cancelled);
+            }
         }
+    }
 
-        let chat_response: ChatResponse = response.json().await?;
-        
-        if let Some(choice) = chat_response.choices.first() {
-            Ok(choice.message.content.clone())
-        } else {
-            Err(anyhow!("API 返回空响应"))
+    /// 等待指定时间，期间被取消时返回 false
+    async fn sleep_unless_cancelled(&self, delay: Duration) -> bool {
+        match &self.cancel_token {
+            Some(token) => tokio::select! {
+                _ = tokio::time::sleep(delay) => true,
+                _ = token.cancelled() => false,
+            },
+            None => {
+                tokio::time::sleep(delay).await;
+                true
+            }
         }
     }
+
+    /// 发送一次请求，同时返回服务端要求的等待时间（如果有）和是否已经输出了流式内容
+    async fn send_once(
+        &self,
+        request: &ChatRequest,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send + '_)>,
+    ) -> (std::result::Result<ChatReply, AIError>, Option<Duration>, bool) {
+        use crate::ai_trace;
+
+        let exchange_future = async {
+            wait_for_rate_limit(&self.api_url, self.requests_per_minute).await;
+            self.exchange(request, on_delta).await
+        };
+        let started = Instant::now();
+        let exchange = match &self.cancel_token {
+            Some(token) => tokio::select! {
+                exchange = exchange_future => exchange,
+                _ = token.cancelled() => Exchange::default().fail(AIError::Cancelled),
+            },
+            None => exchange_future.await,
+        };
+
+        if ai_trace::is_enabled() {
+            ai_trace::record(
+                &self.model_name,
+                &self.api_url,
+                &serde_json::to_string(request).unwrap_or_default(),
+                exchange.status,
+                started.elapsed(),
+                exchange.usage,
+                &exchange.response_text,
+                exchange.result.as_ref().err().map(|e| e.to_string()),
+            );
+        }
+
+        (exchange.result, exchange.retry_after, exchange.streamed)
+    }
+
+    /// 发送请求并读取响应
+    async fn exchange(
+        &self,
+        request: &ChatRequest,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send + '_)>,
+    ) -> Exchange {
+        let mut exchange = Exchange::default();
+
+        let mut builder = self
+            .client
+            .post(&self.api_url)
+            .header("Content-Type", "application/json")
+            .json(request);
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        let response = match self.authorize(builder).send().await {
+            Ok(response) => response,
+            Err(e) => return exchange.fail(e.into()),
+        };
+
+        let status = response.status();
+        exchange.status = Some(status.as_u16());
+        if !status.is_success() {
+            exchange.retry_after = parse_retry_after(response.headers());
+            exchange.response_text = response.text().await.unwrap_or_default();
+            let error = AIError::from_response(status, &exchange.response_text);
+            return exchange.fail(error);
+        }
+        if let Some(on_delta) = on_delta {
+            return self.read_stream(request, response, exchange, on_delta).await;
+        }
+
+        exchange.response_text = match response.text().await {
+            Ok(text) => text,
+            Err(e) => return exchange.fail(e.into()),
+        };
+        let body: serde_json::Value = match serde_json::from_str(&exchange.response_text) {
+            Ok(body) => body,
+            Err(e) => return exchange.fail(AIError::Request(format!("响应不是有效的 JSON: {}", e))),
+        };
+
+        let reply = match extract_reply(&body) {
+            Some(reply) => reply,
+            None => return exchange.fail(AIError::EmptyResponse),
+        };
+
+        exchange.usage = Some(self.record_usage(request, &body, &reply.content));
+        exchange.result = Ok(reply);
+        exchange
+    }
+
+    /// 逐行读取流式响应，每收到一段回复内容调用一次 on_delta
+    async fn read_stream(
+        &self,
+        request: &ChatRequest,
+        mut response: reqwest::Response,
+        mut exchange: Exchange,
+        on_delta: &mut (dyn FnMut(&str) + Send),
+    ) -> Exchange {
+        let mut stream = StreamReply::default();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut done = false;
+
+        while !done {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => {
+                    exchange.streamed = !stream.content.is_empty();
+                    exchange.response_text = stream.content;
+                    return exchange.fail(e.into());
+                }
+            };
+            buffer.extend_from_slice(&chunk);
+
+            // 按行处理，未结束的行留到下一次
+            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                if stream.push_line(&line, on_delta) {
+                    done = true;
+                    break;
+                }
+            }
+        }
+        // 最后一行没有换行结尾时仍留在缓冲区中
+        if !done && !buffer.is_empty() {
+            stream.push_line(&buffer, on_delta);
+        }
+
+        exchange.streamed = !stream.content.is_empty();
+        exchange.usage = Some(self.record_usage(request, &stream.usage_body, &stream.content));
+        // 正文中的 <think> 标签同样作为推理内容
+        let body = serde_json::json!({
+            "message": { "content": stream.content, "thinking": stream.reasoning },
+        });
+        exchange.result = Ok(extract_reply(&body).unwrap_or_default());
+        exchange.response_text = stream.content;
+        exchange
+    }
+
+    /// 记录本次请求的 token 用量，接口未返回用量时按分词器估算
+    fn record_usage(
+        &self,
+        request: &ChatRequest,
+        body: &serde_json::Value,
+        content: &str,
+    ) -> crate::ai_usage::TokenUsage {
+        use crate::ai_usage::{self, TokenUsage};
+
+        let (usage, estimated) = match TokenUsage::from_response(body) {
+            Some(usage) => (usage, false),
+            None => {
+                let prompt_tokens: usize = request.messages.iter().map(|m| count_tokens(&m.content.text())).sum();
+                let usage = TokenUsage {
+                    prompt_tokens: prompt_tokens as u64,
+                    completion_tokens: count_tokens(content) as u64,
+                };
+                (usage, true)
+            }
+        };
+        ai_usage::record(self.usage_scope.as_ref(), &self.model_name, usage, estimated);
+        usage
+    }
+
+    /// 分析文本中的例题
+    pub async fn analyze_examples(&self, text: &str) -> Result<String> {
+        Ok(self.analyze_examples_with_images(text, &[]).await?.content)
+    }
+
+    /// 分析文本中的例题，同时附带页面中的图片（几何图形、电路图等）
+    pub async fn analyze_examples_with_images(&self, text: &str, images: &[PathBuf]) -> Result<ChatReply> {
+        self.chat_json_reply(examples_messages(text, images, self.language)).await
+    }
+
+    /// 分析文本中的课后习题
+    pub async fn analyze_exercises(&self, text: &str, context: &str) -> Result<String> {
+        Ok(self.analyze_exercises_with_images(text, context, &[]).await?.content)
+    }
+
+    /// 分析文本中的课后习题，同时附带页面中的图片
+    pub async fn analyze_exercises_with_images(
+        &self,
+        text: &str,
+        context: &str,
+        images: &[PathBuf],
+    ) -> Result<ChatReply> {
+        self.chat_json_reply(exercises_messages(text, context, images, self.language)).await
+    }
+
+    /// 生成题目答案
+    pub async fn generate_answer(&self, question: &str, context: &str) -> Result<String> {
+        let messages = match self.language.resolve(question) {
+            PromptLanguage::English => vec![
+                ChatMessage::system(
+                    r#"You are a professional educational content analyst. Using the knowledge points and context provided, write a detailed answer and explanation for the given question.
+
+Return the result as JSON, written in the same language as the question:
+{
+  "answer": "concise answer",
+  "analysis": "detailed solution steps and reasoning",
+  "knowledge_points": ["knowledge points involved"]
+}"#,
+                ),
+                ChatMessage::user(format!(
+                    "Reference knowledge points and context:\n{}\n\nWrite an answer for the following question:\n\n{}",
+                    context, question
+                )),
+            ],
+            _ => vec![
+                ChatMessage::system(
+                    r#"你是一个专业的教育内容分析助手。请根据提供的知识点和上下文，为给定的题目生成详细的答案和解析。
+
+请以 JSON 格式返回结果：
+{
+  "answer": "简洁的答案",
+  "analysis": "详细的解题步骤和思路分析",
+  "knowledge_points": ["涉及的知识点"]
+}"#,
+                ),
+                ChatMessage::user(format!(
+                    "参考知识点和上下文：\n{}\n\n请为以下题目生成答案：\n\n{}",
+                    context, question
+                )),
+            ],
+        };
+
+        self.chat_json(messages).await
+    }
+
+    /// 对照参考答案为用户的作答评分
+    pub async fn grade_answer(
+        &self,
+        question: &str,
+        reference_answer: &str,
+        analysis: &str,
+        user_answer: &str,
+    ) -> Result<String> {
+        let messages = match self.language.resolve(question) {
+            PromptLanguage::English => vec![
+                ChatMessage::system(
+                    r#"You are a strict but fair teacher. Grade the student's answer against the reference answer and solution on a 100-point scale.
+Split the score into 2-5 rubric items (e.g. final result, method, key steps, presentation); the item maxima must add up to 100.
+Equivalent answers written in a different form still earn full credit.
+
+Return the result as JSON:
+{
+  "score": 85,
+  "verdict": "correct | partially_correct | incorrect",
+  "rubric": [
+    {
+      "criterion": "rubric item",
+      "points": 40,
+      "max_points": 50,
+      "comment": "why points were given or deducted"
+    }
+  ],
+  "feedback": "overall feedback and how to improve"
+}"#,
+                ),
+                ChatMessage::user(format!(
+                    "Question:\n{}\n\nReference answer:\n{}\n\nReference solution:\n{}\n\nStudent answer:\n{}",
+                    question, reference_answer, analysis, user_answer
+                )),
+            ],
+            _ => vec![
+                ChatMessage::system(
+                    r#"你是一位严格但公正的老师。请对照参考答案和解析，以百分制为学生的作答评分。
+将分数拆分为 2 到 5 个评分项（例如最终结果、解题方法、关键步骤、书写规范），各评分项满分之和为 100。
+形式不同但等价的答案同样给满分。
+
+请以 JSON 格式返回结果：
+{
+  "score": 85,
+  "verdict": "correct | partially_correct | incorrect",
+  "rubric": [
+    {
+      "criterion": "评分项",
+      "points": 40,
+      "max_points": 50,
+      "comment": "得分或扣分原因"
+    }
+  ],
+  "feedback": "总体评价和改进建议"
+}"#,
+                ),
+                ChatMessage::user(format!(
+                    "题目：\n{}\n\n参考答案：\n{}\n\n参考解析：\n{}\n\n学生作答：\n{}",
+                    question, reference_answer, analysis, user_answer
+                )),
+            ],
+        };
+
+        self.chat_json(messages).await
+    }
+
+    /// 为检索到的段落打分（0-10），用于对 RAG 检索结果重新排序
+    pub async fn rerank(&self, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+        #[derive(Deserialize)]
+        struct RerankResponse {
+            scores: Vec<f32>,
+        }
+
+        let listing: String = passages
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("[{}]\n{}\n\n", i, truncate_to_tokens(p, RERANK_PASSAGE_TOKENS)))
+            .collect();
+        let messages = vec![
+            ChatMessage::system(
+                r#"你负责为教材问答系统的参考内容排序。
+请评估每段内容对回答或解答查询的帮助程度，0 分表示无关，10 分表示直接给出答案。
+只返回 JSON，按给出的顺序为每段内容给一个分数：{"scores": [7, 0, 3]}"#,
+            ),
+            ChatMessage::user(format!("查询：\n{}\n\n参考内容：\n{}", query, listing)),
+        ];
+
+        let response = self.chat_json(messages).await?;
+        let parsed: RerankResponse = crate::json_repair::parse_relaxed(&response)?;
+        if parsed.scores.len() != passages.len() {
+            return Err(anyhow::anyhow!(
+                "重排序结果数量不匹配：{} 段内容，{} 个分数",
+                passages.len(),
+                parsed.scores.len()
+            ));
+        }
+        Ok(parsed.scores)
+    }
+
+    /// 扩展检索查询：生成同义词、公式名称和中英文术语对，提高习题措辞与讲解正文不同时的召回率
+    pub async fn expand_query(&self, query: &str) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct ExpansionResponse {
+            terms: Vec<String>,
+        }
+
+        let messages = vec![
+            ChatMessage::system(
+                r#"你负责为教材检索系统扩展查询词。
+请根据查询内容给出有助于在教材正文中找到相关讲解的检索词，包括：同义词和常见说法、涉及的公式或定理名称、对应的中文或英文术语。
+不要解释，不要重复查询原文，最多 12 个词。只返回 JSON：{"terms": ["导数", "derivative", "微商"]}"#,
+            ),
+            ChatMessage::user(format!("查询：\n{}", truncate_to_tokens(query, QUERY_EXPANSION_INPUT_TOKENS))),
+        ];
+
+        let response = self.chat_json(messages).await?;
+        let parsed: ExpansionResponse = crate::json_repair::parse_relaxed(&response)?;
+        Ok(parsed
+            .terms
+            .into_iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .take(12)
+            .collect())
+    }
+
+    /// 翻译题目：content 为待翻译内容的 JSON（公式已替换为占位符），返回同样结构的 JSON
+    pub async fn translate_question(&self, content: &str, target_language: &str) -> Result<String> {
+        let messages = vec![
+            ChatMessage::system(format!(
+                r#"你是一位专业的教材翻译，请将题目内容翻译为{}。
+要求：
+1. [[F1]]、[[F2]] 这样的占位符代表公式，必须原样保留在译文中对应的位置，不得修改、合并或删除；
+2. 保留题号、小问编号（如 (1)、①、第 2 问）和换行，保持 Markdown 格式；
+3. options 中的键是选项标签，保持不变，只翻译选项内容；
+4. 专业术语使用目标语言教材中的通用译法，内容为空的字段返回空字符串。
+
+只返回 JSON，结构与输入相同：
+{{
+  "question_text": "题干译文",
+  "options": {{"A": "选项译文"}},
+  "answer": "答案译文",
+  "analysis": "解析译文"
+}}"#,
+                target_language
+            )),
+            ChatMessage::user(format!("待翻译内容：\n{}", content)),
+        ];
+
+        self.chat_json(messages).await
+    }
+
+    /// 提取章节结构（用于分析前的目录预处理，page 为目录中印刷的页码）
+    pub async fn extract_structure(&self, text: &str) -> Result<String> {
+        let messages = match self.language.resolve(text) {
+            PromptLanguage::English => vec![
+                ChatMessage::system(
+                    r#"You are a professional educational content analyst. Analyze the following text (usually the table of contents and first pages of a textbook) and identify its chapter structure and main knowledge points.
+Copy chapter and section titles exactly as they appear. If the table of contents lists page numbers, put them in "page"; otherwise use 0.
+
+Return the result as JSON, written in the same language as the text:
+{
+  "chapters": [
+    {
+      "name": "chapter title",
+      "page": 1,
+      "sections": [
+        {
+          "name": "section title",
+          "page": 3,
+          "knowledge_points": ["knowledge point 1", "knowledge point 2"]
+        }
+      ]
+    }
+  ]
+}"#,
+                ),
+                ChatMessage::user(format!("Analyze the chapter structure of the following text:\n\n{}", text)),
+            ],
+            _ => vec![
+                ChatMessage::system(
+                    r#"你是一个专业的教育内容分析助手。请分析以下文本（通常是教材的目录和前几页），识别出章节结构和主要知识点。
+章节和小节名称请按原文照抄。目录中列出页码时填入 "page"，没有页码时填 0。
+
+请以 JSON 格式返回结果：
+{
+  "chapters": [
+    {
+      "name": "章节名称",
+      "page": 1,
+      "sections": [
+        {
+          "name": "小节名称",
+          "page": 3,
+          "knowledge_points": ["知识点1", "知识点2"]
+        }
+      ]
+    }
+  ]
+}"#,
+                ),
+                ChatMessage::user(format!("请分析以下文本的章节结构：\n\n{}", text)),
+            ],
+        };
+
+        self.chat_json(messages).await
+    }
+
+    /// 阅读页面：概括内容并判断是否包含例题、习题，用于跳过没有题目的页面
+    pub async fn read_page(&self, text: &str) -> Result<String> {
+        let messages = match self.language.resolve(text) {
+            PromptLanguage::English => vec![
+                ChatMessage::system(
+                    r#"You are a professional educational content analyst. Read the following textbook page, summarize it in one or two sentences, and decide whether it contains worked examples (questions with a solution) or exercises (practice questions without answers).
+If unsure, answer true.
+
+Return the result as JSON, written in the same language as the text:
+{
+  "summary": "page summary",
+  "has_examples": true,
+  "has_exercises": false
+}"#,
+                ),
+                ChatMessage::user(format!("Read the following page:\n\n{}", text)),
+            ],
+            _ => vec![
+                ChatMessage::system(
+                    r#"你是一个专业的教育内容分析助手。请阅读以下教材页面，用一两句话概括内容，并判断其中是否包含例题（带解答的题目）或课后习题（没有答案的练习题）。
+不确定时请判断为 true。
+
+请以 JSON 格式返回结果：
+{
+  "summary": "页面内容概括",
+  "has_examples": true,
+  "has_exercises": false
+}"#,
+                ),
+                ChatMessage::user(format!("请阅读以下页面：\n\n{}", text)),
+            ],
+        };
+
+        self.chat_json(messages).await
+    }
+}
+
+/// 测试模型连接
+impl AIService {
+    pub async fn test_connection(&self) -> Result<String> {
+        let messages = vec![
+            ChatMessage::user("Hello, this is a test message. Please respond with 'OK'."),
+        ];
+
+        let request = ChatRequest {
+            model: self.model_name.clone(),
+            messages,
+            temperature: Some(0.1),
+            max_tokens: Some(50),
+            stream: Some(false),
+            response_format: None,
+            format: None,
+        };
+
+        // 测试连接不重试，尽快给出结果
+        Ok(self.send_once(&request, None).await.0?.content)
+    }
+
+    /// 测试连接并探测模型能力：延迟、上下文长度、JSON 模式和图片输入支持
+    pub async fn probe(&self) -> Result<ModelTestReport> {
+        let started = Instant::now();
+        let reply = self.test_connection().await?;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        // 模型列表接口不是必需的，获取失败时只使用推断结果
+        let remote_models = self.fetch_model_list().await.unwrap_or_default();
+        let info = remote_models.iter().find(|m| m.id == self.model_name);
+
+        let (max_context, context_detected) = match info.and_then(|m| m.context_length) {
+            Some(length) => (length, true),
+            None => (self.context_window(), false),
+        };
+        let vision = info.and_then(|m| m.vision).unwrap_or(self.vision);
+        let json_mode = supports_json_mode(&self.api_url) && self.probe_json_mode().await;
+
+        Ok(ModelTestReport {
+            reply,
+            latency_ms,
+            model_listed: if remote_models.is_empty() {
+                None
+            } else {
+                Some(info.is_some())
+            },
+            available_models: remote_models.into_iter().map(|m| m.id).collect(),
+            max_context,
+            context_detected,
+            json_mode,
+            vision,
+        })
+    }
+
+    /// 发送一次 JSON 模式请求，接口接受该参数即视为支持
+    async fn probe_json_mode(&self) -> bool {
+        let mut request = self.build_request(
+            vec![ChatMessage::user("请只回复 JSON：{\"ok\": true}")],
+            true,
+        );
+        request.max_tokens = Some(20);
+        request.temperature = Some(0.0);
+        self.send_once(&request, None).await.0.is_ok()
+    }
+
+    /// 获取服务端的模型列表
+    /// OpenAI 兼容接口使用 /models，Ollama 使用 /api/tags
+    async fn fetch_model_list(&self) -> Result<Vec<RemoteModel>> {
+        if is_ollama_native(&self.api_url) {
+            let models = list_ollama_models(&self.api_url).await?;
+            return Ok(models
+                .into_iter()
+                .map(|m| RemoteModel {
+                    id: m.name,
+                    context_length: None,
+                    vision: None,
+                })
+                .collect());
+        }
+
+        let url = models_url(&self.api_url).ok_or_else(|| anyhow::anyhow!("无法确定模型列表地址"))?;
+        let builder = self.client.get(url).timeout(Duration::from_secs(15));
+        let response = self.authorize(builder).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("获取模型列表失败: {}", response.status()));
+        }
+        let body: serde_json::Value = response.json().await?;
+
+        let models = body
+            .get("data")
+            .and_then(|v| v.as_array())
+            .map(|list| list.iter().filter_map(parse_remote_model).collect())
+            .unwrap_or_default();
+        Ok(models)
+    }
+}
+
+/// 模型测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelTestReport {
+    pub reply: String,
+    pub latency_ms: u64,
+    pub available_models: Vec<String>, // 接口不提供模型列表时为空
+    pub model_listed: Option<bool>,    // 模型是否在列表中，没有列表时为空
+    pub max_context: usize,
+    pub context_detected: bool, // true 表示上下文长度来自接口，false 表示按模型名称推断
+    pub json_mode: bool,
+    pub vision: bool,
+}
+
+/// 模型列表中的一项
+#[derive(Debug, Clone)]
+struct RemoteModel {
+    id: String,
+    context_length: Option<usize>,
+    vision: Option<bool>,
+}
+
+/// 解析 /models 返回的模型信息
+/// 上下文长度字段因服务商而异：context_length（OpenRouter）、context_window（Groq）、max_model_len（vLLM）
+fn parse_remote_model(item: &serde_json::Value) -> Option<RemoteModel> {
+    let id = item.get("id")?.as_str()?.to_string();
+    let context_length = ["context_length", "context_window", "max_model_len", "max_context_length"]
+        .iter()
+        .find_map(|key| item.get(*key).and_then(|v| v.as_u64()))
+        .map(|v| v as usize);
+    let vision = item
+        .pointer("/architecture/input_modalities")
+        .and_then(|v| v.as_array())
+        .map(|modalities| modalities.iter().any(|m| m.as_str() == Some("image")));
+    Some(RemoteModel {
+        id,
+        context_length,
+        vision,
+    })
+}
+
+/// 由聊天接口地址推出模型列表地址
+fn models_url(api_url: &str) -> Option<String> {
+    let url = api_url.trim().trim_end_matches('/');
+    url.strip_suffix("/chat/completions")
+        .map(|base| format!("{}/models", base))
+}
+
+/// 模型回复
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatReply {
+    pub content: String,           // 已去掉 <think> 推理片段
+    pub reasoning: Option<String>, // 推理模型（DeepSeek-R1、QwQ 等）的思考过程
+}
+
+// 推理模型在正文中输出的思考过程（只匹配完整的 <think>...</think>，未闭合的标签单独处理）
+static THINK_RE: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"(?s)<think>(.*?)</think>").unwrap());
+
+/// 构造例题识别的消息
+pub fn examples_messages(text: &str, images: &[PathBuf], language: PromptLanguage) -> Vec<ChatMessage> {
+    let (system_prompt, instruction) = match language.resolve(text) {
+        PromptLanguage::English => (
+            r#"You are a professional educational content analyst. Analyze the following text and identify the worked examples in it (questions that come with a complete answer or solution).
+
+For each example, extract:
+1. The question
+2. The answer or solution
+3. The knowledge points involved
+4. The chapter it belongs to (if identifiable)
+5. The difficulty: "easy", "medium" or "hard"
+6. A confidence between 0 and 1: how sure you are that the question was extracted completely and the answer is correct (lower it for blurry text, missing figures or cut-off questions)
+
+For multiple-choice questions, put the options in "options" (not in "question") and the letters of the correct options in "correct_options"; for other questions leave both empty.
+
+Return the result as JSON, written in the same language as the text:
+{
+  "examples": [
+    {
+      "question": "question text",
+      "answer": "answer",
+      "analysis": "detailed solution",
+      "knowledge_points": ["knowledge point 1", "knowledge point 2"],
+      "chapter": "chapter title",
+      "section": "section title",
+      "difficulty": "medium",
+      "options": [{"label": "A", "text": "option text"}],
+      "correct_options": ["A"],
+      "confidence": 0.9
+    }
+  ]
+}"#,
+            "Identify the worked examples in the following text:",
+        ),
+        _ => (
+            r#"你是一个专业的教育内容分析助手。请分析以下文本，识别出其中的例题（带有完整答案或解析的题目）。
+
+对于每道例题，请提取：
+1. 题目内容
+2. 答案或解析
+3. 涉及的知识点
+4. 所属章节（如果能识别）
+5. 难度："easy"（容易）、"medium"（中等）或 "hard"（困难）
+6. 置信度（0~1）：题目是否完整提取、答案是否正确的把握程度（文字模糊、缺少插图或题目被截断时应降低）
+
+选择题的选项放在 "options" 中（不要写进 "question"），正确选项的字母放在 "correct_options" 中；其他题型这两项留空。
+
+请以 JSON 格式返回结果：
+{
+  "examples": [
+    {
+      "question": "题目内容",
+      "answer": "答案内容",
+      "analysis": "详细解析",
+      "knowledge_points": ["知识点1", "知识点2"],
+      "chapter": "章节名称",
+      "section": "小节名称",
+      "difficulty": "medium",
+      "options": [{"label": "A", "text": "选项内容"}],
+      "correct_options": ["A"],
+      "confidence": 0.9
+    }
+  ]
+}"#,
+            "请分析以下文本中的例题：",
+        ),
+    };
+
+    vec![
+        ChatMessage::system(system_prompt),
+        ChatMessage::user_with_images(format!("{}\n\n{}", instruction, text), images),
+    ]
+}
+
+/// 构造课后习题识别的消息
+pub fn exercises_messages(text: &str, context: &str, images: &[PathBuf], language: PromptLanguage) -> Vec<ChatMessage> {
+    let (system_prompt, context_label, instruction) = match language.resolve(text) {
+        PromptLanguage::English => (
+            r#"You are a professional educational content analyst. Analyze the following text and identify the exercises in it (practice questions without answers).
+
+Use the knowledge points and worked examples in the reference context to solve these questions.
+Each reference excerpt starts with a citation marker such as [file:page]; when the analysis relies on an excerpt, include its marker verbatim.
+
+For each exercise, extract and generate:
+1. The question
+2. A detailed answer (reasoned from the knowledge points and examples)
+3. An explanation of the solution approach
+4. The knowledge points involved
+5. The chapter it belongs to (if identifiable)
+6. The difficulty: "easy", "medium" or "hard"
+7. A confidence between 0 and 1: how sure you are that the question was extracted completely and the answer is correct (lower it for blurry text, missing figures, cut-off questions or answers you could not verify)
+
+For multiple-choice questions, put the options in "options" (not in "question") and the letters of the correct options in "correct_options"; for other questions leave both empty.
+
+Return the result as JSON, written in the same language as the text:
+{
+  "exercises": [
+    {
+      "question": "question text",
+      "answer": "generated answer",
+      "analysis": "detailed solution",
+      "knowledge_points": ["knowledge point 1", "knowledge point 2"],
+      "chapter": "chapter title",
+      "section": "section title",
+      "difficulty": "medium",
+      "options": [{"label": "A", "text": "option text"}],
+      "correct_options": ["A"],
+      "confidence": 0.9
+    }
+  ]
+}"#,
+            "Reference context:",
+            "Identify the exercises in the following text and answer them:",
+        ),
+        _ => (
+            r#"你是一个专业的教育内容分析助手。请分析以下文本，识别出其中的课后习题（没有答案的练习题）。
+
+参考以下知识点和例题上下文来解答这些题目。
+每段参考内容开头都有 [文件:页码] 形式的引用标记，解析中用到某段内容时请原样附上它的引用标记。
+
+对于每道习题，请提取并生成：
+1. 题目内容
+2. 详细答案（根据知识点和例题推理）
+3. 解题思路分析
+4. 涉及的知识点
+5. 所属章节（如果能识别）
+6. 难度："easy"（容易）、"medium"（中等）或 "hard"（困难）
+7. 置信度（0~1）：题目是否完整提取、答案是否正确的把握程度（文字模糊、缺少插图、题目被截断或答案无法验证时应降低）
+
+选择题的选项放在 "options" 中（不要写进 "question"），正确选项的字母放在 "correct_options" 中；其他题型这两项留空。
+
+请以 JSON 格式返回结果：
+{
+  "exercises": [
+    {
+      "question": "题目内容",
+      "answer": "生成的答案",
+      "analysis": "详细解析",
+      "knowledge_points": ["知识点1", "知识点2"],
+      "chapter": "章节名称",
+      "section": "小节名称",
+      "difficulty": "medium",
+      "options": [{"label": "A", "text": "选项内容"}],
+      "correct_options": ["A"],
+      "confidence": 0.9
+    }
+  ]
+}"#,
+            "参考上下文：",
+            "请分析以下文本中的课后习题并给出答案：",
+        ),
+    };
+
+    vec![
+        ChatMessage::system(system_prompt),
+        ChatMessage::user_with_images(
+            format!("{}\n{}\n\n{}\n\n{}", context_label, context, instruction, text),
+            images,
+        ),
+    ]
+}
+
+/// 从响应中提取回复和推理内容
+/// 推理内容可能在 reasoning_content（DeepSeek）、reasoning（OpenRouter）、thinking（Ollama）字段中，
+/// 也可能以 <think>...</think> 的形式出现在正文开头
+fn extract_reply(body: &serde_json::Value) -> Option<ChatReply> {
+    let content = extract_message_content(body)?;
+
+    let mut reasoning = [
+        "/choices/0/message/reasoning_content",
+        "/choices/0/message/reasoning",
+        "/message/thinking",
+    ]
+    .iter()
+    .find_map(|pointer| body.pointer(pointer).and_then(|v| v.as_str()))
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty());
+
+    let mut inline_reasoning = Vec::new();
+    let mut content = THINK_RE
+        .replace_all(&content, |caps: &regex::Captures| {
+            inline_reasoning.push(caps[1].trim().to_string());
+            String::new()
+        })
+        .to_string();
+    // 输出被截断等原因导致 <think> 没有闭合时，最后一个空行之后的内容视为回复，之前的视为推理；
+    // 没有空行时无法区分，整段都作为推理，回复只保留标签之前的内容
+    if let Some(start) = content.rfind("<think>") {
+        let unclosed = content[start + "<think>".len()..].to_string();
+        let (thinking, answer) = match unclosed.trim_end().rfind("\n\n") {
+            Some(pos) => (&unclosed[..pos], &unclosed[pos..]),
+            None => (unclosed.as_str(), ""),
+        };
+        if !thinking.trim().is_empty() {
+            inline_reasoning.push(thinking.trim().to_string());
+        }
+        content = format!("{}{}", &content[..start], answer);
+    }
+    let content = content.trim().to_string();
+    if reasoning.is_none() && !inline_reasoning.is_empty() {
+        reasoning = Some(inline_reasoning.join("\n\n"));
+    }
+
+    Some(ChatReply { content, reasoning })
+}
+
+/// 从响应中提取回复内容
+/// 兼容 OpenAI 格式（choices[0].message）和 Ollama 原生 /api/chat 格式（message）
+fn extract_message_content(body: &serde_json::Value) -> Option<String> {
+    if let Ok(chat_response) = serde_json::from_value::<ChatResponse>(body.clone()) {
+        return chat_response.choices.first().map(|c| c.message.content.text());
+    }
+    body.pointer("/choices/0/message/content")
+        .or_else(|| body.pointer("/message/content"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// 解析模型配置中的附加请求头
+pub fn parse_headers(headers: &HashMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow::anyhow!("请求头名称无效: {}", name))?;
+        let header_value = HeaderValue::from_str(value.trim())
+            .map_err(|_| anyhow::anyhow!("请求头 {} 的值无效", name))?;
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
+
+/// 添加认证和附加请求头
+/// 附加请求头中有 Authorization 时使用它（网关的自定义认证方式），否则有 API Key 时使用 Bearer 认证
+fn apply_headers(
+    builder: reqwest::RequestBuilder,
+    api_key: &str,
+    headers: &HeaderMap,
+) -> reqwest::RequestBuilder {
+    // Ollama 等本地服务不需要 API Key
+    let builder = if !api_key.is_empty() && !headers.contains_key(AUTHORIZATION) {
+        builder.bearer_auth(api_key)
+    } else {
+        builder
+    };
+    builder.headers(headers.clone())
+}
+
+/// 是否为 Ollama 原生的 /api/chat 接口
+fn is_ollama_native(api_url: &str) -> bool {
+    api_url.trim_end_matches('/').ends_with("/api/chat")
+}
+
+/// 根据模型名称判断是否支持图片输入
+pub fn supports_vision(model_name: &str) -> bool {
+    let name = model_name.to_lowercase();
+    const VISION_MODELS: &[&str] = &[
+        "gpt-4o", "gpt-4.1", "gpt-4-turbo", "gpt-4-vision", "claude-3", "claude-sonnet", "claude-opus",
+        "gemini", "glm-4v", "qwen-vl", "qwen2-vl", "qwen2.5-vl", "llava", "minicpm-v", "vision", "-vl",
+    ];
+    VISION_MODELS.iter().any(|m| name.contains(m))
+}
+
+/// 接口是否支持 JSON 模式
+/// OpenAI 兼容的 /chat/completions 接口支持 response_format，Ollama 原生接口支持 format
+pub fn supports_json_mode(api_url: &str) -> bool {
+    let url = api_url.trim_end_matches('/');
+    url.ends_with("/chat/completions") || is_ollama_native(url)
+}
+
+/// 是否为本地模型服务（Ollama、LM Studio 等）
+pub fn is_local_endpoint(api_url: &str) -> bool {
+    let url = match reqwest::Url::parse(api_url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    matches!(url.host_str(), Some("localhost") | Some("127.0.0.1") | Some("0.0.0.0") | Some("::1"))
+        || url.port() == Some(11434)
+}
+
+/// Ollama 中已下载的模型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+/// 通过 /api/tags 列出 Ollama 中已下载的模型
+/// api_url 可以是 /api/chat、/v1/chat/completions 或服务根地址
+pub async fn list_ollama_models(api_url: &str) -> Result<Vec<OllamaModel>> {
+    let mut url = reqwest::Url::parse(api_url)?;
+    url.set_path("/api/tags");
+    url.set_query(None);
+
+    let client = crate::http_client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    let response = client.get(url).send().await.map_err(|e| {
+        if e.is_connect() {
+            anyhow::anyhow!("无法连接到 Ollama，请确认 Ollama 已启动")
+        } else {
+            anyhow::anyhow!("获取模型列表失败: {}", e)
+        }
+    })?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(anyhow::anyhow!("获取模型列表失败: {} {}", status, error_text));
+    }
+
+    let tags: OllamaTagsResponse = response.json().await?;
+    Ok(tags.models)
+}
+
+/// 计算第 attempt 次重试前的等待时间：1s、2s、4s……最长 60s
+fn backoff_delay(attempt: u32) -> Duration {
+    let secs = 1u64 << attempt.min(6);
+    Duration::from_secs(secs.min(60))
+}
+
+/// 解析 Retry-After 响应头（秒数），部分服务使用 retry-after-ms
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    if let Some(ms) = headers
+        .get("retry-after-ms")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+    {
+        return Some(Duration::from_millis(ms.min(120_000)));
+    }
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map(|secs| Duration::from_secs_f64(secs.clamp(0.0, 120.0)))
+}
+
+/// 等待服务商的请求配额
+/// 按每分钟请求数把请求均匀分布开，先预约时间片再等待，避免并发请求同时发出
+async fn wait_for_rate_limit(api_url: &str, requests_per_minute: u32) {
+    if requests_per_minute == 0 {
+        return;
+    }
+
+    let interval = Duration::from_secs_f64(60.0 / requests_per_minute as f64);
+    let wait = {
+        let mut limits = RATE_LIMITS.lock();
+        let now = Instant::now();
+        let slot = limits
+            .get(&provider_key(api_url))
+            .copied()
+            .filter(|next| *next > now)
+            .unwrap_or(now);
+        limits.insert(provider_key(api_url), slot + interval);
+        slot - now
+    };
+
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// 服务商标识：接口地址的主机名和端口
+fn provider_key(api_url: &str) -> String {
+    reqwest::Url::parse(api_url)
+        .ok()
+        .map(|url| format!("{}:{}", url.host_str().unwrap_or(""), url.port_or_known_default().unwrap_or(0)))
+        .unwrap_or_else(|| api_url.to_string())
+}
+
+/// 并发 AI 请求执行器，限制同时进行的请求数
+#[derive(Debug, Clone)]
+pub struct AIRequestPool {
+    semaphore: Arc<Semaphore>,
+    max_parallel: usize,
+}
+
+impl AIRequestPool {
+    pub fn new(max_parallel: usize) -> Self {
+        let max_parallel = max_parallel.clamp(1, MAX_PARALLEL_REQUESTS);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_parallel)),
+            max_parallel,
+        }
+    }
+
+    pub fn max_parallel(&self) -> usize {
+        self.max_parallel
+    }
+
+    /// 并发执行一组任务，结果顺序与输入一致
+    pub async fn run_all<T, F, Fut>(&self, items: Vec<T>, task: F) -> Vec<Fut::Output>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        self.run_all_with_progress(items, task, |_| {}).await
+    }
+
+    /// 并发执行一组任务，每个任务完成时立即调用 on_done（按完成顺序），结果顺序与输入一致
+    pub async fn run_all_with_progress<T, F, Fut, P>(&self, items: Vec<T>, task: F, mut on_done: P) -> Vec<Fut::Output>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+        P: FnMut(&Fut::Output),
+    {
+        use crate::logger;
+
+        let mut set = tokio::task::JoinSet::new();
+        for (index, item) in items.into_iter().enumerate() {
+            let semaphore = self.semaphore.clone();
+            let future = task(item);
+            set.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (index, future.await)
+            });
+        }
+
+        let mut results = Vec::with_capacity(set.len());
+        while let Some(joined) = set.join_next().await {
+            match joined {
+                Ok(result) => {
+                    on_done(&result.1);
+                    results.push(result);
+                }
+                Err(e) => logger::error("ai", &format!("AI 请求任务异常退出: {}", e)),
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, output)| output).collect()
+    }
+}
+
+/// 备用模型链：主模型出错时依次尝试后面的模型
+/// 认证失败、额度不足的模型立即停用；其他错误连续出现多次后切换到下一个模型
+#[derive(Debug, Clone)]
+pub struct FallbackChain {
+    services: Arc<Vec<AIService>>,
+    active: Arc<AtomicUsize>,
+    consecutive_failures: Arc<AtomicU32>,
+}
+
+impl FallbackChain {
+    pub fn new(services: Vec<AIService>) -> Self {
+        Self {
+            services: Arc::new(services),
+            active: Arc::new(AtomicUsize::new(0)),
+            consecutive_failures: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// 当前使用的模型
+    pub fn active_model(&self) -> Option<&str> {
+        self.services
+            .get(self.active.load(Ordering::SeqCst))
+            .map(|s| s.model_name())
+    }
+
+    /// 所有模型中最小的可用输入长度，切换模型后内容仍然放得下
+    pub fn input_budget(&self) -> usize {
+        self.services
+            .iter()
+            .map(|s| s.input_budget())
+            .min()
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    }
+
+    /// 所有模型中最小的参考上下文可用长度
+    pub fn context_budget(&self, prompt_tokens: usize) -> usize {
+        self.services
+            .iter()
+            .map(|s| s.context_budget(prompt_tokens))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// 执行请求，返回结果和实际使用的模型名称
+    pub async fn run<T, F, Fut>(&self, request: F) -> (Result<T>, String)
+    where
+        F: Fn(AIService) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        use crate::logger;
+
+        let start = self.active.load(Ordering::SeqCst);
+        let mut last_error = anyhow::anyhow!("没有可用的分析模型");
+        let mut last_model = String::new();
+
+        for index in start..self.services.len() {
+            let service = &self.services[index];
+            match request(service.clone()).await {
+                Ok(output) => {
+                    if index == self.active.load(Ordering::SeqCst) {
+                        self.consecutive_failures.store(0, Ordering::SeqCst);
+                    }
+                    return (Ok(output), service.model_name().to_string());
+                }
+                Err(e) if matches!(e.downcast_ref::<AIError>(), Some(AIError::Cancelled)) => {
+                    return (Err(e), service.model_name().to_string());
+                }
+                Err(e) => {
+                    let fatal = e
+                        .downcast_ref::<AIError>()
+                        .map(|err| err.is_fatal())
+                        .unwrap_or(false);
+                    let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+                    if fatal || failures >= FALLBACK_AFTER_FAILURES {
+                        self.deactivate(index);
+                    }
+                    if let Some(next) = self.services.get(index + 1) {
+                        logger::warn(
+                            "ai",
+                            &format!("模型 {} 请求失败，改用备用模型 {}: {}", service.model_name(), next.model_name(), e),
+                        );
+                    }
+                    last_model = service.model_name().to_string();
+                    last_error = e;
+                }
+            }
+        }
+
+        (Err(last_error), last_model)
+    }
+
+    /// 停用指定模型，之后的请求直接从下一个模型开始
+    fn deactivate(&self, index: usize) {
+        use crate::logger;
+
+        // 最后一个模型不停用，至少保留一个可尝试的模型
+        if index + 1 >= self.services.len() {
+            return;
+        }
+        if self
+            .active
+            .compare_exchange(index, index + 1, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            self.consecutive_failures.store(0, Ordering::SeqCst);
+            logger::warn(
+                "ai",
+                &format!(
+                    "模型 {} 多次失败，后续请求改用 {}",
+                    self.services[index].model_name(),
+                    self.services[index + 1].model_name()
+                ),
+            );
+        }
+    }
+}
+
+/// 计算文本的 token 数
+pub fn count_tokens(text: &str) -> usize {
+    match TOKENIZER.as_ref() {
+        Some(bpe) => bpe.encode_ordinary(text).len(),
+        None => estimate_tokens(text),
+    }
+}
+
+/// 计算消息列表的 token 数：文本内容、每条消息的格式开销和图片
+pub fn count_message_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| {
+            let images = m.content.image_urls().len() + m.images.as_ref().map_or(0, |i| i.len());
+            count_tokens(&m.content.text()) + MESSAGE_OVERHEAD_TOKENS + images * IMAGE_TOKENS
+        })
+        .sum()
+}
+
+/// 分词器不可用时的估算：中日韩字符约 1 个 token，其余约 4 字节 1 个 token
+fn estimate_tokens(text: &str) -> usize {
+    let (cjk, other_bytes) = text.chars().fold((0usize, 0usize), |(cjk, other), c| {
+        if is_cjk(c) {
+            (cjk + 1, other)
+        } else {
+            (cjk, other + c.len_utf8())
+        }
+    });
+    cjk + (other_bytes + 3) / 4
+}
+
+/// 提示词语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PromptLanguage {
+    #[default]
+    Auto, // 根据页面内容选择
+    Chinese,
+    English,
+}
+
+impl PromptLanguage {
+    /// 从配置读取："auto"、"zh"、"en"
+    pub fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "zh" | "zh-cn" | "chinese" => PromptLanguage::Chinese,
+            "en" | "english" => PromptLanguage::English,
+            _ => PromptLanguage::Auto,
+        }
+    }
+
+    /// 自动模式下根据文本判断：中文教材中常夹杂公式和英文，中文字符数达到英文字母数的五分之一即视为中文
+    pub fn resolve(self, text: &str) -> Self {
+        if self != PromptLanguage::Auto {
+            return self;
+        }
+        let cjk = text.chars().filter(|c| is_cjk(*c)).count();
+        let latin = text.chars().filter(|c| c.is_ascii_alphabetic()).count();
+        if latin > 0 && cjk * 5 < latin {
+            PromptLanguage::English
+        } else {
+            PromptLanguage::Chinese
+        }
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3000..=0x303F | 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF)
+}
+
+/// 将文本截断到不超过 max_tokens 个 token
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    if let Some(bpe) = TOKENIZER.as_ref() {
+        let tokens = bpe.encode_ordinary(text);
+        if tokens.len() <= max_tokens {
+            return text.to_string();
+        }
+        // 截断点可能落在多字节字符中间，向前退几个 token 直到能解码
+        let mut end = max_tokens;
+        while end > 0 && max_tokens - end < 4 {
+            if let Ok(s) = bpe.decode(tokens[..end].to_vec()) {
+                return s;
+            }
+            end -= 1;
+        }
+    }
+
+    // 按估算逐字符截断
+    let mut used = 0;
+    let mut end = 0;
+    for (i, c) in text.char_indices() {
+        used += if is_cjk(c) { 4 } else { c.len_utf8() };
+        if (used + 3) / 4 > max_tokens {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+    text[..end].to_string()
+}
+
+/// 根据模型名称推断上下文长度
+pub fn context_window(model_name: &str) -> usize {
+    let name = model_name.to_lowercase();
+
+    // 名称中带有长度后缀的模型，例如 moonshot-v1-32k
+    for (suffix, window) in [("1m", 1_000_000), ("200k", 200_000), ("128k", 128_000), ("32k", 32_768), ("16k", 16_384), ("8k", 8_192)] {
+        if name.ends_with(suffix) || name.contains(&format!("-{}-", suffix)) {
+            return window;
+        }
+    }
+
+    if name.contains("gemini") {
+        1_000_000
+    } else if name.contains("claude") {
+        200_000
+    } else if name.contains("gpt-4o")
+        || name.contains("gpt-4.1")
+        || name.contains("gpt-4-turbo")
+        || name.starts_with("o1")
+        || name.starts_with("o3")
+        || name.starts_with("o4")
+        || name.contains("glm-4")
+        || name.contains("qwen-long")
+        || name.contains("qwen-plus")
+        || name.contains("qwen2.5")
+    {
+        128_000
+    } else if name.contains("deepseek") {
+        64_000
+    } else if name.contains("qwen") {
+        32_768
+    } else if name.contains("gpt-3.5") {
+        16_385
+    } else {
+        DEFAULT_CONTEXT_WINDOW
+    }
+}
+
+/// OpenAI Batch 接口客户端
+/// 批量任务在 24 小时内完成，费用约为实时请求的一半，适合不需要即时结果的整本书分析
+#[derive(Debug, Clone)]
+pub struct BatchClient {
+    client: Client,
+    api_base: String,     // 例如 https://api.openai.com/v1
+    endpoint_path: String, // 例如 /v1/chat/completions
+    api_key: String,
+    headers: HeaderMap,
+}
+
+/// 批量任务状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatus {
+    pub id: String,
+    pub status: String, // validating、in_progress、finalizing、completed、failed、expired、cancelling、cancelled
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    #[serde(default)]
+    pub error_file_id: Option<String>,
+    #[serde(default)]
+    pub request_counts: Option<BatchRequestCounts>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchRequestCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+impl BatchStatus {
+    /// 任务是否已结束（不会再变化）
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed" | "expired" | "cancelled")
+    }
+}
+
+/// 批量任务中一条请求的结果
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    pub custom_id: String,
+    pub result: std::result::Result<ChatReply, String>,
+    pub usage: Option<crate::ai_usage::TokenUsage>,
+}
+
+impl BatchClient {
+    /// api_url 为聊天接口地址，必须是 OpenAI 兼容的 /chat/completions 接口
+    pub fn new(api_url: &str, api_key: &str) -> Result<Self> {
+        let url = reqwest::Url::parse(api_url.trim())?;
+        let endpoint_path = url.path().trim_end_matches('/').to_string();
+        let api_base = api_url
+            .trim()
+            .trim_end_matches('/')
+            .strip_suffix("/chat/completions")
+            .ok_or_else(|| anyhow::anyhow!("批量任务仅支持 OpenAI 兼容的 /chat/completions 接口"))?
+            .to_string();
+
+        let client = crate::http_client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()?;
+
+        Ok(Self {
+            client,
+            api_base,
+            endpoint_path,
+            api_key: api_key.to_string(),
+            headers: HeaderMap::new(),
+        })
+    }
+
+    /// 设置附加请求头
+    pub fn with_headers(mut self, headers: &HashMap<String, String>) -> Result<Self> {
+        self.headers = parse_headers(headers)?;
+        Ok(self)
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        apply_headers(builder, &self.api_key, &self.headers)
+    }
+
+    /// 生成批量任务输入文件中的一行
+    pub fn request_line(&self, custom_id: &str, request: &ChatRequest) -> Result<String> {
+        Ok(serde_json::to_string(&serde_json::json!({
+            "custom_id": custom_id,
+            "method": "POST",
+            "url": self.endpoint_path,
+            "body": request,
+        }))?)
+    }
+
+    /// 上传 JSONL 输入文件，返回文件 ID
+    pub async fn upload(&self, jsonl: String) -> Result<String> {
+        let part = reqwest::multipart::Part::bytes(jsonl.into_bytes())
+            .file_name("batch_input.jsonl")
+            .mime_str("application/jsonl")?;
+        let form = reqwest::multipart::Form::new()
+            .text("purpose", "batch")
+            .part("file", part);
+
+        let response = self
+            .authorize(self.client.post(format!("{}/files", self.api_base)))
+            .multipart(form)
+            .send()
+            .await
+            .map_err(AIError::from)?;
+        let body = Self::json_body(response).await?;
+        body.get("id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow::anyhow!("上传批量任务文件失败：响应中没有文件 ID"))
+    }
+
+    /// 创建批量任务
+    pub async fn create(&self, input_file_id: &str) -> Result<BatchStatus> {
+        let response = self
+            .authorize(self.client.post(format!("{}/batches", self.api_base)))
+            .json(&serde_json::json!({
+                "input_file_id": input_file_id,
+                "endpoint": self.endpoint_path,
+                "completion_window": "24h",
+            }))
+            .send()
+            .await
+            .map_err(AIError::from)?;
+        Ok(serde_json::from_value(Self::json_body(response).await?)?)
+    }
+
+    /// 查询批量任务状态
+    pub async fn retrieve(&self, batch_id: &str) -> Result<BatchStatus> {
+        let response = self
+            .authorize(self.client.get(format!("{}/batches/{}", self.api_base, batch_id)))
+            .send()
+            .await
+            .map_err(AIError::from)?;
+        Ok(serde_json::from_value(Self::json_body(response).await?)?)
+    }
+
+    /// 取消批量任务
+    pub async fn cancel(&self, batch_id: &str) -> Result<BatchStatus> {
+        let response = self
+            .authorize(self.client.post(format!("{}/batches/{}/cancel", self.api_base, batch_id)))
+            .send()
+            .await
+            .map_err(AIError::from)?;
+        Ok(serde_json::from_value(Self::json_body(response).await?)?)
+    }
+
+    /// 下载结果文件并解析每条请求的回复
+    pub async fn download_results(&self, file_id: &str) -> Result<Vec<BatchResult>> {
+        let response = self
+            .authorize(self.client.get(format!("{}/files/{}/content", self.api_base, file_id)))
+            .send()
+            .await
+            .map_err(AIError::from)?;
+        let status = response.status();
+        let text = response.text().await.map_err(AIError::from)?;
+        if !status.is_success() {
+            return Err(AIError::from_response(status, &text).into());
+        }
+
+        Ok(text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+            .filter_map(|line| parse_batch_line(&line))
+            .collect())
+    }
+
+    async fn json_body(response: reqwest::Response) -> Result<serde_json::Value> {
+        let status = response.status();
+        let text = response.text().await.map_err(AIError::from)?;
+        if !status.is_success() {
+            return Err(AIError::from_response(status, &text).into());
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+/// 解析结果文件中的一行
+fn parse_batch_line(line: &serde_json::Value) -> Option<BatchResult> {
+    let custom_id = line.get("custom_id")?.as_str()?.to_string();
+
+    if let Some(error) = line.get("error").filter(|e| !e.is_null()) {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| error.to_string());
+        return Some(BatchResult {
+            custom_id,
+            result: Err(message),
+            usage: None,
+        });
+    }
+
+    let status_code = line.pointer("/response/status_code").and_then(|v| v.as_u64()).unwrap_or(0);
+    let body = line.pointer("/response/body")?;
+    let result = if status_code != 200 {
+        Err(format!("{} {}", status_code, body))
+    } else {
+        extract_reply(body).ok_or_else(|| "响应中没有回复内容".to_string())
+    };
+
+    Some(BatchResult {
+        custom_id,
+        result,
+        usage: crate::ai_usage::TokenUsage::from_response(body),
+    })
+}
+
+/// 重排序时每段内容保留的最大 token 数
+const RERANK_PASSAGE_TOKENS: usize = 400;
+
+/// 扩展查询时查询内容保留的最大 token 数（构建习题上下文时查询是整页内容）
+const QUERY_EXPANSION_INPUT_TOKENS: usize = 600;
+
+/// 默认每批发送的文本数
+pub const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 32;
+
+/// 向量化服务，支持 OpenAI 兼容的 /embeddings 接口和 Ollama 的 /api/embed 接口
+#[derive(Debug, Clone)]
+pub struct EmbeddingService {
+    client: Client,
+    api_url: String,
+    api_key: String,
+    model_name: String,
+    batch_size: usize,
+    max_retries: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+impl EmbeddingService {
+    /// api_url 可以填写聊天接口地址，会自动换成同一服务的向量化接口
+    pub fn new(api_url: &str, api_key: &str, model_name: &str) -> Self {
+        let timeout = if is_local_endpoint(api_url) { 300 } else { 60 };
+        let client = crate::http_client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .unwrap();
+
+        Self {
+            client,
+            api_url: embeddings_url(api_url),
+            api_key: api_key.to_string(),
+            model_name: model_name.to_string(),
+            batch_size: DEFAULT_EMBEDDING_BATCH_SIZE,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    /// 设置每批发送的文本数
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// 设置失败后的最大重试次数
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    /// 向量化单条文本
+    pub async fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| AIError::EmptyResponse.into())
+    }
+
+    /// 分批向量化，返回顺序与输入一致
+    /// 先按内容哈希查找向量缓存，只请求未缓存的文本，相同文本只请求一次
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        use crate::embedding_cache;
+        use crate::logger;
+
+        let keys: Vec<String> = texts
+            .iter()
+            .map(|text| embedding_cache::cache_key(&self.api_url, &self.model_name, text))
+            .collect();
+        let mut embeddings = embedding_cache::get_many(&keys);
+
+        let mut seen = std::collections::HashSet::new();
+        let missing: Vec<usize> = (0..texts.len())
+            .filter(|&i| embeddings[i].is_none() && seen.insert(keys[i].as_str()))
+            .collect();
+        let cached = embeddings.iter().filter(|e| e.is_some()).count();
+        if cached > 0 {
+            logger::debug("ai", &format!("向量缓存命中 {}/{} 条", cached, texts.len()));
+        }
+
+        // 每批请求完成后立即写入缓存，中途失败时已完成的批次不会白费
+        let mut fetched = HashMap::new();
+        for batch in missing.chunks(self.batch_size) {
+            let batch_texts: Vec<String> = batch.iter().map(|&i| texts[i].clone()).collect();
+            let vectors = self.embed_batch_with_retry(&batch_texts).await?;
+            let entries: Vec<(String, Vec<f32>)> = batch.iter().map(|&i| keys[i].clone()).zip(vectors).collect();
+            embedding_cache::put_many(&self.model_name, &entries);
+            fetched.extend(entries);
+        }
+
+        for (i, embedding) in embeddings.iter_mut().enumerate() {
+            if embedding.is_none() {
+                *embedding = fetched.get(&keys[i]).cloned();
+            }
+        }
+        embeddings
+            .into_iter()
+            .map(|e| e.ok_or_else(|| AIError::EmptyResponse.into()))
+            .collect()
+    }
+
+    /// 发送一批请求，遇到限流、网络或服务端错误时按指数退避重试
+    async fn embed_batch_with_retry(&self, batch: &[String]) -> std::result::Result<Vec<Vec<f32>>, AIError> {
+        use crate::logger;
+
+        let mut attempt = 0;
+        loop {
+            let (result, retry_after) = self.embed_batch_once(batch).await;
+            let error = match result {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(e) => e,
+            };
+
+            if !error.is_retryable() || attempt >= self.max_retries {
+                return Err(error);
+            }
+
+            let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+            attempt += 1;
+            logger::warn("ai", &format!(
+                "向量化请求失败: {}，{:.1} 秒后第 {}/{} 次重试",
+                error,
+                delay.as_secs_f32(),
+                attempt,
+                self.max_retries
+            ));
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn embed_batch_once(
+        &self,
+        batch: &[String],
+    ) -> (std::result::Result<Vec<Vec<f32>>, AIError>, Option<Duration>) {
+        let request = EmbeddingRequest {
+            model: &self.model_name,
+            input: batch,
+        };
+        let mut builder = self.client.post(&self.api_url).json(&request);
+        if !self.api_key.is_empty() {
+            builder = builder.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+        let response = match builder.send().await {
+            Ok(response) => response,
+            Err(e) => return (Err(e.into()), None),
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
+            let error_text = response.text().await.unwrap_or_default();
+            return (Err(AIError::from_response(status, &error_text)), retry_after);
+        }
+
+        let body: serde_json::Value = match response.json().await {
+            Ok(body) => body,
+            Err(e) => return (Err(e.into()), None),
+        };
+
+        let embeddings = match parse_embeddings(&body) {
+            Some(embeddings) if embeddings.len() == batch.len() => embeddings,
+            Some(embeddings) => {
+                return (
+                    Err(AIError::Request(format!(
+                        "向量数量与输入不一致：输入 {} 条，返回 {} 条",
+                        batch.len(),
+                        embeddings.len()
+                    ))),
+                    None,
+                )
+            }
+            None => return (Err(AIError::EmptyResponse), None),
+        };
+
+        // 记录用量，向量化只有输入 token
+        let usage = crate::ai_usage::TokenUsage::from_response(&body);
+        let estimated = usage.is_none();
+        let usage = usage.unwrap_or_else(|| crate::ai_usage::TokenUsage {
+            prompt_tokens: batch.iter().map(|t| count_tokens(t) as u64).sum(),
+            completion_tokens: 0,
+        });
+        crate::ai_usage::record(None, &self.model_name, usage, estimated);
+
+        (Ok(embeddings), None)
+    }
+}
+
+/// 解析向量化响应
+/// OpenAI 格式：data[].embedding（按 index 排序）；Ollama 格式：embeddings
+fn parse_embeddings(body: &serde_json::Value) -> Option<Vec<Vec<f32>>> {
+    let to_vector = |value: &serde_json::Value| -> Option<Vec<f32>> {
+        value
+            .as_array()?
+            .iter()
+            .map(|v| v.as_f64().map(|f| f as f32))
+            .collect()
+    };
+
+    if let Some(data) = body.get("data").and_then(|v| v.as_array()) {
+        let mut items: Vec<(u64, Vec<f32>)> = data
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let index = item.get("index").and_then(|v| v.as_u64()).unwrap_or(i as u64);
+                Some((index, to_vector(item.get("embedding")?)?))
+            })
+            .collect::<Option<_>>()?;
+        items.sort_by_key(|(index, _)| *index);
+        return Some(items.into_iter().map(|(_, v)| v).collect());
+    }
+
+    body.get("embeddings")
+        .and_then(|v| v.as_array())
+        .and_then(|list| list.iter().map(to_vector).collect())
+}
+
+/// 由聊天接口地址推出向量化接口地址
+/// 例如 https://api.openai.com/v1/chat/completions -> https://api.openai.com/v1/embeddings，
+/// http://localhost:11434/api/chat -> http://localhost:11434/api/embed
+pub fn embeddings_url(api_url: &str) -> String {
+    let url = api_url.trim().trim_end_matches('/');
+    if let Some(base) = url.strip_suffix("/chat/completions") {
+        format!("{}/embeddings", base)
+    } else if let Some(base) = url.strip_suffix("/api/chat") {
+        format!("{}/api/embed", base)
+    } else {
+        url.to_string()
+    }
 }
 
 /// 创建 AI 服务实例
@@ -0,0 +1,132 @@
+// AI 调用追踪模块 - 调试模式下记录请求和响应
+// 题目提取质量下降时，可以查看最近的请求内容、状态码、耗时和 token 用量
+
+use crate::ai_usage::TokenUsage;
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// 保留的调用记录数
+const MAX_CALL_HISTORY: usize = 100;
+
+/// 请求和响应正文保留的最大字符数
+const MAX_BODY_CHARS: usize = 8000;
+
+// 是否开启调试追踪
+static TRACE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// 最近的调用记录
+static CALL_HISTORY: Lazy<RwLock<VecDeque<AICallRecord>>> = Lazy::new(|| RwLock::new(VecDeque::new()));
+
+// 图片 data URL 中的 base64 内容
+static DATA_URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"data:([a-z]+/[a-z0-9.+-]+);base64,[A-Za-z0-9+/=]+").unwrap());
+
+// 请求正文中的密钥字段
+static SECRET_FIELD_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)"(api_key|apikey|key|token|secret|password)"\s*:\s*"[^"]*""#).unwrap());
+
+/// 一次 AI 调用的记录
+#[derive(Debug, Clone, Serialize)]
+pub struct AICallRecord {
+    pub timestamp: String,
+    pub model: String,
+    pub endpoint: String,       // 已去掉查询参数
+    pub status: Option<u16>,    // 网络错误时为空
+    pub latency_ms: u64,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub request_body: String,   // 已脱敏、截断
+    pub response_body: String,  // 已截断
+    pub error: Option<String>,
+}
+
+/// 开启或关闭调试追踪
+pub fn set_enabled(enabled: bool) {
+    TRACE_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    TRACE_ENABLED.load(Ordering::SeqCst)
+}
+
+/// 记录一次调用
+#[allow(clippy::too_many_arguments)]
+pub fn record(
+    model: &str,
+    api_url: &str,
+    request_body: &str,
+    status: Option<u16>,
+    latency: Duration,
+    usage: Option<TokenUsage>,
+    response_body: &str,
+    error: Option<String>,
+) {
+    use crate::logger;
+
+    let record = AICallRecord {
+        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        model: model.to_string(),
+        endpoint: sanitize_url(api_url),
+        status,
+        latency_ms: latency.as_millis() as u64,
+        prompt_tokens: usage.map(|u| u.prompt_tokens),
+        completion_tokens: usage.map(|u| u.completion_tokens),
+        request_body: truncate(&sanitize_body(request_body)),
+        response_body: truncate(response_body),
+        error,
+    };
+
+    logger::debug(
+        "ai",
+        &format!(
+            "{} {} 状态 {} 耗时 {} ms，tokens {}/{}",
+            record.model,
+            record.endpoint,
+            record.status.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()),
+            record.latency_ms,
+            record.prompt_tokens.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string()),
+            record.completion_tokens.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string()),
+        ),
+    );
+    logger::debug("ai", &format!("请求: {}", record.request_body));
+    match &record.error {
+        Some(error) => logger::debug("ai", &format!("错误: {}", error)),
+        None => logger::debug("ai", &format!("响应: {}", record.response_body)),
+    }
+
+    let mut history = CALL_HISTORY.write();
+    history.push_back(record);
+    while history.len() > MAX_CALL_HISTORY {
+        history.pop_front();
+    }
+}
+
+/// 获取最近的 limit 条调用记录，最新的在前
+pub fn history(limit: usize) -> Vec<AICallRecord> {
+    CALL_HISTORY.read().iter().rev().take(limit).cloned().collect()
+}
+
+/// 去掉图片数据和密钥字段
+fn sanitize_body(body: &str) -> String {
+    let body = DATA_URL_RE.replace_all(body, |caps: &regex::Captures| {
+        format!("data:{};base64,<{} 字节>", &caps[1], caps[0].len())
+    });
+    SECRET_FIELD_RE
+        .replace_all(&body, |caps: &regex::Captures| format!("\"{}\":\"***\"", &caps[1]))
+        .to_string()
+}
+
+/// 去掉地址中的查询参数（部分服务商把密钥放在 ?key= 中）
+fn sanitize_url(api_url: &str) -> String {
+    api_url.split('?').next().unwrap_or(api_url).to_string()
+}
+
+fn truncate(text: &str) -> String {
+    crate::utils::truncate_string(text, MAX_BODY_CHARS)
+}
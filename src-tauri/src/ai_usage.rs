@@ -0,0 +1,287 @@
+// AI 用量统计模块 - 记录每次调用的 token 用量
+// 按文件、分析批次和模型汇总，结合模型单价估算费用
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 用量记录文件路径（每行一条 JSON 记录）
+static USAGE_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 已加载的用量记录
+static USAGE_RECORDS: Lazy<RwLock<Vec<UsageRecord>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 一次请求的 token 用量
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// 从响应中读取用量
+    /// 兼容 OpenAI 格式（usage.prompt_tokens）和 Ollama 原生格式（prompt_eval_count）
+    pub fn from_response(body: &Value) -> Option<Self> {
+        let get = |pointer: &str| body.pointer(pointer).and_then(|v| v.as_u64());
+
+        if let Some(prompt_tokens) = get("/usage/prompt_tokens") {
+            return Some(Self {
+                prompt_tokens,
+                completion_tokens: get("/usage/completion_tokens").unwrap_or(0),
+            });
+        }
+        if let Some(prompt_tokens) = get("/prompt_eval_count") {
+            return Some(Self {
+                prompt_tokens,
+                completion_tokens: get("/eval_count").unwrap_or(0),
+            });
+        }
+        None
+    }
+}
+
+/// 用量归属：所属文件和分析批次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageScope {
+    pub file_id: String,
+    pub run_id: String,
+}
+
+/// 一条用量记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: String,
+    #[serde(default)]
+    pub file_id: String,
+    #[serde(default)]
+    pub run_id: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub estimated: bool, // 接口未返回用量时按分词器估算
+    #[serde(default)]
+    pub batch: bool, // 通过批量接口提交，按 BATCH_PRICE_FACTOR 计费
+}
+
+/// 汇总的用量
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub estimated_requests: u64,
+    pub cost: f64, // 按模型单价估算的费用，未设置单价的模型不计入
+}
+
+/// 按模型汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub totals: UsageTotals,
+}
+
+/// 按文件汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct FileUsage {
+    pub file_id: String,
+    pub totals: UsageTotals,
+}
+
+/// 按分析批次汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct RunUsage {
+    pub run_id: String,
+    pub file_id: String,
+    pub started_at: String,
+    pub totals: UsageTotals,
+}
+
+/// 用量统计结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageSummary {
+    pub total: UsageTotals,
+    pub by_model: Vec<ModelUsage>,
+    pub by_file: Vec<FileUsage>,
+    pub by_run: Vec<RunUsage>,
+}
+
+/// 模型单价（每百万 token）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// 批量接口的费用相对实时请求的比例
+pub const BATCH_PRICE_FACTOR: f64 = 0.5;
+
+/// 按模型名整理配置中的单价
+pub fn model_prices(models: &[crate::commands::ModelConfig]) -> HashMap<String, ModelPrice> {
+    models
+        .iter()
+        .map(|m| {
+            (
+                m.model_name.clone(),
+                ModelPrice {
+                    input: m.input_price,
+                    output: m.output_price,
+                },
+            )
+        })
+        .collect()
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord, price: Option<&ModelPrice>) {
+        self.requests += 1;
+        self.prompt_tokens += record.prompt_tokens;
+        self.completion_tokens += record.completion_tokens;
+        if record.estimated {
+            self.estimated_requests += 1;
+        }
+        if let Some(price) = price {
+            let factor = if record.batch { BATCH_PRICE_FACTOR } else { 1.0 };
+            self.cost += (record.prompt_tokens as f64 * price.input
+                + record.completion_tokens as f64 * price.output)
+                * factor
+                / 1_000_000.0;
+        }
+    }
+}
+
+/// 初始化用量存储并加载历史记录（在应用启动时调用）
+pub fn init_usage_store(app_dir: &Path) {
+    use crate::logger;
+
+    let path = app_dir.join("ai_usage.jsonl");
+    let records: Vec<UsageRecord> = fs::read_to_string(&path)
+        .map(|content| {
+            content
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .filter_map(|l| serde_json::from_str(l).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !records.is_empty() {
+        logger::debug("ai", &format!("已加载 {} 条 AI 用量记录", records.len()));
+    }
+
+    *USAGE_RECORDS.write() = records;
+    *USAGE_PATH.write() = Some(path);
+}
+
+/// 记录一次请求的用量
+pub fn record(scope: Option<&UsageScope>, model: &str, usage: TokenUsage, estimated: bool) {
+    save_record(new_record(scope, model, usage, estimated));
+}
+
+/// 记录一次批量接口请求的用量，费用按批量价格计算
+pub fn record_batch(scope: Option<&UsageScope>, model: &str, usage: TokenUsage) {
+    let mut record = new_record(scope, model, usage, false);
+    record.batch = true;
+    save_record(record);
+}
+
+fn new_record(scope: Option<&UsageScope>, model: &str, usage: TokenUsage, estimated: bool) -> UsageRecord {
+    UsageRecord {
+        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        file_id: scope.map(|s| s.file_id.clone()).unwrap_or_default(),
+        run_id: scope.map(|s| s.run_id.clone()).unwrap_or_default(),
+        model: model.to_string(),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        estimated,
+        batch: false,
+    }
+}
+
+fn save_record(record: UsageRecord) {
+    use crate::logger;
+
+    if let Some(path) = USAGE_PATH.read().as_ref() {
+        let result = serde_json::to_string(&record)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", line)?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            logger::warn("ai", &format!("保存 AI 用量记录失败: {}", e));
+        }
+    }
+
+    USAGE_RECORDS.write().push(record);
+}
+
+/// 汇总用量，可按文件过滤
+pub fn summarize(file_id: Option<&str>, prices: &HashMap<String, ModelPrice>) -> UsageSummary {
+    let records = USAGE_RECORDS.read();
+    let mut summary = UsageSummary::default();
+
+    for record in records.iter() {
+        if let Some(file_id) = file_id {
+            if record.file_id != file_id {
+                continue;
+            }
+        }
+        let price = prices.get(&record.model);
+
+        summary.total.add(record, price);
+
+        match summary.by_model.iter_mut().find(|m| m.model == record.model) {
+            Some(entry) => entry.totals.add(record, price),
+            None => {
+                let mut totals = UsageTotals::default();
+                totals.add(record, price);
+                summary.by_model.push(ModelUsage { model: record.model.clone(), totals });
+            }
+        }
+
+        if record.file_id.is_empty() {
+            continue;
+        }
+
+        match summary.by_file.iter_mut().find(|f| f.file_id == record.file_id) {
+            Some(entry) => entry.totals.add(record, price),
+            None => {
+                let mut totals = UsageTotals::default();
+                totals.add(record, price);
+                summary.by_file.push(FileUsage { file_id: record.file_id.clone(), totals });
+            }
+        }
+
+        match summary.by_run.iter_mut().find(|r| r.run_id == record.run_id) {
+            Some(entry) => entry.totals.add(record, price),
+            None => {
+                let mut totals = UsageTotals::default();
+                totals.add(record, price);
+                summary.by_run.push(RunUsage {
+                    run_id: record.run_id.clone(),
+                    file_id: record.file_id.clone(),
+                    started_at: record.timestamp.clone(),
+                    totals,
+                });
+            }
+        }
+    }
+
+    summary
+}
+
+/// 清空用量记录
+pub fn clear() {
+    USAGE_RECORDS.write().clear();
+    if let Some(path) = USAGE_PATH.read().as_ref() {
+        let _ = fs::remove_file(path);
+    }
+}
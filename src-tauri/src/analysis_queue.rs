@@ -0,0 +1,302 @@
+// 分析队列模块 - 按顺序依次分析多个文件
+// 可以一次排入多本教材（例如夜间无人值守），队列保存到文件，应用重启后继续执行；
+// 重启前正在分析的文件从检查点继续
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+/// 队列中的分析任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisQueueJob {
+    pub job_id: String,
+    pub file_id: String,
+    pub file_name: String,
+    pub status: String, // "queued", "running", "completed", "stopped", "failed", "cancelled"
+    pub enqueued_at: String,
+    #[serde(default)]
+    pub started_at: Option<String>,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    #[serde(default)]
+    pub error: String,
+    // 从检查点继续（应用重启时中断的任务）
+    #[serde(default)]
+    pub resume: bool,
+}
+
+/// 队列状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisQueueStatus {
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub jobs: Vec<AnalysisQueueJob>,
+}
+
+// 队列文件路径
+static QUEUE_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+static QUEUE: Lazy<Mutex<AnalysisQueueStatus>> = Lazy::new(|| Mutex::new(AnalysisQueueStatus::default()));
+
+// 是否已有执行队列的后台任务
+static WORKER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 任务是否已结束
+pub fn is_finished(status: &str) -> bool {
+    matches!(status, "completed" | "stopped" | "failed" | "cancelled")
+}
+
+/// 初始化队列文件路径并加载（在应用启动时调用）
+/// 上次退出时正在运行的任务重新排队，并从检查点继续
+pub fn init_queue(app_dir: &Path) {
+    let path = app_dir.join("analysis_queue.json");
+    let mut status: AnalysisQueueStatus = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    for job in status.jobs.iter_mut().filter(|j| j.status == "running") {
+        job.status = "queued".to_string();
+        job.resume = true;
+    }
+    *QUEUE.lock() = status;
+    *QUEUE_PATH.lock() = Some(path);
+    save_queue();
+}
+
+/// 将文件加入队列，已在队列中等待或运行的文件不重复加入
+pub fn enqueue(app_handle: &AppHandle, files: &[(String, String)]) -> Result<Vec<AnalysisQueueJob>> {
+    let mut added = Vec::new();
+    {
+        let mut queue = QUEUE.lock();
+        for (file_id, file_name) in files {
+            if queue.jobs.iter().any(|j| &j.file_id == file_id && !is_finished(&j.status)) {
+                continue;
+            }
+            let job = AnalysisQueueJob {
+                job_id: uuid::Uuid::new_v4().to_string(),
+                file_id: file_id.clone(),
+                file_name: file_name.clone(),
+                status: "queued".to_string(),
+                enqueued_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                started_at: None,
+                finished_at: None,
+                error: String::new(),
+                resume: false,
+            };
+            queue.jobs.push(job.clone());
+            added.push(job);
+        }
+    }
+    save_queue();
+    notify(app_handle);
+    start_worker(app_handle);
+    Ok(added)
+}
+
+/// 获取队列状态
+pub fn get_status() -> AnalysisQueueStatus {
+    QUEUE.lock().clone()
+}
+
+/// 取消任务：等待中的任务直接标记为已取消，运行中的任务停止分析（已识别的题目会保存）
+pub async fn cancel(app_handle: &AppHandle, job_id: &str) -> Result<()> {
+    let running_file = {
+        let mut queue = QUEUE.lock();
+        let job = queue
+            .jobs
+            .iter_mut()
+            .find(|j| j.job_id == job_id)
+            .ok_or_else(|| anyhow!("队列中没有该任务"))?;
+        match job.status.as_str() {
+            "queued" => {
+                job.status = "cancelled".to_string();
+                job.finished_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                None
+            }
+            "running" => {
+                job.status = "cancelled".to_string();
+                Some(job.file_id.clone())
+            }
+            _ => return Err(anyhow!("任务已结束")),
+        }
+    };
+    if let Some(file_id) = running_file {
+        crate::question_analyzer::stop_analysis(app_handle, &file_id).await?;
+    }
+    save_queue();
+    notify(app_handle);
+    Ok(())
+}
+
+/// 调整等待中任务的顺序，position 为在等待任务中的位置（从 0 开始）
+pub fn move_job(app_handle: &AppHandle, job_id: &str, position: usize) -> Result<()> {
+    {
+        let mut queue = QUEUE.lock();
+        let index = queue
+            .jobs
+            .iter()
+            .position(|j| j.job_id == job_id && j.status == "queued")
+            .ok_or_else(|| anyhow!("只能调整等待中的任务"))?;
+        let job = queue.jobs.remove(index);
+        let queued: Vec<usize> = queue
+            .jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, j)| j.status == "queued")
+            .map(|(i, _)| i)
+            .collect();
+        let insert_at = match queued.get(position) {
+            Some(&i) => i,
+            None => queued.last().map(|&i| i + 1).unwrap_or(queue.jobs.len()),
+        };
+        queue.jobs.insert(insert_at, job);
+    }
+    save_queue();
+    notify(app_handle);
+    Ok(())
+}
+
+/// 删除已结束的任务记录，返回删除的数量
+pub fn clear_finished(app_handle: &AppHandle) -> usize {
+    let removed = {
+        let mut queue = QUEUE.lock();
+        let before = queue.jobs.len();
+        queue.jobs.retain(|j| !is_finished(&j.status));
+        before - queue.jobs.len()
+    };
+    save_queue();
+    notify(app_handle);
+    removed
+}
+
+/// 暂停或继续队列：暂停后当前任务继续运行，之后的任务不再启动
+pub fn set_paused(app_handle: &AppHandle, paused: bool) {
+    QUEUE.lock().paused = paused;
+    save_queue();
+    notify(app_handle);
+    if !paused {
+        start_worker(app_handle);
+    }
+}
+
+/// 启动执行队列的后台任务（已在运行时不重复启动）
+pub fn start_worker(app_handle: &AppHandle) {
+    if WORKER_RUNNING
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        run_worker(&app_handle).await;
+    });
+}
+
+/// 依次执行等待中的任务，没有可执行的任务或队列暂停时退出
+async fn run_worker(app_handle: &AppHandle) {
+    use crate::logger;
+    use crate::question_analyzer;
+
+    loop {
+        let job = {
+            let mut queue = QUEUE.lock();
+            let next = if queue.paused {
+                None
+            } else {
+                queue.jobs.iter_mut().find(|j| j.status == "queued")
+            };
+            match next {
+                Some(job) => {
+                    job.status = "running".to_string();
+                    job.started_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                    job.clone()
+                }
+                None => {
+                    // 持有队列锁时清除标记，之后加入的任务会重新启动后台任务
+                    WORKER_RUNNING.store(false, Ordering::SeqCst);
+                    return;
+                }
+            }
+        };
+        save_queue();
+        notify(app_handle);
+
+        logger::info("ai", &format!("分析队列：开始分析 {}（{}）", job.file_name, job.file_id));
+        let resume = job.resume
+            && matches!(question_analyzer::get_analysis_checkpoint(app_handle, &job.file_id), Ok(Some(_)));
+        let result = if question_analyzer::is_analyzing(&job.file_id) {
+            Err(anyhow!("该文件正在分析中"))
+        } else if resume {
+            question_analyzer::resume_analysis(app_handle, &job.file_id).await
+        } else {
+            question_analyzer::start_analysis(app_handle, &job.file_id).await
+        };
+        let stopped = question_analyzer::get_analysis_progress(app_handle, &job.file_id)
+            .await
+            .map(|p| p.status == "stopped")
+            .unwrap_or(false);
+
+        {
+            let mut queue = QUEUE.lock();
+            if let Some(entry) = queue.jobs.iter_mut().find(|j| j.job_id == job.job_id) {
+                entry.finished_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                entry.resume = false;
+                match &result {
+                    Err(e) => {
+                        entry.error = e.to_string();
+                        if entry.status == "running" {
+                            entry.status = "failed".to_string();
+                        }
+                    }
+                    // 已取消的任务保持 cancelled
+                    Ok(()) if entry.status == "running" => {
+                        entry.status = if stopped { "stopped" } else { "completed" }.to_string();
+                    }
+                    Ok(()) => {}
+                }
+            }
+        }
+        match &result {
+            Ok(()) => logger::info("ai", &format!("分析队列：{} 分析结束", job.file_name)),
+            Err(e) => logger::error("ai", &format!("分析队列：{} 分析失败: {}", job.file_name, e)),
+        }
+        save_queue();
+        notify(app_handle);
+    }
+}
+
+/// 推送队列状态变化
+fn notify(app_handle: &AppHandle) {
+    let _ = app_handle.emit_all("analysis-queue-updated", get_status());
+}
+
+fn save_queue() {
+    use crate::logger;
+
+    let path = match QUEUE_PATH.lock().clone() {
+        Some(path) => path,
+        None => return,
+    };
+    // 写入完成前一直持有队列锁：同时保存时不会共用临时文件，也不会用较早的状态覆盖较新的状态
+    let queue = QUEUE.lock();
+    let result = serde_json::to_string_pretty(&*queue)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| {
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(&tmp_path, content)?;
+            fs::rename(&tmp_path, &path)?;
+            Ok(())
+        });
+    drop(queue);
+    if let Err(e) = result {
+        logger::warn("ai", &format!("保存分析队列失败: {}", e));
+    }
+}
@@ -0,0 +1,328 @@
+// Anki 导出模块 - 将题目导出为 Anki 卡组（.apkg），在 Anki 中复习
+// 正面为题干（含选项和插图），背面为答案和解析；章节、知识点作为标签；公式转换为 MathJax 分隔符
+// .apkg 是 zip 包：collection.anki2（SQLite 数据库）、media（媒体文件名映射）和编号的媒体文件
+
+use crate::commands::Question;
+use crate::question_images::{image_files, strip_image_links};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 笔记类型 ID 固定，重复导入时 Anki 沿用同一个笔记类型
+const MODEL_ID: i64 = 1_716_000_000_001;
+
+// 行间公式 $$...$$ 和行内公式 $...$（Anki 的 MathJax 使用 \[...\] 和 \(...\)）
+static DISPLAY_MATH_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)\$\$(.+?)\$\$").unwrap());
+static INLINE_MATH_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$([^$\n]+?)\$").unwrap());
+
+static HTML_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+const CARD_CSS: &str = ".card { font-family: arial, sans-serif; font-size: 18px; text-align: left; color: black; background-color: white; }
+.options { list-style: none; padding-left: 0; }
+.analysis { margin-top: 12px; color: #555; }
+img { max-width: 100%; }";
+
+// Anki 数据库结构（2.1 兼容的 schema 11）
+const SCHEMA: &str = r#"
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null, scm integer not null,
+    ver integer not null, dty integer not null, usn integer not null, ls integer not null,
+    conf text not null, models text not null, decks text not null, dconf text not null, tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null, mod integer not null,
+    usn integer not null, tags text not null, flds text not null, sfld integer not null,
+    csum integer not null, flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null, ord integer not null,
+    mod integer not null, usn integer not null, type integer not null, queue integer not null,
+    due integer not null, ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null, odid integer not null,
+    flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null, ease integer not null,
+    ivl integer not null, lastIvl integer not null, factor integer not null, time integer not null,
+    type integer not null
+);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_usn on notes (usn);
+CREATE INDEX ix_cards_usn on cards (usn);
+CREATE INDEX ix_revlog_usn on revlog (usn);
+CREATE INDEX ix_cards_nid on cards (nid);
+CREATE INDEX ix_cards_sched on cards (did, queue, due);
+CREATE INDEX ix_revlog_cid on revlog (cid);
+CREATE INDEX ix_notes_csum on notes (csum);
+"#;
+
+/// 要导出的题目，base_dir 为题目插图路径的基准目录（文件存储目录）
+pub struct AnkiNote<'a> {
+    pub question: &'a Question,
+    pub base_dir: PathBuf,
+}
+
+/// 生成 .apkg 文件，返回导出的卡片数
+pub fn write_apkg(dest_path: &Path, deck_name: &str, notes: &[AnkiNote]) -> Result<usize> {
+    let temp_dir = std::env::temp_dir().join(format!("booq_anki_{}", uuid::Uuid::new_v4().simple()));
+    fs::create_dir_all(&temp_dir)?;
+    let result = build_package(&temp_dir, dest_path, deck_name, notes);
+    let _ = fs::remove_dir_all(&temp_dir);
+    result
+}
+
+fn build_package(temp_dir: &Path, dest_path: &Path, deck_name: &str, notes: &[AnkiNote]) -> Result<usize> {
+    let now = chrono::Local::now();
+    let now_secs = now.timestamp();
+    let now_millis = now.timestamp_millis();
+    let deck_id = now_millis;
+
+    // 媒体文件：包内编号 -> (Anki 中的文件名, 源文件)
+    let mut media: Vec<(String, PathBuf)> = Vec::new();
+    let collection_path = temp_dir.join("collection.anki2");
+    let conn = Connection::open(&collection_path)?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?3, 11, 0, 0, 0, ?4, ?5, ?6, ?7, '{}')",
+        params![
+            now_secs,
+            now_millis,
+            now_millis,
+            collection_conf(deck_id).to_string(),
+            models_json(deck_id, now_secs).to_string(),
+            decks_json(deck_id, deck_name, now_secs).to_string(),
+            dconf_json().to_string(),
+        ],
+    )?;
+
+    for (index, note) in notes.iter().enumerate() {
+        let q = note.question;
+        let mut images = Vec::new();
+        for source in image_files(q, &note.base_dir) {
+            let name = source
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| format!("{}.png", media.len()));
+            images.push(name.clone());
+            media.push((name, source));
+        }
+
+        let front = front_html(q, &images);
+        let back = back_html(q);
+        let sort_field = HTML_TAG_RE.replace_all(&front, "").to_string();
+        let note_id = now_millis + index as i64;
+        conn.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ?5, ?6, ?7, ?8, 0, '')",
+            params![
+                note_id,
+                guid(&q.id),
+                MODEL_ID,
+                now_secs,
+                tags(q),
+                format!("{}\x1f{}", front, back),
+                sort_field,
+                checksum(&sort_field),
+            ],
+        )?;
+        conn.execute(
+            "INSERT INTO cards VALUES (?1, ?2, ?3, 0, ?4, -1, 0, 0, ?5, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+            params![note_id, note_id, deck_id, now_secs, index as i64 + 1],
+        )?;
+    }
+    conn.close().map_err(|(_, e)| e)?;
+
+    let file = fs::File::create(dest_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("collection.anki2", options)?;
+    zip.write_all(&fs::read(&collection_path)?)?;
+
+    let media_map: HashMap<String, String> = media
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (i.to_string(), name.clone()))
+        .collect();
+    zip.start_file("media", options)?;
+    zip.write_all(serde_json::to_string(&media_map)?.as_bytes())?;
+    for (i, (_, source)) in media.iter().enumerate() {
+        zip.start_file(i.to_string(), options)?;
+        zip.write_all(&fs::read(source)?)?;
+    }
+    zip.finish()?;
+    Ok(notes.len())
+}
+
+/// 正面：题干、选项和插图
+fn front_html(q: &Question, images: &[String]) -> String {
+    let text = strip_image_links(&q.question_text);
+    let mut html = markdown_to_html(&text);
+    if !q.options.is_empty() {
+        html.push_str("<ul class=\"options\">");
+        for option in &q.options {
+            html.push_str(&format!("<li>{}. {}</li>", option.label, markdown_to_html(&option.text)));
+        }
+        html.push_str("</ul>");
+    }
+    for image in images {
+        html.push_str(&format!("<div><img src=\"{}\"></div>", escape_html(image)));
+    }
+    html
+}
+
+/// 背面：答案（选择题先给出正确选项）和解析
+fn back_html(q: &Question) -> String {
+    let mut html = String::new();
+    let labels = q.correct_options.join("");
+    if !labels.is_empty() && !q.answer.trim_start().starts_with(&labels) {
+        html.push_str(&format!("<div><b>{}</b></div>", labels));
+    }
+    html.push_str(&markdown_to_html(&q.answer));
+    if !q.analysis.trim().is_empty() {
+        html.push_str(&format!(
+            "<div class=\"analysis\"><b>解析</b><br>{}</div>",
+            markdown_to_html(&q.analysis)
+        ));
+    }
+    html
+}
+
+/// 转义 HTML，公式改用 MathJax 分隔符，换行改为 <br>（Moodle、QTI 导出也使用）
+pub fn markdown_to_html(text: &str) -> String {
+    let escaped = escape_html(text.trim());
+    let display = DISPLAY_MATH_RE.replace_all(&escaped, r"\[$1\]");
+    let inline = INLINE_MATH_RE.replace_all(&display, r"\($1\)");
+    inline.replace('\n', "<br>")
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// 标签：章节、小节和知识点（Anki 标签不能包含空格）
+fn tags(q: &Question) -> String {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in [&q.chapter, &q.section].into_iter().chain(q.knowledge_points.iter()) {
+        let tag: String = tag.split_whitespace().collect::<Vec<_>>().join("_");
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    if tags.is_empty() {
+        String::new()
+    } else {
+        format!(" {} ", tags.join(" "))
+    }
+}
+
+/// 由题目 ID 生成固定的 guid，重复导入时 Anki 更新同一条笔记
+fn guid(question_id: &str) -> String {
+    hex::encode(&Sha1::digest(format!("booq:{}", question_id).as_bytes())[..8])
+}
+
+/// 排序字段的校验和（SHA-1 前 8 位十六进制），Anki 用来查找重复笔记
+fn checksum(sort_field: &str) -> i64 {
+    let digest = Sha1::digest(sort_field.as_bytes());
+    i64::from(u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]))
+}
+
+fn collection_conf(deck_id: i64) -> serde_json::Value {
+    json!({
+        "activeDecks": [deck_id],
+        "curDeck": deck_id,
+        "newSpread": 0,
+        "collapseTime": 1200,
+        "timeLim": 0,
+        "estTimes": true,
+        "dueCounts": true,
+        "curModel": MODEL_ID.to_string(),
+        "nextPos": 1,
+        "sortType": "noteFld",
+        "sortBackwards": false,
+        "addToCur": true
+    })
+}
+
+fn models_json(deck_id: i64, now_secs: i64) -> serde_json::Value {
+    let field = |name: &str, ord: u32| {
+        json!({"name": name, "ord": ord, "sticky": false, "rtl": false, "font": "Arial", "size": 20, "media": []})
+    };
+    json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID,
+            "name": "BooQ 题目",
+            "type": 0,
+            "mod": now_secs,
+            "usn": -1,
+            "sortf": 0,
+            "did": deck_id,
+            "tmpls": [{
+                "name": "Card 1",
+                "ord": 0,
+                "qfmt": "{{Front}}",
+                "afmt": "{{FrontSide}}<hr id=answer>{{Back}}",
+                "did": null,
+                "bqfmt": "",
+                "bafmt": ""
+            }],
+            "flds": [field("Front", 0), field("Back", 1)],
+            "css": CARD_CSS,
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "latexsvg": false,
+            "req": [[0, "any", [0]]],
+            "tags": [],
+            "vers": []
+        }
+    })
+}
+
+fn decks_json(deck_id: i64, deck_name: &str, now_secs: i64) -> serde_json::Value {
+    let deck = |id: i64, name: &str| {
+        json!({
+            "id": id,
+            "name": name,
+            "mod": now_secs,
+            "usn": -1,
+            "lrnToday": [0, 0],
+            "revToday": [0, 0],
+            "newToday": [0, 0],
+            "timeToday": [0, 0],
+            "collapsed": false,
+            "desc": "",
+            "dyn": 0,
+            "conf": 1,
+            "extendNew": 10,
+            "extendRev": 50
+        })
+    };
+    json!({
+        "1": deck(1, "Default"),
+        deck_id.to_string(): deck(deck_id, deck_name),
+    })
+}
+
+fn dconf_json() -> serde_json::Value {
+    json!({
+        "1": {
+            "id": 1,
+            "name": "Default",
+            "mod": 0,
+            "usn": 0,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {"bury": true, "delays": [1, 10], "initialFactor": 2500, "ints": [1, 4, 7], "order": 1, "perDay": 20, "separate": true},
+            "lapse": {"delays": [10], "leechAction": 0, "leechFails": 8, "minInt": 1, "mult": 0},
+            "rev": {"bury": true, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1, "maxIvl": 36500, "minSpace": 1, "perDay": 100}
+        }
+    })
+}
@@ -0,0 +1,310 @@
+// 本地 HTTP 接口模块 - 在 127.0.0.1 上提供题库和检索接口，供 LMS 插件、脚本等其他工具读取 BooQ 的分析结果
+// 所有接口（/api/health 除外）都需要令牌：Authorization: Bearer <token> 或 X-BooQ-Token: <token>
+// 默认只读；开启 allow_write 后可以修改题目和审核状态
+//
+// GET  /api/health                              服务状态和版本
+// GET  /api/files                               文件列表
+// GET  /api/files/{file_id}                     文件信息
+// GET  /api/files/{file_id}/questions           文件中的全部题目
+// GET  /api/questions/search?q=&file_id=&question_type=&chapter=&knowledge_point=&review_status=&max_confidence=&page=&page_size=
+// GET  /api/documents/search?q=&limit=          全文检索页面内容
+// GET  /api/banks                               组合题库列表
+// GET  /api/banks/{bank_id}                     组合题库内容
+// PUT  /api/files/{file_id}/questions/{id}      修改题目（需要 allow_write）
+// POST /api/files/{file_id}/review              批量设置审核状态 {"question_ids": [...], "status": "approved"}（需要 allow_write）
+
+use crate::commands::{ApiServerConfig, Question, QuestionFilter};
+use crate::utils::is_safe_id;
+use anyhow::{anyhow, Result};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+pub const DEFAULT_PORT: u16 = 17321;
+
+// 请求体大小上限
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+struct RunningServer {
+    config: ApiServerConfig,
+    shutdown: oneshot::Sender<()>,
+}
+
+static SERVER: Lazy<Mutex<Option<RunningServer>>> = Lazy::new(|| Mutex::new(None));
+
+/// 接口服务状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiServerStatus {
+    pub running: bool,
+    pub address: String, // 例如 http://127.0.0.1:17321
+    pub allow_write: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewRequest {
+    question_ids: Vec<String>,
+    status: String,
+}
+
+/// 生成随机令牌
+pub fn generate_token() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+/// 按配置启动、重启或停止接口服务；配置没有变化时不做任何事
+pub fn apply(app_handle: &AppHandle, config: &ApiServerConfig) -> Result<()> {
+    use crate::logger;
+
+    let mut server = SERVER.lock();
+    if let Some(running) = server.as_ref() {
+        if config.enabled && same_config(&running.config, config) {
+            return Ok(());
+        }
+    }
+    if let Some(running) = server.take() {
+        running.shutdown.send(()).ok();
+        logger::info("system", "本地接口服务已停止");
+    }
+    if !config.enabled {
+        return Ok(());
+    }
+    if config.token.trim().is_empty() {
+        return Err(anyhow!("请先设置接口令牌"));
+    }
+
+    let port = if config.port == 0 { DEFAULT_PORT } else { config.port };
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        .map_err(|e| anyhow!("无法监听端口 {}: {}", port, e))?;
+    listener.set_nonblocking(true)?;
+
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let app = app_handle.clone();
+    let settings = config.clone();
+    tauri::async_runtime::spawn(async move {
+        let make_svc = make_service_fn(move |_conn| {
+            let app = app.clone();
+            let settings = settings.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let app = app.clone();
+                    let settings = settings.clone();
+                    async move { Ok::<_, Infallible>(handle(&app, &settings, req).await) }
+                }))
+            }
+        });
+        let server = match Server::from_tcp(listener) {
+            Ok(builder) => builder.serve(make_svc),
+            Err(e) => {
+                logger::warn("system", &format!("本地接口服务启动失败: {}", e));
+                return;
+            }
+        };
+        let graceful = server.with_graceful_shutdown(async {
+            shutdown_rx.await.ok();
+        });
+        if let Err(e) = graceful.await {
+            logger::warn("system", &format!("本地接口服务异常退出: {}", e));
+        }
+    });
+
+    logger::info(
+        "system",
+        &format!(
+            "本地接口服务已启动: http://127.0.0.1:{}（{}）",
+            port,
+            if config.allow_write { "可写" } else { "只读" }
+        ),
+    );
+    *server = Some(RunningServer {
+        config: config.clone(),
+        shutdown,
+    });
+    Ok(())
+}
+
+pub fn status() -> ApiServerStatus {
+    match SERVER.lock().as_ref() {
+        Some(running) => ApiServerStatus {
+            running: true,
+            address: format!(
+                "http://127.0.0.1:{}",
+                if running.config.port == 0 { DEFAULT_PORT } else { running.config.port }
+            ),
+            allow_write: running.config.allow_write,
+        },
+        None => ApiServerStatus::default(),
+    }
+}
+
+fn same_config(a: &ApiServerConfig, b: &ApiServerConfig) -> bool {
+    a.port == b.port && a.token == b.token && a.allow_write == b.allow_write
+}
+
+async fn handle(app_handle: &AppHandle, config: &ApiServerConfig, req: Request<Body>) -> Response<Body> {
+    let path = req.uri().path().trim_end_matches('/').to_string();
+    if req.method() == Method::GET && path == "/api/health" {
+        return json_response(
+            StatusCode::OK,
+            &serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        );
+    }
+    if !authorized(config, &req) {
+        return error_response(StatusCode::UNAUTHORIZED, "令牌无效");
+    }
+
+    let query: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|q| reqwest::Url::parse(&format!("http://localhost/?{}", q)))
+        .and_then(|url| url.ok())
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default();
+    let segments: Vec<String> = path.split('/').skip(1).map(str::to_string).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let method = req.method().clone();
+
+    let result = match (&method, segments.as_slice()) {
+        (&Method::GET, ["api", "files"]) => crate::file_manager::get_file_list(app_handle).await.and_then(to_json),
+        (&Method::GET, ["api", "files", id]) if is_safe_id(id) => {
+            crate::file_manager::get_file_info(app_handle, id).await.and_then(to_json)
+        }
+        (&Method::GET, ["api", "files", id, "questions"]) if is_safe_id(id) => {
+            crate::question_analyzer::get_questions(app_handle, id).await.and_then(to_json)
+        }
+        (&Method::GET, ["api", "questions", "search"]) => {
+            let filter = QuestionFilter {
+                question_type: query.get("question_type").cloned(),
+                chapter: query.get("chapter").cloned(),
+                knowledge_point: query.get("knowledge_point").cloned(),
+                max_confidence: query.get("max_confidence").and_then(|v| v.parse().ok()),
+                review_status: query.get("review_status").cloned(),
+            };
+            crate::question_analyzer::search_questions(
+                app_handle,
+                query.get("file_id").map(String::as_str).filter(|id| !id.is_empty()),
+                query.get("q").map(String::as_str).unwrap_or(""),
+                &filter,
+                query.get("page").and_then(|v| v.parse().ok()).unwrap_or(1),
+                query.get("page_size").and_then(|v| v.parse().ok()).unwrap_or(20),
+            )
+            .await
+            .and_then(to_json)
+        }
+        (&Method::GET, ["api", "documents", "search"]) => crate::database::search_documents(
+            &crate::file_manager::get_storage_root(app_handle),
+            query.get("q").map(String::as_str).unwrap_or(""),
+            query.get("limit").and_then(|v| v.parse().ok()).unwrap_or(50),
+        )
+        .and_then(to_json),
+        (&Method::GET, ["api", "banks"]) => to_json(crate::question_analyzer::get_banks(app_handle)),
+        (&Method::GET, ["api", "banks", id]) if is_safe_id(id) => {
+            crate::question_analyzer::get_bank(app_handle, id).and_then(to_json)
+        }
+        (&Method::PUT, ["api", "files", id, "questions", question_id]) if is_safe_id(id) => {
+            if !config.allow_write {
+                return error_response(StatusCode::FORBIDDEN, "接口为只读模式");
+            }
+            let question_id = question_id.to_string();
+            let id = id.to_string();
+            match read_json::<Question>(req).await {
+                Ok(mut question) => {
+                    question.id = question_id;
+                    crate::question_analyzer::update_question(app_handle, &id, question)
+                        .await
+                        .and_then(to_json)
+                }
+                Err(response) => return response,
+            }
+        }
+        (&Method::POST, ["api", "files", id, "review"]) if is_safe_id(id) => {
+            if !config.allow_write {
+                return error_response(StatusCode::FORBIDDEN, "接口为只读模式");
+            }
+            let id = id.to_string();
+            match read_json::<ReviewRequest>(req).await {
+                Ok(review) => crate::question_analyzer::set_review_status(
+                    app_handle,
+                    &id,
+                    &review.question_ids,
+                    &review.status,
+                )
+                .await
+                .and_then(|changed| to_json(serde_json::json!({ "changed": changed }))),
+                Err(response) => return response,
+            }
+        }
+        _ => return error_response(StatusCode::NOT_FOUND, "接口不存在"),
+    };
+
+    match result {
+        Ok(value) => json_response(StatusCode::OK, &value),
+        Err(e) => {
+            let message = e.to_string();
+            let status = if message.contains("不存在") { StatusCode::NOT_FOUND } else { StatusCode::BAD_REQUEST };
+            error_response(status, &message)
+        }
+    }
+}
+
+/// 校验令牌（逐字节比较全部内容，避免按耗时猜测令牌）
+fn authorized(config: &ApiServerConfig, req: &Request<Body>) -> bool {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    let provided = header("x-booq-token")
+        .or_else(|| header("authorization").and_then(|v| v.strip_prefix("Bearer ")).map(str::trim))
+        .unwrap_or("");
+    let expected = config.token.trim();
+    if expected.is_empty() || provided.len() != expected.len() {
+        return false;
+    }
+    provided.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// 读取 JSON 请求体，失败时直接返回错误响应（超出大小上限为 413）
+async fn read_json<T: serde::de::DeserializeOwned>(req: Request<Body>) -> std::result::Result<T, Response<Body>> {
+    let too_large = || error_response(StatusCode::PAYLOAD_TOO_LARGE, "请求内容过大");
+    let declared_too_large = req
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok())
+        .is_some_and(|len| len > MAX_BODY_BYTES);
+    if declared_too_large {
+        return Err(too_large());
+    }
+    // 分块读取并累计长度，分块传输（没有 Content-Length）同样受上限约束
+    let mut body = req.into_body();
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| error_response(StatusCode::BAD_REQUEST, &e.to_string()))?;
+        if buf.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(too_large());
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&buf)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, &format!("请求内容不是有效的 JSON: {}", e)))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+fn json_response(status: StatusCode, value: &serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json; charset=utf-8")
+        .body(Body::from(value.to_string()))
+        .unwrap_or_default()
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &serde_json::json!({ "error": message }))
+}
@@ -0,0 +1,247 @@
+// 题库交换模块 - 以带版本号的 JSON 格式导出、导入题库，用于升级后保留题库和在用户之间交换题库
+// 文件格式：{ "format": "booq-question-bank", "schema_version": N, "exported_at", "app_version", "name", "questions": [...] }
+// 每道题为题目的 JSON 字段，另外附带 "image_data"（插图文件名和 base64 内容），导入时写回题库目录
+// 导入时先把旧版本的文件逐步迁移到当前版本，再逐题校验；没有版本信息的题目数组（all_questions.json）视为版本 0
+
+use crate::commands::Question;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FORMAT_NAME: &str = "booq-question-bank";
+
+/// 当前的格式版本，修改题目字段的含义或结构时递增，并在 migrate 中增加对应的迁移步骤
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 校验失败时最多列出的错误数
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// 导出文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankFile {
+    pub format: String,
+    pub schema_version: u32,
+    #[serde(default)]
+    pub exported_at: String,
+    #[serde(default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub name: String,
+    pub questions: Vec<ExchangeQuestion>,
+}
+
+/// 导出文件中的一道题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeQuestion {
+    #[serde(flatten)]
+    pub question: Question,
+    #[serde(default)]
+    pub image_data: Vec<ImageData>,
+}
+
+/// 随题目导出的插图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageData {
+    pub name: String,
+    pub data: String, // base64
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub name: String,
+    pub source_schema_version: u32,
+    pub imported: usize,
+    pub skipped_duplicates: usize, // 目标文件中已有的题目（ID 和题干都相同）
+    pub renamed_ids: usize,        // ID 与已有题目冲突、重新分配 ID 的题目
+}
+
+/// 生成导出文件，base_dir 返回题目插图路径的基准目录
+pub fn build(name: &str, questions: &[Question], base_dir: impl Fn(&Question) -> PathBuf) -> BankFile {
+    let questions = questions
+        .iter()
+        .map(|q| {
+            let dir = base_dir(q);
+            let image_data = q
+                .images
+                .iter()
+                .filter_map(|image| {
+                    let path = dir.join(image);
+                    let data = fs::read(&path).ok()?;
+                    Some(ImageData {
+                        name: path.file_name()?.to_string_lossy().to_string(),
+                        data: general_purpose::STANDARD.encode(data),
+                    })
+                })
+                .collect();
+            let mut question = q.clone();
+            // 插图路径在导入时重新生成
+            question.images = Vec::new();
+            ExchangeQuestion { question, image_data }
+        })
+        .collect();
+    BankFile {
+        format: FORMAT_NAME.to_string(),
+        schema_version: SCHEMA_VERSION,
+        exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        name: name.to_string(),
+        questions,
+    }
+}
+
+/// 读取导入文件：迁移到当前版本并校验，返回文件内容和原始版本
+pub fn read(path: &Path) -> Result<(BankFile, u32)> {
+    let value: Value =
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| anyhow!("不是有效的 JSON 文件: {}", e))?;
+    let (value, source_version) = migrate(value)?;
+    Ok((validate(value)?, source_version))
+}
+
+/// 逐步迁移到当前版本，返回迁移后的内容和原始版本
+pub fn migrate(value: Value) -> Result<(Value, u32)> {
+    let source_version = match &value {
+        Value::Array(_) => 0,
+        Value::Object(map) => {
+            if map.get("format").and_then(|f| f.as_str()) != Some(FORMAT_NAME) {
+                return Err(anyhow!("不是 BooQ 题库文件"));
+            }
+            map.get("schema_version")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| anyhow!("题库文件缺少 schema_version"))? as u32
+        }
+        _ => return Err(anyhow!("不是 BooQ 题库文件")),
+    };
+    if source_version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "题库文件版本为 {}，当前程序只支持到版本 {}，请升级 BooQ",
+            source_version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    let mut value = value;
+    for version in source_version..SCHEMA_VERSION {
+        value = match version {
+            0 => migrate_v0(value),
+            _ => value,
+        };
+    }
+    Ok((value, source_version))
+}
+
+/// 版本 0（题目数组）-> 版本 1：加上文件头，插图只有路径没有内容，导入后需要重新关联
+fn migrate_v0(value: Value) -> Value {
+    json!({
+        "format": FORMAT_NAME,
+        "schema_version": 1,
+        "name": "",
+        "questions": value,
+    })
+}
+
+/// 逐题校验，所有错误一起报告
+fn validate(value: Value) -> Result<BankFile> {
+    let mut map = match value {
+        Value::Object(map) => map,
+        _ => return Err(anyhow!("题库文件格式错误")),
+    };
+    let questions = match map.remove("questions") {
+        Some(Value::Array(questions)) => questions,
+        _ => return Err(anyhow!("题库文件缺少题目列表")),
+    };
+
+    let mut errors = Vec::new();
+    let mut parsed = Vec::new();
+    for (index, question) in questions.into_iter().enumerate() {
+        match serde_json::from_value::<ExchangeQuestion>(question) {
+            Ok(q) if q.question.question_text.trim().is_empty() => {
+                errors.push(format!("第 {} 道题: 题目内容为空", index + 1))
+            }
+            Ok(q) => {
+                if let Some(image) = q
+                    .image_data
+                    .iter()
+                    .find(|image| general_purpose::STANDARD.decode(&image.data).is_err())
+                {
+                    errors.push(format!("第 {} 道题: 插图 {} 的内容无法解码", index + 1, image.name));
+                } else {
+                    parsed.push(q);
+                }
+            }
+            Err(e) => errors.push(format!("第 {} 道题: {}", index + 1, e)),
+        }
+    }
+    if !errors.is_empty() {
+        let more = errors.len().saturating_sub(MAX_REPORTED_ERRORS);
+        let mut message = errors.into_iter().take(MAX_REPORTED_ERRORS).collect::<Vec<_>>().join("\n");
+        if more > 0 {
+            message.push_str(&format!("\n……另有 {} 处错误", more));
+        }
+        return Err(anyhow!("题库文件校验失败:\n{}", message));
+    }
+
+    Ok(BankFile {
+        format: FORMAT_NAME.to_string(),
+        schema_version: SCHEMA_VERSION,
+        exported_at: map.get("exported_at").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        app_version: map.get("app_version").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        name: map.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        questions: parsed,
+    })
+}
+
+/// 把导入的题目合并到文件的题目中：file_id 改为目标文件，ID 冲突时重新分配，插图写入 images_dir
+/// base_dir 为题目插图路径的基准目录，返回导入的题目数、跳过的重复题目数和重新分配 ID 的题目数
+pub fn merge_into(
+    existing: &mut Vec<Question>,
+    imported: Vec<ExchangeQuestion>,
+    file_id: &str,
+    images_dir: &Path,
+    base_dir: &Path,
+) -> Result<(usize, usize, usize)> {
+    let mut ids: HashSet<String> = existing.iter().map(|q| q.id.clone()).collect();
+    let (mut added, mut skipped, mut renamed) = (0, 0, 0);
+    for item in imported {
+        let mut q = item.question;
+        if existing.iter().any(|e| e.id == q.id && e.question_text == q.question_text) {
+            skipped += 1;
+            continue;
+        }
+        if q.id.is_empty() || ids.contains(&q.id) {
+            let suffix = uuid::Uuid::new_v4().simple().to_string();
+            q.id = format!("{}_{}_import_{}", file_id, q.page_number, &suffix[..8]);
+            renamed += 1;
+        }
+        q.file_id = file_id.to_string();
+
+        if !item.image_data.is_empty() {
+            q.images = Vec::new();
+            fs::create_dir_all(images_dir)?;
+        }
+        for (n, image) in item.image_data.iter().enumerate() {
+            let ext = Path::new(&image.name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric()))
+                .unwrap_or("jpg");
+            let dest = images_dir.join(format!("{}_{}.{}", q.id, n, ext));
+            fs::write(&dest, general_purpose::STANDARD.decode(&image.data)?)?;
+            let relative = dest.strip_prefix(base_dir).unwrap_or(&dest);
+            q.images.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+
+        ids.insert(q.id.clone());
+        let position = existing
+            .iter()
+            .position(|e| e.page_number > q.page_number)
+            .unwrap_or(existing.len());
+        existing.insert(position, q);
+        added += 1;
+    }
+    Ok((added, skipped, renamed))
+}
@@ -0,0 +1,316 @@
+// 章节结构模块 - 由目录页提取的章节树，为每页确定所属的章节和小节
+// 分析前先用 AI 读取目录和前几页建立章节树，再在页面标题中定位各章节的起始页，
+// 题目的章节、小节以此为准，不再依赖模型逐页猜测
+
+use crate::commands::Question;
+use crate::utils::normalize_text;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 一页中出现的章节标题达到这个数量时视为目录页，不用于定位
+const TOC_PAGE_MIN_TITLES: usize = 3;
+
+/// 作为标题识别的行的最大字符数（不含 Markdown 标记）
+const MAX_HEADING_CHARS: usize = 40;
+
+/// 章节树
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChapterTree {
+    #[serde(default)]
+    pub chapters: Vec<ChapterNode>,
+}
+
+/// 章
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChapterNode {
+    pub name: String,
+    #[serde(default)]
+    pub page: u32, // 目录中印刷的页码，未知时为 0
+    #[serde(default)]
+    pub start_page: u32, // 在正文中定位到的起始页（文件页码），未定位时为 0
+    #[serde(default)]
+    pub sections: Vec<SectionNode>,
+}
+
+/// 小节
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SectionNode {
+    pub name: String,
+    #[serde(default)]
+    pub page: u32,
+    #[serde(default)]
+    pub start_page: u32,
+    #[serde(default)]
+    pub knowledge_points: Vec<String>,
+}
+
+/// 章节目录视图中的节点（章或小节），带页码范围和题目数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChapterOutlineNode {
+    pub name: String,
+    pub level: String, // "chapter", "section"
+    pub start_page: Option<u32>,
+    pub end_page: Option<u32>,
+    pub question_count: u32,
+    pub example_count: u32,
+    pub exercise_count: u32,
+    #[serde(default)]
+    pub children: Vec<ChapterOutlineNode>,
+}
+
+impl ChapterOutlineNode {
+    fn new(name: &str, level: &str, start_page: Option<u32>, end_page: Option<u32>) -> Self {
+        Self {
+            name: name.to_string(),
+            level: level.to_string(),
+            start_page,
+            end_page,
+            ..Default::default()
+        }
+    }
+
+    fn count(&mut self, question: &Question) {
+        self.question_count += 1;
+        match question.question_type.as_str() {
+            "example" => self.example_count += 1,
+            "exercise" => self.exercise_count += 1,
+            _ => {}
+        }
+    }
+
+    /// 没有章节结构的节点按题目所在页确定页码范围
+    fn extend_pages(&mut self, page: u32) {
+        self.start_page = Some(self.start_page.map_or(page, |start| start.min(page)));
+        self.end_page = Some(self.end_page.map_or(page, |end| end.max(page)));
+    }
+}
+
+impl ChapterTree {
+    /// 解析模型返回的章节结构，去掉名称为空的项
+    pub fn parse(response: &str) -> Result<Self> {
+        let mut tree: ChapterTree = crate::json_repair::parse_relaxed(response)?;
+        tree.chapters.retain(|c| !c.name.trim().is_empty());
+        for chapter in &mut tree.chapters {
+            chapter.name = chapter.name.trim().to_string();
+            chapter.sections.retain(|s| !s.name.trim().is_empty());
+            for section in &mut chapter.sections {
+                section.name = section.name.trim().to_string();
+            }
+        }
+        Ok(tree)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chapters.is_empty()
+    }
+
+    /// 在一页的标题行中查找章节名称，记录首次出现的页码
+    /// 同时出现多个章节名称的页面视为目录页，跳过
+    pub fn locate_in_page(&mut self, page: u32, markdown: &str) {
+        let headings: Vec<String> = markdown
+            .lines()
+            .filter_map(heading_text)
+            .map(|line| normalize_text(&line))
+            .filter(|line| !line.is_empty())
+            .collect();
+        if headings.is_empty() {
+            return;
+        }
+
+        let matches = |name: &str| {
+            let name = normalize_text(name);
+            !name.is_empty() && headings.iter().any(|h| title_matches(h, &name))
+        };
+        let title_count = self
+            .chapters
+            .iter()
+            .flat_map(|c| std::iter::once(&c.name).chain(c.sections.iter().map(|s| &s.name)))
+            .filter(|name| matches(name))
+            .count();
+        if title_count >= TOC_PAGE_MIN_TITLES {
+            return;
+        }
+
+        for chapter in &mut self.chapters {
+            if chapter.start_page == 0 && matches(&chapter.name) {
+                chapter.start_page = page;
+            }
+            for section in &mut chapter.sections {
+                if section.start_page == 0 && matches(&section.name) {
+                    section.start_page = page;
+                }
+            }
+        }
+    }
+
+    /// 查找页面所属的章节和小节（小节未知时为空字符串）
+    pub fn lookup(&self, page: u32) -> Option<(String, String)> {
+        let offset = self.page_offset();
+        let chapter = self
+            .chapters
+            .iter()
+            .filter_map(|c| effective_start(c.start_page, c.page, offset).map(|start| (start, c)))
+            .filter(|(start, _)| *start <= page)
+            .max_by_key(|(start, _)| *start)
+            .map(|(_, c)| c)?;
+        let section = chapter
+            .sections
+            .iter()
+            .filter_map(|s| effective_start(s.start_page, s.page, offset).map(|start| (start, s)))
+            .filter(|(start, _)| *start <= page)
+            .max_by_key(|(start, _)| *start)
+            .map(|(_, s)| s.name.clone())
+            .unwrap_or_default();
+        Some((chapter.name.clone(), section))
+    }
+
+    /// 生成章节目录视图：各章、小节的页码范围（到下一章节开始前一页）和题目数
+    /// 题目的章节不在结构中时（没有章节结构或模型给出的章节）按题目中的章节名称归入额外的节点
+    pub fn outline(&self, total_pages: u32, questions: &[Question]) -> Vec<ChapterOutlineNode> {
+        let offset = self.page_offset();
+        let starts: Vec<Option<u32>> = self
+            .chapters
+            .iter()
+            .map(|c| effective_start(c.start_page, c.page, offset))
+            .collect();
+        // 下一个已知起始页的前一页为结束页，最后一章到文件末尾
+        let end_before = |starts: &[Option<u32>], index: usize, last: Option<u32>| {
+            starts[index]?;
+            starts[index + 1..]
+                .iter()
+                .flatten()
+                .find(|&&next| next > starts[index].unwrap_or(0))
+                .map(|next| next - 1)
+                .or(last)
+        };
+
+        let mut nodes: Vec<ChapterOutlineNode> = self
+            .chapters
+            .iter()
+            .enumerate()
+            .map(|(i, chapter)| {
+                let end = end_before(&starts, i, (total_pages > 0).then_some(total_pages));
+                let mut node = ChapterOutlineNode::new(&chapter.name, "chapter", starts[i], end);
+                let section_starts: Vec<Option<u32>> = chapter
+                    .sections
+                    .iter()
+                    .map(|s| effective_start(s.start_page, s.page, offset))
+                    .collect();
+                node.children = chapter
+                    .sections
+                    .iter()
+                    .enumerate()
+                    .map(|(j, section)| {
+                        let end = end_before(&section_starts, j, end);
+                        ChapterOutlineNode::new(&section.name, "section", section_starts[j], end)
+                    })
+                    .collect();
+                node
+            })
+            .collect();
+
+        let structured = nodes.len();
+        for q in questions {
+            let index = match nodes.iter().position(|n| n.name == q.chapter) {
+                Some(index) => index,
+                None => {
+                    let name = if q.chapter.trim().is_empty() { "未分章节" } else { q.chapter.as_str() };
+                    match nodes[structured..].iter().position(|n| n.name == name) {
+                        Some(index) => structured + index,
+                        None => {
+                            nodes.push(ChapterOutlineNode::new(name, "chapter", None, None));
+                            nodes.len() - 1
+                        }
+                    }
+                }
+            };
+            let from_structure = index < structured;
+            let node = &mut nodes[index];
+            node.count(q);
+            if !from_structure {
+                node.extend_pages(q.page_number);
+            }
+            if q.section.trim().is_empty() {
+                continue;
+            }
+            let child = match node.children.iter().position(|c| c.name == q.section) {
+                Some(child) => child,
+                None => {
+                    node.children.push(ChapterOutlineNode::new(&q.section, "section", None, None));
+                    node.children.len() - 1
+                }
+            };
+            // 结构中的小节使用结构中的页码，其余按题目所在页确定
+            let structured_sections = if from_structure { self.chapters[index].sections.len() } else { 0 };
+            let derived = child >= structured_sections;
+            let child = &mut node.children[child];
+            child.count(q);
+            if derived {
+                child.extend_pages(q.page_number);
+            }
+        }
+        nodes
+    }
+
+    /// 印刷页码与文件页码的差值：取已定位章节的中位数，用来估算未定位章节的起始页
+    fn page_offset(&self) -> Option<i64> {
+        let mut offsets: Vec<i64> = self
+            .chapters
+            .iter()
+            .flat_map(|c| std::iter::once((c.start_page, c.page)).chain(c.sections.iter().map(|s| (s.start_page, s.page))))
+            .filter(|(start, page)| *start > 0 && *page > 0)
+            .map(|(start, page)| start as i64 - page as i64)
+            .collect();
+        if offsets.is_empty() {
+            return None;
+        }
+        offsets.sort_unstable();
+        Some(offsets[offsets.len() / 2])
+    }
+}
+
+/// 读取保存的章节结构
+pub fn load(path: &Path) -> Option<ChapterTree> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 保存章节结构
+pub fn save(path: &Path, tree: &ChapterTree) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(tree)?)?;
+    Ok(())
+}
+
+/// 已定位时使用定位的页码，否则按印刷页码加偏移估算
+fn effective_start(start_page: u32, page: u32, offset: Option<i64>) -> Option<u32> {
+    if start_page > 0 {
+        return Some(start_page);
+    }
+    let estimated = page as i64 + offset?;
+    (page > 0 && estimated > 0).then_some(estimated as u32)
+}
+
+/// 取可能是标题的行：Markdown 标题，或较短的单独一行
+fn heading_text(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let text = line.trim_start_matches('#').trim();
+    if line.starts_with('#') || text.chars().count() <= MAX_HEADING_CHARS {
+        Some(text.to_string())
+    } else {
+        None
+    }
+}
+
+/// 标题行与章节名称一致，或以章节名称开头/结尾（OCR 结果中常带有页码、编号）
+fn title_matches(heading: &str, name: &str) -> bool {
+    heading == name
+        || (heading.len() <= name.len() * 2 && (heading.starts_with(name) || heading.ends_with(name)))
+}
@@ -1,6 +1,6 @@
 // Tauri 命令处理模块
 
-use crate::{config, file_manager, ocr_service, question_analyzer};
+use crate::{config, database, file_manager, ocr_service, question_analyzer};
 use serde::{Deserialize, Serialize};
 
 // ==================== 数据结构定义 ====================
@@ -39,17 +39,177 @@ pub struct Question {
     pub analysis: String,
     pub page_number: u32,
     pub has_original_answer: bool,
+    // 生成该题目的模型
+    #[serde(default)]
+    pub model: String,
+    // 解答时参考的教材内容来源（习题）
+    #[serde(default)]
+    pub references: Vec<crate::rag_service::Citation>,
+    // 题目插图，相对于文件存储目录的路径
+    #[serde(default)]
+    pub images: Vec<String>,
+    // 模型给出的识别置信度（0~1），手动添加或旧数据为空
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    // 选择题的选项和正确选项（非选择题为空）
+    #[serde(default)]
+    pub options: Vec<crate::question_options::ChoiceOption>,
+    #[serde(default)]
+    pub correct_options: Vec<String>,
+    // 难度："easy", "medium", "hard"，未知时为空
+    #[serde(default)]
+    pub difficulty: String,
+    // 人工审核状态："pending", "approved", "rejected"
+    #[serde(default = "default_review_status")]
+    pub review_status: String,
+    // 题目译文，键为目标语言代码（例如 "en"），原文保持不变
+    #[serde(default)]
+    pub translations: std::collections::BTreeMap<String, crate::question_translation::QuestionTranslation>,
+}
+
+fn default_review_status() -> String {
+    "pending".to_string()
+}
+
+/// 题目筛选条件，为空的条件不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuestionFilter {
+    #[serde(default)]
+    pub question_type: Option<String>, // "example", "exercise"
+    #[serde(default)]
+    pub chapter: Option<String>,
+    #[serde(default)]
+    pub knowledge_point: Option<String>,
+    #[serde(default)]
+    pub max_confidence: Option<f32>, // 只返回置信度不高于该值的题目，按置信度从低到高排序
+    #[serde(default)]
+    pub review_status: Option<String>, // "pending", "approved", "rejected"
+}
+
+/// 一页题目检索结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuestionPage {
+    pub items: Vec<Question>,
+    pub total: usize,     // 符合条件的题目总数
+    pub page: usize,      // 从 1 开始
+    pub page_size: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisProgress {
     pub file_id: String,
-    pub status: String, // "idle", "analyzing", "completed", "error"
+    pub status: String, // "idle", "analyzing", "stopping", "stopped", "completed", "error"
     pub current_page: u32,
     pub total_pages: u32,
     pub current_step: String,
     pub questions_found: u32,
     pub message: String,
+    // 本次分析中模型响应解析失败的次数
+    #[serde(default)]
+    pub parse_failures: u32,
+    // 本次分析范围内已处理完的页数（并发分析时页面完成顺序不固定）
+    #[serde(default)]
+    pub pages_completed: u32,
+}
+
+/// 一次分析的记录，每次分析结束（完成或停止）时保存题目快照，可以回滚到该版本
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisRun {
+    pub run_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub status: String,      // "completed", "stopped"
+    pub models: Vec<String>, // 生成题目的模型
+    pub page_from: u32,
+    pub page_to: u32,
+    pub pages_completed: u32,
+    pub question_count: u32, // 本次分析识别的题目数
+    pub total_questions: u32, // 合并后题库中的题目数
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+    pub has_snapshot: bool, // 快照超出保留数量被清理后为 false
+}
+
+/// 分析进度检查点：记录已完成的页面，中途退出后可以继续分析
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisCheckpoint {
+    pub run_id: String,
+    pub total_pages: u32,
+    pub completed_pages: Vec<u32>,
+    pub updated_at: String,
+    // 只分析部分页面时的页码范围，为 0 时分析全部页面
+    #[serde(default)]
+    pub page_from: u32,
+    #[serde(default)]
+    pub page_to: u32,
+    // 中途停止时正在处理的页码，为 0 表示没有停止过（程序异常退出）
+    #[serde(default)]
+    pub stopped_at_page: u32,
+    // 只分析范围内的这些页面（重试失败页面时），为空时分析范围内全部页面
+    #[serde(default)]
+    pub pages: Vec<u32>,
+}
+
+/// 分析失败的页面，可以只重试这些页面
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedPage {
+    pub page: u32,
+    pub stage: String, // "ocr", "examples", "exercises"
+    pub kind: String,  // "ocr_error", "request_error", "parse_error"
+    pub error: String,
+    pub failed_at: String,
+}
+
+/// 作答评分结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradeResult {
+    pub question_id: String,
+    pub score: f64,      // 百分制
+    pub verdict: String, // "correct", "partially_correct", "incorrect"
+    pub rubric: Vec<RubricItem>,
+    pub feedback: String,
+    pub model: String,
+}
+
+/// 评分项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RubricItem {
+    pub criterion: String,
+    pub points: f64,
+    pub max_points: f64,
+    #[serde(default)]
+    pub comment: String,
+}
+
+/// 文档问答结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAnswer {
+    pub answer: String,
+    pub reasoning: Option<String>,
+    pub sources: Vec<crate::rag_service::Citation>, // 检索到的参考内容来源，与回答中的引用标记对应
+}
+
+/// 批量分析任务（OpenAI Batch 接口）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub file_id: String,
+    pub batch_id: String,
+    pub model_id: String,
+    pub model_name: String,
+    pub status: String, // 与接口返回的状态一致，结果导入后为 "imported"
+    pub total_requests: u64,
+    pub completed_requests: u64,
+    pub failed_requests: u64,
+    pub questions_found: u32,
+    pub created_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    // 各页习题请求使用的参考内容来源，导入结果时写入题目
+    #[serde(default)]
+    pub references: std::collections::HashMap<u32, Vec<crate::rag_service::Citation>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,10 +220,110 @@ pub struct ModelConfig {
     pub api_url: String,
     pub api_key: String,
     pub model_name: String,
+    // 每百万 token 的输入、输出单价，用于估算费用，0 表示不计费
+    #[serde(default)]
+    pub input_price: f64,
+    #[serde(default)]
+    pub output_price: f64,
+    // 该服务商每分钟允许的请求数，0 表示不限制
+    #[serde(default)]
+    pub requests_per_minute: u32,
+    // 附加的请求头，例如 one-api 等网关需要的 X-Org；设置 Authorization 时替代默认的 Bearer 认证
+    #[serde(default)]
+    pub headers: std::collections::HashMap<String, String>,
+    // 不认识的字段（由更新版本的应用写入），保存时原样写回
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 向量化模型配置，api_url 为空时不启用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingConfig {
+    pub api_url: String, // /embeddings 接口地址，也可以填写聊天接口地址
+    pub api_key: String,
+    pub model_name: String,
+    #[serde(default = "default_embedding_batch_size")]
+    pub batch_size: usize,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            api_url: String::new(),
+            api_key: String::new(),
+            model_name: String::new(),
+            batch_size: default_embedding_batch_size(),
+        }
+    }
+}
+
+fn default_embedding_batch_size() -> usize {
+    crate::ai_service::DEFAULT_EMBEDDING_BATCH_SIZE
+}
+
+/// 题库同步设置，provider 为空时不启用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub provider: String, // "webdav", "s3"
+    // WebDAV 地址（例如 https://dav.jianguoyun.com/dav），或 S3 服务地址（例如 https://s3.amazonaws.com、MinIO 地址）
+    #[serde(default)]
+    pub endpoint: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub bucket: String,
+    #[serde(default)]
+    pub region: String,
+    #[serde(default)]
+    pub access_key: String,
+    #[serde(default)]
+    pub secret_key: String,
+    // 远端目录（S3 为对象前缀），为空时使用 booq
+    #[serde(default)]
+    pub remote_dir: String,
+    // 同时同步配置（模型、提示词等，不包括存储路径和同步设置）
+    #[serde(default)]
+    pub include_config: bool,
+}
+
+/// 本地 HTTP 接口设置，只监听 127.0.0.1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiServerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_api_server_port")]
+    pub port: u16,
+    // 访问令牌，启用时为空则自动生成
+    #[serde(default)]
+    pub token: String,
+    // 允许通过接口修改题目和审核状态
+    #[serde(default)]
+    pub allow_write: bool,
+}
+
+impl Default for ApiServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_api_server_port(),
+            token: String::new(),
+            allow_write: false,
+        }
+    }
+}
+
+fn default_api_server_port() -> u16 {
+    crate::api_server::DEFAULT_PORT
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    // 配置格式版本，旧版本的配置在读取时升级，见 config::CONFIG_VERSION
+    #[serde(default)]
+    pub config_version: u32,
     pub storage_path: String,
     pub theme: String, // "light", "dark", "system"
     pub models: Vec<ModelConfig>,
@@ -79,6 +339,166 @@ pub struct AppConfig {
     pub paddle_ocr_url: String,
     #[serde(default)]
     pub paddle_ocr_token: String,
+    // MinerU 逐页转换模式：只转换正在阅读的页面，而不是整本书
+    #[serde(default)]
+    pub mineru_page_mode: bool,
+    // 同时运行的 MinerU 转换进程数上限
+    #[serde(default = "default_mineru_max_concurrent")]
+    pub mineru_max_concurrent: usize,
+    // MinerU 单次转换的超时时间（秒），0 表示不限制
+    #[serde(default = "default_mineru_timeout_secs")]
+    pub mineru_timeout_secs: u64,
+    // 自定义可执行文件路径（conda、便携版 Python 等），为空时自动检测
+    #[serde(default)]
+    pub python_path: String,
+    #[serde(default)]
+    pub pip_path: String,
+    #[serde(default)]
+    pub magic_pdf_path: String,
+    // 单独指定的 MinerU 模型目录，为空时使用存储路径下的 mineru_models
+    #[serde(default)]
+    pub mineru_models_dir: String,
+    // AI 请求失败（限流、网络错误等）时的最大重试次数
+    #[serde(default = "default_ai_max_retries")]
+    pub ai_max_retries: u32,
+    // 模型下载源："modelscope", "huggingface", "hf-mirror"
+    #[serde(default = "default_model_download_source")]
+    pub model_download_source: String,
+    // 分析模型出错时依次尝试的备用模型 ID
+    #[serde(default)]
+    pub analysis_fallback_models: Vec<String>,
+    // 分析时向支持图片输入的模型发送页面中的插图
+    #[serde(default)]
+    pub ai_send_page_images: bool,
+    // 访问大模型和 OCR 接口使用的代理，例如 http://127.0.0.1:7890 或 socks5://127.0.0.1:1080
+    #[serde(default)]
+    pub proxy_url: String,
+    // 不走代理的主机，逗号分隔
+    #[serde(default)]
+    pub no_proxy: String,
+    // 调试模式：在日志中记录 AI 请求和响应
+    #[serde(default)]
+    pub ai_debug_trace: bool,
+    // 向量化模型，用于 RAG 检索
+    #[serde(default)]
+    pub embedding: EmbeddingConfig,
+    // 缓存模型回复，重复分析同一页时直接使用
+    #[serde(default)]
+    pub ai_cache_enabled: bool,
+    // 缓存有效期（小时），0 表示永不过期
+    #[serde(default = "default_ai_cache_ttl_hours")]
+    pub ai_cache_ttl_hours: u64,
+    // 将推理模型的思考过程保存为题目解析
+    #[serde(default)]
+    pub store_reasoning_as_analysis: bool,
+    // 单次 AI 请求的超时时间（秒），0 表示使用默认值
+    #[serde(default)]
+    pub ai_request_timeout_secs: u64,
+    // 分析时同时进行的 AI 请求数
+    #[serde(default = "default_ai_max_parallel")]
+    pub ai_max_parallel: u32,
+    // 提示词语言："auto" 根据页面内容选择，"zh" 中文，"en" 英文
+    #[serde(default = "default_prompt_language")]
+    pub prompt_language: String,
+    // 全局知识库：收录所有已分析文件的内容，解答习题时可以引用其他教材
+    #[serde(default)]
+    pub global_knowledge_base: bool,
+    // 对 RAG 检索结果重新排序的模型 ID，为空时不重排序；建议选择便宜、快速的模型
+    #[serde(default)]
+    pub rerank_model: String,
+    // 检索前扩展查询词（同义词、公式名称、中英文术语）的模型 ID，为空时不扩展
+    #[serde(default)]
+    pub query_expansion_model: String,
+    // 练习、组卷和合并题库时只使用审核通过的题目
+    #[serde(default)]
+    pub approved_questions_only: bool,
+    // 导出 PDF 使用的 typst 可执行文件路径，为空时使用 PATH 中的 typst
+    #[serde(default)]
+    pub typst_path: String,
+    // 题库同步（WebDAV / S3）
+    #[serde(default)]
+    pub sync: SyncConfig,
+    // 本地 HTTP 接口
+    #[serde(default)]
+    pub api_server: ApiServerConfig,
+    // 不认识的字段（由更新版本的应用写入），保存时原样写回
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_mineru_max_concurrent() -> usize {
+    crate::mineru_queue::DEFAULT_MAX_CONCURRENT
+}
+
+fn default_mineru_timeout_secs() -> u64 {
+    crate::mineru_service::DEFAULT_CONVERSION_TIMEOUT_SECS
+}
+
+fn default_ai_max_retries() -> u32 {
+    crate::ai_service::DEFAULT_MAX_RETRIES
+}
+
+fn default_ai_cache_ttl_hours() -> u64 {
+    crate::ai_cache::DEFAULT_CACHE_TTL_HOURS
+}
+
+fn default_ai_max_parallel() -> u32 {
+    crate::ai_service::DEFAULT_MAX_PARALLEL_REQUESTS as u32
+}
+
+fn default_prompt_language() -> String {
+    "auto".to_string()
+}
+
+fn default_model_download_source() -> String {
+    "modelscope".to_string()
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            config_version: crate::config::CONFIG_VERSION,
+            storage_path: String::new(),
+            theme: "system".to_string(),
+            models: Vec::new(),
+            reading_model: String::new(),
+            analysis_model: String::new(),
+            solving_model: String::new(),
+            use_paddle_ocr: false,
+            mineru_installed: false,
+            paddle_ocr_url: String::new(),
+            paddle_ocr_token: String::new(),
+            mineru_page_mode: false,
+            mineru_max_concurrent: default_mineru_max_concurrent(),
+            mineru_timeout_secs: default_mineru_timeout_secs(),
+            python_path: String::new(),
+            pip_path: String::new(),
+            magic_pdf_path: String::new(),
+            mineru_models_dir: String::new(),
+            ai_max_retries: default_ai_max_retries(),
+            model_download_source: default_model_download_source(),
+            ai_max_parallel: default_ai_max_parallel(),
+            analysis_fallback_models: Vec::new(),
+            ai_send_page_images: false,
+            proxy_url: String::new(),
+            no_proxy: String::new(),
+            ai_debug_trace: false,
+            embedding: EmbeddingConfig::default(),
+            ai_cache_enabled: false,
+            ai_cache_ttl_hours: default_ai_cache_ttl_hours(),
+            store_reasoning_as_analysis: false,
+            ai_request_timeout_secs: 0,
+            prompt_language: default_prompt_language(),
+            global_knowledge_base: false,
+            rerank_model: String::new(),
+            query_expansion_model: String::new(),
+            approved_questions_only: false,
+            typst_path: String::new(),
+            sync: SyncConfig::default(),
+            api_server: ApiServerConfig::default(),
+            extra: serde_json::Map::new(),
+        }
+    }
 }
 
 // ==================== 文件管理命令 ====================
@@ -256,123 +676,1222 @@ pub async fn start_analysis(
         .map_err(|e| e.to_string())
 }
 
+/// 只分析指定页码范围（含两端），结果与已有题目合并
 #[tauri::command]
-pub async fn stop_analysis(
+pub async fn start_analysis_range(
     app_handle: tauri::AppHandle,
     file_id: String,
+    from: u32,
+    to: u32,
 ) -> Result<(), String> {
-    question_analyzer::stop_analysis(&app_handle, &file_id)
+    question_analyzer::start_analysis_range(&app_handle, &file_id, from, to)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 从上次中断的位置继续分析
 #[tauri::command]
-pub async fn get_analysis_progress(
+pub async fn resume_analysis(
     app_handle: tauri::AppHandle,
     file_id: String,
-) -> Result<AnalysisProgress, String> {
-    question_analyzer::get_analysis_progress(&app_handle, &file_id)
+) -> Result<(), String> {
+    question_analyzer::resume_analysis(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 获取上次分析中失败的页面
 #[tauri::command]
-pub async fn get_questions(
+pub async fn get_failed_pages(
     app_handle: tauri::AppHandle,
     file_id: String,
-) -> Result<Vec<Question>, String> {
-    question_analyzer::get_questions(&app_handle, &file_id)
+) -> Result<Vec<FailedPage>, String> {
+    question_analyzer::get_failed_pages(&app_handle, &file_id).map_err(|e| e.to_string())
+}
+
+/// 只重新分析失败的页面，结果与已有题目合并
+#[tauri::command]
+pub async fn retry_failed_pages(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<(), String> {
+    question_analyzer::retry_failed_pages(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 获取未完成的分析进度，没有时返回 null
 #[tauri::command]
-pub async fn get_question_detail(
+pub async fn get_analysis_checkpoint(
     app_handle: tauri::AppHandle,
     file_id: String,
-    question_id: String,
-) -> Result<Question, String> {
-    question_analyzer::get_question_detail(&app_handle, &file_id, &question_id)
+) -> Result<Option<AnalysisCheckpoint>, String> {
+    question_analyzer::get_analysis_checkpoint(&app_handle, &file_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stop_analysis(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<(), String> {
+    question_analyzer::stop_analysis(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())
 }
 
-// ==================== 配置命令 ====================
+/// 将文件加入分析队列，按加入顺序依次分析
+#[tauri::command]
+pub async fn enqueue_analysis(
+    app_handle: tauri::AppHandle,
+    file_ids: Vec<String>,
+) -> Result<Vec<crate::analysis_queue::AnalysisQueueJob>, String> {
+    let files = crate::file_manager::get_file_list(&app_handle)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut entries = Vec::with_capacity(file_ids.len());
+    for file_id in file_ids {
+        let file = files
+            .iter()
+            .find(|f| f.id == file_id)
+            .ok_or_else(|| format!("文件不存在: {}", file_id))?;
+        entries.push((file.id.clone(), file.display_name.clone()));
+    }
+    crate::analysis_queue::enqueue(&app_handle, &entries).map_err(|e| e.to_string())
+}
 
+/// 获取分析队列状态
 #[tauri::command]
-pub async fn get_config(app_handle: tauri::AppHandle) -> Result<AppConfig, String> {
-    config::get_config(&app_handle)
+pub fn get_analysis_queue() -> crate::analysis_queue::AnalysisQueueStatus {
+    crate::analysis_queue::get_status()
+}
+
+/// 取消队列中的任务（运行中的任务会停止分析）
+#[tauri::command]
+pub async fn cancel_queued_analysis(app_handle: tauri::AppHandle, job_id: String) -> Result<(), String> {
+    crate::analysis_queue::cancel(&app_handle, &job_id)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 调整等待中任务的顺序
 #[tauri::command]
-pub async fn save_config(
+pub fn move_queued_analysis(app_handle: tauri::AppHandle, job_id: String, position: usize) -> Result<(), String> {
+    crate::analysis_queue::move_job(&app_handle, &job_id, position).map_err(|e| e.to_string())
+}
+
+/// 删除已结束的队列任务记录
+#[tauri::command]
+pub fn clear_finished_analysis_jobs(app_handle: tauri::AppHandle) -> usize {
+    crate::analysis_queue::clear_finished(&app_handle)
+}
+
+/// 暂停或继续分析队列
+#[tauri::command]
+pub fn set_analysis_queue_paused(app_handle: tauri::AppHandle, paused: bool) {
+    crate::analysis_queue::set_paused(&app_handle, paused)
+}
+
+#[tauri::command]
+pub async fn get_analysis_progress(
     app_handle: tauri::AppHandle,
-    config_data: AppConfig,
-) -> Result<(), String> {
-    config::save_config(&app_handle, config_data)
+    file_id: String,
+) -> Result<AnalysisProgress, String> {
+    question_analyzer::get_analysis_progress(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 在文件的知识库中检索相关内容，结果中包含页码，用于定位概念的讲解位置
 #[tauri::command]
-pub async fn get_models(app_handle: tauri::AppHandle) -> Result<Vec<ModelConfig>, String> {
-    config::get_models(&app_handle)
+pub async fn search_knowledge(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    query: String,
+    top_k: Option<usize>,
+    filters: Option<crate::rag_service::SearchFilter>,
+) -> Result<Vec<crate::rag_service::SearchResult>, String> {
+    question_analyzer::search_knowledge(
+        &app_handle,
+        &file_id,
+        &query,
+        top_k.unwrap_or(10),
+        &filters.unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 重建文件的 RAG 索引，pages 为空时重建全部页面
+#[tauri::command]
+pub async fn rebuild_rag_index(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    pages: Option<Vec<u32>>,
+) -> Result<usize, String> {
+    question_analyzer::rebuild_rag_index(&app_handle, &file_id, pages)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 导出 RAG 索引到文件，file_id 为空时导出全局知识库，返回导出的文档数
 #[tauri::command]
-pub async fn add_model(
+pub async fn export_rag_index(
     app_handle: tauri::AppHandle,
-    model: ModelConfig,
-) -> Result<(), String> {
-    config::add_model(&app_handle, model)
-        .await
+    file_id: Option<String>,
+    dest_path: String,
+) -> Result<usize, String> {
+    question_analyzer::export_rag_index(&app_handle, file_id.as_deref(), std::path::Path::new(&dest_path))
         .map_err(|e| e.to_string())
 }
 
+/// 导入 RAG 索引，file_id 为空时导入全局知识库，返回新增的文档数
 #[tauri::command]
-pub async fn remove_model(
+pub async fn import_rag_index(
     app_handle: tauri::AppHandle,
-    model_id: String,
-) -> Result<(), String> {
-    config::remove_model(&app_handle, &model_id)
+    file_id: Option<String>,
+    src_path: String,
+) -> Result<usize, String> {
+    question_analyzer::import_rag_index(&app_handle, file_id.as_deref(), std::path::Path::new(&src_path))
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 压缩 RAG 索引，合并重复文档，file_id 为空时压缩全局知识库
 #[tauri::command]
-pub async fn set_storage_path(
+pub async fn compact_rag_index(
     app_handle: tauri::AppHandle,
-    path: String,
-) -> Result<(), String> {
-    config::set_storage_path(&app_handle, &path)
+    file_id: Option<String>,
+) -> Result<crate::rag_service::CompactResult, String> {
+    question_analyzer::compact_rag_index(&app_handle, file_id.as_deref()).map_err(|e| e.to_string())
+}
+
+/// 针对文档提问，回答内容通过 ask-document-delta 事件逐段推送
+#[tauri::command]
+pub async fn ask_document(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question: String,
+) -> Result<DocumentAnswer, String> {
+    use tauri::Manager;
+    
+    let emitter = app_handle.clone();
+    let event_file_id = file_id.clone();
+    question_analyzer::ask_document(&app_handle, &file_id, &question, move |delta| {
+        let _ = emitter.emit_all("ask-document-delta", serde_json::json!({
+            "file_id": event_file_id,
+            "delta": delta
+        }));
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 创建批量分析任务，并在后台定期查询状态，完成后自动导入结果
+#[tauri::command]
+pub async fn start_batch_analysis(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<BatchJob, String> {
+    let job = question_analyzer::start_batch_analysis(&app_handle, &file_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    question_analyzer::spawn_batch_polling(&app_handle, &file_id);
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn poll_batch_analysis(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<BatchJob, String> {
+    question_analyzer::poll_batch_analysis(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_storage_path(app_handle: tauri::AppHandle) -> Result<String, String> {
-    config::get_storage_path(&app_handle)
+pub async fn cancel_batch_analysis(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<BatchJob, String> {
+    question_analyzer::cancel_batch_analysis(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())
 }
 
-// ==================== 系统命令 ====================
+#[tauri::command]
+pub async fn get_batch_job(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<Option<BatchJob>, String> {
+    question_analyzer::get_batch_job(&app_handle, &file_id)
+        .await
+        .map_err(|e| e.to_string())
+}
 
 #[tauri::command]
-pub async fn test_model(
-    api_url: String,
-    api_key: String,
-    model_name: String,
-) -> Result<String, String> {
-    use crate::ai_service::AIService;
-    
-    let service = AIService::new(&api_url, &api_key, &model_name);
-    service
-        .test_connection()
+pub async fn get_questions(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<Vec<Question>, String> {
+    question_analyzer::get_questions(&app_handle, &file_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取文件的知识图谱（知识点和章节的共现关系），用于绘制概念图
+#[tauri::command]
+pub async fn get_knowledge_graph(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<crate::knowledge_graph::KnowledgeGraph, String> {
+    question_analyzer::get_knowledge_graph(&app_handle, &file_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_question_detail(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question_id: String,
+) -> Result<Question, String> {
+    question_analyzer::get_question_detail(&app_handle, &file_id, &question_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 按关键词和条件检索题目，file_id 为空时检索全部文件，结果分页返回
+#[tauri::command]
+pub async fn search_questions(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    query: String,
+    filters: Option<QuestionFilter>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<QuestionPage, String> {
+    question_analyzer::search_questions(
+        &app_handle,
+        file_id.as_deref(),
+        &query,
+        &filters.unwrap_or_default(),
+        page.unwrap_or(1),
+        page_size.unwrap_or(20),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 在所有文件已转换的页面中全文检索，返回文件、页码和匹配位置附近的内容
+#[tauri::command]
+pub async fn search_documents(
+    app_handle: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<database::DocumentHit>, String> {
+    database::search_documents(&file_manager::get_storage_root(&app_handle), &query, limit.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+/// 手动添加题目
+#[tauri::command]
+pub async fn create_question(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question: Question,
+) -> Result<Question, String> {
+    question_analyzer::create_question(&app_handle, &file_id, question)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 修改题目
+#[tauri::command]
+pub async fn update_question(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question: Question,
+) -> Result<Question, String> {
+    question_analyzer::update_question(&app_handle, &file_id, question)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 删除题目
+#[tauri::command]
+pub async fn delete_question(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question_id: String,
+) -> Result<(), String> {
+    question_analyzer::delete_question(&app_handle, &file_id, &question_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取文件的分析记录，最新的在前
+#[tauri::command]
+pub async fn get_analysis_runs(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<Vec<AnalysisRun>, String> {
+    question_analyzer::get_analysis_runs(&app_handle, &file_id).map_err(|e| e.to_string())
+}
+
+/// 将题目恢复为某次分析结束时的版本
+#[tauri::command]
+pub async fn rollback_questions(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    run_id: String,
+) -> Result<Vec<Question>, String> {
+    question_analyzer::rollback_questions(&app_handle, &file_id, &run_id).map_err(|e| e.to_string())
+}
+
+/// 查找近似重复的题目，include_other_files 为 true 时也与其他文件比较
+#[tauri::command]
+pub async fn find_duplicate_questions(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    include_other_files: bool,
+) -> Result<Vec<crate::question_dedup::DuplicateGroup>, String> {
+    question_analyzer::find_duplicate_questions(&app_handle, &file_id, include_other_files)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 合并用户确认的重复题目（find_duplicate_questions 返回的组，可去掉不需要合并的组或副本），返回删除的题目数
+#[tauri::command]
+pub async fn merge_duplicates(
+    app_handle: tauri::AppHandle,
+    groups: Vec<crate::question_dedup::DuplicateGroup>,
+) -> Result<usize, String> {
+    question_analyzer::merge_duplicate_questions(&app_handle, &groups)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 统计知识点出现次数，file_id 为空时统计全部文件
+#[tauri::command]
+pub async fn get_knowledge_points(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+) -> Result<Vec<crate::knowledge_points::KnowledgePointStat>, String> {
+    question_analyzer::get_knowledge_points(&app_handle, file_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 记录一次作答
+#[tauri::command]
+pub async fn record_attempt(
+    app_handle: tauri::AppHandle,
+    question_id: String,
+    correct: bool,
+    user_answer: String,
+) -> Result<crate::study_records::Attempt, String> {
+    question_analyzer::record_attempt(&app_handle, &question_id, correct, &user_answer)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取错题本，file_id 为空时包含全部文件
+#[tauri::command]
+pub async fn get_wrong_questions(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+) -> Result<Vec<crate::study_records::WrongQuestion>, String> {
+    question_analyzer::get_wrong_questions(&app_handle, file_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 按知识点统计掌握程度，file_id 为空时统计全部文件
+#[tauri::command]
+pub async fn get_knowledge_point_mastery(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+) -> Result<Vec<crate::study_records::KnowledgePointMastery>, String> {
+    question_analyzer::get_knowledge_point_mastery(&app_handle, file_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 开始练习：按条件抽题，file_id 为空时从整个题库抽题，count 为 0 时使用全部符合条件的题目
+#[tauri::command]
+pub async fn start_practice(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    count: usize,
+    shuffle: bool,
+) -> Result<crate::practice_session::PracticeSession, String> {
+    question_analyzer::start_practice(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        count,
+        shuffle,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 提交练习答案，非选择题未给出 correct 时由模型评分
+#[tauri::command]
+pub async fn submit_answer(
+    app_handle: tauri::AppHandle,
+    session_id: String,
+    question_id: String,
+    user_answer: String,
+    correct: Option<bool>,
+) -> Result<crate::practice_session::SessionAnswer, String> {
+    question_analyzer::submit_practice_answer(&app_handle, &session_id, &question_id, &user_answer, correct)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 结束练习并生成成绩报告
+#[tauri::command]
+pub async fn finish_session(
+    app_handle: tauri::AppHandle,
+    session_id: String,
+) -> Result<crate::practice_session::SessionReport, String> {
+    question_analyzer::finish_practice(&app_handle, &session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出练习会话
+#[tauri::command]
+pub fn get_practice_sessions(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+) -> Vec<crate::practice_session::SessionSummary> {
+    question_analyzer::get_practice_sessions(&app_handle, file_id.as_deref())
+}
+
+/// 获取练习会话详情
+#[tauri::command]
+pub async fn get_practice_session(
+    app_handle: tauri::AppHandle,
+    session_id: String,
+) -> Result<crate::practice_session::PracticeSession, String> {
+    question_analyzer::get_practice_session(&app_handle, &session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取某一页的题目
+#[tauri::command]
+pub fn get_questions_by_page(app_handle: tauri::AppHandle, file_id: String, page: u32) -> Result<Vec<Question>, String> {
+    question_analyzer::get_questions_by_page(&app_handle, &file_id, page).map_err(|e| e.to_string())
+}
+
+/// 获取章节目录（页码范围和题目数）
+#[tauri::command]
+pub async fn get_chapter_tree(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<Vec<crate::chapter_structure::ChapterOutlineNode>, String> {
+    question_analyzer::get_chapter_tree(&app_handle, &file_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 检查题目中的公式能否正常渲染，fix 为 true 时尝试自动整理
+#[tauri::command]
+pub async fn validate_latex(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    fix: bool,
+) -> Result<Vec<crate::latex_check::LatexReport>, String> {
+    question_analyzer::validate_latex(&app_handle, file_id.as_deref(), fix)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取题目的修改记录
+#[tauri::command]
+pub async fn get_question_history(
+    app_handle: tauri::AppHandle,
+    question_id: String,
+) -> Result<Vec<crate::question_history::QuestionEdit>, String> {
+    question_analyzer::get_question_history(&app_handle, &question_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 把题目恢复为某次修改之前的内容，返回恢复后的题目（题目被删除时为空）
+#[tauri::command]
+pub async fn revert_question(
+    app_handle: tauri::AppHandle,
+    question_id: String,
+    edit_id: String,
+) -> Result<Option<Question>, String> {
+    question_analyzer::revert_question(&app_handle, &question_id, &edit_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 批量通过审核
+#[tauri::command]
+pub async fn approve_questions(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question_ids: Vec<String>,
+) -> Result<usize, String> {
+    question_analyzer::set_review_status(&app_handle, &file_id, &question_ids, "approved")
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 批量驳回
+#[tauri::command]
+pub async fn reject_questions(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question_ids: Vec<String>,
+) -> Result<usize, String> {
+    question_analyzer::set_review_status(&app_handle, &file_id, &question_ids, "rejected")
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 合并多个文件的题目为组合题库，dedupe 为 true 时合并近似重复的题目
+#[tauri::command]
+pub async fn merge_banks(
+    app_handle: tauri::AppHandle,
+    file_ids: Vec<String>,
+    dedupe: bool,
+    name: Option<String>,
+) -> Result<crate::question_banks::QuestionBank, String> {
+    question_analyzer::merge_banks(&app_handle, &file_ids, dedupe, name.as_deref().unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出组合题库
+#[tauri::command]
+pub fn get_banks(app_handle: tauri::AppHandle) -> Vec<crate::question_banks::BankSummary> {
+    question_analyzer::get_banks(&app_handle)
+}
+
+/// 获取组合题库详情
+#[tauri::command]
+pub fn get_bank(app_handle: tauri::AppHandle, bank_id: String) -> Result<crate::question_banks::QuestionBank, String> {
+    question_analyzer::get_bank(&app_handle, &bank_id).map_err(|e| e.to_string())
+}
+
+/// 删除组合题库
+#[tauri::command]
+pub fn delete_bank(app_handle: tauri::AppHandle, bank_id: String) -> Result<(), String> {
+    question_analyzer::delete_bank(&app_handle, &bank_id).map_err(|e| e.to_string())
+}
+
+/// 导出 Anki 卡组（.apkg），file_id 为空时从全部文件中按条件导出
+#[tauri::command]
+pub async fn export_anki(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+    deck_name: Option<String>,
+) -> Result<usize, String> {
+    question_analyzer::export_anki(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+        deck_name.as_deref().unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出 Moodle XML 题库文件，file_id 为空时从全部文件中按条件导出
+#[tauri::command]
+pub async fn export_moodle_xml(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+) -> Result<usize, String> {
+    question_analyzer::export_moodle_xml(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出 GIFT 题库文件，file_id 为空时从全部文件中按条件导出
+#[tauri::command]
+pub async fn export_gift(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+) -> Result<usize, String> {
+    question_analyzer::export_gift(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出 QTI 2.1 内容包（可导入 Canvas、Blackboard），file_id 为空时从全部文件中按条件导出
+#[tauri::command]
+pub async fn export_qti(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+    title: Option<String>,
+) -> Result<usize, String> {
+    question_analyzer::export_qti(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+        title.as_deref().unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出 CSV 表格，columns 为要导出的列（question、answer、analysis、chapter、knowledge_points、difficulty、page 等），为空时导出默认列
+#[tauri::command]
+pub async fn export_questions_csv(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+    columns: Option<Vec<String>>,
+) -> Result<usize, String> {
+    question_analyzer::export_questions_table(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+        &columns.unwrap_or_default(),
+        "csv",
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出 Excel 表格（.xlsx），列的设置与 CSV 导出相同
+#[tauri::command]
+pub async fn export_questions_xlsx(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+    columns: Option<Vec<String>>,
+) -> Result<usize, String> {
+    question_analyzer::export_questions_table(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+        &columns.unwrap_or_default(),
+        "xlsx",
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出为 Markdown 笔记（可放入 Obsidian 笔记库），dest_dir 为导出目录；返回写入的文件数
+#[tauri::command]
+pub async fn export_markdown(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_dir: String,
+    per_question: bool,
+) -> Result<usize, String> {
+    question_analyzer::export_markdown(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_dir),
+        per_question,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出为带版本号的 BooQ 题库文件（JSON，含插图），可在升级后或其他用户的 BooQ 中导入
+#[tauri::command]
+pub async fn export_bank(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+    name: Option<String>,
+) -> Result<usize, String> {
+    question_analyzer::export_bank(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+        name.as_deref().unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 将 BooQ 题库文件导入到指定文件的题目中
+#[tauri::command]
+pub fn import_bank(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    src_path: String,
+) -> Result<crate::bank_exchange::ImportSummary, String> {
+    question_analyzer::import_bank(&app_handle, &file_id, std::path::Path::new(&src_path)).map_err(|e| e.to_string())
+}
+
+/// 翻译题目，译文与原文一起保存；target_lang 为语言代码（例如 "en"），file_id 为空时翻译全部文件中符合条件的题目
+#[tauri::command]
+pub async fn translate_questions(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    target_lang: String,
+    overwrite: Option<bool>,
+) -> Result<crate::question_translation::TranslationSummary, String> {
+    question_analyzer::translate_questions(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        &target_lang,
+        overwrite.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 按组卷蓝图生成试卷
+#[tauri::command]
+pub async fn generate_paper(
+    app_handle: tauri::AppHandle,
+    spec: crate::exam_paper::PaperSpec,
+) -> Result<crate::exam_paper::Paper, String> {
+    question_analyzer::generate_paper(&app_handle, spec)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出已生成的试卷
+#[tauri::command]
+pub fn get_papers(app_handle: tauri::AppHandle) -> Vec<crate::exam_paper::PaperSummary> {
+    question_analyzer::get_papers(&app_handle)
+}
+
+/// 获取试卷详情
+#[tauri::command]
+pub fn get_paper(app_handle: tauri::AppHandle, paper_id: String) -> Result<crate::exam_paper::Paper, String> {
+    question_analyzer::get_paper(&app_handle, &paper_id).map_err(|e| e.to_string())
+}
+
+/// 删除试卷
+#[tauri::command]
+pub fn delete_paper(app_handle: tauri::AppHandle, paper_id: String) -> Result<(), String> {
+    question_analyzer::delete_paper(&app_handle, &paper_id).map_err(|e| e.to_string())
+}
+
+/// 导出试卷为 Markdown，include_answers 为 true 时附上答案；
+/// 给出 answer_key_path 时试卷只含题目，答案单独导出到该文件
+#[tauri::command]
+pub fn export_paper(
+    app_handle: tauri::AppHandle,
+    paper_id: String,
+    dest_path: String,
+    include_answers: bool,
+    answer_key_path: Option<String>,
+) -> Result<(), String> {
+    question_analyzer::export_paper(
+        &app_handle,
+        &paper_id,
+        std::path::Path::new(&dest_path),
+        include_answers,
+        answer_key_path.as_deref().map(std::path::Path::new),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 将试卷导出为 PDF，include_answers 为 true 时附上答案和解析；
+/// 给出 answer_key_path 时答案单独导出为另一个 PDF（题号与试卷一致）
+#[tauri::command]
+pub async fn export_paper_pdf(
+    app_handle: tauri::AppHandle,
+    paper_id: String,
+    dest_path: String,
+    include_answers: bool,
+    answer_key_path: Option<String>,
+) -> Result<(), String> {
+    question_analyzer::export_paper_pdf(
+        &app_handle,
+        &paper_id,
+        std::path::Path::new(&dest_path),
+        include_answers,
+        answer_key_path.as_deref().map(std::path::Path::new),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 将题目导出为 PDF 题目集，file_id 为空时从全部文件中按条件导出；
+/// 给出 answer_key_path 时答案单独导出为另一个 PDF，用于分发练习卷
+#[tauri::command]
+pub async fn export_questions_pdf(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+    filters: Option<QuestionFilter>,
+    dest_path: String,
+    title: Option<String>,
+    include_answers: bool,
+    answer_key_path: Option<String>,
+) -> Result<usize, String> {
+    question_analyzer::export_questions_pdf(
+        &app_handle,
+        file_id.as_deref(),
+        &filters.unwrap_or_default(),
+        std::path::Path::new(&dest_path),
+        title.as_deref().unwrap_or_default(),
+        include_answers,
+        answer_key_path.as_deref().map(std::path::Path::new),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 清除作答记录，question_id 为空时清除全部
+#[tauri::command]
+pub fn clear_attempts(question_id: Option<String>) -> Result<(), String> {
+    crate::study_records::clear(question_id.as_deref()).map_err(|e| e.to_string())
+}
+
+/// 给出可以合并的知识点（写法相同或向量相似）
+#[tauri::command]
+pub async fn suggest_knowledge_point_merges(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+) -> Result<Vec<crate::knowledge_points::KnowledgePointCluster>, String> {
+    question_analyzer::suggest_knowledge_point_merges(&app_handle, file_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 将多个知识点合并为一个（全部文件），并记入别名表，返回修改的题目数
+#[tauri::command]
+pub async fn merge_knowledge_points(
+    app_handle: tauri::AppHandle,
+    sources: Vec<String>,
+    target: String,
+) -> Result<usize, String> {
+    question_analyzer::merge_knowledge_points(&app_handle, &sources, &target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 重命名知识点（全部文件），返回修改的题目数
+#[tauri::command]
+pub async fn rename_knowledge_point(
+    app_handle: tauri::AppHandle,
+    old_name: String,
+    new_name: String,
+) -> Result<usize, String> {
+    question_analyzer::merge_knowledge_points(&app_handle, &[old_name], &new_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取知识点别名表
+#[tauri::command]
+pub fn get_knowledge_point_aliases() -> Vec<crate::knowledge_points::KnowledgePointAlias> {
+    crate::knowledge_points::list_aliases()
+}
+
+/// 删除知识点别名（已合并的题目不会恢复）
+#[tauri::command]
+pub fn remove_knowledge_point_alias(alias: String) -> Result<(), String> {
+    crate::knowledge_points::remove_alias(&alias).map_err(|e| e.to_string())
+}
+
+/// 使用解题模型为用户的作答评分
+#[tauri::command]
+pub async fn grade_answer(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    question_id: String,
+    user_answer: String,
+) -> Result<GradeResult, String> {
+    question_analyzer::grade_answer(&app_handle, &file_id, &question_id, &user_answer)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// ==================== 配置命令 ====================
+
+#[tauri::command]
+pub async fn get_config(app_handle: tauri::AppHandle) -> Result<AppConfig, String> {
+    config::get_config(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_config(
+    app_handle: tauri::AppHandle,
+    config_data: AppConfig,
+) -> Result<(), String> {
+    config::save_config(&app_handle, config_data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_models(app_handle: tauri::AppHandle) -> Result<Vec<ModelConfig>, String> {
+    config::get_models(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_model(
+    app_handle: tauri::AppHandle,
+    model: ModelConfig,
+) -> Result<(), String> {
+    config::add_model(&app_handle, model)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_model(
+    app_handle: tauri::AppHandle,
+    model_id: String,
+) -> Result<(), String> {
+    config::remove_model(&app_handle, &model_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_storage_path(
+    app_handle: tauri::AppHandle,
+    path: String,
+) -> Result<(), String> {
+    config::set_storage_path(&app_handle, &path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_storage_path(app_handle: tauri::AppHandle) -> Result<String, String> {
+    config::get_storage_path(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 导出当前配置，include_secrets 为 true 时包含 API Key 等密钥
+#[tauri::command]
+pub async fn export_config(
+    app_handle: tauri::AppHandle,
+    dest_path: String,
+    include_secrets: Option<bool>,
+) -> Result<(), String> {
+    config::export_config(&app_handle, std::path::Path::new(&dest_path), include_secrets.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 导入配置文件，检查通过后替换当前配置
+#[tauri::command]
+pub async fn import_config(
+    app_handle: tauri::AppHandle,
+    src_path: String,
+) -> Result<config::ConfigImportSummary, String> {
+    config::import_config(&app_handle, std::path::Path::new(&src_path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 恢复默认配置，keep_models 为 true 时保留已添加的模型
+#[tauri::command]
+pub async fn reset_config(app_handle: tauri::AppHandle, keep_models: Option<bool>) -> Result<AppConfig, String> {
+    config::reset_config(&app_handle, keep_models.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出配置方案
+#[tauri::command]
+pub async fn list_profiles(app_handle: tauri::AppHandle) -> Result<Vec<crate::config_profiles::ProfileSummary>, String> {
+    crate::config_profiles::list_profiles(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 新建配置方案，from_current 为 true（默认）时复制当前配置
+#[tauri::command]
+pub async fn create_profile(
+    app_handle: tauri::AppHandle,
+    name: String,
+    from_current: Option<bool>,
+) -> Result<crate::config_profiles::ProfileSummary, String> {
+    crate::config_profiles::create_profile(&app_handle, &name, from_current.unwrap_or(true))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 切换配置方案，返回切换后的配置
+#[tauri::command]
+pub async fn switch_profile(app_handle: tauri::AppHandle, name: String) -> Result<AppConfig, String> {
+    crate::config_profiles::switch_profile(&app_handle, &name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn rename_profile(app_handle: tauri::AppHandle, name: String, new_name: String) -> Result<(), String> {
+    crate::config_profiles::rename_profile(&app_handle, &name, &new_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_profile(app_handle: tauri::AppHandle, name: String) -> Result<(), String> {
+    crate::config_profiles::delete_profile(&app_handle, &name).map_err(|e| e.to_string())
+}
+
+/// 备份工作区（配置、数据库、文件和题库）到一个备份文件，include_caches 为 true 时同时备份可重新生成的缓存
+#[tauri::command]
+pub async fn backup_workspace(
+    app_handle: tauri::AppHandle,
+    dest_path: String,
+    include_caches: Option<bool>,
+) -> Result<crate::workspace_backup::BackupSummary, String> {
+    crate::workspace_backup::backup_workspace(
+        &app_handle,
+        std::path::Path::new(&dest_path),
+        include_caches.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 从备份文件恢复工作区，overwrite 为 true 时覆盖已存在的文件和数据，否则跳过
+#[tauri::command]
+pub async fn restore_workspace(
+    app_handle: tauri::AppHandle,
+    src_path: String,
+    overwrite: Option<bool>,
+) -> Result<crate::workspace_backup::RestoreSummary, String> {
+    crate::workspace_backup::restore_workspace(&app_handle, std::path::Path::new(&src_path), overwrite.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 与 WebDAV / S3 同步题库，prefer 为 "local" 或 "remote" 时按该方向解决冲突，否则冲突条目保持不变
+#[tauri::command]
+pub async fn sync_now(
+    app_handle: tauri::AppHandle,
+    prefer: Option<String>,
+) -> Result<crate::sync_service::SyncSummary, String> {
+    let resolution = crate::sync_service::ConflictResolution::parse(prefer.as_deref()).map_err(|e| e.to_string())?;
+    crate::sync_service::sync_now(&app_handle, resolution)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 测试同步设置，返回远端已有的条目数；sync 为空时使用已保存的设置
+#[tauri::command]
+pub async fn test_sync_connection(app_handle: tauri::AppHandle, sync: Option<SyncConfig>) -> Result<usize, String> {
+    let sync = match sync {
+        Some(sync) => sync,
+        None => config::get_config(&app_handle).await.map_err(|e| e.to_string())?.sync,
+    };
+    crate::sync_service::test_connection(&sync).await.map_err(|e| e.to_string())
+}
+
+/// 本地 HTTP 接口的运行状态
+#[tauri::command]
+pub fn get_api_server_status() -> crate::api_server::ApiServerStatus {
+    crate::api_server::status()
+}
+
+/// 重新生成接口令牌并保存，正在运行的接口服务改用新令牌
+#[tauri::command]
+pub async fn regenerate_api_token(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let mut app_config = config::get_config(&app_handle).await.map_err(|e| e.to_string())?;
+    app_config.api_server.token = crate::api_server::generate_token();
+    let token = app_config.api_server.token.clone();
+    config::save_config(&app_handle, app_config).await.map_err(|e| e.to_string())?;
+    Ok(token)
+}
+
+// ==================== 系统命令 ====================
+
+#[tauri::command]
+pub async fn test_model(
+    api_url: String,
+    api_key: String,
+    model_name: String,
+    headers: Option<std::collections::HashMap<String, String>>,
+) -> Result<crate::ai_service::ModelTestReport, String> {
+    use crate::ai_service::AIService;
+    
+    use crate::ai_service::{is_local_endpoint, AIError};
+    
+    let local = is_local_endpoint(&api_url);
+    if api_key.trim().is_empty() && !local {
+        return Err("请填写 API Key（本地模型服务可以留空）".to_string());
+    }
+    
+    let service = AIService::new(&api_url, &api_key, &model_name)
+        .with_headers(&headers.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    service.probe().await.map_err(|e| {
+        match e.downcast_ref::<AIError>() {
+            Some(AIError::Network(_)) if local => {
+                "无法连接到本地模型服务，请确认 Ollama 已启动且地址正确".to_string()
+            }
+            Some(AIError::Request(msg)) if local && msg.contains("not found") => {
+                format!("本地服务中没有模型 {}，请先执行 ollama pull {}", model_name, model_name)
+            }
+            _ => e.to_string(),
+        }
+    })
+}
+
+/// 测试向量化模型，返回向量维度
+#[tauri::command]
+pub async fn test_embedding_model(
+    api_url: String,
+    api_key: String,
+    model_name: String,
+) -> Result<usize, String> {
+    use crate::ai_service::EmbeddingService;
+    
+    let service = EmbeddingService::new(&api_url, &api_key, &model_name).with_max_retries(0);
+    service
+        .embed_one("测试")
+        .await
+        .map(|embedding| embedding.len())
+        .map_err(|e| e.to_string())
+}
+
+/// 列出本地 Ollama 服务中已下载的模型
+#[tauri::command]
+pub async fn list_ollama_models(api_url: String) -> Result<Vec<crate::ai_service::OllamaModel>, String> {
+    crate::ai_service::list_ollama_models(&api_url)
         .await
         .map_err(|e| e.to_string())
 }
@@ -453,22 +1972,197 @@ pub async fn convert_with_mineru(
     app_handle: tauri::AppHandle,
     file_id: String,
 ) -> Result<Vec<String>, String> {
+    run_mineru_conversion(&app_handle, &file_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 转换单个文件并写入按页缓存
+async fn run_mineru_conversion(
+    app_handle: &tauri::AppHandle,
+    file_id: &str,
+) -> anyhow::Result<Vec<String>> {
     use crate::mineru_service::{MineruService, get_mineru_output_dir};
     
     // 获取文件信息
+    let file_info = file_manager::get_file_info(app_handle, file_id).await?;
+    
+    let output_dir = get_mineru_output_dir(app_handle, file_id);
+    
+    let service = MineruService::with_events(app_handle);
+    let markdown_files = service
+        .convert_pdf_full(file_id, &file_info.path, &output_dir)
+        .await?;
+    
+    // 将整本书的结果拆分到按页缓存，分析时可以直接使用
+    let pdf_name = std::path::Path::new(&file_info.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let markdown_dir = output_dir.with_file_name("markdown");
+    if let Err(e) = crate::mineru_service::populate_page_cache(&output_dir, pdf_name, &markdown_dir, file_info.total_pages, true) {
+        crate::logger::warn("mineru", &format!("写入按页缓存失败: {}", e));
+    }
+    
+    Ok(markdown_files)
+}
+
+/// 批量转换中单个文件的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchConvertItem {
+    pub file_id: String,
+    pub file_name: String,
+    pub status: String, // "completed", "error", "cancelled"
+    pub markdown_files: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// 批量转换汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchConvertReport {
+    pub total: u32,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub cancelled: u32,
+    pub items: Vec<BatchConvertItem>,
+}
+
+/// 使用 MinerU 依次转换多个文件
+/// 单个文件失败不会中断整个批次，结束后返回汇总结果
+#[tauri::command]
+pub async fn convert_with_mineru_batch(
+    app_handle: tauri::AppHandle,
+    file_ids: Vec<String>,
+) -> Result<BatchConvertReport, String> {
+    use crate::logger;
+    use tauri::Manager;
+    
+    let total = file_ids.len() as u32;
+    let mut report = BatchConvertReport {
+        total,
+        succeeded: 0,
+        failed: 0,
+        cancelled: 0,
+        items: Vec::new(),
+    };
+    
+    logger::info("mineru", &format!("开始批量转换 {} 个文件", total));
+    
+    for (index, file_id) in file_ids.iter().enumerate() {
+        let file_name = file_manager::get_file_info(&app_handle, file_id)
+            .await
+            .map(|info| info.display_name)
+            .unwrap_or_else(|_| file_id.clone());
+        
+        let _ = app_handle.emit_all("mineru-batch-progress", serde_json::json!({
+            "current": index + 1,
+            "total": total,
+            "file_id": file_id,
+            "file_name": file_name,
+            "status": "running"
+        }));
+        
+        let item = match run_mineru_conversion(&app_handle, file_id).await {
+            Ok(markdown_files) => {
+                report.succeeded += 1;
+                BatchConvertItem {
+                    file_id: file_id.clone(),
+                    file_name,
+                    status: "completed".to_string(),
+                    markdown_files,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                let message = e.to_string();
+                let status = if message.contains(crate::mineru_service::CONVERSION_CANCELLED) {
+                    report.cancelled += 1;
+                    "cancelled"
+                } else {
+                    report.failed += 1;
+                    logger::error("mineru", &format!("批量转换 {} 失败: {}", file_id, message));
+                    "error"
+                };
+                BatchConvertItem {
+                    file_id: file_id.clone(),
+                    file_name,
+                    status: status.to_string(),
+                    markdown_files: Vec::new(),
+                    error: Some(message),
+                }
+            }
+        };
+        
+        let _ = app_handle.emit_all("mineru-batch-progress", serde_json::json!({
+            "current": index + 1,
+            "total": total,
+            "file_id": item.file_id,
+            "file_name": item.file_name,
+            "status": item.status
+        }));
+        report.items.push(item);
+    }
+    
+    logger::info("mineru", &format!(
+        "批量转换完成：成功 {}，失败 {}，取消 {}",
+        report.succeeded, report.failed, report.cancelled
+    ));
+    
+    Ok(report)
+}
+
+/// 使用 MinerU 逐页转换 PDF（pages 为空时转换全部页面）
+#[tauri::command]
+pub async fn convert_pages_with_mineru(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+    pages: Option<Vec<u32>>,
+) -> Result<Vec<String>, String> {
+    use crate::mineru_service::{MineruService, get_mineru_output_dir};
+    
     let file_info = file_manager::get_file_info(&app_handle, &file_id)
         .await
         .map_err(|e| e.to_string())?;
     
+    let pages = match pages {
+        Some(pages) if !pages.is_empty() => pages,
+        _ => (1..=file_info.total_pages).collect(),
+    };
+    
     let output_dir = get_mineru_output_dir(&app_handle, &file_id);
+    let markdown_dir = output_dir.with_file_name("markdown");
+    
+    let config = config::get_config_sync(&app_handle);
+    let storage_path = if config.storage_path.is_empty() {
+        None
+    } else {
+        Some(config.storage_path.as_str())
+    };
     
-    let service = MineruService::new();
+    let service = MineruService::with_events(&app_handle);
     service
-        .convert_pdf_full(&file_info.path, &output_dir)
+        .convert_pdf_by_pages(&file_id, &file_info.path, &pages, &output_dir, &markdown_dir, storage_path)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 取消正在进行的 MinerU 转换
+#[tauri::command]
+pub fn cancel_mineru_conversion(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<(), String> {
+    use tauri::Manager;
+
+    if !crate::mineru_service::MineruService::cancel_conversion(&file_id) {
+        return Err("没有正在进行的转换任务".to_string());
+    }
+
+    let _ = app_handle.emit_all("mineru-convert-status",
+        serde_json::json!({"file_id": file_id, "status": "cancelling"}));
+    Ok(())
+}
+
 /// 获取 MinerU 详细安装信息（包含模型状态）
 #[tauri::command]
 pub fn get_mineru_full_info(app_handle: tauri::AppHandle) -> crate::mineru_service::MineruInstallInfo {
@@ -493,6 +2187,14 @@ pub async fn install_modelscope(app_handle: tauri::AppHandle) -> Result<String,
     result.map_err(|e| e.to_string())
 }
 
+/// 获取 MinerU 版本并检查兼容性
+#[tauri::command]
+pub async fn get_mineru_version() -> Result<crate::mineru_service::MineruVersionCheck, String> {
+    tokio::task::spawn_blocking(crate::mineru_service::MineruService::check_version)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 下载 MinerU 主模型
 #[tauri::command]
 pub async fn download_mineru_models(app_handle: tauri::AppHandle) -> Result<String, String> {
@@ -506,7 +2208,8 @@ pub async fn download_mineru_models(app_handle: tauri::AppHandle) -> Result<Stri
     let result = tokio::task::spawn_blocking(move || {
         crate::mineru_service::MineruService::download_main_models_with_events(
             &app_handle, 
-            storage_path.as_deref()
+            storage_path.as_deref(),
+            &config.model_download_source
         )
     })
     .await
@@ -528,7 +2231,8 @@ pub async fn download_ocr_models(app_handle: tauri::AppHandle) -> Result<String,
     let result = tokio::task::spawn_blocking(move || {
         crate::mineru_service::MineruService::download_ocr_models_with_events(
             &app_handle, 
-            storage_path.as_deref()
+            storage_path.as_deref(),
+            &config.model_download_source
         )
     })
     .await
@@ -537,6 +2241,75 @@ pub async fn download_ocr_models(app_handle: tauri::AppHandle) -> Result<String,
     result.map_err(|e| e.to_string())
 }
 
+/// 校验已下载的 MinerU 模型是否完整
+#[tauri::command]
+pub fn verify_mineru_models(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<crate::mineru_service::ModelVerifyReport>, String> {
+    let config = config::get_config_sync(&app_handle);
+    let storage_path = if config.storage_path.is_empty() {
+        None
+    } else {
+        Some(config.storage_path.as_str())
+    };
+    
+    Ok(crate::mineru_service::MineruService::verify_models(storage_path))
+}
+
+/// 将已下载的 MinerU 模型移动到新目录
+/// 移动完成后更新 magic-pdf.json 和应用配置，任一步失败都会回滚
+#[tauri::command]
+pub async fn relocate_mineru_models(
+    app_handle: tauri::AppHandle,
+    target_dir: String,
+) -> Result<String, String> {
+    use crate::mineru_service::{self, MineruService};
+    use std::path::PathBuf;
+    
+    let mut app_config = config::get_config_sync(&app_handle);
+    let storage_path = if app_config.storage_path.is_empty() {
+        None
+    } else {
+        Some(app_config.storage_path.clone())
+    };
+    let source_dir = MineruService::get_models_dir(storage_path.as_deref());
+    let target = PathBuf::from(&target_dir);
+    if target == source_dir {
+        return Err("目标目录与当前模型目录相同".to_string());
+    }
+    
+    let handle = app_handle.clone();
+    let (from, to) = (source_dir.clone(), target.clone());
+    let moved_bytes = tokio::task::spawn_blocking(move || {
+        MineruService::move_models_dir(&handle, &from, &to)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    
+    // 回滚：把模型移回原目录并恢复原设置
+    let previous_models_dir = app_config.mineru_models_dir.clone();
+    let rollback = |handle: &tauri::AppHandle| {
+        mineru_service::set_models_dir_override(&previous_models_dir);
+        let _ = MineruService::move_models_dir(handle, &target, &source_dir);
+    };
+    
+    mineru_service::set_models_dir_override(&target_dir);
+    if let Err(e) = MineruService::update_config_with_models(storage_path.as_deref()) {
+        rollback(&app_handle);
+        return Err(format!("更新 magic-pdf.json 失败，已恢复原目录: {}", e));
+    }
+    
+    app_config.mineru_models_dir = target_dir.clone();
+    if let Err(e) = config::save_config(&app_handle, app_config).await {
+        rollback(&app_handle);
+        let _ = MineruService::update_config_with_models(storage_path.as_deref());
+        return Err(format!("保存配置失败，已恢复原目录: {}", e));
+    }
+    
+    Ok(format!("已移动 {:.1} GB 模型到 {}", moved_bytes as f64 / 1024.0 / 1024.0 / 1024.0, target_dir))
+}
+
 /// 更新 MinerU 配置文件
 #[tauri::command]
 pub fn update_mineru_config(app_handle: tauri::AppHandle) -> Result<String, String> {
@@ -552,6 +2325,88 @@ pub fn update_mineru_config(app_handle: tauri::AppHandle) -> Result<String, Stri
         .map_err(|e| e.to_string())
 }
 
+/// 获取 MinerU 转换队列状态
+#[tauri::command]
+pub fn get_mineru_queue_status() -> crate::mineru_queue::QueueStatus {
+    crate::mineru_queue::get_status()
+}
+
+/// 创建 MinerU 专用虚拟环境并安装依赖
+#[tauri::command]
+pub async fn create_mineru_venv(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let result = tokio::task::spawn_blocking(move || {
+        crate::python_env::create_venv_with_events(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    // 创建完成后刷新路径检测，使后续调用使用虚拟环境中的 magic-pdf
+    crate::mineru_service::MineruService::refresh_magic_pdf_path();
+    
+    result.map_err(|e| e.to_string())
+}
+
+/// 获取 MinerU 虚拟环境信息
+#[tauri::command]
+pub fn get_python_env_info() -> crate::python_env::PythonEnvInfo {
+    crate::python_env::get_env_info()
+}
+
+/// 删除 MinerU 虚拟环境
+#[tauri::command]
+pub fn remove_mineru_venv() -> Result<(), String> {
+    crate::python_env::remove_venv().map_err(|e| e.to_string())?;
+    crate::mineru_service::MineruService::refresh_magic_pdf_path();
+    Ok(())
+}
+
+/// 删除已下载的 MinerU 模型（model_type: "main" / "ocr"，为空时删除全部）
+#[tauri::command]
+pub async fn remove_mineru_models(
+    app_handle: tauri::AppHandle,
+    model_type: Option<String>,
+) -> Result<crate::mineru_service::CleanupReport, String> {
+    let config = config::get_config_sync(&app_handle);
+    let storage_path = if config.storage_path.is_empty() {
+        None
+    } else {
+        Some(config.storage_path.clone())
+    };
+    
+    tokio::task::spawn_blocking(move || {
+        crate::mineru_service::MineruService::remove_models(
+            storage_path.as_deref(),
+            model_type.as_deref()
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// 删除 magic-pdf.json 配置文件
+#[tauri::command]
+pub fn remove_mineru_config() -> Result<crate::mineru_service::CleanupReport, String> {
+    crate::mineru_service::MineruService::remove_config_file().map_err(|e| e.to_string())
+}
+
+/// 卸载 MinerU 及 modelscope
+#[tauri::command]
+pub async fn uninstall_mineru(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::mineru_service::CleanupReport, String> {
+    let result = tokio::task::spawn_blocking(move || {
+        crate::mineru_service::MineruService::uninstall_with_events(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    // 卸载完成后刷新路径检测
+    crate::mineru_service::MineruService::refresh_magic_pdf_path();
+    
+    result.map_err(|e| e.to_string())
+}
+
 // ==================== 日志命令 ====================
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -562,6 +2417,56 @@ pub struct LogEntry {
     pub message: String,
 }
 
+/// 获取 AI 用量统计，file_id 为空时统计全部
+#[tauri::command]
+pub async fn get_ai_usage(
+    app_handle: tauri::AppHandle,
+    file_id: Option<String>,
+) -> Result<crate::ai_usage::UsageSummary, String> {
+    use crate::ai_usage;
+
+    let config = config::get_config(&app_handle).await.map_err(|e| e.to_string())?;
+    let prices = ai_usage::model_prices(&config.models);
+
+    Ok(ai_usage::summarize(file_id.as_deref(), &prices))
+}
+
+/// 清空 AI 用量统计
+#[tauri::command]
+pub fn clear_ai_usage() {
+    crate::ai_usage::clear();
+}
+
+/// 获取 AI 响应缓存统计
+#[tauri::command]
+pub fn get_ai_cache_stats() -> crate::ai_cache::CacheStats {
+    crate::ai_cache::stats()
+}
+
+/// 清空 AI 响应缓存，返回删除的条目数
+#[tauri::command]
+pub fn clear_ai_cache() -> Result<u64, String> {
+    crate::ai_cache::clear().map_err(|e| e.to_string())
+}
+
+/// 获取向量缓存统计
+#[tauri::command]
+pub fn get_embedding_cache_stats() -> crate::embedding_cache::EmbeddingCacheStats {
+    crate::embedding_cache::stats()
+}
+
+/// 清空向量缓存，返回删除的条目数
+#[tauri::command]
+pub fn clear_embedding_cache() -> Result<u64, String> {
+    crate::embedding_cache::clear().map_err(|e| e.to_string())
+}
+
+/// 获取最近的 AI 调用记录（需开启调试模式），limit 默认 20
+#[tauri::command]
+pub fn get_ai_call_history(limit: Option<usize>) -> Vec<crate::ai_trace::AICallRecord> {
+    crate::ai_trace::history(limit.unwrap_or(20))
+}
+
 /// 获取运行日志
 #[tauri::command]
 pub fn get_logs() -> Vec<LogEntry> {
@@ -4,6 +4,8 @@ use crate::commands::{AppConfig, ModelConfig};
 use anyhow::{anyhow, Result};
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::fs;
 use std::path::{Path, PathBuf};
 use tauri::AppHandle;
@@ -11,6 +13,13 @@ use tauri::AppHandle;
 // 全局配置缓存
 static CONFIG_CACHE: Lazy<RwLock<Option<AppConfig>>> = Lazy::new(|| RwLock::new(None));
 
+/// 当前的配置格式版本；修改 AppConfig / ModelConfig 的字段（改名、改类型、新增不能缺省的字段）时加一，
+/// 并在 MIGRATIONS 末尾添加对应的升级步骤
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 配置升级步骤，第 i 项把版本 i 的配置升级到版本 i + 1
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v0_to_v1];
+
 /// 获取配置文件路径
 fn get_config_path(app_handle: &AppHandle) -> PathBuf {
     app_handle
@@ -25,22 +34,14 @@ pub fn init_config(app_dir: &Path) {
     let config_path = app_dir.join("config.json");
     
     if !config_path.exists() {
-        let default_config = AppConfig {
-            storage_path: String::new(),
-            theme: "system".to_string(),
-            models: Vec::new(),
-            reading_model: String::new(),
-            analysis_model: String::new(),
-            solving_model: String::new(),
-            use_paddle_ocr: false,
-            mineru_installed: false,
-            paddle_ocr_url: String::new(),
-            paddle_ocr_token: String::new(),
-        };
+        let default_config = AppConfig::default();
         
         if let Ok(content) = serde_json::to_string_pretty(&default_config) {
             fs::write(&config_path, content).ok();
         }
+    } else if let Err(e) = read_config_file(&config_path) {
+        use crate::logger;
+        logger::warn("system", &format!("配置文件无法读取: {}", e));
     }
 }
 
@@ -49,8 +50,7 @@ pub async fn get_config(app_handle: &AppHandle) -> Result<AppConfig> {
     let config_path = get_config_path(app_handle);
     
     if config_path.exists() {
-        let content = fs::read_to_string(&config_path)?;
-        let config: AppConfig = serde_json::from_str(&content)?;
+        let config = read_config_file(&config_path)?;
         
         // 更新缓存
         let mut cache = CONFIG_CACHE.write();
@@ -58,18 +58,7 @@ pub async fn get_config(app_handle: &AppHandle) -> Result<AppConfig> {
         
         Ok(config)
     } else {
-        Ok(AppConfig {
-            storage_path: String::new(),
-            theme: "system".to_string(),
-            models: Vec::new(),
-            reading_model: String::new(),
-            analysis_model: String::new(),
-            solving_model: String::new(),
-            use_paddle_ocr: false,
-            mineru_installed: false,
-            paddle_ocr_url: String::new(),
-            paddle_ocr_token: String::new(),
-        })
+        Ok(AppConfig::default())
     }
 }
 
@@ -87,32 +76,30 @@ pub fn get_config_sync(app_handle: &AppHandle) -> AppConfig {
     let config_path = get_config_path(app_handle);
     
     if config_path.exists() {
-        if let Ok(content) = fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str::<AppConfig>(&content) {
-                // 更新缓存
-                let mut cache = CONFIG_CACHE.write();
-                *cache = Some(config.clone());
-                return config;
-            }
+        if let Ok(config) = read_config_file(&config_path) {
+            // 更新缓存
+            let mut cache = CONFIG_CACHE.write();
+            *cache = Some(config.clone());
+            return config;
         }
     }
     
-    AppConfig {
-        storage_path: String::new(),
-        theme: "system".to_string(),
-        models: Vec::new(),
-        reading_model: String::new(),
-        analysis_model: String::new(),
-        solving_model: String::new(),
-        use_paddle_ocr: false,
-        mineru_installed: false,
-        paddle_ocr_url: String::new(),
-        paddle_ocr_token: String::new(),
-    }
+    AppConfig::default()
 }
 
 /// 保存配置
-pub async fn save_config(app_handle: &AppHandle, config: AppConfig) -> Result<()> {
+pub async fn save_config(app_handle: &AppHandle, mut config: AppConfig) -> Result<()> {
+    crate::http_client::validate_proxy_url(&config.proxy_url).map_err(|e| anyhow!(e))?;
+    
+    // 启用本地接口时没有令牌则自动生成；先启动接口服务，端口被占用时不保存
+    if config.api_server.enabled && config.api_server.token.trim().is_empty() {
+        config.api_server.token = crate::api_server::generate_token();
+    }
+    crate::api_server::apply(app_handle, &config.api_server)?;
+    
+    // 前端提交的配置可能不带版本号；由更新版本的应用写入的配置保留原来的版本号
+    config.config_version = config.config_version.max(CONFIG_VERSION);
+    
     let config_path = get_config_path(app_handle);
     
     // 确保目录存在
@@ -123,6 +110,16 @@ pub async fn save_config(app_handle: &AppHandle, config: AppConfig) -> Result<()
     let content = serde_json::to_string_pretty(&config)?;
     fs::write(&config_path, content)?;
     
+    // 应用运行时设置
+    crate::mineru_queue::set_max_concurrent(config.mineru_max_concurrent);
+    crate::mineru_service::set_conversion_timeout(config.mineru_timeout_secs);
+    crate::python_env::set_path_overrides(&config.python_path, &config.pip_path, &config.magic_pdf_path);
+    crate::mineru_service::set_models_dir_override(&config.mineru_models_dir);
+    crate::http_client::set_proxy(&config.proxy_url, &config.no_proxy);
+    crate::ai_trace::set_enabled(config.ai_debug_trace);
+    crate::ai_cache::set_cache_settings(config.ai_cache_enabled, config.ai_cache_ttl_hours);
+    crate::rag_service::set_global_enabled(config.global_knowledge_base);
+    
     // 更新缓存
     let mut cache = CONFIG_CACHE.write();
     *cache = Some(config);
@@ -145,6 +142,9 @@ pub async fn add_model(app_handle: &AppHandle, model: ModelConfig) -> Result<()>
         return Err(anyhow!("模型 ID 已存在"));
     }
     
+    // 检查附加请求头
+    crate::ai_service::parse_headers(&model.headers)?;
+    
     config.models.push(model);
     save_config(app_handle, config).await
 }
@@ -165,6 +165,13 @@ pub async fn remove_model(app_handle: &AppHandle, model_id: &str) -> Result<()>
     if config.solving_model == model_id {
         config.solving_model = String::new();
     }
+    config.analysis_fallback_models.retain(|id| id != model_id);
+    if config.rerank_model == model_id {
+        config.rerank_model = String::new();
+    }
+    if config.query_expansion_model == model_id {
+        config.query_expansion_model = String::new();
+    }
     
     save_config(app_handle, config).await
 }
@@ -198,3 +205,333 @@ pub async fn get_storage_path(app_handle: &AppHandle) -> Result<String> {
         Ok(config.storage_path)
     }
 }
+
+// 配置导出文件的格式标识
+const CONFIG_EXPORT_FORMAT: &str = "booq-config";
+
+/// 导出的配置文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigExport {
+    pub format: String,
+    pub app_version: String,
+    pub exported_at: String,
+    pub include_secrets: bool,
+    pub config: AppConfig,
+}
+
+/// 导入配置的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigImportSummary {
+    pub models: usize,
+    pub secrets_kept: usize,    // 导入文件中为空、沿用本机已有值的密钥数
+    pub warnings: Vec<String>, // 已自动修正的问题，例如引用了不存在的模型
+}
+
+/// 导出当前配置，include_secrets 为 false 时清空 API Key、令牌、密码等
+/// 存储路径与本机有关，不导出
+pub async fn export_config(app_handle: &AppHandle, dest: &Path, include_secrets: bool) -> Result<()> {
+    let mut config = get_config(app_handle).await?;
+    config.storage_path = String::new();
+    if !include_secrets {
+        strip_secrets(&mut config);
+    }
+    let export = ConfigExport {
+        format: CONFIG_EXPORT_FORMAT.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        include_secrets,
+        config,
+    };
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(dest, serde_json::to_string_pretty(&export)?)?;
+    Ok(())
+}
+
+/// 导入配置文件（导出的文件或 config.json），检查后替换当前配置
+/// 存储路径和 MinerU 安装状态保留本机的；导入文件中为空的密钥沿用本机同一模型或同一设置已有的值
+pub async fn import_config(app_handle: &AppHandle, src: &Path) -> Result<ConfigImportSummary> {
+    let content = fs::read_to_string(src)?;
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(|e| anyhow!("配置文件不是有效的 JSON: {}", e))?;
+    let config_value = if value.get("format").and_then(|f| f.as_str()) == Some(CONFIG_EXPORT_FORMAT) {
+        value.get("config").cloned().ok_or_else(|| anyhow!("配置文件缺少配置内容"))?
+    } else {
+        value
+    };
+    let mut config = parse_config(config_value).map_err(|e| anyhow!("配置文件格式错误: {}", e))?;
+
+    let current = get_config(app_handle).await?;
+    config.storage_path = current.storage_path.clone();
+    config.mineru_installed = current.mineru_installed;
+
+    let mut summary = ConfigImportSummary {
+        models: config.models.len(),
+        secrets_kept: restore_secrets(&mut config, &current),
+        warnings: Vec::new(),
+    };
+    summary.warnings = validate_config(&mut config)?;
+    save_config(app_handle, config).await?;
+    Ok(summary)
+}
+
+/// 恢复默认配置，存储路径和 MinerU 安装状态保留；keep_models 为 true 时保留模型列表和模型选择
+pub async fn reset_config(app_handle: &AppHandle, keep_models: bool) -> Result<AppConfig> {
+    let current = get_config(app_handle).await?;
+    let mut config = AppConfig {
+        storage_path: current.storage_path.clone(),
+        mineru_installed: current.mineru_installed,
+        ..AppConfig::default()
+    };
+    if keep_models {
+        config.models = current.models;
+        config.reading_model = current.reading_model;
+        config.analysis_model = current.analysis_model;
+        config.solving_model = current.solving_model;
+        config.analysis_fallback_models = current.analysis_fallback_models;
+        config.rerank_model = current.rerank_model;
+        config.query_expansion_model = current.query_expansion_model;
+        config.embedding = current.embedding;
+    }
+    save_config(app_handle, config.clone()).await?;
+    Ok(config)
+}
+
+/// 检查配置：模型 ID 重复、请求头无效、同步方式未知时报错；
+/// 引用了不存在的模型、主题或提示词语言等取值无效时改为默认值，返回修正说明
+fn validate_config(config: &mut AppConfig) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+
+    let mut ids = std::collections::HashSet::new();
+    for model in &config.models {
+        if model.id.trim().is_empty() {
+            return Err(anyhow!("模型 {} 缺少 ID", model.name));
+        }
+        if !ids.insert(model.id.as_str()) {
+            return Err(anyhow!("模型 ID 重复: {}", model.id));
+        }
+        crate::ai_service::parse_headers(&model.headers).map_err(|e| anyhow!("模型 {} 的请求头无效: {}", model.name, e))?;
+    }
+    if !matches!(config.sync.provider.as_str(), "" | "webdav" | "s3") {
+        return Err(anyhow!("不支持的同步方式: {}", config.sync.provider));
+    }
+
+    let model_ids: std::collections::HashSet<String> = config.models.iter().map(|m| m.id.clone()).collect();
+    for (label, model_id) in [
+        ("阅读模型", &mut config.reading_model),
+        ("分析模型", &mut config.analysis_model),
+        ("解题模型", &mut config.solving_model),
+        ("重排序模型", &mut config.rerank_model),
+        ("查询扩展模型", &mut config.query_expansion_model),
+    ] {
+        if !model_id.is_empty() && !model_ids.contains(model_id.as_str()) {
+            warnings.push(format!("{}引用的模型 {} 不存在，已清空", label, model_id));
+            model_id.clear();
+        }
+    }
+    let fallback_count = config.analysis_fallback_models.len();
+    config.analysis_fallback_models.retain(|id| model_ids.contains(id));
+    if config.analysis_fallback_models.len() != fallback_count {
+        warnings.push("已移除不存在的备用模型".to_string());
+    }
+
+    if !matches!(config.theme.as_str(), "light" | "dark" | "system") {
+        warnings.push(format!("主题 {} 无效，已改为跟随系统", config.theme));
+        config.theme = "system".to_string();
+    }
+    if !matches!(config.prompt_language.as_str(), "auto" | "zh" | "en") {
+        warnings.push(format!("提示词语言 {} 无效，已改为自动", config.prompt_language));
+        config.prompt_language = "auto".to_string();
+    }
+    if !matches!(config.model_download_source.as_str(), "modelscope" | "huggingface" | "hf-mirror") {
+        warnings.push(format!("模型下载源 {} 无效，已改为 modelscope", config.model_download_source));
+        config.model_download_source = "modelscope".to_string();
+    }
+    Ok(warnings)
+}
+
+/// 请求头名称看起来是认证信息时视为密钥
+fn is_secret_header(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["auth", "key", "token", "secret", "cookie"].iter().any(|s| name.contains(s))
+}
+
+/// 清空配置中的密钥
+fn strip_secrets(config: &mut AppConfig) {
+    for model in &mut config.models {
+        model.api_key.clear();
+        for (name, value) in model.headers.iter_mut() {
+            if is_secret_header(name) {
+                value.clear();
+            }
+        }
+    }
+    config.paddle_ocr_token.clear();
+    config.embedding.api_key.clear();
+    config.sync.password.clear();
+    config.sync.secret_key.clear();
+    config.api_server.token.clear();
+    // 代理地址中的密码
+    if let Ok(mut url) = reqwest::Url::parse(&config.proxy_url) {
+        if url.password().is_some() && url.set_password(None).is_ok() {
+            config.proxy_url = url.to_string();
+        }
+    }
+}
+
+/// 导入文件中为空的密钥沿用本机的值（仅限接口地址相同的配置），返回沿用的数量
+fn restore_secrets(config: &mut AppConfig, current: &AppConfig) -> usize {
+    let mut kept = 0;
+    let mut keep = |value: &mut String, existing: &str| {
+        if value.is_empty() && !existing.is_empty() {
+            *value = existing.to_string();
+            kept += 1;
+        }
+    };
+    for model in &mut config.models {
+        // 接口地址不同时不沿用旧密钥，避免把密钥发到别的服务
+        if let Some(existing) = current.models.iter().find(|m| m.id == model.id && m.api_url == model.api_url) {
+            keep(&mut model.api_key, &existing.api_key);
+            for (name, value) in model.headers.iter_mut() {
+                if let Some(existing_value) = existing.headers.get(name) {
+                    keep(value, existing_value);
+                }
+            }
+        }
+    }
+    if config.paddle_ocr_url == current.paddle_ocr_url {
+        keep(&mut config.paddle_ocr_token, &current.paddle_ocr_token);
+    }
+    if config.embedding.api_url == current.embedding.api_url {
+        keep(&mut config.embedding.api_key, &current.embedding.api_key);
+    }
+    if config.sync.endpoint == current.sync.endpoint {
+        keep(&mut config.sync.password, &current.sync.password);
+        keep(&mut config.sync.secret_key, &current.sync.secret_key);
+    }
+    keep(&mut config.api_server.token, &current.api_server.token);
+    kept
+}
+
+/// 解析配置（config.json、导出的配置、配置方案等），旧版本的配置先升级到当前版本
+pub fn parse_config(mut value: Value) -> Result<AppConfig> {
+    migrate_config(&mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// 读取配置文件；旧版本的配置升级后写回，原文件备份为 config.json.v<版本>.bak
+fn read_config_file(path: &Path) -> Result<AppConfig> {
+    use crate::logger;
+
+    let content = fs::read_to_string(path)?;
+    let mut value: Value = serde_json::from_str(&content)?;
+    let version = migrate_config(&mut value)?;
+    let config: AppConfig = serde_json::from_value(value)?;
+
+    if version < CONFIG_VERSION {
+        let backup = path.with_extension(format!("json.v{}.bak", version));
+        if !backup.exists() {
+            fs::write(&backup, &content)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&config)?)?;
+        logger::info(
+            "system",
+            &format!("配置已从版本 {} 升级到 {}，原文件备份为 {}", version, CONFIG_VERSION, backup.display()),
+        );
+    }
+    Ok(config)
+}
+
+/// 把配置升级到当前版本，返回升级前的版本；比当前版本新的配置不做修改，不认识的字段原样保留
+fn migrate_config(value: &mut Value) -> Result<u32> {
+    use crate::logger;
+
+    let map = value.as_object_mut().ok_or_else(|| anyhow!("配置不是 JSON 对象"))?;
+    let version = map.get("config_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        logger::warn(
+            "system",
+            &format!("配置由更新版本的应用创建（版本 {}，当前支持 {}），部分设置可能不生效", version, CONFIG_VERSION),
+        );
+        return Ok(version);
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(map);
+    }
+    map.insert("config_version".to_string(), Value::from(CONFIG_VERSION));
+    Ok(version)
+}
+
+/// 版本 0（没有 config_version 的配置）-> 1：
+/// 补全缺失或为 null 的字段，避免早期版本的配置因缺少 solving_model 等字段无法读取；
+/// 模型缺少 ID 时生成新 ID，缺少名称时使用模型名，缺少服务商时视为自定义
+fn migrate_v0_to_v1(map: &mut Map<String, Value>) {
+    if let Ok(Value::Object(defaults)) = serde_json::to_value(AppConfig::default()) {
+        for (key, default) in defaults {
+            if map.get(&key).map_or(true, Value::is_null) {
+                map.insert(key, default);
+            }
+        }
+    }
+    if let Some(Value::Array(models)) = map.get_mut("models") {
+        for model in models.iter_mut().filter_map(Value::as_object_mut) {
+            for key in ["api_url", "api_key", "model_name"] {
+                if !model.get(key).is_some_and(Value::is_string) {
+                    model.insert(key.to_string(), Value::from(""));
+                }
+            }
+            if !model.get("id").and_then(Value::as_str).is_some_and(|id| !id.is_empty()) {
+                model.insert("id".to_string(), Value::from(uuid::Uuid::new_v4().to_string()));
+            }
+            if !model.get("name").and_then(Value::as_str).is_some_and(|name| !name.is_empty()) {
+                let name = model.get("model_name").cloned().unwrap_or_default();
+                model.insert("name".to_string(), name);
+            }
+            if !model.get("provider").and_then(Value::as_str).is_some_and(|p| !p.is_empty()) {
+                model.insert("provider".to_string(), Value::from("custom"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved_config() -> AppConfig {
+        let mut config = AppConfig {
+            paddle_ocr_url: "https://ocr.example.com".to_string(),
+            paddle_ocr_token: "paddle-token".to_string(),
+            ..AppConfig::default()
+        };
+        config.embedding.api_url = "https://embed.example.com".to_string();
+        config.embedding.api_key = "embed-key".to_string();
+        config
+    }
+
+    #[test]
+    fn restore_secrets_keeps_token_for_same_url() {
+        let current = saved_config();
+        let mut imported = saved_config();
+        imported.paddle_ocr_token.clear();
+        imported.embedding.api_key.clear();
+
+        assert_eq!(restore_secrets(&mut imported, &current), 2);
+        assert_eq!(imported.paddle_ocr_token, "paddle-token");
+        assert_eq!(imported.embedding.api_key, "embed-key");
+    }
+
+    #[test]
+    fn restore_secrets_drops_token_when_url_changes() {
+        let current = saved_config();
+        let mut imported = saved_config();
+        imported.paddle_ocr_url = "https://other.example.com".to_string();
+        imported.paddle_ocr_token.clear();
+        imported.embedding.api_url = "https://other.example.com".to_string();
+        imported.embedding.api_key.clear();
+
+        assert_eq!(restore_secrets(&mut imported, &current), 0);
+        assert!(imported.paddle_ocr_token.is_empty());
+        assert!(imported.embedding.api_key.is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+// 配置方案模块 - 保存多套命名的配置（存储路径、模型、提示词语言等），运行时切换，例如分别为两门课程或两所学校准备材料
+// config.json 始终是当前方案的配置，其他方案保存在 profiles/<名称>.json；切换时先保存当前方案，再把目标方案写入 config.json 并应用
+
+use crate::commands::AppConfig;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const PROFILES_DIR: &str = "profiles";
+const STATE_FILE: &str = "profiles.json";
+const DEFAULT_PROFILE: &str = "默认";
+const MAX_NAME_CHARS: usize = 64;
+
+/// 方案概要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub active: bool,
+    pub storage_path: String, // 为空表示默认存储路径
+    pub model_count: usize,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfileState {
+    #[serde(default)]
+    active: String,
+}
+
+/// 列出全部方案，当前方案排在最前面
+pub async fn list_profiles(app_handle: &AppHandle) -> Result<Vec<ProfileSummary>> {
+    let dir = profiles_dir(app_handle)?;
+    let active = active_profile(&dir);
+    let current = crate::config::get_config(app_handle).await?;
+
+    let config_path = dir.parent().map(|p| p.join("config.json")).unwrap_or_default();
+    let mut profiles = vec![summary(&active, &current, true, &modified_at(&config_path))];
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            if path.file_name().is_some_and(|n| n == STATE_FILE) || !path.extension().is_some_and(|ext| ext == "json") {
+                continue;
+            }
+            let name = match path.file_stem() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => continue,
+            };
+            if name == active {
+                continue;
+            }
+            if let Some(config) = load_profile(&path) {
+                profiles.push(summary(&name, &config, false, &modified_at(&path)));
+            }
+        }
+    }
+    profiles[1..].sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+/// 新建方案，from_current 为 true 时复制当前配置，否则使用默认配置
+pub async fn create_profile(app_handle: &AppHandle, name: &str, from_current: bool) -> Result<ProfileSummary> {
+    let name = validate_name(name)?;
+    let dir = profiles_dir(app_handle)?;
+    if name == active_profile(&dir) || profile_path(&dir, &name).exists() {
+        return Err(anyhow!("方案 {} 已存在", name));
+    }
+    let config = if from_current {
+        crate::config::get_config(app_handle).await?
+    } else {
+        AppConfig::default()
+    };
+    save_profile(&dir, &name, &config)?;
+    Ok(summary(&name, &config, false, &modified_at(&profile_path(&dir, &name))))
+}
+
+/// 切换到指定方案，返回切换后的配置
+/// 正在分析或分析队列中还有任务时不能切换，否则分析结果可能写入另一个方案的存储路径
+pub async fn switch_profile(app_handle: &AppHandle, name: &str) -> Result<AppConfig> {
+    use crate::logger;
+
+    let name = validate_name(name)?;
+    let dir = profiles_dir(app_handle)?;
+    let active = active_profile(&dir);
+    if name == active {
+        return crate::config::get_config(app_handle).await;
+    }
+    let target_path = profile_path(&dir, &name);
+    let target = load_profile(&target_path).ok_or_else(|| anyhow!("方案 {} 不存在", name))?;
+
+    if crate::question_analyzer::any_analyzing() {
+        return Err(anyhow!("有文件正在分析，请停止或等待分析完成后再切换方案"));
+    }
+    if crate::analysis_queue::get_status()
+        .jobs
+        .iter()
+        .any(|j| !crate::analysis_queue::is_finished(&j.status))
+    {
+        return Err(anyhow!("分析队列中还有任务，请等待完成或取消后再切换方案"));
+    }
+
+    // 保存当前方案，再应用目标方案；应用失败（例如接口端口被占用）时保持当前方案
+    let current = crate::config::get_config(app_handle).await?;
+    save_profile(&dir, &active, &current)?;
+    crate::config::save_config(app_handle, target.clone()).await?;
+    fs::remove_file(&target_path).ok();
+    save_state(&dir, &name)?;
+
+    logger::info("system", &format!("已切换到配置方案: {}", name));
+    let _ = app_handle.emit_all("profile-switched", &name);
+    crate::config::get_config(app_handle).await
+}
+
+/// 删除方案（不能删除当前方案）
+pub fn delete_profile(app_handle: &AppHandle, name: &str) -> Result<()> {
+    let name = validate_name(name)?;
+    let dir = profiles_dir(app_handle)?;
+    if name == active_profile(&dir) {
+        return Err(anyhow!("不能删除当前使用的方案"));
+    }
+    let path = profile_path(&dir, &name);
+    if !path.exists() {
+        return Err(anyhow!("方案 {} 不存在", name));
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// 重命名方案
+pub fn rename_profile(app_handle: &AppHandle, name: &str, new_name: &str) -> Result<()> {
+    let name = validate_name(name)?;
+    let new_name = validate_name(new_name)?;
+    let dir = profiles_dir(app_handle)?;
+    let active = active_profile(&dir);
+    if new_name == active || profile_path(&dir, &new_name).exists() {
+        return Err(anyhow!("方案 {} 已存在", new_name));
+    }
+    if name == active {
+        return save_state(&dir, &new_name);
+    }
+    let path = profile_path(&dir, &name);
+    if !path.exists() {
+        return Err(anyhow!("方案 {} 不存在", name));
+    }
+    fs::rename(path, profile_path(&dir, &new_name))?;
+    Ok(())
+}
+
+fn summary(name: &str, config: &AppConfig, active: bool, updated_at: &str) -> ProfileSummary {
+    ProfileSummary {
+        name: name.to_string(),
+        active,
+        storage_path: config.storage_path.clone(),
+        model_count: config.models.len(),
+        updated_at: updated_at.to_string(),
+    }
+}
+
+/// 方案名称用作文件名，不能包含路径分隔符等字符
+fn validate_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("方案名称不能为空"));
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(anyhow!("方案名称不能超过 {} 个字符", MAX_NAME_CHARS));
+    }
+    if name.starts_with('.') || name.chars().any(|c| c.is_control() || r#"/\:*?"<>|"#.contains(c)) {
+        return Err(anyhow!("方案名称不能包含 / \\ : * ? \" < > | 等字符"));
+    }
+    if format!("{}.json", name) == STATE_FILE {
+        return Err(anyhow!("方案名称无效"));
+    }
+    Ok(name.to_string())
+}
+
+fn active_profile(dir: &Path) -> String {
+    fs::read_to_string(dir.join(STATE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<ProfileState>(&content).ok())
+        .map(|state| state.active)
+        .filter(|active| !active.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn save_state(dir: &Path, active: &str) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let state = ProfileState {
+        active: active.to_string(),
+    };
+    fs::write(dir.join(STATE_FILE), serde_json::to_string_pretty(&state)?)?;
+    Ok(())
+}
+
+fn profile_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.json", name))
+}
+
+fn load_profile(path: &Path) -> Option<AppConfig> {
+    let content = fs::read_to_string(path).ok()?;
+    crate::config::parse_config(serde_json::from_str(&content).ok()?).ok()
+}
+
+fn save_profile(dir: &Path, name: &str, config: &AppConfig) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(profile_path(dir, name), serde_json::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+fn profiles_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+    app_handle
+        .path_resolver()
+        .app_data_dir()
+        .map(|dir| dir.join(PROFILES_DIR))
+        .ok_or_else(|| anyhow!("无法获取应用数据目录"))
+}
+
+fn modified_at(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
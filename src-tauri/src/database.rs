@@ -0,0 +1,708 @@
+// 数据库模块 - 应用级 SQLite 数据库（booq.db），保存文件信息、题目和分析状态，用于快速筛选、分页查询和事务更新；
+// 另外为所有文件已缓存的页面 Markdown 建立 FTS5 全文索引，可以跨文件查找公式或概念出现的位置
+// 各文件目录中的 meta.json 和 questions/all_questions.json 仍然照常写入（备份、复制文件和旧版本兼容），数据库与之同步：
+// 每次启动后首次访问某个存储目录时导入其中的 JSON 文件（题目文件未变化时跳过），之后写入 JSON 的同时更新数据库
+
+use crate::commands::{AnalysisProgress, FileInfo, Question, QuestionFilter};
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 数据库路径
+static DB_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 已打开的数据库连接，首次使用时打开
+static DB_CONN: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+// 本次启动后已从 JSON 文件同步过的存储目录
+static SYNCED_ROOTS: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// 当前的表结构版本（PRAGMA user_version），修改表结构时递增并在 migrate 中增加对应的步骤
+const SCHEMA_VERSION: i32 = 2;
+
+const SCHEMA_V1: &str = r#"
+CREATE TABLE IF NOT EXISTS files (
+    id TEXT PRIMARY KEY,
+    storage_root TEXT NOT NULL,
+    name TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    file_type TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    total_pages INTEGER NOT NULL,
+    questions_mtime INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_files_root ON files(storage_root, created_at);
+
+CREATE TABLE IF NOT EXISTS questions (
+    file_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    page_number INTEGER NOT NULL,
+    question_type TEXT NOT NULL,
+    chapter TEXT NOT NULL,
+    chapter_key TEXT NOT NULL,
+    section TEXT NOT NULL,
+    difficulty TEXT NOT NULL,
+    review_status TEXT NOT NULL,
+    confidence REAL,
+    search_text TEXT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (file_id, position)
+);
+CREATE INDEX IF NOT EXISTS idx_questions_id ON questions(file_id, id);
+CREATE INDEX IF NOT EXISTS idx_questions_page ON questions(file_id, page_number);
+
+CREATE TABLE IF NOT EXISTS question_knowledge_points (
+    file_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    knowledge_point TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_knowledge_points_question ON question_knowledge_points(file_id, position);
+
+CREATE TABLE IF NOT EXISTS analysis_state (
+    file_id TEXT PRIMARY KEY,
+    status TEXT NOT NULL,
+    data TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#;
+
+// 版本 2：页面 Markdown 全文索引，trigram 分词可以匹配中文和公式中的任意片段（至少三个字符）
+const SCHEMA_V2: &str = r#"
+CREATE TABLE IF NOT EXISTS page_documents (
+    file_id TEXT NOT NULL,
+    page_number INTEGER NOT NULL,
+    source_mtime INTEGER,
+    fts_rowid INTEGER NOT NULL,
+    PRIMARY KEY (file_id, page_number)
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS page_fts USING fts5(
+    content,
+    file_id UNINDEXED,
+    page_number UNINDEXED,
+    tokenize = 'trigram'
+);
+"#;
+
+/// trigram 分词能检索的最短关键词（字符数），更短的关键词逐页按子串匹配
+const MIN_FTS_TERM_CHARS: usize = 3;
+
+/// 摘要中关键词前后保留的字符数
+const SNIPPET_CONTEXT_BEFORE: usize = 30;
+const SNIPPET_CONTEXT_AFTER: usize = 60;
+
+/// 全文检索的一条结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentHit {
+    pub file_id: String,
+    pub file_name: String,
+    pub page_number: u32,
+    pub snippet: String, // 第一个关键词附近的内容
+}
+
+/// 初始化数据库路径（在应用启动时调用）
+pub fn init_database(app_dir: &Path) {
+    *DB_PATH.write() = Some(app_dir.join("booq.db"));
+    *DB_CONN.lock() = None;
+    SYNCED_ROOTS.lock().clear();
+}
+
+/// 文件列表（按创建时间从新到旧）
+pub fn list_files(storage_root: &Path) -> Result<Vec<FileInfo>> {
+    ensure_synced(storage_root)?;
+    let root = root_key(storage_root);
+    with_conn(|conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM files WHERE storage_root = ?1 ORDER BY created_at DESC",
+            FILE_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![root], file_from_row)?;
+        rows.collect()
+    })
+}
+
+/// 保存文件信息（上传、重命名后调用），meta.json 已经写入
+pub fn upsert_file(storage_root: &Path, info: &FileInfo) -> Result<()> {
+    ensure_synced(storage_root)?;
+    let root = root_key(storage_root);
+    with_conn(|conn| write_file(conn, &root, info)).map_err(|e| invalidate(storage_root, e))
+}
+
+/// 导入文件目录（复制文件后调用）：文件信息和题目在同一个事务中写入
+pub fn import_file_dir(storage_root: &Path, file_dir: &Path) -> Result<()> {
+    ensure_synced(storage_root)?;
+    let root = root_key(storage_root);
+    with_conn(|conn| {
+        let tx = conn.transaction()?;
+        sync_file_dir(&tx, &root, file_dir, None)?;
+        tx.commit()
+    })
+    .map_err(|e| invalidate(storage_root, e))
+}
+
+/// 删除文件及其题目和分析状态
+pub fn delete_file(file_id: &str) -> Result<()> {
+    with_conn(|conn| {
+        let tx = conn.transaction()?;
+        delete_file_rows(&tx, file_id)?;
+        tx.execute("DELETE FROM analysis_state WHERE file_id = ?1", params![file_id])?;
+        tx.commit()
+    })
+}
+
+/// 文件的全部题目（按保存顺序），数据库中没有该文件时返回 None
+pub fn file_questions(storage_root: &Path, file_id: &str) -> Result<Option<Vec<Question>>> {
+    ensure_synced(storage_root)?;
+    let root = root_key(storage_root);
+    with_conn(|conn| {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM files WHERE id = ?1 AND storage_root = ?2)",
+            params![file_id, root],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Ok(None);
+        }
+        let mut stmt = conn.prepare_cached("SELECT data FROM questions WHERE file_id = ?1 ORDER BY position")?;
+        let rows = stmt.query_map(params![file_id], |row| row.get::<_, String>(0))?;
+        let mut questions = Vec::new();
+        for data in rows {
+            questions.push(decode_question(&data?)?);
+        }
+        Ok(Some(questions))
+    })
+}
+
+/// 替换文件的全部题目（all_questions.json 写入后调用），在一个事务中完成
+/// questions_file 为刚写入的 all_questions.json，记录其修改时间，下次启动时文件未变化则不再导入
+pub fn replace_questions(storage_root: &Path, file_id: &str, questions: &[Question], questions_file: &Path) -> Result<()> {
+    ensure_synced(storage_root)?;
+    with_conn(|conn| {
+        let tx = conn.transaction()?;
+        write_questions(&tx, file_id, questions)?;
+        tx.execute(
+            "UPDATE files SET questions_mtime = ?1 WHERE id = ?2",
+            params![modified_nanos(questions_file), file_id],
+        )?;
+        tx.commit()
+    })
+    .map_err(|e| invalidate(storage_root, e))
+}
+
+/// 按条件分页检索题目，返回当前页的题目和符合条件的总数
+/// file_id 为空时检索存储目录中的全部文件（按文件创建时间从新到旧），terms 为已转为小写的关键词，需要全部出现在题干、答案或解析中
+/// 筛选规则与 question_analyzer::matches_filter 一致；指定 max_confidence 时置信度最低的排在前面
+pub fn query_questions(
+    storage_root: &Path,
+    file_id: Option<&str>,
+    terms: &[String],
+    filter: &QuestionFilter,
+    page: usize,
+    page_size: usize,
+) -> Result<(Vec<Question>, usize)> {
+    ensure_synced(storage_root)?;
+
+    let mut conditions = vec!["f.storage_root = ?".to_string()];
+    let mut values: Vec<rusqlite::types::Value> = vec![root_key(storage_root).into()];
+    let mut add = |condition: &str, value: rusqlite::types::Value| {
+        conditions.push(condition.to_string());
+        values.push(value);
+    };
+    if let Some(file_id) = file_id {
+        add("q.file_id = ?", file_id.to_string().into());
+    }
+    for term in terms {
+        add("instr(q.search_text, ?) > 0", term.clone().into());
+    }
+    let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    if let Some(question_type) = filter.question_type.clone().filter(|t| !t.is_empty()) {
+        add("q.question_type = ?", question_type.into());
+    }
+    if let Some(chapter) = non_empty(&filter.chapter) {
+        add("instr(q.chapter_key, ?) > 0", chapter.to_lowercase().into());
+    }
+    if let Some(knowledge_point) = non_empty(&filter.knowledge_point) {
+        add(
+            "EXISTS(SELECT 1 FROM question_knowledge_points k WHERE k.file_id = q.file_id AND k.position = q.position AND instr(k.knowledge_point, ?) > 0)",
+            knowledge_point.to_lowercase().into(),
+        );
+    }
+    if let Some(max) = filter.max_confidence {
+        add("q.confidence <= ?", (max as f64).into());
+    }
+    if let Some(status) = filter.review_status.clone().filter(|s| !s.is_empty()) {
+        add("q.review_status = ?", status.into());
+    }
+
+    let from = format!(
+        "FROM questions q JOIN files f ON f.id = q.file_id WHERE {}",
+        conditions.join(" AND ")
+    );
+    let order = if filter.max_confidence.is_some() {
+        "ORDER BY COALESCE(q.confidence, 1.0), f.created_at DESC, q.file_id, q.position"
+    } else {
+        "ORDER BY f.created_at DESC, q.file_id, q.position"
+    };
+    let offset = page.saturating_sub(1) * page_size;
+
+    with_conn(|conn| {
+        let total: i64 = conn.query_row(&format!("SELECT COUNT(*) {}", from), params_from_iter(values.iter()), |row| {
+            row.get(0)
+        })?;
+        let mut stmt = conn.prepare(&format!("SELECT q.data {} {} LIMIT {} OFFSET {}", from, order, page_size, offset))?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| row.get::<_, String>(0))?;
+        let mut questions = Vec::new();
+        for data in rows {
+            questions.push(decode_question(&data?)?);
+        }
+        Ok((questions, total.max(0) as usize))
+    })
+}
+
+/// 保存分析结束（完成、停止或出错）时的进度，重启后仍能显示上次分析的结果
+pub fn save_analysis_state(progress: &AnalysisProgress) -> Result<()> {
+    let data = serde_json::to_string(progress)?;
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    with_conn(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO analysis_state (file_id, status, data, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![progress.file_id, progress.status, data, now],
+        )
+        .map(|_| ())
+    })
+}
+
+/// 上次分析结束时的进度
+pub fn load_analysis_state(file_id: &str) -> Result<Option<AnalysisProgress>> {
+    let data = with_conn(|conn| {
+        conn.query_row("SELECT data FROM analysis_state WHERE file_id = ?1", params![file_id], |row| {
+            row.get::<_, String>(0)
+        })
+        .optional()
+    })?;
+    match data {
+        Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+        None => Ok(None),
+    }
+}
+
+/// 把数据库导出为一致的快照（备份工作区时使用），dest 不能已存在
+pub fn snapshot(dest: &Path) -> Result<()> {
+    let dest = dest.to_string_lossy().to_string();
+    with_conn(|conn| conn.execute("VACUUM INTO ?1", params![dest]).map(|_| ()))
+}
+
+/// 恢复工作区后调用：下次访问时从各文件目录的 JSON 文件重新同步
+pub fn reload() {
+    SYNCED_ROOTS.lock().clear();
+}
+
+/// 从备份的数据库中导入分析状态，overwrite 为 false 时保留已有的记录，返回导入的记录数
+pub fn import_analysis_states(backup_path: &Path, overwrite: bool) -> Result<usize> {
+    let backup_path = backup_path.to_string_lossy().to_string();
+    let verb = if overwrite { "INSERT OR REPLACE" } else { "INSERT OR IGNORE" };
+    with_conn(|conn| {
+        conn.execute("ATTACH DATABASE ?1 AS backup", params![backup_path])?;
+        let result = conn.execute(
+            &format!(
+                "{} INTO analysis_state (file_id, status, data, updated_at)
+                 SELECT file_id, status, data, updated_at FROM backup.analysis_state",
+                verb
+            ),
+            [],
+        );
+        conn.execute("DETACH DATABASE backup", [])?;
+        result
+    })
+}
+
+/// 在所有文件已缓存的页面 Markdown 中检索，关键词按空白拆分，每个词都要出现在页面中（不区分大小写）
+/// 检索前先更新索引：加入新缓存或有变化的页面，删除已清除缓存的页面；结果按相关度排序，最多返回 limit 条
+pub fn search_documents(storage_root: &Path, query: &str, limit: usize) -> Result<Vec<DocumentHit>> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    ensure_synced(storage_root)?;
+    sync_page_index(storage_root)?;
+
+    let (long_terms, short_terms): (Vec<&String>, Vec<&String>) =
+        terms.iter().partition(|t| t.chars().count() >= MIN_FTS_TERM_CHARS);
+    let mut conditions = vec!["f.storage_root = ?".to_string()];
+    let mut values: Vec<rusqlite::types::Value> = vec![root_key(storage_root).into()];
+    if !long_terms.is_empty() {
+        // 每个词作为一个短语，避免公式中的符号被当作 FTS5 查询语法
+        let expression = long_terms
+            .iter()
+            .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        conditions.push("page_fts MATCH ?".to_string());
+        values.push(expression.into());
+    }
+    for term in &short_terms {
+        conditions.push("page_fts.content LIKE ? ESCAPE '\\'".to_string());
+        let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        values.push(format!("%{}%", escaped).into());
+    }
+    let order = if long_terms.is_empty() {
+        "ORDER BY f.created_at DESC, page_fts.file_id, page_fts.page_number"
+    } else {
+        "ORDER BY bm25(page_fts), f.created_at DESC, page_fts.page_number"
+    };
+    let sql = format!(
+        "SELECT page_fts.file_id, f.display_name, page_fts.page_number, page_fts.content
+         FROM page_fts JOIN files f ON f.id = page_fts.file_id WHERE {} {} LIMIT {}",
+        conditions.join(" AND "),
+        order,
+        limit
+    );
+
+    let rows: Vec<(String, String, u32, String)> = with_conn(|conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        rows.collect()
+    })?;
+    Ok(rows
+        .into_iter()
+        .map(|(file_id, file_name, page_number, content)| DocumentHit {
+            snippet: snippet(&content, &terms),
+            file_id,
+            file_name,
+            page_number,
+        })
+        .collect())
+}
+
+/// 更新页面全文索引：按页面缓存文件的修改时间判断是否需要重新写入
+fn sync_page_index(storage_root: &Path) -> Result<()> {
+    let root = root_key(storage_root);
+    with_conn(|conn| {
+        let file_ids: Vec<String> = {
+            let mut stmt = conn.prepare_cached("SELECT id FROM files WHERE storage_root = ?1")?;
+            let rows = stmt.query_map(params![root], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let tx = conn.transaction()?;
+        for file_id in &file_ids {
+            let indexed: HashMap<u32, Option<i64>> = {
+                let mut stmt = tx.prepare_cached("SELECT page_number, source_mtime FROM page_documents WHERE file_id = ?1")?;
+                let rows = stmt.query_map(params![file_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            let pages = cached_pages(&storage_root.join(file_id).join("markdown"));
+            for (page, path) in &pages {
+                let mtime = modified_nanos(path);
+                if indexed.get(page).is_some_and(|indexed_mtime| *indexed_mtime == mtime) {
+                    continue;
+                }
+                let content = match fs::read_to_string(path) {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                };
+                if indexed.contains_key(page) {
+                    delete_page_rows(&tx, file_id, *page)?;
+                }
+                tx.execute(
+                    "INSERT INTO page_fts (content, file_id, page_number) VALUES (?1, ?2, ?3)",
+                    params![content, file_id, page],
+                )?;
+                tx.execute(
+                    "INSERT INTO page_documents (file_id, page_number, source_mtime, fts_rowid) VALUES (?1, ?2, ?3, ?4)",
+                    params![file_id, page, mtime, tx.last_insert_rowid()],
+                )?;
+            }
+            for page in indexed.keys().filter(|page| !pages.contains_key(*page)) {
+                delete_page_rows(&tx, file_id, *page)?;
+            }
+        }
+        tx.commit()
+    })
+}
+
+/// 文件目录中缓存的页面 Markdown（0001_page.md ...），页码 -> 路径
+fn cached_pages(markdown_dir: &Path) -> HashMap<u32, PathBuf> {
+    let entries = match fs::read_dir(markdown_dir) {
+        Ok(entries) => entries,
+        Err(_) => return HashMap::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let page = name.strip_suffix("_page.md")?.parse::<u32>().ok()?;
+            Some((page, path))
+        })
+        .collect()
+}
+
+fn delete_page_rows(tx: &Transaction, file_id: &str, page: u32) -> rusqlite::Result<()> {
+    tx.execute(
+        "DELETE FROM page_fts WHERE rowid IN
+             (SELECT fts_rowid FROM page_documents WHERE file_id = ?1 AND page_number = ?2)",
+        params![file_id, page],
+    )?;
+    tx.execute(
+        "DELETE FROM page_documents WHERE file_id = ?1 AND page_number = ?2",
+        params![file_id, page],
+    )?;
+    Ok(())
+}
+
+/// 第一个出现的关键词附近的内容，空白合并为一个空格
+fn snippet(content: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = content.split_whitespace().collect::<Vec<_>>().join(" ").chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let position = terms
+        .iter()
+        .filter_map(|term| {
+            let term: Vec<char> = term.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+            lower.windows(term.len()).position(|window| window == term.as_slice())
+        })
+        .min()
+        .unwrap_or(0);
+    let start = position.saturating_sub(SNIPPET_CONTEXT_BEFORE);
+    let end = (position + SNIPPET_CONTEXT_AFTER).min(chars.len());
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// 首次访问存储目录时从 JSON 文件同步：导入新增或有变化的文件，删除目录中已不存在的文件
+fn ensure_synced(storage_root: &Path) -> Result<()> {
+    use crate::logger;
+
+    let mut synced = SYNCED_ROOTS.lock();
+    if synced.contains(storage_root) {
+        return Ok(());
+    }
+    let root = root_key(storage_root);
+    let dirs: Vec<PathBuf> = match fs::read_dir(storage_root) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.join("meta.json").is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let imported = with_conn(|conn| {
+        let known: HashMap<String, Option<i64>> = {
+            let mut stmt = conn.prepare("SELECT id, questions_mtime FROM files WHERE storage_root = ?1")?;
+            let rows = stmt.query_map(params![root], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let tx = conn.transaction()?;
+        let mut present = HashSet::new();
+        let mut imported = 0;
+        for dir in &dirs {
+            if let Some(id) = sync_file_dir(&tx, &root, dir, Some(&known))? {
+                imported += 1;
+                present.insert(id);
+            } else if let Some(id) = dir.file_name().map(|n| n.to_string_lossy().to_string()) {
+                present.insert(id);
+            }
+        }
+        for id in known.keys().filter(|id| !present.contains(*id)) {
+            delete_file_rows(&tx, id)?;
+        }
+        tx.commit()?;
+        Ok(imported)
+    })?;
+    if imported > 0 {
+        logger::info("system", &format!("已从 {} 导入 {} 个文件的题目到数据库", root, imported));
+    }
+    synced.insert(storage_root.to_path_buf());
+    Ok(())
+}
+
+/// 写入失败时数据库与 JSON 文件不再一致，下次访问该存储目录时重新同步
+fn invalidate(storage_root: &Path, error: anyhow::Error) -> anyhow::Error {
+    SYNCED_ROOTS.lock().remove(storage_root);
+    error
+}
+
+/// 从文件目录同步文件信息和题目，返回题目被重新导入的文件 ID
+/// known 为数据库中已有文件记录的题目文件修改时间，修改时间相同时不重新导入题目；meta.json 无法解析时跳过该目录
+fn sync_file_dir(
+    tx: &Transaction,
+    root: &str,
+    file_dir: &Path,
+    known: Option<&HashMap<String, Option<i64>>>,
+) -> rusqlite::Result<Option<String>> {
+    use crate::logger;
+
+    let info: FileInfo = match fs::read_to_string(file_dir.join("meta.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        Some(info) => info,
+        None => {
+            logger::warn("system", &format!("无法读取文件信息，跳过: {}", file_dir.display()));
+            return Ok(None);
+        }
+    };
+    write_file(tx, root, &info)?;
+
+    let questions_file = file_dir.join("questions").join("all_questions.json");
+    let mtime = modified_nanos(&questions_file);
+    if known.and_then(|known| known.get(&info.id)).is_some_and(|known_mtime| *known_mtime == mtime) {
+        return Ok(None);
+    }
+    let questions: Vec<Question> = match fs::read_to_string(&questions_file) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(questions) => questions,
+            Err(e) => {
+                logger::warn("system", &format!("题目文件解析失败，跳过: {}: {}", questions_file.display(), e));
+                return Ok(None);
+            }
+        },
+        Err(_) => Vec::new(),
+    };
+    write_questions(tx, &info.id, &questions)?;
+    tx.execute("UPDATE files SET questions_mtime = ?1 WHERE id = ?2", params![mtime, info.id])?;
+    Ok(Some(info.id))
+}
+
+const FILE_COLUMNS: &str = "id, name, display_name, file_type, path, size, created_at, total_pages";
+
+fn file_from_row(row: &rusqlite::Row) -> rusqlite::Result<FileInfo> {
+    Ok(FileInfo {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        display_name: row.get(2)?,
+        file_type: row.get(3)?,
+        path: row.get(4)?,
+        size: row.get::<_, i64>(5)?.max(0) as u64,
+        created_at: row.get(6)?,
+        total_pages: row.get(7)?,
+    })
+}
+
+fn write_file(conn: &Connection, root: &str, info: &FileInfo) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO files (id, storage_root, name, display_name, file_type, path, size, created_at, total_pages)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(id) DO UPDATE SET storage_root = excluded.storage_root, name = excluded.name,
+             display_name = excluded.display_name, file_type = excluded.file_type, path = excluded.path,
+             size = excluded.size, created_at = excluded.created_at, total_pages = excluded.total_pages",
+        params![
+            info.id,
+            root,
+            info.name,
+            info.display_name,
+            info.file_type,
+            info.path,
+            info.size as i64,
+            info.created_at,
+            info.total_pages
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_questions(tx: &Transaction, file_id: &str, questions: &[Question]) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM questions WHERE file_id = ?1", params![file_id])?;
+    tx.execute("DELETE FROM question_knowledge_points WHERE file_id = ?1", params![file_id])?;
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO questions (file_id, position, id, page_number, question_type, chapter, chapter_key, section,
+             difficulty, review_status, confidence, search_text, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )?;
+    let mut insert_kp =
+        tx.prepare_cached("INSERT INTO question_knowledge_points (file_id, position, knowledge_point) VALUES (?1, ?2, ?3)")?;
+    for (position, q) in questions.iter().enumerate() {
+        let data = serde_json::to_string(q).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let search_text = format!("{}\n{}\n{}", q.question_text, q.answer, q.analysis).to_lowercase();
+        insert.execute(params![
+            file_id,
+            position as i64,
+            q.id,
+            q.page_number,
+            q.question_type,
+            q.chapter,
+            q.chapter.to_lowercase(),
+            q.section,
+            q.difficulty,
+            q.review_status,
+            q.confidence.map(|c| c as f64),
+            search_text,
+            data
+        ])?;
+        for kp in &q.knowledge_points {
+            insert_kp.execute(params![file_id, position as i64, kp.to_lowercase()])?;
+        }
+    }
+    Ok(())
+}
+
+fn delete_file_rows(tx: &Transaction, file_id: &str) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM files WHERE id = ?1", params![file_id])?;
+    tx.execute("DELETE FROM questions WHERE file_id = ?1", params![file_id])?;
+    tx.execute("DELETE FROM question_knowledge_points WHERE file_id = ?1", params![file_id])?;
+    tx.execute(
+        "DELETE FROM page_fts WHERE rowid IN (SELECT fts_rowid FROM page_documents WHERE file_id = ?1)",
+        params![file_id],
+    )?;
+    tx.execute("DELETE FROM page_documents WHERE file_id = ?1", params![file_id])?;
+    Ok(())
+}
+
+fn decode_question(data: &str) -> rusqlite::Result<Question> {
+    serde_json::from_str(data).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn root_key(storage_root: &Path) -> String {
+    storage_root.to_string_lossy().replace('\\', "/")
+}
+
+/// 文件修改时间（纳秒），文件不存在时为 None
+fn modified_nanos(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let duration = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(duration.as_nanos() as i64)
+}
+
+/// 升级表结构到当前版本
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < 1 {
+        conn.execute_batch(SCHEMA_V1)?;
+    }
+    if version < 2 {
+        conn.execute_batch(SCHEMA_V2)?;
+    }
+    if version < SCHEMA_VERSION {
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    }
+    Ok(())
+}
+
+/// 在数据库连接上执行操作，首次使用时打开数据库并升级表结构
+fn with_conn<T>(f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T> {
+    let mut guard = DB_CONN.lock();
+    if guard.is_none() {
+        let path = DB_PATH.read().clone().ok_or_else(|| anyhow!("数据库未初始化"))?;
+        let conn = Connection::open(&path)
+            .and_then(|conn| conn.busy_timeout(std::time::Duration::from_secs(10)).map(|_| conn))
+            .and_then(|conn| conn.execute_batch("PRAGMA journal_mode = WAL;").map(|_| conn))
+            .and_then(|conn| migrate(&conn).map(|_| conn))
+            .map_err(|e| anyhow!("打开数据库失败: {}", e))?;
+        *guard = Some(conn);
+    }
+    let conn = guard.as_mut().ok_or_else(|| anyhow!("数据库未初始化"))?;
+    f(conn).map_err(|e| anyhow!("数据库操作失败: {}", e))
+}
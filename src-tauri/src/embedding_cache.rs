@@ -0,0 +1,145 @@
+// 向量缓存模块 - 按内容哈希在磁盘上缓存文本向量
+// 重新分析或重新切分时，未变化的文本块不需要再次请求向量化接口
+
+use crate::rag_service::{decode_embedding, encode_embedding};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 缓存数据库路径
+static CACHE_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 已打开的数据库连接，首次使用时打开
+static CACHE_CONN: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS embeddings (
+    key TEXT PRIMARY KEY,
+    model TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
+"#;
+
+/// 向量缓存统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EmbeddingCacheStats {
+    pub entries: u64,
+    pub total_size: u64,
+}
+
+/// 初始化缓存路径（在应用启动时调用）
+pub fn init_cache(app_dir: &Path) {
+    *CACHE_PATH.write() = Some(app_dir.join("embedding_cache.db"));
+    *CACHE_CONN.lock() = None;
+}
+
+/// 计算缓存键：接口地址、模型名和文本内容的哈希
+pub fn cache_key(api_url: &str, model: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(api_url.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(model.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 批量读取缓存的向量，返回顺序与 keys 一致，未命中为 None
+pub fn get_many(keys: &[String]) -> Vec<Option<Vec<f32>>> {
+    let result = with_conn(|conn| {
+        let mut stmt = conn.prepare_cached("SELECT vector FROM embeddings WHERE key = ?1")?;
+        keys.iter()
+            .map(|key| {
+                stmt.query_row(params![key], |row| row.get::<_, Vec<u8>>(0))
+                    .optional()
+                    .map(|bytes| bytes.map(|b| decode_embedding(&b)))
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    result.unwrap_or_else(|| vec![None; keys.len()])
+}
+
+/// 批量写入向量
+pub fn put_many(model: &str, entries: &[(String, Vec<f32>)]) {
+    use crate::logger;
+
+    if entries.is_empty() {
+        return;
+    }
+    let now = chrono::Utc::now().timestamp();
+    let result = with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO embeddings (key, model, vector, created_at) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (key, vector) in entries {
+                stmt.execute(params![key, model, encode_embedding(vector), now])?;
+            }
+        }
+        tx.commit()
+    });
+    if result.is_none() {
+        logger::warn("ai", "写入向量缓存失败");
+    }
+}
+
+/// 统计缓存条目数和占用空间
+pub fn stats() -> EmbeddingCacheStats {
+    let entries = with_conn(|conn| conn.query_row("SELECT COUNT(*) FROM embeddings", [], |row| row.get::<_, i64>(0)))
+        .unwrap_or(0);
+    let total_size = CACHE_PATH
+        .read()
+        .as_ref()
+        .and_then(|path| fs::metadata(path).ok())
+        .map(|m| m.len())
+        .unwrap_or(0);
+    EmbeddingCacheStats {
+        entries: entries.max(0) as u64,
+        total_size,
+    }
+}
+
+/// 清空缓存，返回删除的条目数
+pub fn clear() -> anyhow::Result<u64> {
+    let entries = stats().entries;
+    *CACHE_CONN.lock() = None;
+    if let Some(path) = CACHE_PATH.read().as_ref() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(entries)
+}
+
+/// 在缓存连接上执行操作，未初始化或出错时返回 None
+fn with_conn<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Option<T> {
+    use crate::logger;
+
+    let mut guard = CACHE_CONN.lock();
+    if guard.is_none() {
+        let path = CACHE_PATH.read().clone()?;
+        let conn = Connection::open(&path)
+            .and_then(|conn| conn.busy_timeout(std::time::Duration::from_secs(10)).map(|_| conn))
+            .and_then(|conn| conn.execute_batch(SCHEMA).map(|_| conn));
+        match conn {
+            Ok(conn) => *guard = Some(conn),
+            Err(e) => {
+                logger::warn("ai", &format!("打开向量缓存失败: {}", e));
+                return None;
+            }
+        }
+    }
+    match f(guard.as_ref()?) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            logger::debug("ai", &format!("向量缓存操作失败: {}", e));
+            None
+        }
+    }
+}
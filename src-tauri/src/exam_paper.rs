@@ -0,0 +1,346 @@
+// 试卷模块 - 按组卷蓝图（各章节/难度/题型的题数和分值）从题库抽题，生成试卷和答案
+// 试卷保存在应用数据目录下的 papers 目录中，可以导出为 Markdown 或 PDF
+
+use crate::commands::{Question, QuestionFilter};
+use crate::json_store;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// 未指定时避免与最近几份试卷重复
+const DEFAULT_RECENT_PAPERS: usize = 3;
+
+/// 未指定分值时每道题的分值
+const DEFAULT_QUESTION_SCORE: f64 = 5.0;
+
+/// 组卷要求
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PaperSpec {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub file_id: Option<String>, // 为空时从整个题库抽题
+    pub items: Vec<BlueprintItem>,
+    #[serde(default)]
+    pub total_score: Option<f64>, // 给出时按各题分值的比例折算为该总分
+    #[serde(default)]
+    pub avoid_recent_papers: Option<usize>, // 不使用最近几份试卷中出现过的题目
+}
+
+/// 蓝图中的一项：符合条件的题目抽取 count 道
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlueprintItem {
+    #[serde(flatten)]
+    pub filter: QuestionFilter,
+    #[serde(default)]
+    pub difficulty: Option<String>, // "easy", "medium", "hard"
+    pub count: usize,
+    #[serde(default)]
+    pub score: Option<f64>, // 每道题的分值
+}
+
+/// 试卷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Paper {
+    pub paper_id: String,
+    pub title: String,
+    pub created_at: String,
+    pub spec: PaperSpec,
+    pub sections: Vec<PaperSection>,
+    pub total_score: f64,
+    pub answer_key: Vec<AnswerKeyItem>,
+    #[serde(default)]
+    pub shortfalls: Vec<Shortfall>, // 符合条件的题目不足的蓝图项
+}
+
+/// 试卷中的一个大题（选择题、解答题）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperSection {
+    pub title: String,
+    pub questions: Vec<PaperQuestion>,
+}
+
+/// 试卷中的一道题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperQuestion {
+    pub number: u32,
+    pub score: f64,
+    pub question: Question,
+}
+
+/// 答案中的一道题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerKeyItem {
+    pub number: u32,
+    pub question_id: String,
+    pub answer: String,
+    #[serde(default)]
+    pub correct_options: Vec<String>,
+    #[serde(default)]
+    pub analysis: String,
+}
+
+/// 题目不足的蓝图项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shortfall {
+    pub item_index: usize,
+    pub requested: usize,
+    pub selected: usize,
+}
+
+/// 试卷列表中的摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperSummary {
+    pub paper_id: String,
+    pub title: String,
+    pub created_at: String,
+    pub question_count: u32,
+    pub total_score: f64,
+}
+
+impl Paper {
+    pub fn summary(&self) -> PaperSummary {
+        PaperSummary {
+            paper_id: self.paper_id.clone(),
+            title: self.title.clone(),
+            created_at: self.created_at.clone(),
+            question_count: self.sections.iter().map(|s| s.questions.len() as u32).sum(),
+            total_score: self.total_score,
+        }
+    }
+
+    /// 试卷中的全部题目 ID
+    pub fn question_ids(&self) -> impl Iterator<Item = &str> {
+        self.sections
+            .iter()
+            .flat_map(|s| s.questions.iter())
+            .map(|q| q.question.id.as_str())
+    }
+}
+
+/// 题目是否符合蓝图项的难度要求
+pub fn matches_difficulty(question: &Question, item: &BlueprintItem) -> bool {
+    match item.difficulty.as_deref().map(str::trim) {
+        Some(difficulty) if !difficulty.is_empty() => question.difficulty == difficulty,
+        _ => true,
+    }
+}
+
+/// 按蓝图组卷：每项从符合条件的题目中随机抽取，同一道题只出现一次，
+/// 最近试卷中出现过的题目不使用。matches 判断题目是否符合蓝图项的筛选条件
+pub fn generate(
+    spec: PaperSpec,
+    questions: &[Question],
+    recent_papers: &[Paper],
+    matches: impl Fn(&Question, &BlueprintItem) -> bool,
+) -> Paper {
+    let recent_count = spec.avoid_recent_papers.unwrap_or(DEFAULT_RECENT_PAPERS);
+    let mut used: HashSet<&str> = recent_papers
+        .iter()
+        .take(recent_count)
+        .flat_map(|p| p.question_ids())
+        .collect();
+
+    // 选出的题目和每题的原始分值
+    let mut selected: Vec<(Question, f64)> = Vec::new();
+    let mut shortfalls = Vec::new();
+    for (index, item) in spec.items.iter().enumerate() {
+        let mut candidates: Vec<&Question> = questions
+            .iter()
+            .filter(|q| !used.contains(q.id.as_str()) && matches(q, item))
+            .collect();
+        crate::practice_session::shuffle(&mut candidates);
+        candidates.truncate(item.count);
+        if candidates.len() < item.count {
+            shortfalls.push(Shortfall {
+                item_index: index,
+                requested: item.count,
+                selected: candidates.len(),
+            });
+        }
+        for q in candidates {
+            used.insert(q.id.as_str());
+            selected.push((q.clone(), item.score.unwrap_or(DEFAULT_QUESTION_SCORE)));
+        }
+    }
+
+    let scores = distribute_scores(
+        &selected.iter().map(|(_, score)| *score).collect::<Vec<_>>(),
+        spec.total_score,
+    );
+    let mut selected: Vec<(Question, f64)> = selected
+        .into_iter()
+        .zip(scores)
+        .map(|((q, _), score)| (q, score))
+        .collect();
+
+    // 选择题在前，同一大题内按教材顺序（文件、页码）排列
+    selected.sort_by(|(a, _), (b, _)| {
+        a.options
+            .is_empty()
+            .cmp(&b.options.is_empty())
+            .then_with(|| a.file_id.cmp(&b.file_id))
+            .then_with(|| a.page_number.cmp(&b.page_number))
+    });
+
+    let mut sections: Vec<PaperSection> = Vec::new();
+    let mut answer_key = Vec::new();
+    for (index, (question, score)) in selected.into_iter().enumerate() {
+        let number = index as u32 + 1;
+        let title = if question.options.is_empty() { "解答题" } else { "选择题" };
+        answer_key.push(AnswerKeyItem {
+            number,
+            question_id: question.id.clone(),
+            answer: question.answer.clone(),
+            correct_options: question.correct_options.clone(),
+            analysis: question.analysis.clone(),
+        });
+        let paper_question = PaperQuestion { number, score, question };
+        match sections.last_mut().filter(|s| s.title == title) {
+            Some(section) => section.questions.push(paper_question),
+            None => sections.push(PaperSection {
+                title: title.to_string(),
+                questions: vec![paper_question],
+            }),
+        }
+    }
+
+    let total_score = sections.iter().flat_map(|s| &s.questions).map(|q| q.score).sum();
+    let title = if spec.title.trim().is_empty() {
+        format!("试卷 {}", Local::now().format("%Y-%m-%d %H:%M"))
+    } else {
+        spec.title.trim().to_string()
+    };
+    Paper {
+        paper_id: uuid::Uuid::new_v4().to_string(),
+        title,
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        spec,
+        sections,
+        total_score,
+        answer_key,
+        shortfalls,
+    }
+}
+
+/// 按原始分值的比例折算为总分，每题取 0.5 的整数倍，舍入误差计入最后一题
+fn distribute_scores(raw: &[f64], total: Option<f64>) -> Vec<f64> {
+    let total = match total {
+        Some(total) if total > 0.0 && !raw.is_empty() => total,
+        _ => return raw.to_vec(),
+    };
+    let raw_sum: f64 = raw.iter().sum();
+    if raw_sum <= 0.0 {
+        return vec![total / raw.len() as f64; raw.len()];
+    }
+    let mut scores: Vec<f64> = raw
+        .iter()
+        .map(|score| (score / raw_sum * total * 2.0).round() / 2.0)
+        .collect();
+    let diff = total - scores.iter().sum::<f64>();
+    if let Some(last) = scores.last_mut() {
+        *last += diff;
+    }
+    scores
+}
+
+/// 导出为 Markdown，include_answers 为 true 时在末尾附上答案
+pub fn to_markdown(paper: &Paper, include_answers: bool) -> String {
+    let mut md = format!("# {}\n\n满分：{}\n\n", paper.title, format_score(paper.total_score));
+    for (index, section) in paper.sections.iter().enumerate() {
+        md.push_str(&format!("## {}\n\n", section_heading(index, section)));
+        for item in &section.questions {
+            md.push_str(&format!(
+                "{}. （{} 分）{}\n\n",
+                item.number,
+                format_score(item.score),
+                item.question.question_text.trim()
+            ));
+            for option in &item.question.options {
+                md.push_str(&format!("   {}. {}\n", option.label, option.text));
+            }
+            if !item.question.options.is_empty() {
+                md.push('\n');
+            }
+        }
+    }
+
+    if include_answers {
+        md.push_str("---\n\n# 参考答案\n\n");
+        push_answers(&mut md, paper);
+    }
+    md
+}
+
+/// 单独的答案文档，题号与试卷一致
+pub fn answer_key_markdown(paper: &Paper) -> String {
+    let mut md = format!("# {}（参考答案）\n\n", paper.title);
+    push_answers(&mut md, paper);
+    md
+}
+
+fn push_answers(md: &mut String, paper: &Paper) {
+    for item in &paper.answer_key {
+        let answer = if item.correct_options.is_empty() {
+            item.answer.trim().to_string()
+        } else {
+            item.correct_options.join("")
+        };
+        md.push_str(&format!("{}. {}\n\n", item.number, answer));
+        if !item.analysis.trim().is_empty() {
+            md.push_str(&format!("   解析：{}\n\n", item.analysis.trim()));
+        }
+    }
+}
+
+/// 大题标题，例如 "一、选择题（共 20 分）"
+pub fn section_heading(index: usize, section: &PaperSection) -> String {
+    let section_score: f64 = section.questions.iter().map(|q| q.score).sum();
+    format!(
+        "{}、{}（共 {} 分）",
+        chinese_numeral(index + 1),
+        section.title,
+        format_score(section_score)
+    )
+}
+
+pub fn format_score(score: f64) -> String {
+    if score.fract() == 0.0 {
+        format!("{}", score as i64)
+    } else {
+        format!("{:.1}", score)
+    }
+}
+
+fn chinese_numeral(n: usize) -> String {
+    const DIGITS: [&str; 10] = ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+    match n {
+        0..=9 => DIGITS[n].to_string(),
+        10..=19 => format!("十{}", if n == 10 { "" } else { DIGITS[n - 10] }),
+        _ => n.to_string(),
+    }
+}
+
+/// 读取试卷
+pub fn load(dir: &Path, paper_id: &str) -> Result<Option<Paper>> {
+    Ok(json_store::load(&json_store::record_path(dir, paper_id)?))
+}
+
+/// 保存试卷
+pub fn save(dir: &Path, paper: &Paper) -> Result<()> {
+    json_store::save(dir, &paper.paper_id, paper)
+}
+
+/// 删除试卷，返回试卷是否存在
+pub fn remove(dir: &Path, paper_id: &str) -> Result<bool> {
+    json_store::remove(dir, paper_id)
+}
+
+/// 列出目录中的试卷，最近生成的排在前面
+pub fn list(dir: &Path) -> Vec<Paper> {
+    let mut papers: Vec<Paper> = json_store::list(dir);
+    papers.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    papers
+}
@@ -19,7 +19,7 @@ fn generate_file_id(content: &[u8]) -> String {
 }
 
 /// 获取存储根路径
-fn get_storage_root(app_handle: &AppHandle) -> PathBuf {
+pub fn get_storage_root(app_handle: &AppHandle) -> PathBuf {
     let config = crate::config::get_config_sync(app_handle);
     if !config.storage_path.is_empty() {
         PathBuf::from(&config.storage_path)
@@ -94,6 +94,7 @@ pub async fn upload_file(
     let meta_path = file_dir.join("meta.json");
     let meta_json = serde_json::to_string_pretty(&file_info)?;
     fs::write(meta_path, meta_json)?;
+    sync_file_to_database(&storage_root, &file_info);
     
     // 创建 markdown 目录
     fs::create_dir_all(file_dir.join("markdown"))?;
@@ -129,6 +130,12 @@ fn get_file_pages(file_path: &Path) -> Result<u32> {
 pub async fn get_file_list(app_handle: &AppHandle) -> Result<Vec<FileInfo>> {
     let storage_root = get_storage_root(app_handle);
     
+    // 优先从数据库读取，数据库不可用时扫描各文件目录的 meta.json
+    match crate::database::list_files(&storage_root) {
+        Ok(files) => return Ok(files),
+        Err(e) => crate::logger::warn("system", &format!("从数据库读取文件列表失败: {}", e)),
+    }
+    
     if !storage_root.exists() {
         return Ok(Vec::new());
     }
@@ -180,6 +187,15 @@ pub async fn delete_file(app_handle: &AppHandle, file_id: &str) -> Result<()> {
         fs::remove_dir_all(file_dir)?;
     }
     
+    if let Err(e) = crate::database::delete_file(file_id) {
+        crate::logger::warn("system", &format!("从数据库删除文件失败: {}", e));
+    }
+    
+    // 从全局知识库中删除该文件的内容
+    if let Err(e) = crate::rag_service::remove_file_from_global_store(file_id) {
+        crate::logger::warn("system", &format!("从全局知识库删除文件失败: {}", e));
+    }
+    
     Ok(())
 }
 
@@ -198,6 +214,7 @@ pub async fn rename_file(app_handle: &AppHandle, file_id: &str, new_name: &str)
     
     let meta_json = serde_json::to_string_pretty(&file_info)?;
     fs::write(meta_path, meta_json)?;
+    sync_file_to_database(&storage_root, &file_info);
     
     Ok(())
 }
@@ -243,9 +260,21 @@ pub async fn copy_file(app_handle: &AppHandle, file_id: &str) -> Result<FileInfo
     let meta_json = serde_json::to_string_pretty(&new_info)?;
     fs::write(new_meta_path, meta_json)?;
     
+    // 文件信息和复制过来的题目一起写入数据库
+    if let Err(e) = crate::database::import_file_dir(&storage_root, &new_dir) {
+        crate::logger::warn("system", &format!("复制的文件写入数据库失败: {}", e));
+    }
+    
     Ok(new_info)
 }
 
+/// meta.json 写入后同步到数据库，失败时只记录日志（下次访问时从 meta.json 重新导入）
+fn sync_file_to_database(storage_root: &Path, file_info: &FileInfo) {
+    if let Err(e) = crate::database::upsert_file(storage_root, file_info) {
+        crate::logger::warn("system", &format!("文件信息写入数据库失败: {}", e));
+    }
+}
+
 /// 递归复制目录
 fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     fs::create_dir_all(dst)?;
@@ -0,0 +1,94 @@
+// HTTP 客户端模块 - 统一创建带代理设置的 reqwest 客户端
+// 校园网等环境需要通过 HTTP/SOCKS 代理访问大模型和 OCR 接口
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use reqwest::{Client, ClientBuilder, Proxy, Url};
+
+/// 代理设置
+#[derive(Debug, Clone, Default)]
+struct ProxySettings {
+    url: Option<String>,    // http://、https://、socks5:// 或 socks5h:// 开头
+    no_proxy: Vec<String>, // 不走代理的主机
+}
+
+// 当前代理设置
+static PROXY_SETTINGS: Lazy<RwLock<ProxySettings>> = Lazy::new(|| RwLock::new(ProxySettings::default()));
+
+/// 本地地址始终不走代理（Ollama、LM Studio 等本地服务）
+const ALWAYS_BYPASS: &[&str] = &["localhost", "127.0.0.1", "::1", "[::1]"];
+
+/// 设置代理，空字符串表示不使用代理
+/// no_proxy 为逗号或换行分隔的主机列表，支持 "example.com"、".example.com"、"*.example.com"
+pub fn set_proxy(url: &str, no_proxy: &str) {
+    let url = url.trim();
+    let no_proxy = no_proxy
+        .split([',', ';', '\n'])
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    *PROXY_SETTINGS.write() = ProxySettings {
+        url: if url.is_empty() { None } else { Some(url.to_string()) },
+        no_proxy,
+    };
+}
+
+/// 检查代理地址格式
+pub fn validate_proxy_url(url: &str) -> Result<(), String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Ok(());
+    }
+    let parsed = Url::parse(url).map_err(|e| format!("代理地址格式错误: {}", e))?;
+    match parsed.scheme() {
+        "http" | "https" | "socks5" | "socks5h" => Ok(()),
+        scheme => Err(format!("不支持的代理协议: {}，请使用 http、https、socks5 或 socks5h", scheme)),
+    }
+}
+
+/// 创建应用了代理设置的客户端构建器
+pub fn builder() -> ClientBuilder {
+    use crate::logger;
+
+    let settings = PROXY_SETTINGS.read().clone();
+    let proxy_url = match settings.url.as_deref().map(Url::parse) {
+        Some(Ok(url)) => url,
+        Some(Err(e)) => {
+            logger::warn("system", &format!("代理地址无效，已忽略: {}", e));
+            return Client::builder();
+        }
+        None => return Client::builder(),
+    };
+
+    let no_proxy = settings.no_proxy;
+    let proxy = Proxy::custom(move |url| {
+        let host = url.host_str().unwrap_or("").to_lowercase();
+        if should_bypass(&host, &no_proxy) {
+            None
+        } else {
+            Some(proxy_url.clone())
+        }
+    });
+
+    Client::builder().proxy(proxy)
+}
+
+/// 创建应用了代理设置的客户端
+pub fn client() -> Client {
+    builder().build().unwrap_or_else(|_| Client::new())
+}
+
+/// 主机是否在不走代理的列表中
+fn should_bypass(host: &str, no_proxy: &[String]) -> bool {
+    if ALWAYS_BYPASS.contains(&host) {
+        return true;
+    }
+    no_proxy.iter().any(|pattern| {
+        if pattern == "*" {
+            return true;
+        }
+        let suffix = pattern.trim_start_matches('*').trim_start_matches('.');
+        host == suffix || host.ends_with(&format!(".{}", suffix))
+    })
+}
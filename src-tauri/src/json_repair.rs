@@ -0,0 +1,217 @@
+// JSON 修复模块 - 宽松解析大模型返回的 JSON
+// 模型经常输出代码块包裹、尾随逗号、未转义的引号和 LaTeX 反斜杠，直接解析会失败
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+
+// 以 n 开头的常用 LaTeX 命令
+const LATEX_N_COMMANDS: &[&str] = &[
+    "nabla", "ne", "neq", "neg", "ni", "nu", "not", "notin", "newline", "newpage", "noindent", "nonumber",
+    "nolimits", "nleq", "ngeq", "nless", "ngtr", "nmid", "nparallel", "nsubseteq", "nsupseteq", "nexists",
+    "nearrow", "nwarrow", "natural", "normalsize",
+];
+
+/// 宽松解析：依次尝试直接解析、提取 JSON 片段后解析、修复后解析
+pub fn parse_relaxed<T: DeserializeOwned>(text: &str) -> Result<T> {
+    let trimmed = text.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Ok(value);
+    }
+
+    let extracted = extract_json_block(trimmed);
+    if let Ok(value) = serde_json::from_str(&extracted) {
+        return Ok(value);
+    }
+
+    let repaired = repair_json(&extracted);
+    serde_json::from_str(&repaired).map_err(|e| anyhow!("JSON 解析失败: {}", e))
+}
+
+/// 去掉 Markdown 代码块标记，截取第一个 { 或 [ 开始的 JSON 片段
+pub fn extract_json_block(text: &str) -> String {
+    let mut text = text.trim();
+
+    // ```json ... ```
+    if let Some(start) = text.find("```") {
+        let after = &text[start + 3..];
+        let after = after.strip_prefix("json").unwrap_or(after);
+        text = match after.rfind("```") {
+            Some(end) => &after[..end],
+            None => after,
+        };
+    }
+
+    let start = match text.find(['{', '[']) {
+        Some(start) => start,
+        None => return text.trim().to_string(),
+    };
+    let end = text.rfind(['}', ']']).map(|e| e + 1).unwrap_or(text.len());
+    if end > start {
+        text[start..end].to_string()
+    } else {
+        text[start..].to_string()
+    }
+}
+
+/// 修复常见的 JSON 格式错误
+/// - 字符串中的换行、制表符
+/// - 无效的转义（LaTeX 中的 \frac、\alpha 等）
+/// - 字符串中未转义的双引号
+/// - 对象和数组末尾多余的逗号
+/// - 输出被截断导致的括号不闭合
+pub fn repair_json(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() + 16);
+    let mut stack: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if in_string {
+            match c {
+                '\\' => {
+                    let next = chars.get(i + 1).copied();
+                    let after = chars.get(i + 2).copied();
+                    if is_valid_escape(next, after, &chars[i + 1..]) {
+                        out.push('\\');
+                        if let Some(n) = next {
+                            out.push(n);
+                        }
+                        i += 2;
+                        continue;
+                    }
+                    // 无效转义视为字面量反斜杠
+                    out.push_str("\\\\");
+                }
+                '"' => {
+                    if is_closing_quote(&chars, i) {
+                        in_string = false;
+                        out.push('"');
+                    } else {
+                        out.push_str("\\\"");
+                    }
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => {}
+                '\t' => out.push_str("\\t"),
+                _ => out.push(c),
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' => {
+                stack.push('}');
+                out.push(c);
+            }
+            '[' => {
+                stack.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                remove_trailing_comma(&mut out);
+                if stack.last() == Some(&c) {
+                    stack.pop();
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    // 输出被截断：补齐字符串和括号
+    if in_string {
+        out.push('"');
+    }
+    remove_trailing_comma(&mut out);
+    while let Some(closer) = stack.pop() {
+        out.push(closer);
+    }
+
+    out
+}
+
+/// 判断反斜杠后的转义是否合法
+/// \b \f \r \t 后面紧跟字母时（例如 \frac、\beta、\right、\times）按 LaTeX 命令处理
+/// \n 后面紧跟字母很常见（换行后接英文），只有组成已知的 LaTeX 命令（例如 \nabla、\neq）时才按 LaTeX 处理
+fn is_valid_escape(next: Option<char>, after: Option<char>, rest: &[char]) -> bool {
+    match next {
+        Some('"') | Some('\\') | Some('/') => true,
+        Some('n') => {
+            let command: String = rest.iter().take_while(|c| c.is_ascii_alphabetic()).collect();
+            !LATEX_N_COMMANDS.contains(&command.as_str())
+        }
+        Some('b') | Some('f') | Some('r') | Some('t') => {
+            !after.map(|a| a.is_ascii_alphabetic()).unwrap_or(false)
+        }
+        Some('u') => rest.len() >= 5 && rest[1..5].iter().all(|c| c.is_ascii_hexdigit()),
+        _ => false,
+    }
+}
+
+/// 判断字符串中的双引号是否为结束引号：其后（跳过空白）应为 , : } ] 或文本结束
+fn is_closing_quote(chars: &[char], index: usize) -> bool {
+    let next = chars[index + 1..].iter().find(|c| !c.is_whitespace());
+    match next {
+        None => true,
+        Some(',') => {
+            // 逗号后应当是下一个键、值或右括号，否则仍然在字符串内
+            let after = chars[index + 1..]
+                .iter()
+                .skip_while(|c| c.is_whitespace() || **c == ',')
+                .find(|c| !c.is_whitespace());
+            matches!(after, None | Some('"') | Some('}') | Some(']') | Some('{') | Some('['))
+                || after.map(|c| c.is_ascii_digit() || *c == '-' || *c == 't' || *c == 'f' || *c == 'n').unwrap_or(false)
+        }
+        Some(':') | Some('}') | Some(']') => true,
+        _ => false,
+    }
+}
+
+/// 删除输出末尾（忽略空白）多余的逗号
+fn remove_trailing_comma(out: &mut String) {
+    let trimmed_len = out.trim_end().len();
+    if out[..trimmed_len].ends_with(',') {
+        out.truncate(trimmed_len - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repaired_text(json: &str) -> String {
+        let value: serde_json::Value = serde_json::from_str(&repair_json(json)).unwrap();
+        value["text"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn latex_commands_starting_with_n_are_kept() {
+        for command in ["nabla", "neq", "nu", "newline", "not"] {
+            let json = format!(r#"{{"text": "$\alpha \{} x$"}}"#, command);
+            assert_eq!(repaired_text(&json), format!("$\\alpha \\{} x$", command));
+        }
+        assert_eq!(repaired_text(r#"{"text": "$\nabla f \neq 0, \nu_i \not= 1$"}"#), "$\\nabla f \\neq 0, \\nu_i \\not= 1$");
+    }
+
+    #[test]
+    fn newline_escapes_are_kept() {
+        assert_eq!(repaired_text(r#"{"text": "first\nsecond\nnote\n1. \nThe end"}"#), "first\nsecond\nnote\n1. \nThe end");
+        assert_eq!(repaired_text(r#"{"text": "$\frac{1}{2}$\n下一行"}"#), "$\\frac{1}{2}$\n下一行");
+    }
+
+    #[test]
+    fn parse_relaxed_repairs_latex_with_nabla() {
+        let value: serde_json::Value =
+            parse_relaxed("```json\n{\"text\": \"$\\nabla \\cdot E = 0$\",}\n```").unwrap();
+        assert_eq!(value["text"], "$\\nabla \\cdot E = 0$");
+    }
+}
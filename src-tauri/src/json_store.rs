@@ -0,0 +1,60 @@
+// JSON 记录存储模块 - 组合题库、试卷、练习会话等按 ID 保存为目录中的 <ID>.json
+// ID 可能来自前端或本地接口，用作文件名前先校验；写入时先写临时文件再替换，中途退出不会留下写了一半的文件
+
+use crate::utils::is_safe_id;
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 记录文件路径，ID 不能用作文件名时返回错误
+pub fn record_path(dir: &Path, id: &str) -> Result<PathBuf> {
+    if !is_safe_id(id) {
+        return Err(anyhow!("无效的 ID: {}", id));
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+/// 读取记录，文件不存在或格式错误时返回 None
+pub fn load<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 保存记录
+pub fn save<T: Serialize>(dir: &Path, id: &str, value: &T) -> Result<()> {
+    let path = record_path(dir, id)?;
+    fs::create_dir_all(dir)?;
+    // 临时文件名各不相同，同时保存同一条记录时不会互相覆盖临时文件
+    let tmp_path = dir.join(format!(".{}.{}.tmp", id, uuid::Uuid::new_v4().simple()));
+    fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    if let Err(e) = fs::rename(&tmp_path, &path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// 删除记录，返回记录是否存在
+pub fn remove(dir: &Path, id: &str) -> Result<bool> {
+    let path = record_path(dir, id)?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    fs::remove_file(path)?;
+    Ok(true)
+}
+
+/// 读取目录中的全部记录（跳过无法解析的文件）
+pub fn list<T: DeserializeOwned>(dir: &Path) -> Vec<T> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+                .filter_map(|entry| load(&entry.path()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,203 @@
+// 知识图谱模块 - 由题目和 RAG 文档构建知识点关系图
+// 节点为知识点和章节，边为同一题目或同一段内容中共同出现的次数，用于在界面上绘制概念图
+
+use crate::commands::Question;
+use crate::rag_service::Document;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// 在正文中匹配知识点时要求的最少字符数，避免"数"、"点"这类过短的名称到处命中
+const MIN_MATCH_CHARS: usize = 2;
+
+/// 知识图谱
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    pub file_id: String,
+    pub generated_at: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// 图谱节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,        // "kp:名称" 或 "chapter:名称"
+    pub label: String,
+    pub node_type: String, // "knowledge_point", "chapter"
+    pub weight: u32,       // 出现次数
+    pub pages: Vec<u32>,   // 出现的页码（升序）
+}
+
+/// 图谱边
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub edge_type: String, // "co_occurrence"（知识点之间）, "belongs_to"（知识点到章节）
+    pub weight: u32,
+}
+
+/// 构建过程中的节点
+#[derive(Default)]
+struct NodeEntry {
+    label: String,
+    node_type: &'static str,
+    weight: u32,
+    pages: BTreeSet<u32>,
+}
+
+#[derive(Default)]
+struct GraphBuilder {
+    nodes: HashMap<String, NodeEntry>,
+    edges: HashMap<(String, String, &'static str), u32>,
+}
+
+impl GraphBuilder {
+    fn add_node(&mut self, node_type: &'static str, label: &str, page: u32) -> String {
+        let prefix = if node_type == "chapter" { "chapter" } else { "kp" };
+        let id = format!("{}:{}", prefix, label);
+        let entry = self.nodes.entry(id.clone()).or_insert_with(|| NodeEntry {
+            label: label.to_string(),
+            node_type,
+            ..Default::default()
+        });
+        entry.weight += 1;
+        entry.pages.insert(page);
+        id
+    }
+
+    fn add_edge(&mut self, source: &str, target: &str, edge_type: &'static str) {
+        if source == target {
+            return;
+        }
+        // 共现关系无方向，按字典序存储
+        let (source, target) = if edge_type == "co_occurrence" && source > target {
+            (target, source)
+        } else {
+            (source, target)
+        };
+        *self
+            .edges
+            .entry((source.to_string(), target.to_string(), edge_type))
+            .or_insert(0) += 1;
+    }
+
+    /// 记录同一题目或同一段内容中出现的一组知识点
+    fn add_group(&mut self, knowledge_points: &[String], chapter: &str, page: u32) {
+        let ids: Vec<String> = knowledge_points
+            .iter()
+            .map(|kp| self.add_node("knowledge_point", kp, page))
+            .collect();
+        for (i, a) in ids.iter().enumerate() {
+            for b in &ids[i + 1..] {
+                self.add_edge(a, b, "co_occurrence");
+            }
+        }
+        if !chapter.is_empty() {
+            let chapter_id = self.add_node("chapter", chapter, page);
+            for id in &ids {
+                self.add_edge(id, &chapter_id, "belongs_to");
+            }
+        }
+    }
+
+    fn finish(self, file_id: &str) -> KnowledgeGraph {
+        let mut nodes: Vec<GraphNode> = self
+            .nodes
+            .into_iter()
+            .map(|(id, entry)| GraphNode {
+                id,
+                label: entry.label,
+                node_type: entry.node_type.to_string(),
+                weight: entry.weight,
+                pages: entry.pages.into_iter().collect(),
+            })
+            .collect();
+        nodes.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.id.cmp(&b.id)));
+
+        let mut edges: Vec<GraphEdge> = self
+            .edges
+            .into_iter()
+            .map(|((source, target, edge_type), weight)| GraphEdge {
+                source,
+                target,
+                edge_type: edge_type.to_string(),
+                weight,
+            })
+            .collect();
+        edges.sort_by(|a, b| {
+            b.weight
+                .cmp(&a.weight)
+                .then_with(|| a.source.cmp(&b.source))
+                .then_with(|| a.target.cmp(&b.target))
+        });
+
+        KnowledgeGraph {
+            file_id: file_id.to_string(),
+            generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            nodes,
+            edges,
+        }
+    }
+}
+
+/// 由题目的知识点标注和 RAG 中的知识点内容构建图谱
+/// 知识点名称来自题目；知识内容中出现多个知识点名称时也记为共现
+pub fn build(file_id: &str, questions: &[Question], documents: &[Document]) -> KnowledgeGraph {
+    let mut builder = GraphBuilder::default();
+    let mut vocabulary: BTreeSet<String> = BTreeSet::new();
+
+    for q in questions {
+        let knowledge_points = normalize(&q.knowledge_points);
+        vocabulary.extend(knowledge_points.iter().cloned());
+        builder.add_group(&knowledge_points, q.chapter.trim(), q.page_number);
+    }
+
+    let vocabulary: Vec<String> = vocabulary
+        .into_iter()
+        .filter(|kp| kp.chars().count() >= MIN_MATCH_CHARS)
+        .collect();
+    for doc in documents.iter().filter(|d| d.metadata.doc_type == "knowledge") {
+        let content = doc.content.to_lowercase();
+        let found: Vec<String> = vocabulary
+            .iter()
+            .filter(|kp| content.contains(&kp.to_lowercase()))
+            .cloned()
+            .collect();
+        if !found.is_empty() {
+            builder.add_group(&found, doc.metadata.chapter.trim(), doc.metadata.page_number);
+        }
+    }
+
+    builder.finish(file_id)
+}
+
+/// 读取保存的图谱
+pub fn load(path: &Path) -> Option<KnowledgeGraph> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 保存图谱
+pub fn save(path: &Path, graph: &KnowledgeGraph) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(graph)?)?;
+    Ok(())
+}
+
+/// 去掉空白、空项和重复项
+fn normalize(knowledge_points: &[String]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for kp in knowledge_points {
+        let kp = kp.trim();
+        if !kp.is_empty() && !result.iter().any(|k| k == kp) {
+            result.push(kp.to_string());
+        }
+    }
+    result
+}
@@ -0,0 +1,215 @@
+// 知识点规范化模块 - 统一题目中知识点的写法
+// 模型给出的知识点是自由文本（"一元二次方程"、"二次方程"），按别名表替换为统一名称，
+// 并按写法和向量相似度给出合并建议，合并后记入别名表，之后分析出的题目自动使用统一名称
+
+use crate::commands::Question;
+use crate::utils::{normalize_text, DisjointSet};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 向量相似度达到这个值的知识点作为合并建议
+pub const MERGE_SIMILARITY: f32 = 0.9;
+
+// 别名表文件路径
+static ALIAS_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 别名表：规范化后的别名 -> 别名和统一名称
+static ALIASES: Lazy<RwLock<BTreeMap<String, KnowledgePointAlias>>> = Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// 知识点统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgePointStat {
+    pub name: String,
+    pub count: u32,          // 出现在多少道题目中
+    pub files: Vec<String>,  // 出现的文件
+}
+
+/// 合并建议：members 中的知识点可以合并为 canonical
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgePointCluster {
+    pub canonical: String,
+    pub members: Vec<String>,
+    pub reason: String, // "exact"（写法仅有空白、大小写、标点差异）, "embedding"
+    pub similarity: f32,
+}
+
+/// 别名表中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgePointAlias {
+    pub alias: String,
+    pub canonical: String,
+}
+
+/// 初始化别名表路径并加载（在应用启动时调用）
+pub fn init_alias_store(app_dir: &Path) {
+    let path = app_dir.join("knowledge_point_aliases.json");
+    let aliases: Vec<KnowledgePointAlias> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    *ALIASES.write() = aliases
+        .into_iter()
+        .map(|a| (normalize_text(&a.alias), a))
+        .collect();
+    *ALIAS_PATH.write() = Some(path);
+}
+
+/// 知识点的统一名称：别名表中有记录时替换，否则只去掉首尾空白
+pub fn canonicalize(name: &str) -> String {
+    let name = name.trim();
+    ALIASES
+        .read()
+        .get(&normalize_text(name))
+        .map(|a| a.canonical.clone())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// 将题目的知识点替换为统一名称，并去掉空项和重复项
+pub fn normalize_questions(questions: &mut [Question]) {
+    for q in questions.iter_mut() {
+        let mut points: Vec<String> = Vec::with_capacity(q.knowledge_points.len());
+        for kp in &q.knowledge_points {
+            let kp = canonicalize(kp);
+            if !kp.is_empty() && !points.iter().any(|p| normalize_text(p) == normalize_text(&kp)) {
+                points.push(kp);
+            }
+        }
+        q.knowledge_points = points;
+    }
+}
+
+/// 统计知识点出现次数，按次数从多到少排序
+pub fn collect_stats(questions: &[Question]) -> Vec<KnowledgePointStat> {
+    let mut stats: HashMap<String, KnowledgePointStat> = HashMap::new();
+    for q in questions {
+        for kp in &q.knowledge_points {
+            let stat = stats.entry(kp.clone()).or_insert_with(|| KnowledgePointStat {
+                name: kp.clone(),
+                count: 0,
+                files: Vec::new(),
+            });
+            stat.count += 1;
+            if !stat.files.contains(&q.file_id) {
+                stat.files.push(q.file_id.clone());
+            }
+        }
+    }
+    let mut stats: Vec<KnowledgePointStat> = stats.into_values().collect();
+    stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    stats
+}
+
+/// 写法仅有空白、大小写、标点差异的知识点，以出现最多的写法为统一名称
+pub fn exact_clusters(stats: &[KnowledgePointStat]) -> Vec<KnowledgePointCluster> {
+    let mut groups: BTreeMap<String, Vec<&KnowledgePointStat>> = BTreeMap::new();
+    for stat in stats {
+        groups.entry(normalize_text(&stat.name)).or_default().push(stat);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| cluster_from(&group, "exact", 1.0))
+        .collect()
+}
+
+/// 按名称向量的余弦相似度聚类（单链接），embeddings 与 stats 顺序一致
+pub fn embedding_clusters(
+    stats: &[KnowledgePointStat],
+    embeddings: &[Vec<f32>],
+    threshold: f32,
+) -> Vec<KnowledgePointCluster> {
+    let n = stats.len().min(embeddings.len());
+    let mut sets = DisjointSet::new(n);
+    let mut min_similarity: Vec<f32> = vec![1.0; n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if normalize_text(&stats[i].name) == normalize_text(&stats[j].name) {
+                continue; // 已在写法相同的建议中
+            }
+            let similarity = crate::rag_service::cosine_similarity(&embeddings[i], &embeddings[j]);
+            if similarity >= threshold {
+                if let Some((root, child)) = sets.union(i, j) {
+                    min_similarity[root] = min_similarity[root].min(min_similarity[child]).min(similarity);
+                }
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<&KnowledgePointStat>> = BTreeMap::new();
+    for (i, stat) in stats.iter().enumerate().take(n) {
+        let root = sets.find(i);
+        groups.entry(root).or_default().push(stat);
+    }
+    groups
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|(root, group)| cluster_from(&group, "embedding", min_similarity[root]))
+        .collect()
+}
+
+/// 记录别名：aliases 中的写法之后都替换为 canonical
+pub fn add_aliases(aliases: &[String], canonical: &str) -> Result<()> {
+    {
+        let mut table = ALIASES.write();
+        for alias in aliases {
+            let key = normalize_text(alias);
+            if !key.is_empty() && key != normalize_text(canonical) {
+                table.insert(
+                    key,
+                    KnowledgePointAlias {
+                        alias: alias.trim().to_string(),
+                        canonical: canonical.to_string(),
+                    },
+                );
+            }
+        }
+        // 原来指向别名的记录改为指向新的统一名称
+        let alias_keys: Vec<String> = aliases.iter().map(|a| normalize_text(a)).collect();
+        for entry in table.values_mut() {
+            if alias_keys.contains(&normalize_text(&entry.canonical)) {
+                entry.canonical = canonical.to_string();
+            }
+        }
+        table.remove(&normalize_text(canonical));
+    }
+    save_aliases()
+}
+
+/// 删除别名
+pub fn remove_alias(alias: &str) -> Result<()> {
+    ALIASES.write().remove(&normalize_text(alias));
+    save_aliases()
+}
+
+/// 列出别名表
+pub fn list_aliases() -> Vec<KnowledgePointAlias> {
+    ALIASES.read().values().cloned().collect()
+}
+
+fn save_aliases() -> Result<()> {
+    let path = match ALIAS_PATH.read().clone() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    fs::write(path, serde_json::to_string_pretty(&list_aliases())?)?;
+    Ok(())
+}
+
+fn cluster_from(group: &[&KnowledgePointStat], reason: &str, similarity: f32) -> KnowledgePointCluster {
+    let canonical = group
+        .iter()
+        .max_by(|a, b| a.count.cmp(&b.count).then_with(|| b.name.len().cmp(&a.name.len())))
+        .map(|s| s.name.clone())
+        .unwrap_or_default();
+    KnowledgePointCluster {
+        members: group.iter().map(|s| s.name.clone()).filter(|name| *name != canonical).collect(),
+        canonical,
+        reason: reason.to_string(),
+        similarity,
+    }
+}
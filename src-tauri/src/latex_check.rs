@@ -0,0 +1,270 @@
+// 公式检查模块 - 检查题目 Markdown 中的 LaTeX 公式能否正常渲染
+// 检查未闭合的 $ / $$、括号不匹配、\begin 与 \end 不配对、公式分隔符以外的环境，以及 KaTeX 不支持的命令
+
+use crate::commands::Question;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// 问题片段的最大显示长度（字符）
+const SNIPPET_CHARS: usize = 40;
+
+// 代码块和行内代码中的内容不按公式处理
+static CODE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)```.*?```|`[^`\n]*`").unwrap());
+
+static ENVIRONMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\(begin|end)\{([^}]*)\}").unwrap());
+
+// 命令名称；换行符 \\ 整体匹配，避免把后面的字母当作命令
+static COMMAND_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\(?:\\|([A-Za-z]+))").unwrap());
+
+/// KaTeX 支持的常用命令（前端使用 KaTeX 渲染公式）
+static KNOWN_COMMANDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        // 结构
+        "frac", "dfrac", "tfrac", "cfrac", "sqrt", "binom", "dbinom", "tbinom", "over", "choose",
+        "begin", "end", "left", "right", "middle", "big", "Big", "bigg", "Bigg", "bigl", "bigr", "Bigl",
+        "Bigr", "biggl", "biggr", "Biggl", "Biggr", "overline", "underline", "overbrace", "underbrace",
+        "overset", "underset", "stackrel", "xrightarrow", "xleftarrow", "hat", "widehat", "tilde",
+        "widetilde", "bar", "vec", "overrightarrow", "overleftarrow", "dot", "ddot", "acute", "grave",
+        "check", "breve", "boxed", "not", "cancel", "limits", "nolimits", "displaystyle", "textstyle",
+        "scriptstyle", "substack", "tag", "notag", "nonumber", "label", "hline", "cline", "hdashline",
+        "phantom", "hphantom", "vphantom", "color", "textcolor", "pmod", "bmod", "mod", "pod",
+        // 字体和文字
+        "text", "textrm", "textbf", "textit", "textsf", "texttt", "mathrm", "mathbf", "mathit", "mathsf",
+        "mathtt", "mathbb", "mathcal", "mathscr", "mathfrak", "boldsymbol", "bm", "operatorname", "mbox",
+        "rm", "bf", "it", "cal",
+        // 间距
+        "quad", "qquad", "enspace", "thinspace", "medspace", "thickspace", "hspace", "vspace", "kern",
+        "mkern", "space", "nobreakspace", "newline",
+        // 希腊字母
+        "alpha", "beta", "gamma", "delta", "epsilon", "varepsilon", "zeta", "eta", "theta", "vartheta",
+        "iota", "kappa", "lambda", "mu", "nu", "xi", "pi", "varpi", "rho", "varrho", "sigma", "varsigma",
+        "tau", "upsilon", "phi", "varphi", "chi", "psi", "omega", "Gamma", "Delta", "Theta", "Lambda",
+        "Xi", "Pi", "Sigma", "Upsilon", "Phi", "Psi", "Omega",
+        // 运算符
+        "times", "div", "cdot", "pm", "mp", "ast", "star", "circ", "bullet", "oplus", "ominus", "otimes",
+        "odot", "cap", "cup", "bigcap", "bigcup", "sqcup", "wedge", "vee", "land", "lor", "lnot", "neg",
+        "setminus", "backslash", "sum", "prod", "coprod", "int", "iint", "iiint", "oint", "lim", "limsup",
+        "liminf", "sup", "inf", "max", "min", "arg", "det", "dim", "ker", "deg", "gcd", "exp", "log", "ln",
+        "lg", "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh",
+        "tanh", "coth", "Pr", "partial", "nabla", "infty", "prime", "triangle", "triangledown",
+        "angle", "measuredangle", "perp", "parallel", "nparallel", "mid", "nmid", "degree", "circledcirc",
+        // 关系
+        "leq", "le", "geq", "ge", "neq", "ne", "leqslant", "geqslant", "ll", "gg", "approx", "sim",
+        "simeq", "cong", "equiv", "propto", "doteq", "in", "notin", "ni", "subset", "supset", "subseteq",
+        "supseteq", "subsetneq", "supsetneq", "nsubseteq", "emptyset", "varnothing", "forall", "exists",
+        "nexists", "therefore", "because", "iff", "implies", "impliedby", "to", "gets", "mapsto",
+        "rightarrow", "leftarrow", "Rightarrow", "Leftarrow", "leftrightarrow", "Leftrightarrow",
+        "longrightarrow", "longleftarrow", "Longrightarrow", "Longleftarrow", "longleftrightarrow",
+        "Longleftrightarrow", "uparrow", "downarrow", "Uparrow", "Downarrow", "updownarrow", "nearrow",
+        "searrow", "nwarrow", "swarrow", "rightleftharpoons", "lt", "gt", "sphericalangle", "square",
+        "blacksquare", "Box", "lozenge",
+        // 定界符和符号
+        "langle", "rangle", "lfloor", "rfloor", "lceil", "rceil", "lbrace", "rbrace", "lvert", "rvert",
+        "lVert", "rVert", "vert", "Vert", "ldots", "cdots", "vdots", "ddots", "dots", "dotsc", "dotsb",
+        "hbar", "ell", "Re", "Im", "aleph", "wp", "imath", "jmath", "percent", "S", "P", "dagger",
+        "ddagger", "checkmark", "complement", "surd", "top", "bot", "diamond", "heartsuit", "spadesuit",
+        "clubsuit", "diamondsuit", "flat", "sharp", "natural", "textdegree", "And",
+    ]
+    .into_iter()
+    .collect()
+});
+
+/// KaTeX 支持的环境
+static KNOWN_ENVIRONMENTS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        "matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix", "array", "cases",
+        "rcases", "dcases", "aligned", "alignedat", "gathered", "split", "equation", "equation*", "align",
+        "align*", "gather", "gather*", "alignat", "alignat*", "CD", "darray", "subarray",
+    ]
+    .into_iter()
+    .collect()
+});
+
+/// 一处公式问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatexIssue {
+    pub field: String, // "question_text", "answer", "analysis", "options.A"
+    pub kind: String, // "unclosed_math", "unbalanced_braces", "environment", "unknown_command", "left_right"
+    pub message: String,
+    pub snippet: String,
+}
+
+/// 一道题的检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatexReport {
+    pub question_id: String,
+    pub file_id: String,
+    pub page_number: u32,
+    pub issues: Vec<LatexIssue>,
+    #[serde(default)]
+    pub fixed: bool, // 规范化后已修复
+}
+
+/// 检查一道题的题干、选项、答案和解析
+pub fn check_question(question: &Question) -> Vec<LatexIssue> {
+    let mut issues = check_text("question_text", &question.question_text);
+    for option in &question.options {
+        issues.extend(check_text(&format!("options.{}", option.label), &option.text));
+    }
+    issues.extend(check_text("answer", &question.answer));
+    issues.extend(check_text("analysis", &question.analysis));
+    issues
+}
+
+/// 检查一段 Markdown 中的公式
+pub fn check_text(field: &str, text: &str) -> Vec<LatexIssue> {
+    let text = CODE_RE.replace_all(text, |caps: &regex::Captures| " ".repeat(caps[0].len()));
+    let mut issues = Vec::new();
+    let mut issue = |kind: &str, message: String, snippet: &str| {
+        issues.push(LatexIssue {
+            field: field.to_string(),
+            kind: kind.to_string(),
+            message,
+            snippet: snippet.chars().take(SNIPPET_CHARS).collect(),
+        });
+    };
+
+    let mut rest: &str = &text;
+    let mut outside = String::new(); // 公式以外的文字
+    while let Some(start) = find_math_start(rest) {
+        outside.push_str(&rest[..start]);
+        let after = &rest[start..];
+        let (open, close) = if after.starts_with("$$") {
+            ("$$", "$$")
+        } else if after.starts_with('$') {
+            ("$", "$")
+        } else if after.starts_with("\\(") {
+            ("\\(", "\\)")
+        } else {
+            ("\\[", "\\]")
+        };
+        let body_start = &after[open.len()..];
+        match find_unescaped(body_start, close) {
+            Some(end) => {
+                check_math(&body_start[..end], &mut issue);
+                rest = &body_start[end + close.len()..];
+            }
+            None => {
+                issue("unclosed_math", format!("公式缺少结束符号 {}", close), after);
+                rest = "";
+            }
+        }
+    }
+    outside.push_str(rest);
+
+    // 公式分隔符以外的环境不会被渲染
+    if let Some(m) = ENVIRONMENT_RE.find(&outside) {
+        issue(
+            "environment",
+            "公式环境没有放在 $$ 中，无法渲染".to_string(),
+            &outside[m.start()..],
+        );
+    }
+    issues
+}
+
+/// 检查公式内容：大括号、环境、\left 与 \right、命令
+fn check_math(math: &str, issue: &mut impl FnMut(&str, String, &str)) {
+    let mut depth: i32 = 0;
+    let mut escaped = false;
+    for c in math.chars() {
+        match c {
+            '\\' => {
+                escaped = !escaped;
+                continue;
+            }
+            '{' if !escaped => depth += 1,
+            '}' if !escaped => depth -= 1,
+            _ => {}
+        }
+        escaped = false;
+        if depth < 0 {
+            break;
+        }
+    }
+    if depth != 0 {
+        issue("unbalanced_braces", "公式中的大括号不配对".to_string(), math);
+    }
+
+    let mut stack: Vec<&str> = Vec::new();
+    for caps in ENVIRONMENT_RE.captures_iter(math) {
+        let name = caps.get(2).map_or("", |m| m.as_str());
+        if &caps[1] == "begin" {
+            if !KNOWN_ENVIRONMENTS.contains(name) {
+                issue("environment", format!("不支持的环境 {}", name), &caps[0]);
+            }
+            stack.push(name);
+        } else if stack.pop() != Some(name) {
+            issue("environment", format!("\\end{{{}}} 没有对应的 \\begin", name), &caps[0]);
+            return;
+        }
+    }
+    if let Some(name) = stack.last() {
+        issue("environment", format!("\\begin{{{}}} 缺少 \\end", name), math);
+    }
+
+    let mut lefts = 0;
+    let mut rights = 0;
+    let mut unknown: Vec<&str> = Vec::new();
+    for caps in COMMAND_RE.captures_iter(math) {
+        let name = match caps.get(1) {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
+        match name {
+            "left" => lefts += 1,
+            "right" => rights += 1,
+            _ => {}
+        }
+        if !KNOWN_COMMANDS.contains(name) && !unknown.contains(&name) {
+            unknown.push(name);
+        }
+    }
+    if lefts != rights {
+        issue("left_right", "\\left 与 \\right 数量不一致".to_string(), math);
+    }
+    for name in unknown {
+        issue("unknown_command", format!("未定义的命令 \\{}", name), math);
+    }
+}
+
+/// 下一个公式开始符号（$$、$、\(、\[）的位置，跳过转义的 \$
+fn find_math_start(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if i + 1 < bytes.len() => {
+                if bytes[i + 1] == b'(' || bytes[i + 1] == b'[' {
+                    return Some(i);
+                }
+                i += 2;
+                continue;
+            }
+            b'$' => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 查找未转义的结束符号
+fn find_unescaped(text: &str, close: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(close.as_bytes()) {
+            return Some(i);
+        }
+        // \) 和 \] 本身以反斜杠开头，其他情况跳过转义字符
+        if bytes[i] == b'\\' && !close.starts_with('\\') {
+            i += 2;
+            continue;
+        }
+        i += 1;
+    }
+    None
+}
@@ -0,0 +1,255 @@
+// 学习平台导出模块 - 将题目导出为 Moodle XML 和 GIFT 格式，导入 Moodle 等学习平台的题库
+// 有选项和正确选项的题目导出为选择题，答案很短的导出为填空（简答）题，其余导出为问答题
+// 题目按章节放入不同的题库类别
+
+use crate::anki_export::{escape_html, markdown_to_html};
+use crate::commands::Question;
+use base64::{engine::general_purpose, Engine as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 答案不超过这个字符数且只有一行时导出为简答题（由平台自动判分）
+const SHORT_ANSWER_MAX_CHARS: usize = 20;
+
+/// 题目名称取题干的前若干字符
+const NAME_CHARS: usize = 30;
+
+/// 要导出的题目，base_dir 为题目插图路径的基准目录（文件存储目录）
+pub struct ExportQuestion<'a> {
+    pub question: &'a Question,
+    pub base_dir: PathBuf,
+}
+
+/// 导出时的题型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionKind {
+    Choice,
+    ShortAnswer,
+    Essay,
+}
+
+/// 判断导出时的题型
+pub fn question_kind(q: &Question) -> QuestionKind {
+    if !q.options.is_empty() && !q.correct_options.is_empty() {
+        return QuestionKind::Choice;
+    }
+    let answer = q.answer.trim();
+    if !answer.is_empty()
+        && !answer.contains('\n')
+        && !answer.contains('$')
+        && answer.chars().count() <= SHORT_ANSWER_MAX_CHARS
+    {
+        QuestionKind::ShortAnswer
+    } else {
+        QuestionKind::Essay
+    }
+}
+
+/// 题目名称：题干开头的文字（去掉公式符号和换行）
+pub fn question_name(q: &Question) -> String {
+    let text: String = q
+        .question_text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('$', "");
+    let name: String = text.chars().take(NAME_CHARS).collect();
+    if name.is_empty() {
+        q.id.clone()
+    } else {
+        name
+    }
+}
+
+/// 多选题每个正确选项的得分比例（Moodle 只接受特定的比例，100/n 都在其中）
+fn choice_fraction(correct_count: usize) -> String {
+    let fraction = 100.0 / correct_count.max(1) as f64;
+    let formatted = format!("{:.5}", fraction);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 生成 Moodle XML，插图以 base64 嵌入
+pub fn to_moodle_xml(questions: &[ExportQuestion]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<quiz>\n");
+    let mut current_category: Option<&str> = None;
+    for item in questions {
+        let q = item.question;
+        if current_category != Some(q.chapter.as_str()) {
+            current_category = Some(q.chapter.as_str());
+            xml.push_str(&format!(
+                "  <question type=\"category\">\n    <category><text>{}</text></category>\n  </question>\n",
+                escape_xml(&category_path(&q.chapter))
+            ));
+        }
+
+        let (text_html, files) = html_with_images(q, &item.base_dir);
+        let kind = question_kind(q);
+        let type_name = match kind {
+            QuestionKind::Choice => "multichoice",
+            QuestionKind::ShortAnswer => "shortanswer",
+            QuestionKind::Essay => "essay",
+        };
+        xml.push_str(&format!("  <question type=\"{}\">\n", type_name));
+        xml.push_str(&format!("    <name><text>{}</text></name>\n", escape_xml(&question_name(q))));
+        xml.push_str(&format!(
+            "    <questiontext format=\"html\">\n      <text>{}</text>\n",
+            cdata(&text_html)
+        ));
+        for (name, data) in &files {
+            xml.push_str(&format!(
+                "      <file name=\"{}\" path=\"/\" encoding=\"base64\">{}</file>\n",
+                escape_xml(name),
+                data
+            ));
+        }
+        xml.push_str("    </questiontext>\n");
+        xml.push_str(&format!(
+            "    <generalfeedback format=\"html\"><text>{}</text></generalfeedback>\n",
+            cdata(&markdown_to_html(&q.analysis))
+        ));
+        xml.push_str("    <defaultgrade>1</defaultgrade>\n");
+        if !q.knowledge_points.is_empty() {
+            xml.push_str("    <tags>\n");
+            for kp in &q.knowledge_points {
+                xml.push_str(&format!("      <tag><text>{}</text></tag>\n", escape_xml(kp)));
+            }
+            xml.push_str("    </tags>\n");
+        }
+
+        match kind {
+            QuestionKind::Choice => {
+                let single = q.correct_options.len() == 1;
+                let correct_fraction = choice_fraction(q.correct_options.len());
+                xml.push_str(&format!("    <single>{}</single>\n", single));
+                xml.push_str("    <shuffleanswers>1</shuffleanswers>\n");
+                xml.push_str("    <answernumbering>ABCD</answernumbering>\n");
+                for option in &q.options {
+                    let fraction = if q.correct_options.contains(&option.label) {
+                        correct_fraction.as_str()
+                    } else if single {
+                        "0"
+                    } else {
+                        "-100"
+                    };
+                    xml.push_str(&format!(
+                        "    <answer fraction=\"{}\" format=\"html\"><text>{}</text></answer>\n",
+                        fraction,
+                        cdata(&markdown_to_html(&option.text))
+                    ));
+                }
+            }
+            QuestionKind::ShortAnswer => {
+                xml.push_str("    <usecase>0</usecase>\n");
+                xml.push_str(&format!(
+                    "    <answer fraction=\"100\" format=\"plain_text\"><text>{}</text></answer>\n",
+                    escape_xml(q.answer.trim())
+                ));
+            }
+            QuestionKind::Essay => {
+                xml.push_str("    <responseformat>editor</responseformat>\n");
+                xml.push_str(&format!(
+                    "    <graderinfo format=\"html\"><text>{}</text></graderinfo>\n",
+                    cdata(&markdown_to_html(&q.answer))
+                ));
+            }
+        }
+        xml.push_str("  </question>\n");
+    }
+    xml.push_str("</quiz>\n");
+    xml
+}
+
+/// 生成 GIFT 文本（GIFT 不支持嵌入图片，插图不导出）
+pub fn to_gift(questions: &[ExportQuestion]) -> String {
+    let mut gift = String::new();
+    let mut current_category: Option<&str> = None;
+    for item in questions {
+        let q = item.question;
+        if current_category != Some(q.chapter.as_str()) {
+            current_category = Some(q.chapter.as_str());
+            gift.push_str(&format!("$CATEGORY: {}\n\n", category_path(&q.chapter)));
+        }
+
+        let text = escape_gift(&markdown_to_html(&q.question_text));
+        let feedback = if q.analysis.trim().is_empty() {
+            String::new()
+        } else {
+            format!(" ####{}", escape_gift(&markdown_to_html(&q.analysis)))
+        };
+        let answers = match question_kind(q) {
+            QuestionKind::Choice => {
+                let single = q.correct_options.len() == 1;
+                let correct_fraction = choice_fraction(q.correct_options.len());
+                q.options
+                    .iter()
+                    .map(|option| {
+                        let text = escape_gift(&markdown_to_html(&option.text));
+                        let correct = q.correct_options.contains(&option.label);
+                        match (single, correct) {
+                            (true, true) => format!("\n  ={}", text),
+                            (true, false) => format!("\n  ~{}", text),
+                            (false, true) => format!("\n  ~%{}%{}", correct_fraction, text),
+                            (false, false) => format!("\n  ~%-100%{}", text),
+                        }
+                    })
+                    .collect::<String>()
+                    + "\n"
+            }
+            QuestionKind::ShortAnswer => format!("={}", escape_gift(q.answer.trim())),
+            QuestionKind::Essay => String::new(),
+        };
+        gift.push_str(&format!(
+            "::{}::[html]{} {{{}{}}}\n\n",
+            escape_gift(&question_name(q)),
+            text,
+            answers,
+            feedback
+        ));
+    }
+    gift
+}
+
+/// 题目所在的题库类别：BooQ/章节
+fn category_path(chapter: &str) -> String {
+    let chapter = chapter.trim().replace('/', "-");
+    if chapter.is_empty() {
+        "$course$/BooQ/未分章节".to_string()
+    } else {
+        format!("$course$/BooQ/{}", chapter)
+    }
+}
+
+/// 题干 HTML（含选项以外的内容和插图引用）及要嵌入的图片（文件名、base64 内容）
+fn html_with_images(q: &Question, base_dir: &Path) -> (String, Vec<(String, String)>) {
+    let mut html = markdown_to_html(&q.question_text);
+    let mut files = Vec::new();
+    for path in crate::question_images::image_files(q, base_dir) {
+        let (Some(name), Ok(data)) = (path.file_name().map(|n| n.to_string_lossy().to_string()), fs::read(&path))
+        else {
+            continue;
+        };
+        html.push_str(&format!("<p><img src=\"@@PLUGINFILE@@/{}\"></p>", escape_html(&name)));
+        files.push((name, general_purpose::STANDARD.encode(data)));
+    }
+    (html, files)
+}
+
+pub fn escape_xml(text: &str) -> String {
+    escape_html(text).replace('\'', "&apos;")
+}
+
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// GIFT 的特殊字符需要转义
+fn escape_gift(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '~' | '=' | '#' | '{' | '}' | ':') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -6,14 +6,52 @@
     windows_subsystem = "windows"
 )]
 
+mod api_server;
 mod commands;
+mod database;
 mod file_manager;
 mod ai_service;
+mod anki_export;
+mod bank_exchange;
+mod ai_usage;
+mod ai_trace;
+mod ai_cache;
+mod analysis_queue;
+mod embedding_cache;
+mod exam_paper;
+mod json_repair;
+mod json_store;
 mod ocr_service;
 mod mineru_service;
+mod mineru_layout;
+mod mineru_diagnostics;
+mod mineru_queue;
+mod python_env;
 mod rag_service;
+mod knowledge_graph;
+mod knowledge_points;
+mod latex_check;
+mod lms_export;
+mod markdown_export;
+mod chapter_structure;
 mod question_analyzer;
+mod question_banks;
+mod question_dedup;
+mod question_history;
+mod question_images;
+mod question_index;
+mod question_options;
+mod question_translation;
+mod pdf_export;
+mod practice_session;
+mod qti_export;
+mod spreadsheet_export;
+mod study_records;
+mod sync_service;
+mod workspace_backup;
 mod config;
+mod config_profiles;
+mod http_client;
 mod utils;
 mod logger;
 
@@ -30,6 +68,45 @@ fn main() {
             // 初始化配置
             config::init_config(&app_dir);
             
+            // 初始化 MinerU 虚拟环境路径
+            python_env::init_venv_dir(&app_dir);
+            
+            // 加载 AI 用量记录、知识点别名表和作答记录，初始化响应缓存、向量缓存、全局知识库和数据库路径
+            ai_usage::init_usage_store(&app_dir);
+            knowledge_points::init_alias_store(&app_dir);
+            study_records::init_store(&app_dir);
+            ai_cache::init_cache_dir(&app_dir);
+            embedding_cache::init_cache(&app_dir);
+            rag_service::init_global_store(&app_dir);
+            database::init_database(&app_dir);
+            
+            // 应用转换队列并发、超时、自定义路径、模型目录、代理、调试、缓存和知识库设置
+            let app_config = config::get_config_sync(&app.handle());
+            mineru_queue::set_max_concurrent(app_config.mineru_max_concurrent);
+            mineru_service::set_conversion_timeout(app_config.mineru_timeout_secs);
+            python_env::set_path_overrides(
+                &app_config.python_path,
+                &app_config.pip_path,
+                &app_config.magic_pdf_path,
+            );
+            mineru_service::set_models_dir_override(&app_config.mineru_models_dir);
+            http_client::set_proxy(&app_config.proxy_url, &app_config.no_proxy);
+            ai_trace::set_enabled(app_config.ai_debug_trace);
+            ai_cache::set_cache_settings(app_config.ai_cache_enabled, app_config.ai_cache_ttl_hours);
+            rag_service::set_global_enabled(app_config.global_knowledge_base);
+            
+            // 启动本地 HTTP 接口
+            if let Err(e) = api_server::apply(&app.handle(), &app_config.api_server) {
+                logger::warn("system", &format!("本地接口服务启动失败: {}", e));
+            }
+            
+            // 加载分析队列，继续执行上次未完成的任务
+            analysis_queue::init_queue(&app_dir);
+            analysis_queue::start_worker(&app.handle());
+            
+            // 继续查询上次未结束的批量分析任务
+            question_analyzer::resume_batch_polling(&app.handle());
+            
             // 记录启动日志
             logger::info("system", "BooQ 应用启动");
             
@@ -56,10 +133,85 @@ fn main() {
             
             // AI 分析命令
             commands::start_analysis,
+            commands::start_analysis_range,
+            commands::resume_analysis,
+            commands::get_analysis_checkpoint,
+            commands::get_failed_pages,
+            commands::retry_failed_pages,
+            commands::get_analysis_runs,
+            commands::rollback_questions,
             commands::stop_analysis,
+            commands::enqueue_analysis,
+            commands::get_analysis_queue,
+            commands::cancel_queued_analysis,
+            commands::move_queued_analysis,
+            commands::clear_finished_analysis_jobs,
+            commands::set_analysis_queue_paused,
             commands::get_analysis_progress,
+            commands::start_batch_analysis,
+            commands::poll_batch_analysis,
+            commands::cancel_batch_analysis,
+            commands::get_batch_job,
             commands::get_questions,
             commands::get_question_detail,
+            commands::search_questions,
+            commands::search_documents,
+            commands::create_question,
+            commands::update_question,
+            commands::delete_question,
+            commands::find_duplicate_questions,
+            commands::merge_duplicates,
+            commands::get_knowledge_points,
+            commands::suggest_knowledge_point_merges,
+            commands::merge_knowledge_points,
+            commands::rename_knowledge_point,
+            commands::get_knowledge_point_aliases,
+            commands::remove_knowledge_point_alias,
+            commands::record_attempt,
+            commands::get_wrong_questions,
+            commands::get_knowledge_point_mastery,
+            commands::clear_attempts,
+            commands::start_practice,
+            commands::submit_answer,
+            commands::finish_session,
+            commands::get_practice_sessions,
+            commands::get_practice_session,
+            commands::get_questions_by_page,
+            commands::get_chapter_tree,
+            commands::validate_latex,
+            commands::get_question_history,
+            commands::revert_question,
+            commands::approve_questions,
+            commands::reject_questions,
+            commands::merge_banks,
+            commands::get_banks,
+            commands::get_bank,
+            commands::delete_bank,
+            commands::export_anki,
+            commands::export_moodle_xml,
+            commands::export_gift,
+            commands::export_qti,
+            commands::export_questions_csv,
+            commands::export_questions_xlsx,
+            commands::export_markdown,
+            commands::export_bank,
+            commands::import_bank,
+            commands::translate_questions,
+            commands::generate_paper,
+            commands::get_papers,
+            commands::get_paper,
+            commands::delete_paper,
+            commands::export_paper,
+            commands::export_paper_pdf,
+            commands::export_questions_pdf,
+            commands::get_knowledge_graph,
+            commands::ask_document,
+            commands::rebuild_rag_index,
+            commands::export_rag_index,
+            commands::import_rag_index,
+            commands::compact_rag_index,
+            commands::search_knowledge,
+            commands::grade_answer,
             
             // 配置命令
             commands::get_config,
@@ -69,10 +221,33 @@ fn main() {
             commands::remove_model,
             commands::set_storage_path,
             commands::get_storage_path,
+            commands::export_config,
+            commands::import_config,
+            commands::reset_config,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::switch_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::backup_workspace,
+            commands::restore_workspace,
+            commands::sync_now,
+            commands::test_sync_connection,
+            commands::get_api_server_status,
+            commands::regenerate_api_token,
             
             // 系统命令
             commands::get_system_theme,
             commands::test_model,
+            commands::list_ollama_models,
+            commands::test_embedding_model,
+            commands::get_ai_usage,
+            commands::clear_ai_usage,
+            commands::get_ai_call_history,
+            commands::get_ai_cache_stats,
+            commands::clear_ai_cache,
+            commands::get_embedding_cache_stats,
+            commands::clear_embedding_cache,
             
             // MinerU 相关命令
             commands::check_mineru_installed,
@@ -83,8 +258,21 @@ fn main() {
             commands::install_modelscope,
             commands::download_mineru_models,
             commands::download_ocr_models,
+            commands::verify_mineru_models,
+            commands::get_mineru_version,
             commands::update_mineru_config,
+            commands::relocate_mineru_models,
+            commands::create_mineru_venv,
+            commands::get_python_env_info,
+            commands::remove_mineru_venv,
+            commands::remove_mineru_models,
+            commands::remove_mineru_config,
+            commands::uninstall_mineru,
             commands::convert_with_mineru,
+            commands::convert_with_mineru_batch,
+            commands::convert_pages_with_mineru,
+            commands::cancel_mineru_conversion,
+            commands::get_mineru_queue_status,
             
             // 日志命令
             commands::get_logs,
@@ -0,0 +1,241 @@
+// Markdown 导出模块 - 将题目导出为 Markdown 文件，可以直接放入 Obsidian 等笔记库
+// 按章节导出时每章一个文件；按题目导出时每道题一个文件（放在章节目录中），开头带 YAML frontmatter 元数据
+// 插图复制到导出目录下的 assets 目录，公式保持 $...$ 写法；答案和解析放在 Obsidian 可折叠的 callout 中
+
+use crate::commands::Question;
+use crate::question_images::{image_files, strip_image_links};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ASSETS_DIR: &str = "assets";
+
+/// 文件名最多保留的字符数
+const MAX_FILE_NAME_CHARS: usize = 60;
+
+/// 要导出的题目，base_dir 为题目插图路径的基准目录（文件存储目录）
+pub struct MarkdownQuestion<'a> {
+    pub question: &'a Question,
+    pub base_dir: PathBuf,
+}
+
+/// 导出到 dest_dir，per_question 为 true 时每道题一个文件，否则每章一个文件；返回写入的文件数
+pub fn write_vault(dest_dir: &Path, questions: &[MarkdownQuestion], per_question: bool) -> Result<usize> {
+    fs::create_dir_all(dest_dir)?;
+    let mut assets = Assets::new(dest_dir.join(ASSETS_DIR));
+
+    // 按章节分组，保持题目原来的顺序
+    let mut chapters: Vec<(String, Vec<&MarkdownQuestion>)> = Vec::new();
+    for item in questions {
+        let chapter = chapter_name(item.question);
+        match chapters.iter_mut().find(|(name, _)| *name == chapter) {
+            Some((_, items)) => items.push(item),
+            None => chapters.push((chapter, vec![item])),
+        }
+    }
+
+    let mut written = 0;
+    for (chapter, items) in &chapters {
+        if per_question {
+            let chapter_dir = dest_dir.join(safe_file_name(chapter));
+            fs::create_dir_all(&chapter_dir)?;
+            let mut used_names = HashMap::new();
+            for (index, item) in items.iter().enumerate() {
+                let name = unique_name(&mut used_names, &question_file_name(index + 1, item.question));
+                let body = question_markdown(item, &mut assets, "../", None)?;
+                let content = format!("{}\n{}", frontmatter(item.question), body);
+                fs::write(chapter_dir.join(format!("{}.md", name)), content)?;
+                written += 1;
+            }
+        } else {
+            let mut content = format!("# {}\n\n", chapter);
+            for (index, item) in items.iter().enumerate() {
+                content.push_str(&question_markdown(item, &mut assets, "", Some(index + 1))?);
+                content.push('\n');
+            }
+            fs::write(dest_dir.join(format!("{}.md", safe_file_name(chapter))), content)?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// 一道题的 Markdown：题干、选项、插图，以及折叠的答案和解析
+/// heading_number 不为空时以 "## 第 N 题" 开头（按章节导出），asset_prefix 为文件到导出目录的相对路径
+fn question_markdown(
+    item: &MarkdownQuestion,
+    assets: &mut Assets,
+    asset_prefix: &str,
+    heading_number: Option<usize>,
+) -> Result<String> {
+    let q = item.question;
+    let mut md = String::new();
+    if let Some(number) = heading_number {
+        md.push_str(&format!("## 第 {} 题（第 {} 页）\n\n", number, q.page_number));
+    }
+    let text = strip_image_links(&q.question_text);
+    md.push_str(text.trim());
+    md.push_str("\n\n");
+    for option in &q.options {
+        md.push_str(&format!("- {}. {}\n", option.label, option.text.trim()));
+    }
+    if !q.options.is_empty() {
+        md.push('\n');
+    }
+    for source in image_files(q, &item.base_dir) {
+        if let Some(name) = assets.copy(&source)? {
+            md.push_str(&format!("![]({}{}/{})\n\n", asset_prefix, ASSETS_DIR, name));
+        }
+    }
+    if heading_number.is_some() && !q.knowledge_points.is_empty() {
+        md.push_str(&format!("知识点：{}\n\n", tags(q).join(" ")));
+    }
+
+    md.push_str("> [!success]- 答案\n");
+    let labels = q.correct_options.join("");
+    if !labels.is_empty() && !q.answer.trim_start().starts_with(&labels) {
+        md.push_str(&format!("> **{}**\n>\n", labels));
+    }
+    md.push_str(&quote(&q.answer));
+    if !q.analysis.trim().is_empty() {
+        md.push_str("\n> [!info]- 解析\n");
+        md.push_str(&quote(&q.analysis));
+    }
+    Ok(md)
+}
+
+/// 题目元数据（YAML frontmatter，字符串按 JSON 写法加引号，YAML 可以直接解析）
+fn frontmatter(q: &Question) -> String {
+    let string = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let list = |items: &[String]| {
+        if items.is_empty() {
+            " []".to_string()
+        } else {
+            items.iter().map(|i| format!("\n  - {}", string(i))).collect()
+        }
+    };
+    let tags: Vec<String> = tags(q).iter().map(|t| t.trim_start_matches('#').to_string()).collect();
+    let mut yaml = String::from("---\n");
+    yaml.push_str(&format!("id: {}\n", string(&q.id)));
+    yaml.push_str(&format!("file_id: {}\n", string(&q.file_id)));
+    yaml.push_str(&format!("type: {}\n", string(&q.question_type)));
+    yaml.push_str(&format!("chapter: {}\n", string(&q.chapter)));
+    yaml.push_str(&format!("section: {}\n", string(&q.section)));
+    yaml.push_str(&format!("page: {}\n", q.page_number));
+    yaml.push_str(&format!("difficulty: {}\n", string(&q.difficulty)));
+    yaml.push_str(&format!("review_status: {}\n", string(&q.review_status)));
+    yaml.push_str(&format!("knowledge_points:{}\n", list(&q.knowledge_points)));
+    yaml.push_str(&format!("tags:{}\n", list(&tags)));
+    yaml.push_str("---\n");
+    yaml
+}
+
+/// 知识点转换为 Obsidian 标签（不能包含空格和标点）
+fn tags(q: &Question) -> Vec<String> {
+    q.knowledge_points
+        .iter()
+        .map(|kp| {
+            kp.trim()
+                .chars()
+                .map(|c| if c.is_alphanumeric() || c == '_' || c == '-' || c == '/' { c } else { '_' })
+                .collect::<String>()
+        })
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| format!("#{}", tag))
+        .collect()
+}
+
+/// 引用块中的内容，每行加上 "> "
+fn quote(text: &str) -> String {
+    text.trim()
+        .lines()
+        .map(|line| if line.is_empty() { ">\n".to_string() } else { format!("> {}\n", line) })
+        .collect()
+}
+
+fn chapter_name(q: &Question) -> String {
+    match q.chapter.trim() {
+        "" => "未分章节".to_string(),
+        chapter => chapter.to_string(),
+    }
+}
+
+/// 按题目导出时的文件名：序号和题干开头
+fn question_file_name(number: usize, q: &Question) -> String {
+    let text: String = strip_image_links(&q.question_text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('$', "");
+    safe_file_name(&format!("{:03} {}", number, text))
+}
+
+/// 去掉文件名中不允许（或在 Obsidian 链接中有特殊含义）的字符
+fn safe_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .take(MAX_FILE_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').trim();
+    if cleaned.is_empty() {
+        "untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+fn unique_name(used: &mut HashMap<String, usize>, name: &str) -> String {
+    let count = used.entry(name.to_string()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, count)
+    }
+}
+
+/// 复制到 assets 目录的插图，同一源文件只复制一次，不同源文件的同名图片改名
+struct Assets {
+    dir: PathBuf,
+    copied: HashMap<PathBuf, String>,
+    used_names: HashMap<String, usize>,
+}
+
+impl Assets {
+    fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            copied: HashMap::new(),
+            used_names: HashMap::new(),
+        }
+    }
+
+    /// 复制插图并返回 assets 目录中的文件名，源文件不存在时返回 None
+    fn copy(&mut self, source: &Path) -> Result<Option<String>> {
+        if let Some(name) = self.copied.get(source) {
+            return Ok(Some(name.clone()));
+        }
+        if !source.is_file() {
+            return Ok(None);
+        }
+        let stem = source
+            .file_stem()
+            .map(|s| safe_file_name(&s.to_string_lossy()).replace(' ', "_"))
+            .unwrap_or_else(|| "image".to_string());
+        let extension = source
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        let name = format!("{}{}", unique_name(&mut self.used_names, &stem).replace(' ', "_"), extension);
+        fs::create_dir_all(&self.dir)?;
+        fs::copy(source, self.dir.join(&name))?;
+        self.copied.insert(source.to_path_buf(), name.clone());
+        Ok(Some(name))
+    }
+}
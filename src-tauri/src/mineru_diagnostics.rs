@@ -0,0 +1,114 @@
+// MinerU 错误诊断模块 - 从 magic-pdf 的 stderr 中识别常见的失败原因
+// 将显存不足、模型缺失、PDF 损坏等问题归类，并给出可操作的建议
+
+use serde::Serialize;
+
+/// 失败原因类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MineruErrorKind {
+    GpuOutOfMemory,
+    OutOfMemory,
+    MissingModel,
+    BadPdf,
+    MissingDependency,
+    ConfigError,
+    Unknown,
+}
+
+/// 结构化的诊断信息
+#[derive(Debug, Clone, Serialize)]
+pub struct MineruDiagnostic {
+    pub kind: MineruErrorKind,
+    pub message: String,
+    pub suggestion: String,
+    pub detail: Option<String>, // 匹配到的 stderr 行
+}
+
+impl MineruDiagnostic {
+    /// 组合为返回给前端的错误信息
+    pub fn to_error_message(&self) -> String {
+        format!("{}。{}", self.message, self.suggestion)
+    }
+}
+
+/// 失败特征：任意一个关键字命中即归为该类型（均为小写）
+struct Signature {
+    kind: MineruErrorKind,
+    keywords: &'static [&'static str],
+    message: &'static str,
+    suggestion: &'static str,
+}
+
+/// 按优先级排列的失败特征
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        kind: MineruErrorKind::GpuOutOfMemory,
+        keywords: &["cuda out of memory", "outofmemoryerror", "cublas_status_alloc_failed", "cudnn_status_not_enough_memory"],
+        message: "显存不足",
+        suggestion: "请将同时转换数设为 1，或在 magic-pdf.json 中将 device-mode 改为 cpu 后重试",
+    },
+    Signature {
+        kind: MineruErrorKind::OutOfMemory,
+        keywords: &["memoryerror", "cannot allocate memory", "bad_alloc"],
+        message: "内存不足",
+        suggestion: "请关闭其他占用内存的程序，或使用逐页转换模式",
+    },
+    Signature {
+        kind: MineruErrorKind::MissingModel,
+        keywords: &["model not found", "no such file or directory: '", "weights not found", "modelscope.hub", "repository not found"],
+        message: "模型文件缺失或路径错误",
+        suggestion: "请在设置 > 工具部署中校验模型，必要时重新下载并更新配置文件",
+    },
+    Signature {
+        kind: MineruErrorKind::BadPdf,
+        keywords: &["pdfsyntaxerror", "eof marker not found", "invalid pdf", "fpdf_", "pdfium", "file has not been decrypted"],
+        message: "PDF 文件损坏或已加密",
+        suggestion: "请用其他阅读器另存为新的 PDF，或移除密码保护后重试",
+    },
+    Signature {
+        kind: MineruErrorKind::MissingDependency,
+        keywords: &["modulenotfounderror", "no module named", "importerror", "dll load failed"],
+        message: "Python 依赖不完整",
+        suggestion: "请重新安装 MinerU，或重建 MinerU 虚拟环境",
+    },
+    Signature {
+        kind: MineruErrorKind::ConfigError,
+        keywords: &["magic-pdf.json", "keyerror", "jsondecodeerror"],
+        message: "MinerU 配置文件有误",
+        suggestion: "请在设置 > 工具部署中更新 MinerU 配置文件",
+    },
+];
+
+/// 分析 stderr，识别失败原因
+/// 从后往前匹配，因为最终的异常信息通常在末尾
+pub fn diagnose(stderr: &str) -> MineruDiagnostic {
+    let lines: Vec<&str> = stderr.lines().collect();
+
+    for signature in SIGNATURES {
+        let matched = lines.iter().rev().find(|line| {
+            let lower = line.to_lowercase();
+            signature.keywords.iter().any(|k| lower.contains(k))
+        });
+        if let Some(line) = matched {
+            return MineruDiagnostic {
+                kind: signature.kind,
+                message: signature.message.to_string(),
+                suggestion: signature.suggestion.to_string(),
+                detail: Some(line.trim().to_string()),
+            };
+        }
+    }
+
+    // 未识别的错误：取最后一行非空输出作为详情
+    MineruDiagnostic {
+        kind: MineruErrorKind::Unknown,
+        message: "转换失败".to_string(),
+        suggestion: "请查看运行日志中的详细错误信息，或尝试使用 txt 模式转换".to_string(),
+        detail: lines
+            .iter()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .map(|l| l.trim().to_string()),
+    }
+}
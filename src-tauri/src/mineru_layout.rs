@@ -0,0 +1,402 @@
+// MinerU 版面解析模块 - 读取 MinerU 输出的 middle.json
+// 其中包含每页的块类型（标题、正文、公式、图片、表格）和坐标，可用于按块切分题目
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 版面块类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutBlockKind {
+    Heading,
+    Text,
+    Formula,
+    Figure,
+    Table,
+    List,
+    Other,
+}
+
+impl LayoutBlockKind {
+    /// 将 MinerU 的块类型映射为版面块类型
+    fn from_mineru(block_type: &str) -> Self {
+        match block_type {
+            "title" => LayoutBlockKind::Heading,
+            "text" | "index" => LayoutBlockKind::Text,
+            "interline_equation" => LayoutBlockKind::Formula,
+            "image" => LayoutBlockKind::Figure,
+            "table" => LayoutBlockKind::Table,
+            "list" => LayoutBlockKind::List,
+            _ => LayoutBlockKind::Other,
+        }
+    }
+}
+
+/// 页面中的一个版面块
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutBlock {
+    pub kind: LayoutBlockKind,
+    pub bbox: [f32; 4], // [x0, y0, x1, y1]
+    pub text: String,
+}
+
+/// 一页的版面结构
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutPage {
+    pub page_number: u32, // 从 1 开始
+    pub width: f32,
+    pub height: f32,
+    pub blocks: Vec<LayoutBlock>,
+}
+
+/// 匹配题目开头的编号，例如 "例 3"、"12."、"(3)"、"（3）"、"第 5 题"
+static QUESTION_START_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(例\s*\d+|例题\s*\d+|\d+\s*(?:[．、]|\.\D)|[(（]\s*\d+\s*[)）]|第\s*\d+\s*题)").unwrap()
+});
+
+/// 查找 MinerU 生成的 middle.json
+pub fn find_middle_json(output_dir: &Path, pdf_name: &str) -> Option<PathBuf> {
+    let result_dir = crate::mineru_service::find_output_dir(output_dir, pdf_name)?;
+    let path = result_dir.join(format!("{}_middle.json", pdf_name));
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// 加载整本书的版面结构，未找到 middle.json 时返回 None
+pub fn load_layout(output_dir: &Path, pdf_name: &str) -> Option<Vec<LayoutPage>> {
+    use crate::logger;
+
+    let path = find_middle_json(output_dir, pdf_name)?;
+    let content = fs::read_to_string(&path).ok()?;
+    match parse_middle_json(&content) {
+        Ok(pages) => Some(pages),
+        Err(e) => {
+            logger::warn("mineru", &format!("解析版面文件失败 {}: {}", path.display(), e));
+            None
+        }
+    }
+}
+
+/// 解析 middle.json 内容
+pub fn parse_middle_json(content: &str) -> Result<Vec<LayoutPage>> {
+    let root: Value = serde_json::from_str(content)?;
+    let pdf_info = root
+        .get("pdf_info")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("缺少 pdf_info 字段"))?;
+
+    let pages = pdf_info
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            let page_idx = page
+                .get("page_idx")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(i as u64) as u32;
+            let (width, height) = match page.get("page_size").and_then(|v| v.as_array()) {
+                Some(size) if size.len() >= 2 => (
+                    size[0].as_f64().unwrap_or(0.0) as f32,
+                    size[1].as_f64().unwrap_or(0.0) as f32,
+                ),
+                _ => (0.0, 0.0),
+            };
+            let blocks = page
+                .get("para_blocks")
+                .and_then(|v| v.as_array())
+                .map(|blocks| blocks.iter().map(parse_block).collect())
+                .unwrap_or_default();
+
+            LayoutPage {
+                page_number: page_idx + 1,
+                width,
+                height,
+                blocks,
+            }
+        })
+        .collect();
+
+    Ok(pages)
+}
+
+/// 解析单个段落块
+fn parse_block(block: &Value) -> LayoutBlock {
+    let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let kind = LayoutBlockKind::from_mineru(block_type);
+
+    let mut bbox = [0.0f32; 4];
+    if let Some(values) = block.get("bbox").and_then(|v| v.as_array()) {
+        for (slot, value) in bbox.iter_mut().zip(values) {
+            *slot = value.as_f64().unwrap_or(0.0) as f32;
+        }
+    }
+
+    let mut text = String::new();
+    collect_block_text(block, &mut text);
+
+    LayoutBlock {
+        kind,
+        bbox,
+        text: text.trim().to_string(),
+    }
+}
+
+/// 递归收集块内的文字（图片、表格的标题和脚注在子块中）
+fn collect_block_text(block: &Value, text: &mut String) {
+    if let Some(lines) = block.get("lines").and_then(|v| v.as_array()) {
+        for line in lines {
+            let spans = match line.get("spans").and_then(|v| v.as_array()) {
+                Some(spans) => spans,
+                None => continue,
+            };
+            for span in spans {
+                let span_type = span.get("type").and_then(|v| v.as_str()).unwrap_or("");
+                let content = span.get("content").and_then(|v| v.as_str()).unwrap_or("");
+                match span_type {
+                    "inline_equation" => {
+                        text.push('$');
+                        text.push_str(content);
+                        text.push('$');
+                    }
+                    "interline_equation" => {
+                        text.push_str("$$");
+                        text.push_str(content);
+                        text.push_str("$$");
+                    }
+                    "table" => {
+                        // 表格内容是 HTML
+                        if let Some(html) = span.get("html").and_then(|v| v.as_str()) {
+                            text.push_str(html);
+                        }
+                    }
+                    _ => text.push_str(content),
+                }
+            }
+            text.push('\n');
+        }
+    }
+
+    if let Some(children) = block.get("blocks").and_then(|v| v.as_array()) {
+        for child in children {
+            collect_block_text(child, text);
+        }
+    }
+}
+
+impl LayoutBlock {
+    /// 将块渲染为 Markdown
+    pub fn to_markdown(&self) -> String {
+        match self.kind {
+            LayoutBlockKind::Heading => format!("## {}", self.text),
+            LayoutBlockKind::Figure => format!("[图片] {}", self.text),
+            _ => self.text.clone(),
+        }
+    }
+
+    /// 是否是一道题目的开头
+    pub fn starts_question(&self) -> bool {
+        matches!(self.kind, LayoutBlockKind::Text | LayoutBlockKind::List)
+            && QUESTION_START_RE.is_match(&self.text)
+    }
+}
+
+impl LayoutPage {
+    /// 按版面块切分题目
+    /// 标题和带题号的段落开始一个新片段，公式、图片、表格归入当前片段
+    pub fn segment_questions(&self) -> Vec<String> {
+        let mut segments: Vec<String> = Vec::new();
+        let mut current = String::new();
+
+        for block in &self.blocks {
+            if block.text.is_empty() {
+                continue;
+            }
+            let boundary = block.kind == LayoutBlockKind::Heading || block.starts_question();
+            if boundary && !current.trim().is_empty() {
+                segments.push(current.trim().to_string());
+                current.clear();
+            }
+            current.push_str(&block.to_markdown());
+            current.push_str("\n\n");
+        }
+
+        if !current.trim().is_empty() {
+            segments.push(current.trim().to_string());
+        }
+
+        segments
+    }
+
+    /// 生成带分段标记的内容，供 AI 识别题目边界
+    pub fn to_segmented_markdown(&self) -> String {
+        self.segment_questions()
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| format!("<!-- 片段 {} -->\n{}", i + 1, segment))
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n")
+    }
+}
+
+impl LayoutPage {
+    /// 将整页渲染为 Markdown
+    pub fn to_markdown(&self) -> String {
+        self.blocks
+            .iter()
+            .filter(|b| !b.text.is_empty())
+            .map(|b| b.to_markdown())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// 查找 MinerU 生成的 content_list.json
+pub fn find_content_list(output_dir: &Path, pdf_name: &str) -> Option<PathBuf> {
+    let result_dir = crate::mineru_service::find_output_dir(output_dir, pdf_name)?;
+    let path = result_dir.join(format!("{}_content_list.json", pdf_name));
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// 解析 content_list.json，按页码汇总为每页的 Markdown
+/// 每个条目带有 page_idx（从 0 开始），图片路径相对于 MinerU 结果目录
+pub fn parse_content_list(content: &str) -> Result<Vec<(u32, String)>> {
+    let items: Vec<Value> = serde_json::from_str(content)?;
+    let mut pages: Vec<(u32, Vec<String>)> = Vec::new();
+
+    for item in &items {
+        let page_number = item.get("page_idx").and_then(|v| v.as_u64()).unwrap_or(0) as u32 + 1;
+        let markdown = content_item_to_markdown(item);
+        if markdown.trim().is_empty() {
+            continue;
+        }
+        match pages.iter_mut().find(|(page, _)| *page == page_number) {
+            Some((_, parts)) => parts.push(markdown),
+            None => pages.push((page_number, vec![markdown])),
+        }
+    }
+
+    pages.sort_by_key(|(page, _)| *page);
+    Ok(pages
+        .into_iter()
+        .map(|(page, parts)| (page, parts.join("\n\n")))
+        .collect())
+}
+
+/// 将 content_list 中的一个条目渲染为 Markdown
+fn content_item_to_markdown(item: &Value) -> String {
+    let get_str = |key: &str| item.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let get_lines = |key: &str| {
+        item.get(key)
+            .and_then(|v| v.as_array())
+            .map(|lines| {
+                lines
+                    .iter()
+                    .filter_map(|l| l.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default()
+    };
+
+    match item.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+        "text" => {
+            let text = get_str("text");
+            match item.get("text_level").and_then(|v| v.as_u64()) {
+                Some(level) if level > 0 => format!("{} {}", "#".repeat(level.min(6) as usize), text),
+                _ => text,
+            }
+        }
+        "equation" => get_str("text"),
+        "image" => {
+            let mut parts = vec![format!("![]({})", get_str("img_path"))];
+            let caption = get_lines("image_caption");
+            if !caption.is_empty() {
+                parts.push(caption);
+            }
+            parts.join("\n")
+        }
+        "table" => {
+            let mut parts = Vec::new();
+            let caption = get_lines("table_caption");
+            if !caption.is_empty() {
+                parts.push(caption);
+            }
+            let body = get_str("table_body");
+            if !body.is_empty() {
+                parts.push(body);
+            } else {
+                parts.push(format!("![]({})", get_str("img_path")));
+            }
+            parts.join("\n")
+        }
+        _ => get_str("text"),
+    }
+}
+
+/// 获取整本书转换结果中每页的 Markdown
+/// 优先使用 content_list.json，其次使用 middle.json 的版面块，都不存在时返回 None
+pub fn load_page_markdowns(output_dir: &Path, pdf_name: &str) -> Option<Vec<(u32, String)>> {
+    use crate::logger;
+
+    if let Some(path) = find_content_list(output_dir, pdf_name) {
+        match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|c| parse_content_list(&c)) {
+            Ok(pages) if !pages.is_empty() => return Some(pages),
+            Ok(_) => {}
+            Err(e) => logger::warn("mineru", &format!("解析内容列表失败 {}: {}", path.display(), e)),
+        }
+    }
+
+    let pages = load_layout(output_dir, pdf_name)?;
+    Some(
+        pages
+            .iter()
+            .map(|page| (page.page_number, page.to_markdown()))
+            .filter(|(_, markdown)| !markdown.trim().is_empty())
+            .collect(),
+    )
+}
+
+/// 获取每页中的图片（插图、表格、公式截图），路径为绝对路径
+/// 用于向支持图片输入的模型发送几何图形、电路图等 OCR 无法表达的内容
+pub fn load_page_images(output_dir: &Path, pdf_name: &str) -> HashMap<u32, Vec<PathBuf>> {
+    let mut images: HashMap<u32, Vec<PathBuf>> = HashMap::new();
+
+    let result_dir = match crate::mineru_service::find_output_dir(output_dir, pdf_name) {
+        Some(dir) => dir,
+        None => return images,
+    };
+    let items: Vec<Value> = match find_content_list(output_dir, pdf_name)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        Some(items) => items,
+        None => return images,
+    };
+
+    for item in &items {
+        let img_path = match item.get("img_path").and_then(|v| v.as_str()) {
+            Some(p) if !p.is_empty() => p,
+            _ => continue,
+        };
+        let path = result_dir.join(img_path);
+        if !path.exists() {
+            continue;
+        }
+        let page_number = item.get("page_idx").and_then(|v| v.as_u64()).unwrap_or(0) as u32 + 1;
+        images.entry(page_number).or_default().push(path);
+    }
+
+    images
+}
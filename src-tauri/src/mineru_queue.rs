@@ -0,0 +1,183 @@
+// MinerU 转换队列模块 - 控制同时运行的转换进程数量
+// MinerU 转换非常消耗 GPU/内存，多个任务同时启动会互相抢占资源
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::Duration;
+
+/// 默认最大并发转换数
+pub const DEFAULT_MAX_CONCURRENT: usize = 1;
+
+/// 队列中的任务
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEntry {
+    pub task_id: String, // 文件 ID，同一文件同时只能有一个转换
+    pub job_id: String,  // 本次转换的 ID，运行中的进程按它登记
+    pub status: String, // "queued", "running"
+    pub enqueued_at: String,
+    pub started_at: Option<String>,
+    #[serde(skip)]
+    cancelled: bool,
+}
+
+/// 队列状态
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    pub max_concurrent: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub entries: Vec<QueueEntry>,
+}
+
+struct ConversionQueue {
+    entries: Vec<QueueEntry>,
+    max_concurrent: usize,
+}
+
+impl ConversionQueue {
+    fn running_count(&self) -> usize {
+        self.entries.iter().filter(|e| e.status == "running").count()
+    }
+}
+
+static QUEUE: Lazy<Mutex<ConversionQueue>> = Lazy::new(|| {
+    Mutex::new(ConversionQueue {
+        entries: Vec::new(),
+        max_concurrent: DEFAULT_MAX_CONCURRENT,
+    })
+});
+
+/// 队列占位，释放时自动移出队列（排队中或运行中）
+/// 等待中的 acquire 被丢弃时也会通过它移除排队记录
+pub struct QueueSlot {
+    job_id: String,
+}
+
+impl QueueSlot {
+    pub fn job_id(&self) -> &str {
+        &self.job_id
+    }
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        let mut queue = QUEUE.lock();
+        if let Some(pos) = queue.entries.iter().position(|e| e.job_id == self.job_id) {
+            queue.entries.remove(pos);
+        }
+    }
+}
+
+/// 设置最大并发转换数（至少为 1）
+pub fn set_max_concurrent(max: usize) {
+    QUEUE.lock().max_concurrent = max.max(1);
+}
+
+/// 加入队列并等待轮到该任务执行
+/// 任务按先进先出顺序启动，排队期间可以被取消
+pub async fn acquire(task_id: &str) -> Result<QueueSlot> {
+    use crate::logger;
+
+    let job_id = uuid::Uuid::new_v4().to_string();
+    {
+        let mut queue = QUEUE.lock();
+        if queue.entries.iter().any(|e| e.task_id == task_id) {
+            return Err(anyhow!("该文件已在转换队列中"));
+        }
+        queue.entries.push(QueueEntry {
+            task_id: task_id.to_string(),
+            job_id: job_id.clone(),
+            status: "queued".to_string(),
+            enqueued_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            started_at: None,
+            cancelled: false,
+        });
+    }
+    let slot = QueueSlot { job_id: job_id.clone() };
+
+    let mut logged = false;
+    loop {
+        {
+            let mut queue = QUEUE.lock();
+            let running = queue.running_count();
+            let max_concurrent = queue.max_concurrent;
+
+            let pos = queue
+                .entries
+                .iter()
+                .position(|e| e.job_id == job_id)
+                .ok_or_else(|| anyhow!("转换任务已从队列中移除"))?;
+
+            if queue.entries[pos].cancelled {
+                return Err(anyhow!("{}", crate::mineru_service::CONVERSION_CANCELLED));
+            }
+
+            // 只有排在最前面的等待任务才能启动
+            let first_queued = queue.entries.iter().position(|e| e.status == "queued");
+            if running < max_concurrent && first_queued == Some(pos) {
+                let entry = &mut queue.entries[pos];
+                entry.status = "running".to_string();
+                entry.started_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                break;
+            }
+
+            if !logged {
+                logger::info("mineru", &format!("转换任务 {} 排队中，当前运行 {} 个", task_id, running));
+                logged = true;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(300)).await;
+    }
+    Ok(slot)
+}
+
+/// 取消排队中的任务，返回是否找到该任务
+pub fn cancel_queued(task_id: &str) -> bool {
+    let mut queue = QUEUE.lock();
+    if let Some(entry) = queue
+        .entries
+        .iter_mut()
+        .find(|e| e.task_id == task_id && e.status == "queued")
+    {
+        entry.cancelled = true;
+        true
+    } else {
+        false
+    }
+}
+
+/// 标记已拿到名额、但进程可能还没启动的任务为已取消，返回是否找到该任务
+pub fn cancel_running(task_id: &str) -> bool {
+    let mut queue = QUEUE.lock();
+    match queue
+        .entries
+        .iter_mut()
+        .find(|e| e.task_id == task_id && e.status == "running")
+    {
+        Some(entry) => {
+            entry.cancelled = true;
+            true
+        }
+        None => false,
+    }
+}
+
+/// 转换是否已被取消
+pub fn is_cancelled(job_id: &str) -> bool {
+    QUEUE.lock().entries.iter().any(|e| e.job_id == job_id && e.cancelled)
+}
+
+/// 获取队列状态
+pub fn get_status() -> QueueStatus {
+    let queue = QUEUE.lock();
+    let running = queue.running_count();
+    QueueStatus {
+        max_concurrent: queue.max_concurrent,
+        running,
+        queued: queue.entries.len() - running,
+        entries: queue.entries.clone(),
+    }
+}
@@ -6,15 +6,57 @@
 use anyhow::{anyhow, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, Command, ExitStatus};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tauri::{AppHandle, Manager};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use once_cell::sync::Lazy;
 use std::io::Write;
+use crate::{mineru_diagnostics, mineru_queue, python_env};
 
 /// 缓存的 magic-pdf 可执行文件路径
 static MAGIC_PDF_PATH: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
 
+/// 正在运行的 MinerU 转换进程（按转换 ID 索引，同一文件的 OCR 和整本转换互不影响）
+static RUNNING_CONVERSIONS: Lazy<Mutex<HashMap<String, RunningConversion>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 已被用户取消的转换（按转换 ID）
+static CANCELLED_CONVERSIONS: Lazy<RwLock<HashSet<String>>> = Lazy::new(|| RwLock::new(HashSet::new()));
+
+/// 转换被取消时返回的错误信息
+pub const CONVERSION_CANCELLED: &str = "转换已取消";
+
+/// 转换超时时返回的错误信息前缀
+pub const CONVERSION_TIMED_OUT: &str = "转换超时";
+
+/// 默认转换超时时间（秒）
+pub const DEFAULT_CONVERSION_TIMEOUT_SECS: u64 = 30 * 60;
+
+/// 超时时记录到日志的 stderr 末尾行数
+const STDERR_TAIL_LINES: usize = 20;
+
+/// 单独指定的模型目录（为空时使用存储路径下的 mineru_models）
+static MODELS_DIR_OVERRIDE: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+/// 设置单独的模型目录，空字符串表示跟随存储路径
+pub fn set_models_dir_override(dir: &str) {
+    let dir = dir.trim();
+    *MODELS_DIR_OVERRIDE.write() = if dir.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(dir))
+    };
+}
+
+/// 单次转换的超时时间（秒），0 表示不限制
+static CONVERSION_TIMEOUT_SECS: Lazy<RwLock<u64>> = Lazy::new(|| RwLock::new(DEFAULT_CONVERSION_TIMEOUT_SECS));
+
+/// 设置单次转换的超时时间（秒），0 表示不限制
+pub fn set_conversion_timeout(secs: u64) {
+    *CONVERSION_TIMEOUT_SECS.write() = secs;
+}
+
 /// MinerU 安装信息
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct MineruInstallInfo {
@@ -25,6 +67,82 @@ pub struct MineruInstallInfo {
     pub ocr_models_downloaded: bool,
     pub models_dir: Option<String>,
     pub modelscope_installed: bool,
+    pub cli_type: Option<MineruCli>,
+    pub version: Option<String>,
+}
+
+/// MinerU 命令行工具类型
+/// MinerU 1.x 提供 magic-pdf 命令，2.x 改为 mineru 命令，参数和输出目录结构不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MineruCli {
+    MagicPdf,
+    Mineru,
+}
+
+impl MineruCli {
+    /// 可执行文件名（不含扩展名）
+    pub fn executable_name(&self) -> &'static str {
+        match self {
+            MineruCli::MagicPdf => "magic-pdf",
+            MineruCli::Mineru => "mineru",
+        }
+    }
+
+    /// pip 包名
+    pub fn package_name(&self) -> &'static str {
+        match self {
+            MineruCli::MagicPdf => "magic-pdf",
+            MineruCli::Mineru => "mineru",
+        }
+    }
+
+    /// 根据可执行文件路径判断命令类型
+    pub fn from_path(path: &str) -> Self {
+        let stem = Path::new(path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        if stem.eq_ignore_ascii_case("mineru") {
+            MineruCli::Mineru
+        } else {
+            MineruCli::MagicPdf
+        }
+    }
+
+    /// 已验证可用的版本范围（最低版本，最高已测试版本）
+    pub fn supported_version_range(&self) -> (&'static str, &'static str) {
+        match self {
+            MineruCli::MagicPdf => ("1.0.0", "1.3.12"),
+            MineruCli::Mineru => ("2.0.0", "2.5.4"),
+        }
+    }
+
+    /// 升级到最新版本的命令
+    pub fn upgrade_command(&self) -> &'static str {
+        match self {
+            MineruCli::MagicPdf => "pip install -U \"magic-pdf[full]\"",
+            MineruCli::Mineru => "pip install -U \"mineru[core]\"",
+        }
+    }
+
+    /// 构造转换命令参数
+    pub fn build_convert_args(&self, pdf_path: &str, output_dir: &str, parse_mode: &str) -> Vec<String> {
+        let mut args = vec![
+            "-p".to_string(),
+            pdf_path.to_string(),
+            "-o".to_string(),
+            output_dir.to_string(),
+            "-m".to_string(),
+            parse_mode.to_string(),
+        ];
+        if *self == MineruCli::Mineru {
+            // 2.x 默认使用 VLM 后端，这里固定使用与 1.x 行为一致的 pipeline 后端
+            args.push("-b".to_string());
+            args.push("pipeline".to_string());
+        }
+        args
+    }
 }
 
 /// 模型下载状态
@@ -36,9 +154,126 @@ pub struct ModelDownloadStatus {
     pub message: String,
 }
 
+/// 模型校验结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelVerifyReport {
+    pub model_type: String,
+    pub model_dir: String,
+    pub complete: bool,
+    pub total_size: u64,
+    pub missing: Vec<String>,       // 缺失或过小的必需文件
+    pub partial_files: Vec<String>, // 未下载完成的临时文件
+}
+
+/// MinerU 版本兼容性检查结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MineruVersionCheck {
+    pub cli_type: Option<MineruCli>,
+    pub version: Option<String>,
+    pub executable_path: Option<String>,
+    pub status: String, // "ok", "outdated", "untested", "unknown", "not-installed"
+    pub min_version: Option<String>,
+    pub max_tested_version: Option<String>,
+    pub guidance: String,
+}
+
+/// 卸载/清理结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct CleanupReport {
+    pub removed_paths: Vec<String>,
+    pub uninstalled_packages: Vec<String>,
+    pub reclaimed_bytes: u64,
+}
+
+/// 模型下载源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelSource {
+    ModelScope,
+    HuggingFace,
+    HfMirror,
+}
+
+impl ModelSource {
+    /// 从配置字符串解析下载源，未知值回退到 ModelScope
+    pub fn parse(source: &str) -> Self {
+        match source {
+            "huggingface" => ModelSource::HuggingFace,
+            "hf-mirror" => ModelSource::HfMirror,
+            _ => ModelSource::ModelScope,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelSource::ModelScope => "modelscope",
+            ModelSource::HuggingFace => "huggingface",
+            ModelSource::HfMirror => "hf-mirror",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ModelSource::ModelScope => "ModelScope",
+            ModelSource::HuggingFace => "HuggingFace",
+            ModelSource::HfMirror => "hf-mirror.com",
+        }
+    }
+}
+
+/// 模型包中必须存在的文件：目录 + 扩展名 + 最小文件大小
+struct RequiredModelFile {
+    dir: &'static str,
+    extension: &'static str,
+    min_size: u64,
+}
+
+/// 可下载的模型包描述
+struct ModelPackage {
+    model_type: &'static str,
+    display_name: &'static str,
+    modelscope_id: &'static str,
+    huggingface_id: &'static str,
+    target_subdir: &'static str,
+    max_workers: u32,
+    required_files: &'static [RequiredModelFile],
+}
+
+const MB: u64 = 1024 * 1024;
+
+/// MinerU 2.5 主模型
+const MAIN_MODEL_PACKAGE: ModelPackage = ModelPackage {
+    model_type: "main",
+    display_name: "MinerU 2.5",
+    modelscope_id: "OpenDataLab/MinerU2.5-2509-1.2B",
+    huggingface_id: "opendatalab/MinerU2.5-2509-1.2B",
+    target_subdir: "MinerU2.5-2509-1.2B",
+    max_workers: 8,
+    required_files: &[
+        RequiredModelFile { dir: "", extension: "json", min_size: 1 },
+        RequiredModelFile { dir: "", extension: "safetensors", min_size: 100 * MB },
+    ],
+};
+
+/// PDF-Extract-Kit-1.0 模型（布局、公式、OCR）
+const OCR_MODEL_PACKAGE: ModelPackage = ModelPackage {
+    model_type: "ocr",
+    display_name: "PDF-Extract-Kit-1.0 OCR",
+    modelscope_id: "OpenDataLab/PDF-Extract-Kit-1.0",
+    huggingface_id: "opendatalab/PDF-Extract-Kit-1.0",
+    target_subdir: "PDF-Extract-Kit-1.0",
+    max_workers: 16,
+    required_files: &[
+        RequiredModelFile { dir: "models/MFD/YOLO", extension: "pt", min_size: MB },
+        RequiredModelFile { dir: "models/Layout/YOLO", extension: "pt", min_size: MB },
+        RequiredModelFile { dir: "models/OCR/paddleocr_torch", extension: "pth", min_size: MB },
+    ],
+};
+
 /// MinerU 服务
 pub struct MineruService {
     python_path: String,
+    /// 用于发送转换输出事件的应用句柄（可选）
+    app_handle: Option<AppHandle>,
 }
 
 impl MineruService {
@@ -46,20 +281,23 @@ impl MineruService {
     pub fn new() -> Self {
         Self {
             python_path: "python".to_string(),
+            app_handle: None,
+        }
+    }
+
+    /// 创建会向前端发送转换输出事件的服务实例
+    pub fn with_events(app_handle: &AppHandle) -> Self {
+        Self {
+            python_path: "python".to_string(),
+            app_handle: Some(app_handle.clone()),
         }
     }
 
     /// 检查 ModelScope 是否已安装
     pub fn check_modelscope_installed() -> bool {
-        let pip_check = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "pip", "show", "modelscope"])
-                .output()
-        } else {
-            Command::new("pip")
-                .args(["show", "modelscope"])
-                .output()
-        };
+        let pip_check = python_env::pip_command()
+            .args(["show", "modelscope"])
+            .output();
 
         if let Ok(result) = pip_check {
             return result.status.success();
@@ -70,15 +308,9 @@ impl MineruService {
     /// 检查 MinerU 是否已安装（通过 pip）
     pub fn check_installed() -> bool {
         // 方法1: 尝试通过 pip show 检查包是否安装
-        let pip_check = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "pip", "show", "magic-pdf"])
-                .output()
-        } else {
-            Command::new("pip")
-                .args(["show", "magic-pdf"])
-                .output()
-        };
+        let pip_check = python_env::pip_command()
+            .args(["show", "magic-pdf"])
+            .output();
 
         if let Ok(result) = pip_check {
             if result.status.success() {
@@ -106,27 +338,31 @@ impl MineruService {
             }
         }
 
-        // 然后尝试直接调用（依赖 PATH）
-        let version_check = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "magic-pdf", "--version"])
-                .output()
-        } else {
-            Command::new("magic-pdf")
-                .arg("--version")
-                .output()
-        };
-
-        if let Ok(result) = version_check {
-            if result.status.success() {
-                // 命令可用，但还需要检查模型是否存在
-                return Self::check_all_models_exist_with_storage(storage_path);
-            }
+        // 然后尝试直接调用（依赖 PATH），同时兼容 mineru 与 magic-pdf
+        if Self::detect_path_cli().is_some() {
+            // 命令可用，但还需要检查模型是否存在
+            return Self::check_all_models_exist_with_storage(storage_path);
         }
 
         false
     }
 
+    /// 检测 PATH 中可以直接调用的命令，优先 mineru
+    fn detect_path_cli() -> Option<MineruCli> {
+        [MineruCli::Mineru, MineruCli::MagicPdf].into_iter().find(|cli| {
+            let version_check = if cfg!(target_os = "windows") {
+                Command::new("cmd")
+                    .args(["/C", cli.executable_name(), "--version"])
+                    .output()
+            } else {
+                Command::new(cli.executable_name())
+                    .arg("--version")
+                    .output()
+            };
+            version_check.map(|result| result.status.success()).unwrap_or(false)
+        })
+    }
+
     /// 检查所有必需的模型文件是否存在
     /// MinerU 需要主模型才能正常工作
     pub fn check_all_models_exist() -> bool {
@@ -137,6 +373,11 @@ impl MineruService {
     pub fn check_all_models_exist_with_storage(storage_path: Option<&str>) -> bool {
         // 使用 get_mineru_models_dir 检测实际的模型目录
         if let Some(models_path) = Self::get_mineru_models_dir(storage_path) {
+            // 下载未完成的模型无法使用
+            if !find_partial_downloads(&models_path).is_empty() {
+                return false;
+            }
+            
             // 检查 MFD 模型（公式检测）
             let mfd_model = models_path.join("MFD").join("YOLO").join("yolo_v8_ft.pt");
             if mfd_model.exists() {
@@ -162,6 +403,13 @@ impl MineruService {
     /// 获取 magic-pdf 可执行文件的完整路径
     /// 通过 pip show 获取安装位置，然后推断 Scripts 目录
     pub fn get_magic_pdf_path() -> Option<String> {
+        // 设置中指定的路径优先
+        if let Some(path) = python_env::get_magic_pdf_override() {
+            if path.exists() {
+                return Some(path.to_string_lossy().to_string());
+            }
+        }
+
         // 先检查缓存
         {
             let cached = MAGIC_PDF_PATH.read();
@@ -187,22 +435,39 @@ impl MineruService {
         let path = Self::detect_magic_pdf_path();
         let mut cached = MAGIC_PDF_PATH.write();
         *cached = path.clone();
-        path
+        drop(cached);
+        Self::get_magic_pdf_path()
     }
 
-    /// 检测 magic-pdf 可执行文件路径
+    /// 检测 MinerU 可执行文件路径
+    /// 优先查找 MinerU 2.x 的 mineru 命令，其次是旧版的 magic-pdf
     fn detect_magic_pdf_path() -> Option<String> {
-        // 方法1: 通过 pip show 获取安装位置
-        let pip_show = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "pip", "show", "magic-pdf"])
-                .output()
+        for cli in [MineruCli::Mineru, MineruCli::MagicPdf] {
+            if let Some(path) = Self::detect_executable_path(cli.executable_name(), cli.package_name()) {
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    /// 检测指定命令的可执行文件路径
+    fn detect_executable_path(exe_name: &str, package_name: &str) -> Option<String> {
+        let exe_file = if cfg!(target_os = "windows") {
+            format!("{}.exe", exe_name)
         } else {
-            Command::new("pip")
-                .args(["show", "magic-pdf"])
-                .output()
+            exe_name.to_string()
         };
 
+        // 优先使用应用专用虚拟环境中的命令
+        if let Some(venv_exe) = python_env::get_venv_script(exe_name) {
+            return Some(venv_exe.to_string_lossy().to_string());
+        }
+
+        // 方法1: 通过 pip show 获取安装位置
+        let pip_show = python_env::pip_command()
+            .args(["show", package_name])
+            .output();
+
         if let Ok(result) = pip_show {
             if result.status.success() {
                 let output = String::from_utf8_lossy(&result.stdout);
@@ -213,14 +478,9 @@ impl MineruService {
                         // 从 site-packages 路径推断 Scripts 目录
                         // 例如: C:\...\Python311\site-packages -> C:\...\Python311\Scripts\magic-pdf.exe
                         if let Some(scripts_dir) = Self::get_scripts_dir_from_location(location) {
-                            let magic_pdf_exe = if cfg!(target_os = "windows") {
-                                scripts_dir.join("magic-pdf.exe")
-                            } else {
-                                scripts_dir.join("magic-pdf")
-                            };
-                            
-                            if magic_pdf_exe.exists() {
-                                return Some(magic_pdf_exe.to_string_lossy().to_string());
+                            let exe_path = scripts_dir.join(&exe_file);
+                            if exe_path.exists() {
+                                return Some(exe_path.to_string_lossy().to_string());
                             }
                         }
                     }
@@ -229,38 +489,34 @@ impl MineruService {
         }
 
         // 方法2: 使用 python -c 获取 Scripts 目录
-        let python_scripts = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "python", "-c", "import sysconfig; print(sysconfig.get_path('scripts'))"])
-                .output()
-        } else {
-            Command::new("python")
-                .args(["-c", "import sysconfig; print(sysconfig.get_path('scripts'))"])
-                .output()
-        };
+        let python_scripts = python_env::python_command()
+            .args(["-c", "import sysconfig; print(sysconfig.get_path('scripts'))"])
+            .output();
 
         if let Ok(result) = python_scripts {
             if result.status.success() {
                 let scripts_path = String::from_utf8_lossy(&result.stdout).trim().to_string();
-                let magic_pdf_exe = if cfg!(target_os = "windows") {
-                    PathBuf::from(&scripts_path).join("magic-pdf.exe")
-                } else {
-                    PathBuf::from(&scripts_path).join("magic-pdf")
-                };
-                
-                if magic_pdf_exe.exists() {
-                    return Some(magic_pdf_exe.to_string_lossy().to_string());
+                let exe_path = PathBuf::from(&scripts_path).join(&exe_file);
+                if exe_path.exists() {
+                    return Some(exe_path.to_string_lossy().to_string());
                 }
             }
         }
 
-        // 方法3: 检查常见的 Python 安装路径
+        // 方法3: 在 conda 环境中查找
+        for env_dir in python_env::list_conda_envs() {
+            if let Some(exe_path) = python_env::get_conda_env_script(&env_dir, exe_name) {
+                return Some(exe_path.to_string_lossy().to_string());
+            }
+        }
+
+        // 方法4: 检查常见的 Python 安装路径
         if cfg!(target_os = "windows") {
             let common_paths = [
-                std::env::var("LOCALAPPDATA").ok().map(|p| PathBuf::from(p).join("Programs\\Python\\Python311\\Scripts\\magic-pdf.exe")),
-                std::env::var("LOCALAPPDATA").ok().map(|p| PathBuf::from(p).join("Programs\\Python\\Python312\\Scripts\\magic-pdf.exe")),
-                std::env::var("USERPROFILE").ok().map(|p| PathBuf::from(p).join("AppData\\Local\\Programs\\Python\\Python311\\Scripts\\magic-pdf.exe")),
-                std::env::var("USERPROFILE").ok().map(|p| PathBuf::from(p).join("AppData\\Local\\Programs\\Python\\Python312\\Scripts\\magic-pdf.exe")),
+                std::env::var("LOCALAPPDATA").ok().map(|p| PathBuf::from(p).join("Programs\\Python\\Python311\\Scripts").join(&exe_file)),
+                std::env::var("LOCALAPPDATA").ok().map(|p| PathBuf::from(p).join("Programs\\Python\\Python312\\Scripts").join(&exe_file)),
+                std::env::var("USERPROFILE").ok().map(|p| PathBuf::from(p).join("AppData\\Local\\Programs\\Python\\Python311\\Scripts").join(&exe_file)),
+                std::env::var("USERPROFILE").ok().map(|p| PathBuf::from(p).join("AppData\\Local\\Programs\\Python\\Python312\\Scripts").join(&exe_file)),
             ];
 
             for path_opt in common_paths.iter().flatten() {
@@ -273,6 +529,85 @@ impl MineruService {
         None
     }
 
+    /// 获取当前检测到的 MinerU 命令行类型
+    pub fn get_cli_type() -> Option<MineruCli> {
+        Self::get_magic_pdf_path().map(|path| MineruCli::from_path(&path))
+    }
+
+    /// 通过 --version 获取 MinerU 版本号
+    pub fn get_cli_version() -> Option<String> {
+        let exe_path = Self::get_magic_pdf_path()?;
+        let output = Command::new(&exe_path).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // 输出形如 "magic-pdf, version 1.3.12" 或 "mineru, version 2.1.0"
+        // 部分版本把版本信息输出到 stderr
+        let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+        if text.trim().is_empty() {
+            text = String::from_utf8_lossy(&output.stderr).to_string();
+        }
+        text.split_whitespace()
+            .last()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// 检查 MinerU 版本是否在已验证的兼容范围内，并给出升级建议
+    pub fn check_version() -> MineruVersionCheck {
+        let executable_path = Self::get_magic_pdf_path();
+        let cli_type = executable_path.as_deref().map(MineruCli::from_path);
+        let version = Self::get_cli_version();
+
+        let cli = match cli_type {
+            Some(cli) => cli,
+            None => {
+                return MineruVersionCheck {
+                    cli_type: None,
+                    version: None,
+                    executable_path: None,
+                    status: "not-installed".to_string(),
+                    min_version: None,
+                    max_tested_version: None,
+                    guidance: "未检测到 MinerU，请在设置 > 工具部署中安装".to_string(),
+                };
+            }
+        };
+
+        let (min_version, max_tested) = cli.supported_version_range();
+        let (status, guidance) = match version.as_deref() {
+            None => (
+                "unknown",
+                format!("无法获取 {} 版本号，请确认命令可以正常运行", cli.executable_name()),
+            ),
+            Some(v) if compare_versions(v, min_version) == std::cmp::Ordering::Less => (
+                "outdated",
+                format!(
+                    "当前版本 {} 低于最低支持版本 {}，请执行: {}",
+                    v, min_version, cli.upgrade_command()
+                ),
+            ),
+            Some(v) if compare_versions(v, max_tested) == std::cmp::Ordering::Greater => (
+                "untested",
+                format!(
+                    "当前版本 {} 高于已验证的版本 {}，如遇问题可安装已验证版本: pip install \"{}=={}\"",
+                    v, max_tested, cli.package_name(), max_tested
+                ),
+            ),
+            Some(v) => ("ok", format!("当前版本 {} 在支持范围内", v)),
+        };
+
+        MineruVersionCheck {
+            cli_type: Some(cli),
+            version,
+            executable_path,
+            status: status.to_string(),
+            min_version: Some(min_version.to_string()),
+            max_tested_version: Some(max_tested.to_string()),
+            guidance,
+        }
+    }
+
     /// 从 site-packages 路径推断 Scripts 目录
     fn get_scripts_dir_from_location(location: &str) -> Option<PathBuf> {
         let path = PathBuf::from(location);
@@ -352,6 +687,11 @@ impl MineruService {
 
     /// 获取模型存储目录（存放下载的模型包的根目录）
     pub fn get_models_dir(storage_path: Option<&str>) -> PathBuf {
+        // 设置中单独指定的模型目录优先
+        if let Some(dir) = MODELS_DIR_OVERRIDE.read().clone() {
+            return dir;
+        }
+
         if let Some(path) = storage_path {
             if !path.is_empty() {
                 return PathBuf::from(path).join("mineru_models");
@@ -429,6 +769,9 @@ impl MineruService {
         // 检查 PDF-Extract-Kit 的 OCR 模型
         if let Some(models_path) = Self::get_mineru_models_dir(storage_path) {
             let ocr_dir = models_path.join("OCR").join("paddleocr_torch");
+            if !find_partial_downloads(&ocr_dir).is_empty() {
+                return false;
+            }
             if ocr_dir.exists() {
                 if let Ok(entries) = fs::read_dir(&ocr_dir) {
                     for entry in entries.flatten() {
@@ -444,8 +787,13 @@ impl MineruService {
     }
 
     /// 检查主模型（MFD/Layout）是否已下载
+    /// 存在未下载完成的临时文件时视为未下载
     pub fn check_main_models_downloaded(storage_path: Option<&str>) -> bool {
         if let Some(models_path) = Self::get_mineru_models_dir(storage_path) {
+            if !find_partial_downloads(&models_path).is_empty() {
+                return false;
+            }
+            
             // 检查 MFD 模型
             let mfd_model = models_path.join("MFD").join("YOLO").join("yolo_v8_ft.pt");
             if mfd_model.exists() {
@@ -500,15 +848,41 @@ impl MineruService {
             ocr_models_downloaded,
             models_dir: Some(models_dir_display),
             modelscope_installed,
+            cli_type: Self::get_cli_type(),
+            version: Self::get_cli_version(),
         }
     }
 
-    /// 下载 MinerU 主模型（通过 modelscope）
-    pub fn download_main_models_with_events(app_handle: &tauri::AppHandle, storage_path: Option<&str>) -> Result<String> {
-        use std::io::{BufRead, BufReader};
+    /// 下载 MinerU 主模型
+    pub fn download_main_models_with_events(
+        app_handle: &tauri::AppHandle,
+        storage_path: Option<&str>,
+        source: &str,
+    ) -> Result<String> {
+        Self::download_models_with_events(app_handle, storage_path, &MAIN_MODEL_PACKAGE, source)
+    }
+
+    /// 下载 OCR 模型（PDF-Extract-Kit-1.0）
+    pub fn download_ocr_models_with_events(
+        app_handle: &tauri::AppHandle,
+        storage_path: Option<&str>,
+        source: &str,
+    ) -> Result<String> {
+        Self::download_models_with_events(app_handle, storage_path, &OCR_MODEL_PACKAGE, source)
+    }
+
+    /// 下载模型包，支持 ModelScope / HuggingFace / hf-mirror 三种下载源
+    /// 下载中断后会自动重试，已完成的文件会被跳过（断点续传）
+    fn download_models_with_events(
+        app_handle: &tauri::AppHandle,
+        storage_path: Option<&str>,
+        package: &ModelPackage,
+        source: &str,
+    ) -> Result<String> {
         use std::process::Stdio;
         use crate::logger;
 
+        let source = ModelSource::parse(source);
         let models_dir = Self::get_models_dir(storage_path);
         
         // 确保目录存在
@@ -516,49 +890,26 @@ impl MineruService {
             fs::create_dir_all(&models_dir)?;
         }
 
-        logger::info("mineru", &format!("开始下载 MinerU 主模型到: {}", models_dir.display()));
+        logger::info("mineru", &format!(
+            "开始下载 {} 模型到: {}（下载源: {}）",
+            package.display_name,
+            models_dir.display(),
+            source.as_str()
+        ));
 
         let _ = app_handle.emit_all("mineru-model-output", 
             serde_json::json!({
                 "type": "cmd", 
-                "model_type": "main",
-                "message": format!("> 下载 MinerU 2.5 模型到: {}\n", models_dir.display())
+                "model_type": package.model_type,
+                "message": format!("> 下载 {} 模型到: {}\n", package.display_name, models_dir.display())
             }));
 
         // 创建临时 Python 脚本文件
         let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("mineru_download_main.py");
-        let target_dir = models_dir.join("MinerU2.5-2509-1.2B");
+        let script_path = temp_dir.join(format!("mineru_download_{}.py", package.model_type));
+        let target_dir = models_dir.join(package.target_subdir);
         
-        let python_script = format!(
-            r#"# -*- coding: utf-8 -*-
-import sys
-import os
-
-print("正在初始化 ModelScope...", flush=True)
-try:
-    from modelscope import snapshot_download
-    print("ModelScope 已加载", flush=True)
-except ImportError as e:
-    print(f"错误: 无法导入 modelscope: {{e}}", flush=True)
-    sys.exit(1)
-
-target_dir = r'{}'
-print(f"目标目录: {{target_dir}}", flush=True)
-
-try:
-    print("开始下载 MinerU 2.5 模型，请耐心等待...", flush=True)
-    model_dir = snapshot_download(
-        'OpenDataLab/MinerU2.5-2509-1.2B', 
-        local_dir=target_dir
-    )
-    print(f"模型下载成功，存放路径为: {{model_dir}}", flush=True)
-except Exception as e:
-    print(f"下载失败: {{e}}", flush=True)
-    sys.exit(1)
-"#,
-            target_dir.to_string_lossy().replace("\\", "\\\\")
-        );
+        let python_script = build_download_script(package, source, &target_dir);
 
         // 写入脚本文件
         fs::write(&script_path, &python_script)?;
@@ -567,54 +918,52 @@ except Exception as e:
         let _ = app_handle.emit_all("mineru-model-output", 
             serde_json::json!({
                 "type": "info", 
-                "model_type": "main",
-                "message": "正在从 ModelScope 下载 MinerU 2.5 模型...\n这可能需要几分钟到几十分钟，取决于网络速度...\n"
+                "model_type": package.model_type,
+                "message": format!(
+                    "正在从 {} 下载 {} 模型...\n这可能需要较长时间，请耐心等待，中断后会自动续传...\n",
+                    source.display_name(),
+                    package.display_name
+                )
             }));
 
         // 使用 python 执行脚本文件
-        let mut child = Command::new("python")
+        let mut child = python_env::python_command()
             .arg(&script_path)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
 
-        // 读取 stdout - 需要在主线程中等待
+        // 读取输出（tqdm 进度条用 \r 刷新，需要按 \r 和 \n 分行）
         let stdout = child.stdout.take();
         let stderr = child.stderr.take();
+        let model_type = package.model_type;
+        let tracker = Arc::new(Mutex::new(DownloadProgressTracker::default()));
+
+        emit_model_status(app_handle, model_type, "downloading", Some(0.0), "开始下载");
         
         let app_handle_stdout = app_handle.clone();
+        let tracker_stdout = tracker.clone();
         let stdout_thread = std::thread::spawn(move || {
             if let Some(stdout) = stdout {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines().flatten() {
-                    let _ = app_handle_stdout.emit_all("mineru-model-output",
-                        serde_json::json!({
-                            "type": "info", 
-                            "model_type": "main",
-                            "message": format!("{}\n", line)
-                        }));
-                }
+                read_output_lines(stdout, |line| {
+                    emit_model_line(&app_handle_stdout, model_type, &tracker_stdout, "info", &line);
+                });
             }
         });
 
         let app_handle_stderr = app_handle.clone();
+        let tracker_stderr = tracker.clone();
         let stderr_thread = std::thread::spawn(move || {
             if let Some(stderr) = stderr {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines().flatten() {
-                    // ModelScope 的进度信息也走 stderr
+                read_output_lines(stderr, |line| {
+                    // 下载工具的进度信息也走 stderr
                     let msg_type = if line.to_lowercase().contains("error") || line.to_lowercase().contains("failed") {
                         "error"
                     } else {
                         "info"
                     };
-                    let _ = app_handle_stderr.emit_all("mineru-model-output",
-                        serde_json::json!({
-                            "type": msg_type, 
-                            "model_type": "main",
-                            "message": format!("{}\n", line)
-                        }));
-                }
+                    emit_model_line(&app_handle_stderr, model_type, &tracker_stderr, msg_type, &line);
+                });
             }
         });
 
@@ -628,195 +977,222 @@ except Exception as e:
         // 清理临时文件
         let _ = fs::remove_file(&script_path);
 
-        if status.success() {
-            // 验证模型是否真的下载成功
-            if target_dir.exists() && target_dir.read_dir().map(|mut d| d.next().is_some()).unwrap_or(false) {
-                logger::info("mineru", "MinerU 主模型下载成功");
-                let _ = app_handle.emit_all("mineru-model-output",
-                    serde_json::json!({
-                        "type": "success", 
-                        "model_type": "main",
-                        "message": "\n✓ MinerU 2.5 模型下载成功！\n"
-                    }));
-                Ok("MinerU 主模型下载成功".to_string())
-            } else {
-                logger::error("mineru", "下载完成但未找到模型文件");
-                let _ = app_handle.emit_all("mineru-model-output",
-                    serde_json::json!({
-                        "type": "error", 
-                        "model_type": "main",
-                        "message": "\n✗ 下载完成但未找到模型文件，请检查网络连接后重试\n"
-                    }));
-                Err(anyhow!("下载完成但未找到模型文件"))
-            }
+        let last_progress = tracker.lock().overall_percent();
+
+        if !status.success() {
+            logger::error("mineru", &format!("{} 模型下载失败，退出码: {:?}", package.display_name, status.code()));
+            emit_model_status(app_handle, model_type, "error", last_progress, "下载失败");
+            let _ = app_handle.emit_all("mineru-model-output",
+                serde_json::json!({
+                    "type": "error", 
+                    "model_type": package.model_type,
+                    "message": format!("\n✗ 下载失败 (退出码: {:?})，重新下载会从断点继续\n", status.code())
+                }));
+            return Err(anyhow!("{} 模型下载失败", package.display_name));
+        }
+
+        // 验证模型文件是否完整
+        let report = verify_model_package(package, &models_dir);
+        if report.complete {
+            logger::info("mineru", &format!("{} 模型下载成功", package.display_name));
+            emit_model_status(app_handle, model_type, "completed", Some(100.0), "下载完成");
+            let _ = app_handle.emit_all("mineru-model-output",
+                serde_json::json!({
+                    "type": "success", 
+                    "model_type": package.model_type,
+                    "message": format!("\n✓ {} 模型下载成功！\n", package.display_name)
+                }));
+            Ok(format!("{} 模型下载成功", package.display_name))
         } else {
-            logger::error("mineru", &format!("MinerU 主模型下载失败，退出码: {:?}", status.code()));
+            let detail = report.missing.iter()
+                .chain(report.partial_files.iter())
+                .take(5)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ");
+            logger::error("mineru", &format!("{} 模型不完整: {}", package.display_name, detail));
+            emit_model_status(app_handle, model_type, "error", last_progress, "模型文件不完整");
             let _ = app_handle.emit_all("mineru-model-output",
                 serde_json::json!({
                     "type": "error", 
-                    "model_type": "main",
-                    "message": format!("\n✗ 下载失败 (退出码: {:?})，请检查错误信息\n", status.code())
+                    "model_type": package.model_type,
+                    "message": format!("\n✗ 下载完成但模型文件不完整（{}），请重新下载以续传\n", detail)
                 }));
-            Err(anyhow!("模型下载失败"))
+            Err(anyhow!("下载完成但模型文件不完整"))
         }
     }
 
-    /// 下载 OCR 模型（PDF-Extract-Kit-1.0）
-    pub fn download_ocr_models_with_events(app_handle: &tauri::AppHandle, storage_path: Option<&str>) -> Result<String> {
-        use std::io::{BufRead, BufReader};
-        use std::process::Stdio;
+    /// 将模型目录移动到新位置，返回移动的字节数
+    /// 同一磁盘内直接重命名，跨磁盘时逐个复制文件并通过 mineru-model-relocate 事件报告进度
+    pub fn move_models_dir(app_handle: &tauri::AppHandle, from: &Path, to: &Path) -> Result<u64> {
         use crate::logger;
 
-        let models_dir = Self::get_models_dir(storage_path);
-        
-        // 确保目录存在
-        if !models_dir.exists() {
-            fs::create_dir_all(&models_dir)?;
+        if !from.exists() {
+            return Err(anyhow!("模型目录不存在: {}", from.display()));
+        }
+        if to.starts_with(from) {
+            return Err(anyhow!("目标目录不能位于当前模型目录内"));
+        }
+        if to.exists() && fs::read_dir(to)?.next().is_some() {
+            return Err(anyhow!("目标目录不为空: {}", to.display()));
         }
 
-        logger::info("mineru", &format!("开始下载 OCR 模型到: {}", models_dir.display()));
-
-        let _ = app_handle.emit_all("mineru-model-output", 
-            serde_json::json!({
-                "type": "cmd", 
-                "model_type": "ocr",
-                "message": format!("> 下载 PDF-Extract-Kit-1.0 OCR 模型到: {}\n", models_dir.display())
-            }));
+        let total_bytes = dir_size(from);
+        logger::info("mineru", &format!(
+            "移动模型目录: {} -> {}（{} 字节）",
+            from.display(), to.display(), total_bytes
+        ));
 
-        // 创建临时 Python 脚本文件
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join("mineru_download_ocr.py");
-        let target_dir = models_dir.join("PDF-Extract-Kit-1.0");
-        
-        let python_script = format!(
-            r#"# -*- coding: utf-8 -*-
-import sys
-import os
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if to.exists() {
+            fs::remove_dir(to)?;
+        }
 
-print("正在初始化 ModelScope...", flush=True)
-try:
-    from modelscope import snapshot_download
-    print("ModelScope 已加载", flush=True)
-except ImportError as e:
-    print(f"错误: 无法导入 modelscope: {{e}}", flush=True)
-    sys.exit(1)
+        // 同一磁盘内直接重命名
+        if fs::rename(from, to).is_ok() {
+            let _ = app_handle.emit_all("mineru-model-relocate", serde_json::json!({
+                "copied_bytes": total_bytes,
+                "total_bytes": total_bytes,
+                "progress": 100.0
+            }));
+            return Ok(total_bytes);
+        }
 
-target_dir = r'{}'
-print(f"目标目录: {{target_dir}}", flush=True)
+        // 跨磁盘：复制后再删除原目录，复制失败时清理已复制的部分
+        if let Err(e) = copy_dir_with_progress(app_handle, from, to, total_bytes) {
+            let _ = fs::remove_dir_all(to);
+            return Err(e);
+        }
+        if dir_size(to) != total_bytes {
+            let _ = fs::remove_dir_all(to);
+            return Err(anyhow!("复制后的模型大小不一致，已取消移动"));
+        }
+        fs::remove_dir_all(from)?;
 
-try:
-    print("开始下载 PDF-Extract-Kit-1.0 OCR 模型，这可能需要较长时间...", flush=True)
-    model_dir = snapshot_download(
-        'OpenDataLab/PDF-Extract-Kit-1.0', 
-        local_dir=target_dir,
-        max_workers=16
-    )
-    print(f"OCR 模型下载成功，存放路径为: {{model_dir}}", flush=True)
-except Exception as e:
-    print(f"下载失败: {{e}}", flush=True)
-    sys.exit(1)
-"#,
-            target_dir.to_string_lossy().replace("\\", "\\\\")
-        );
+        Ok(total_bytes)
+    }
 
-        // 写入脚本文件
-        fs::write(&script_path, &python_script)?;
-        logger::debug("mineru", &format!("脚本文件: {}", script_path.display()));
+    /// 校验已下载模型的完整性
+    pub fn verify_models(storage_path: Option<&str>) -> Vec<ModelVerifyReport> {
+        let models_dir = Self::get_models_dir(storage_path);
+        [&MAIN_MODEL_PACKAGE, &OCR_MODEL_PACKAGE]
+            .iter()
+            .map(|package| verify_model_package(package, &models_dir))
+            .collect()
+    }
 
-        let _ = app_handle.emit_all("mineru-model-output", 
-            serde_json::json!({
-                "type": "info", 
-                "model_type": "ocr",
-                "message": "正在从 ModelScope 下载 PDF-Extract-Kit-1.0 OCR 模型...\n这可能需要较长时间，请耐心等待...\n"
-            }));
+    /// 删除已下载的模型目录
+    /// model_type 为 "main" 或 "ocr" 时只删除对应的模型，为 None 时删除全部已知的模型包
+    /// 模型目录可能是用户指定的共享目录，只删除其中的模型包子目录，不删除目录本身和其他内容
+    pub fn remove_models(storage_path: Option<&str>, model_type: Option<&str>) -> Result<CleanupReport> {
+        use crate::logger;
 
-        // 使用 python 执行脚本文件
-        let mut child = Command::new("python")
-            .arg(&script_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let models_dir = Self::get_models_dir(storage_path);
+        let targets = match model_type {
+            Some("main") => vec![models_dir.join(MAIN_MODEL_PACKAGE.target_subdir)],
+            Some("ocr") => vec![models_dir.join(OCR_MODEL_PACKAGE.target_subdir)],
+            Some(other) => return Err(anyhow!("未知的模型类型: {}", other)),
+            None => [&MAIN_MODEL_PACKAGE, &OCR_MODEL_PACKAGE]
+                .iter()
+                .map(|package| models_dir.join(package.target_subdir))
+                .collect(),
+        };
 
-        // 读取输出
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-        
-        let app_handle_stdout = app_handle.clone();
-        let stdout_thread = std::thread::spawn(move || {
-            if let Some(stdout) = stdout {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines().flatten() {
-                    let _ = app_handle_stdout.emit_all("mineru-model-output",
-                        serde_json::json!({
-                            "type": "info", 
-                            "model_type": "ocr",
-                            "message": format!("{}\n", line)
-                        }));
-                }
+        let mut report = CleanupReport::default();
+        for target in targets {
+            if !target.exists() {
+                continue;
             }
-        });
+            let size = dir_size(&target);
+            fs::remove_dir_all(&target)?;
+            logger::info("mineru", &format!("已删除模型目录: {}（{} 字节）", target.display(), size));
+            report.reclaimed_bytes += size;
+            report.removed_paths.push(target.to_string_lossy().to_string());
+        }
 
-        let app_handle_stderr = app_handle.clone();
-        let stderr_thread = std::thread::spawn(move || {
-            if let Some(stderr) = stderr {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines().flatten() {
-                    let msg_type = if line.to_lowercase().contains("error") || line.to_lowercase().contains("failed") {
-                        "error"
-                    } else {
-                        "info"
-                    };
-                    let _ = app_handle_stderr.emit_all("mineru-model-output",
-                        serde_json::json!({
-                            "type": msg_type, 
-                            "model_type": "ocr",
-                            "message": format!("{}\n", line)
-                        }));
+        Ok(report)
+    }
+
+    /// 删除 magic-pdf.json 配置文件
+    pub fn remove_config_file() -> Result<CleanupReport> {
+        use crate::logger;
+
+        let mut report = CleanupReport::default();
+        let config_path = get_magic_pdf_config_path().ok_or_else(|| anyhow!("无法获取用户主目录"))?;
+        if config_path.exists() {
+            report.reclaimed_bytes = fs::metadata(&config_path).map(|m| m.len()).unwrap_or(0);
+            fs::remove_file(&config_path)?;
+            logger::info("mineru", &format!("已删除配置文件: {}", config_path.display()));
+            report.removed_paths.push(config_path.to_string_lossy().to_string());
+        }
+
+        Ok(report)
+    }
+
+    /// 使用 pip 卸载 MinerU 及其下载依赖，通过事件发送实时输出
+    pub fn uninstall_with_events(app_handle: &tauri::AppHandle) -> Result<CleanupReport> {
+        use crate::logger;
+
+        let packages = [
+            MineruCli::MagicPdf.package_name(),
+            MineruCli::Mineru.package_name(),
+            "modelscope",
+        ];
+
+        // 卸载前统计各个包占用的空间
+        let mut report = CleanupReport::default();
+        let installed: Vec<&str> = packages
+            .iter()
+            .copied()
+            .filter(|package| {
+                match get_package_files_size(package) {
+                    Some(size) => {
+                        report.reclaimed_bytes += size;
+                        true
+                    }
+                    None => false,
                 }
-            }
-        });
+            })
+            .collect();
 
-        // 等待输出线程完成
-        let _ = stdout_thread.join();
-        let _ = stderr_thread.join();
+        if installed.is_empty() {
+            let _ = app_handle.emit_all("mineru-install-output",
+                serde_json::json!({"type": "info", "message": "未检测到已安装的 MinerU 相关包\n"}));
+            return Ok(report);
+        }
 
-        // 等待进程完成
-        let status = child.wait()?;
+        let _ = app_handle.emit_all("mineru-install-output",
+            serde_json::json!({"type": "cmd", "message": format!("> pip uninstall -y {}\n", installed.join(" "))}));
 
-        // 清理临时文件
-        let _ = fs::remove_file(&script_path);
+        let output = python_env::pip_command()
+            .args(["uninstall", "-y"])
+            .args(&installed)
+            .output()?;
 
-        if status.success() {
-            // 验证模型是否真的下载成功
-            if target_dir.exists() && target_dir.read_dir().map(|mut d| d.next().is_some()).unwrap_or(false) {
-                logger::info("mineru", "OCR 模型下载成功");
-                let _ = app_handle.emit_all("mineru-model-output",
-                    serde_json::json!({
-                        "type": "success", 
-                        "model_type": "ocr",
-                        "message": "\n✓ PDF-Extract-Kit-1.0 OCR 模型下载成功！\n"
-                    }));
-                Ok("OCR 模型下载成功".to_string())
-            } else {
-                logger::error("mineru", "下载完成但未找到 OCR 模型文件");
-                let _ = app_handle.emit_all("mineru-model-output",
-                    serde_json::json!({
-                        "type": "error", 
-                        "model_type": "ocr",
-                        "message": "\n✗ 下载完成但未找到模型文件，请检查网络连接后重试\n"
-                    }));
-                Err(anyhow!("下载完成但未找到模型文件"))
-            }
-        } else {
-            logger::error("mineru", &format!("OCR 模型下载失败，退出码: {:?}", status.code()));
-            let _ = app_handle.emit_all("mineru-model-output",
-                serde_json::json!({
-                    "type": "error", 
-                    "model_type": "ocr",
-                    "message": format!("\n✗ 下载失败 (退出码: {:?})，请检查错误信息\n", status.code())
-                }));
-            Err(anyhow!("OCR 模型下载失败"))
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let _ = app_handle.emit_all("mineru-install-output",
+                serde_json::json!({"type": "info", "message": format!("{}\n", line)}));
         }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            let _ = app_handle.emit_all("mineru-install-output",
+                serde_json::json!({"type": "error", "message": format!("{}\n", line)}));
+        }
+
+        if !output.status.success() {
+            logger::error("mineru", "卸载 MinerU 失败");
+            let _ = app_handle.emit_all("mineru-install-output",
+                serde_json::json!({"type": "error", "message": "\n✗ 卸载失败，请检查错误信息\n"}));
+            return Err(anyhow!("卸载失败"));
+        }
+
+        logger::info("mineru", &format!("已卸载: {}", installed.join(", ")));
+        let _ = app_handle.emit_all("mineru-install-output",
+            serde_json::json!({"type": "success", "message": "\n✓ MinerU 已卸载\n"}));
+        report.uninstalled_packages = installed.iter().map(|p| p.to_string()).collect();
+
+        Ok(report)
     }
 
     /// 安装 modelscope 依赖
@@ -835,7 +1211,7 @@ except Exception as e:
             }));
 
         // 使用 pip 安装
-        let mut child = Command::new("pip")
+        let mut child = python_env::pip_command()
             .args(["install", "-U", "modelscope"])
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -1026,19 +1402,11 @@ except Exception as e:
         let _ = app_handle.emit_all("mineru-install-output", 
             serde_json::json!({"type": "cmd", "message": "> pip install -U \"magic-pdf[full]\"\n"}));
 
-        let mut child = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "pip", "install", "-U", "magic-pdf[full]"])
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-        } else {
-            Command::new("pip")
-                .args(["install", "-U", "magic-pdf[full]"])
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-        }?;
+        let mut child = python_env::pip_command()
+            .args(["install", "-U", "magic-pdf[full]"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
 
         // 读取 stdout
         if let Some(stdout) = child.stdout.take() {
@@ -1080,15 +1448,9 @@ except Exception as e:
 
     /// 安装 MinerU（使用 pip）- 旧版本保留
     pub async fn install() -> Result<String> {
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", "pip", "install", "-U", "magic-pdf[full]"])
-                .output()
-        } else {
-            Command::new("pip")
-                .args(["install", "-U", "magic-pdf[full]"])
-                .output()
-        };
+        let output = python_env::pip_command()
+            .args(["install", "-U", "magic-pdf[full]"])
+            .output();
 
         match output {
             Ok(result) => {
@@ -1222,31 +1584,25 @@ except Exception as e:
 
         // MinerU 使用 magic-pdf 命令行工具
         // magic-pdf -p <pdf_path> -o <output_dir> -m auto
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args([
-                    "/C",
-                    "magic-pdf",
-                    "-p",
-                    pdf_path,
-                    "-o",
-                    output_dir.to_str().unwrap_or("."),
-                    "-m",
-                    "auto",
-                ])
-                .output()
-        } else {
-            Command::new("magic-pdf")
-                .args([
-                    "-p",
-                    pdf_path,
-                    "-o",
-                    output_dir.to_str().unwrap_or("."),
-                    "-m",
-                    "auto",
-                ])
-                .output()
+        let mut command = match Self::get_magic_pdf_path() {
+            Some(exe_path) => Command::new(exe_path),
+            None if cfg!(target_os = "windows") => {
+                let mut cmd = Command::new("cmd");
+                cmd.args(["/C", "magic-pdf"]);
+                cmd
+            }
+            None => Command::new("magic-pdf"),
         };
+        let output = command
+            .args([
+                "-p",
+                pdf_path,
+                "-o",
+                output_dir.to_str().unwrap_or("."),
+                "-m",
+                "auto",
+            ])
+            .output();
 
         match output {
             Ok(result) => {
@@ -1282,15 +1638,18 @@ except Exception as e:
     /// 将整个 PDF 转换为 Markdown
     pub async fn convert_pdf_full(
         &self,
+        task_id: &str,
         pdf_path: &str,
         output_dir: &Path,
     ) -> Result<Vec<String>> {
-        self.convert_pdf_full_with_storage(pdf_path, output_dir, None).await
+        self.convert_pdf_full_with_storage(task_id, pdf_path, output_dir, None).await
     }
 
     /// 将整个 PDF 转换为 Markdown（带存储路径）
+    /// task_id 用于标识转换任务，可通过 cancel_conversion 取消
     pub async fn convert_pdf_full_with_storage(
         &self,
+        task_id: &str,
         pdf_path: &str,
         output_dir: &Path,
         storage_path: Option<&str>,
@@ -1316,78 +1675,140 @@ except Exception as e:
         let parse_mode = Self::get_available_parse_mode_with_storage(storage_path);
         logger::info("mineru", &format!("使用解析模式: {}", parse_mode));
         
-        let output = if let Some(ref exe_path) = magic_pdf_path {
-            // 使用完整路径直接调用可执行文件（不通过 cmd）
-            logger::info("mineru", &format!("使用路径: {}", exe_path));
+        // 根据检测到的命令类型构造参数（magic-pdf 与 mineru 参数不同）
+        let cli = magic_pdf_path
+            .as_deref()
+            .map(MineruCli::from_path)
+            .or_else(Self::detect_path_cli)
+            .unwrap_or(MineruCli::MagicPdf);
+        
+        // 加入转换队列，等待空闲名额，避免多个转换进程争抢 GPU
+        // 拿到名额后再准备临时目录，排队期间不占用磁盘
+        if let Some(ref app_handle) = self.app_handle {
+            let _ = app_handle.emit_all("mineru-convert-status",
+                serde_json::json!({"file_id": task_id, "status": "queued"}));
+        }
+        let slot = mineru_queue::acquire(task_id).await?;
+        if let Some(ref app_handle) = self.app_handle {
+            let _ = app_handle.emit_all("mineru-convert-status",
+                serde_json::json!({"file_id": task_id, "status": "running"}));
+        }
+
+        // 中文文件名或过长的路径经常导致 magic-pdf 出错，先复制到短 ASCII 临时路径再转换
+        let staging = StagedConversion::prepare(pdf_path, output_dir)?;
+        let (run_pdf, run_output) = match staging {
+            Some(ref staged) => {
+                logger::info("mineru", &format!("路径包含非 ASCII 字符或过长，使用临时路径: {}", staged.root.display()));
+                (staged.pdf_path.to_string_lossy().to_string(), staged.output_dir.clone())
+            }
+            None => (pdf_path.to_string(), output_dir.to_path_buf()),
+        };
+        let convert_args = cli.build_convert_args(
+            &run_pdf,
+            run_output.to_str().unwrap_or("."),
+            &parse_mode,
+        );
+        
+        // 直接启动转换进程（不通过 cmd 包装），取消时终止的就是转换进程本身
+        let mut command = if let Some(ref exe_path) = magic_pdf_path {
+            logger::info("mineru", &format!("使用路径: {} ({})", exe_path, cli.executable_name()));
             Command::new(exe_path)
-                .args([
-                    "-p",
-                    pdf_path,
-                    "-o",
-                    output_dir.to_str().unwrap_or("."),
-                    "-m",
-                    &parse_mode,
-                ])
-                .output()
-        } else if cfg!(target_os = "windows") {
-            // 回退到通过 cmd 调用（依赖 PATH）
-            logger::warn("mineru", "未找到完整路径，尝试直接调用 magic-pdf");
-            Command::new("cmd")
-                .args([
-                    "/C",
-                    "chcp",
-                    "65001",
-                    ">nul",
-                    "&&",
-                    "magic-pdf",
-                    "-p",
-                    pdf_path,
-                    "-o",
-                    output_dir.to_str().unwrap_or("."),
-                    "-m",
-                    &parse_mode,
-                ])
-                .output()
         } else {
-            Command::new("magic-pdf")
-                .args([
-                    "-p",
-                    pdf_path,
-                    "-o",
-                    output_dir.to_str().unwrap_or("."),
-                    "-m",
-                    &parse_mode,
-                ])
-                .output()
+            // 依赖 PATH 查找命令，Windows 上会自动补全 .exe
+            logger::warn("mineru", &format!("未找到完整路径，尝试直接调用 {}", cli.executable_name()));
+            Command::new(cli.executable_name())
         };
+        command.args(&convert_args);
+        // 让 Python 使用 UTF-8 输出，避免 Windows 控制台代码页导致中文日志乱码
+        command.env("PYTHONIOENCODING", "utf-8").env("PYTHONUTF8", "1");
 
-        match output {
+        let pdf_name = Path::new(pdf_path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+
+        match run_tracked_process(task_id, slot.job_id(), command, self.app_handle.clone()).await {
             Ok(result) => {
                 // 记录输出
-                let stdout = String::from_utf8_lossy(&result.stdout);
-                let stderr = String::from_utf8_lossy(&result.stderr);
+                if !result.stdout.is_empty() {
+                    logger::debug("mineru", &format!("stdout: {}", result.stdout));
+                }
+                if !result.stderr.is_empty() {
+                    logger::warn("mineru", &format!("stderr: {}", result.stderr));
+                }
+                
+                if result.timed_out {
+                    let timeout_secs = *CONVERSION_TIMEOUT_SECS.read();
+                    let stderr_tail = tail_lines(&result.stderr, STDERR_TAIL_LINES);
+                    logger::error("mineru", &format!(
+                        "转换超时（{} 秒），已终止进程: {}\nstderr 末尾:\n{}",
+                        timeout_secs, task_id, stderr_tail
+                    ));
+                    if let Some(ref app_handle) = self.app_handle {
+                        let _ = app_handle.emit_all("mineru-convert-status",
+                            serde_json::json!({
+                                "file_id": task_id,
+                                "status": "timeout",
+                                "timeout_secs": timeout_secs,
+                                "parse_mode": parse_mode,
+                                "suggested_mode": "txt",
+                                "stderr_tail": stderr_tail
+                            }));
+                    }
+                    let partial_dir = output_dir.join(pdf_name);
+                    if partial_dir.exists() {
+                        let _ = fs::remove_dir_all(&partial_dir);
+                    }
+                    return Err(anyhow!(
+                        "{}（{} 秒），进程已终止。可尝试使用 txt 模式转换，或在设置中延长超时时间",
+                        CONVERSION_TIMED_OUT,
+                        timeout_secs
+                    ));
+                }
+                
+                // 失败时从 stderr 中识别原因
+                let diagnostic = if !result.cancelled && !result.status.success() {
+                    Some(mineru_diagnostics::diagnose(&result.stderr))
+                } else {
+                    None
+                };
                 
-                if !stdout.is_empty() {
-                    logger::debug("mineru", &format!("stdout: {}", stdout));
+                if let Some(ref app_handle) = self.app_handle {
+                    let status = if result.cancelled {
+                        "cancelled"
+                    } else if result.status.success() {
+                        "completed"
+                    } else {
+                        "error"
+                    };
+                    let _ = app_handle.emit_all("mineru-convert-status",
+                        serde_json::json!({"file_id": task_id, "status": status, "diagnostic": diagnostic}));
                 }
-                if !stderr.is_empty() {
-                    logger::warn("mineru", &format!("stderr: {}", stderr));
+                
+                if result.cancelled {
+                    // 清理未完成的输出
+                    let partial_dir = output_dir.join(pdf_name);
+                    if partial_dir.exists() {
+                        let _ = fs::remove_dir_all(&partial_dir);
+                    }
+                    logger::warn("mineru", &format!("转换已取消: {}", task_id));
+                    return Err(anyhow!("{}", CONVERSION_CANCELLED));
                 }
                 
                 if result.status.success() {
                     logger::info("mineru", "PDF 转换成功");
-                    
-                    let pdf_name = Path::new(pdf_path)
-                        .file_stem()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("output");
+
+                    // 将临时路径中的结果移回输出目录，并恢复原文件名
+                    if let Some(ref staged) = staging {
+                        staged.restore(output_dir, pdf_name)?;
+                    }
 
                     // 查找所有生成的 Markdown 文件
-                    let auto_dir = output_dir.join(pdf_name).join("auto");
+                    // 输出子目录取决于解析模式和后端（auto/txt/ocr/vlm）
                     let mut markdown_files = Vec::new();
 
-                    if auto_dir.exists() {
-                        for entry in fs::read_dir(&auto_dir)? {
+                    if let Some(result_dir) = find_output_dir(output_dir, pdf_name) {
+                        for entry in fs::read_dir(&result_dir)? {
                             let entry = entry?;
                             let path = entry.path();
                             if path.extension().map(|e| e == "md").unwrap_or(false) {
@@ -1407,7 +1828,17 @@ except Exception as e:
                 } else {
                     let err_msg = format!("转换失败, 返回码: {:?}", result.status.code());
                     logger::error("mineru", &err_msg);
-                    Err(anyhow!("{}", err_msg))
+                    match diagnostic {
+                        Some(diagnostic) => {
+                            logger::error("mineru", &format!(
+                                "诊断结果: {:?} - {}",
+                                diagnostic.kind,
+                                diagnostic.detail.as_deref().unwrap_or("")
+                            ));
+                            Err(anyhow!("{}", diagnostic.to_error_message()))
+                        }
+                        None => Err(anyhow!("{}", err_msg)),
+                    }
                 }
             }
             Err(e) => {
@@ -1418,6 +1849,142 @@ except Exception as e:
         }
     }
 
+    /// 单独转换 PDF 的某一页
+    /// 先用 lopdf 拆出单页 PDF，再交给 MinerU 转换，返回该页的 Markdown 内容
+    /// 该页引用的图片复制到 markdown_dir，与页面缓存放在一起
+    pub async fn convert_single_page(
+        &self,
+        task_id: &str,
+        pdf_path: &str,
+        page_number: u32,
+        output_dir: &Path,
+        markdown_dir: &Path,
+        storage_path: Option<&str>,
+    ) -> Result<String> {
+        use crate::logger;
+
+        let pages_dir = output_dir.join("pages");
+        fs::create_dir_all(&pages_dir)?;
+
+        let page_name = format!("page_{:04}", page_number);
+        let page_pdf = pages_dir.join(format!("{}.pdf", page_name));
+        let page_bytes = crate::ocr_service::extract_pdf_single_page(pdf_path, page_number)?;
+        fs::write(&page_pdf, &page_bytes)?;
+
+        logger::info("mineru", &format!("逐页转换第 {} 页: {}", page_number, page_pdf.display()));
+
+        let result = self
+            .convert_pdf_full_with_storage(task_id, &page_pdf.to_string_lossy(), output_dir, storage_path)
+            .await;
+
+        // 单页 PDF 只是中间文件，转换完成后删除
+        let _ = fs::remove_file(&page_pdf);
+
+        let markdown_files = result?;
+        let md_file = markdown_files
+            .first()
+            .ok_or_else(|| anyhow!("第 {} 页未生成 Markdown 文件", page_number))?;
+
+        let content = fs::read_to_string(md_file).map_err(|e| anyhow!("读取转换结果失败: {}", e))?;
+        if let Some(result_dir) = Path::new(md_file).parent() {
+            copy_linked_images(&content, result_dir, markdown_dir);
+        }
+        Ok(content)
+    }
+
+    /// 逐页转换指定页面，每完成一页就写入 Markdown 缓存并发送 mineru-page-converted 事件
+    /// 单页失败不会中断整个任务，但取消会立即停止
+    pub async fn convert_pdf_by_pages(
+        &self,
+        task_id: &str,
+        pdf_path: &str,
+        pages: &[u32],
+        output_dir: &Path,
+        markdown_dir: &Path,
+        storage_path: Option<&str>,
+    ) -> Result<Vec<String>> {
+        use crate::logger;
+
+        fs::create_dir_all(markdown_dir)?;
+        let mut markdown_files = Vec::new();
+
+        for (index, &page) in pages.iter().enumerate() {
+            match self.convert_single_page(task_id, pdf_path, page, output_dir, markdown_dir, storage_path).await {
+                Ok(content) => {
+                    let md_file = markdown_dir.join(format!("{:04}_page.md", page));
+                    fs::write(&md_file, &content)?;
+                    let md_path = md_file.to_string_lossy().to_string();
+
+                    if let Some(ref app_handle) = self.app_handle {
+                        let _ = app_handle.emit_all("mineru-page-converted",
+                            serde_json::json!({
+                                "file_id": task_id,
+                                "page": page,
+                                "completed": index + 1,
+                                "total": pages.len(),
+                                "markdown_path": md_path
+                            }));
+                    }
+
+                    markdown_files.push(md_path);
+                }
+                Err(e) if e.to_string() == CONVERSION_CANCELLED => return Err(e),
+                Err(e) => {
+                    logger::error("mineru", &format!("第 {} 页转换失败: {}", page, e));
+                    if let Some(ref app_handle) = self.app_handle {
+                        let _ = app_handle.emit_all("mineru-page-converted",
+                            serde_json::json!({
+                                "file_id": task_id,
+                                "page": page,
+                                "completed": index + 1,
+                                "total": pages.len(),
+                                "error": e.to_string()
+                            }));
+                    }
+                }
+            }
+        }
+
+        Ok(markdown_files)
+    }
+
+    /// 取消正在进行的转换任务，返回是否找到了对应的进程
+    pub fn cancel_conversion(task_id: &str) -> bool {
+        use crate::logger;
+
+        // 还在排队的任务直接从队列中取消
+        if mineru_queue::cancel_queued(task_id) {
+            logger::info("mineru", &format!("已取消排队中的转换: {}", task_id));
+            return true;
+        }
+
+        // 持有运行表的锁再标记，run_tracked_process 登记进程后会检查该标记
+        let mut running = RUNNING_CONVERSIONS.lock();
+        let marked = mineru_queue::cancel_running(task_id);
+        let mut found = false;
+        for (job_id, conversion) in running.iter_mut().filter(|(_, c)| c.task_id == task_id) {
+            CANCELLED_CONVERSIONS.write().insert(job_id.clone());
+            if let Err(e) = conversion.child.kill() {
+                logger::warn("mineru", &format!("终止 MinerU 进程失败: {}", e));
+            }
+            found = true;
+        }
+        if found {
+            logger::info("mineru", &format!("已请求取消转换: {}", task_id));
+            true
+        } else if marked {
+            logger::info("mineru", &format!("已取消尚未启动的转换: {}", task_id));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 检查转换任务是否正在运行
+    pub fn is_converting(task_id: &str) -> bool {
+        RUNNING_CONVERSIONS.lock().values().any(|c| c.task_id == task_id)
+    }
+
     /// 查找 Markdown 输出文件
     fn find_markdown_output(&self, dir: &Path, _base_name: &str) -> Result<String> {
         if !dir.exists() {
@@ -1437,6 +2004,722 @@ except Exception as e:
     }
 }
 
+/// 正在运行的转换进程
+struct RunningConversion {
+    task_id: String,
+    child: Child,
+}
+
+/// 被跟踪的子进程执行结果
+struct TrackedOutput {
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+    cancelled: bool,
+    timed_out: bool,
+}
+
+/// 启动子进程并登记到运行表中，轮询等待其结束
+/// 进程运行期间可以通过 MineruService::cancel_conversion 终止
+/// 如果提供了 app_handle，每行输出都会以 mineru-convert-output 事件发送到前端
+async fn run_tracked_process(
+    task_id: &str,
+    job_id: &str,
+    mut command: Command,
+    app_handle: Option<AppHandle>,
+) -> Result<TrackedOutput> {
+    use std::process::Stdio;
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 在后台线程读取输出，避免管道写满导致进程阻塞
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let stdout_task = task_id.to_string();
+    let app_handle_stdout = app_handle.clone();
+    let stdout_thread = std::thread::spawn(move || {
+        let mut collected = String::new();
+        if let Some(stdout) = stdout {
+            read_output_lines(stdout, |line| {
+                emit_convert_line(app_handle_stdout.as_ref(), &stdout_task, "info", &line);
+                collected.push_str(&line);
+                collected.push('\n');
+            });
+        }
+        collected
+    });
+
+    let stderr_task = task_id.to_string();
+    let app_handle_stderr = app_handle.clone();
+    let stderr_thread = std::thread::spawn(move || {
+        let mut collected = String::new();
+        if let Some(stderr) = stderr {
+            read_output_lines(stderr, |line| {
+                // magic-pdf 的日志和进度条都输出到 stderr，不一定是错误
+                let lower = line.to_lowercase();
+                let msg_type = if lower.contains("error") || lower.contains("traceback") {
+                    "error"
+                } else {
+                    "info"
+                };
+                emit_convert_line(app_handle_stderr.as_ref(), &stderr_task, msg_type, &line);
+                collected.push_str(&line);
+                collected.push('\n');
+            });
+        }
+        collected
+    });
+
+    {
+        let mut running = RUNNING_CONVERSIONS.lock();
+        let mut conversion = RunningConversion {
+            task_id: task_id.to_string(),
+            child,
+        };
+        // 拿到名额后、进程启动前收到的取消请求
+        if mineru_queue::is_cancelled(job_id) {
+            CANCELLED_CONVERSIONS.write().insert(job_id.to_string());
+            let _ = conversion.child.kill();
+        }
+        running.insert(job_id.to_string(), conversion);
+    }
+
+    // 看门狗：进程运行超过设定时间后强制终止，避免卡死的转换一直占用队列
+    let timeout_secs = *CONVERSION_TIMEOUT_SECS.read();
+    let started = std::time::Instant::now();
+    let mut timed_out = false;
+
+    let status = loop {
+        {
+            let mut running = RUNNING_CONVERSIONS.lock();
+            let waited = match running.get_mut(job_id) {
+                Some(conversion) => {
+                    if !timed_out && timeout_secs > 0 && started.elapsed().as_secs() >= timeout_secs {
+                        timed_out = true;
+                        let _ = conversion.child.kill();
+                    }
+                    conversion.child.try_wait()
+                }
+                None => break None,
+            };
+            match waited {
+                Ok(Some(status)) => {
+                    running.remove(job_id);
+                    break Some(Ok(status));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    running.remove(job_id);
+                    break Some(Err(e));
+                }
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    };
+
+    let stdout = stdout_thread.join().unwrap_or_default();
+    let stderr = stderr_thread.join().unwrap_or_default();
+    let cancelled = CANCELLED_CONVERSIONS.write().remove(job_id);
+
+    let status = status.ok_or_else(|| anyhow!("转换进程丢失"))??;
+
+    Ok(TrackedOutput {
+        status,
+        stdout,
+        stderr,
+        cancelled,
+        timed_out,
+    })
+}
+
+/// 比较两个版本号（只比较数字部分，例如 "2.1.0rc1" 视为 2.1.0）
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| -> Vec<u32> {
+        v.trim_start_matches('v')
+            .split('.')
+            .map(|part| {
+                part.chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse()
+                    .unwrap_or(0)
+            })
+            .collect()
+    };
+    let (mut a, mut b) = (parse(a), parse(b));
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+    a.cmp(&b)
+}
+
+/// 超过该长度的路径在 Windows 上容易触发 MAX_PATH 问题
+const MAX_SAFE_PATH_LEN: usize = 180;
+
+/// 临时路径中 PDF 的文件名（不含扩展名）
+const STAGED_PDF_NAME: &str = "input";
+
+/// 判断路径是否可以安全地传给 magic-pdf（纯 ASCII、不含空格且长度适中）
+pub fn is_safe_cli_path(path: &Path) -> bool {
+    let path_str = path.to_string_lossy();
+    path_str.is_ascii() && !path_str.contains(' ') && path_str.len() <= MAX_SAFE_PATH_LEN
+}
+
+/// 获取临时转换目录的根目录
+/// 系统临时目录本身可能包含中文用户名，此时在 Windows 上改用公共目录
+fn staging_root() -> PathBuf {
+    let temp = std::env::temp_dir().join("booq_mineru");
+    if is_safe_cli_path(&temp) {
+        return temp;
+    }
+    if cfg!(target_os = "windows") {
+        if let Ok(public) = std::env::var("PUBLIC") {
+            let public = PathBuf::from(public).join("booq_mineru");
+            if is_safe_cli_path(&public) {
+                return public;
+            }
+        }
+        if let Ok(drive) = std::env::var("SystemDrive") {
+            return PathBuf::from(format!("{}\\booq_mineru", drive));
+        }
+    }
+    temp
+}
+
+/// 在短 ASCII 临时路径中进行的转换
+/// 结束时（无论成功与否）自动删除临时目录
+struct StagedConversion {
+    root: PathBuf,
+    pdf_path: PathBuf,
+    output_dir: PathBuf,
+}
+
+impl StagedConversion {
+    /// 源文件或输出目录路径不安全时，复制源文件到临时目录；路径安全时返回 None
+    fn prepare(pdf_path: &str, output_dir: &Path) -> Result<Option<Self>> {
+        if is_safe_cli_path(Path::new(pdf_path)) && is_safe_cli_path(output_dir) {
+            return Ok(None);
+        }
+
+        // 每次转换使用独立的随机目录名，同一文件的多次转换不会互相删除临时文件
+        let root = staging_root().join(&uuid::Uuid::new_v4().simple().to_string()[..12]);
+        let output = root.join("out");
+        fs::create_dir_all(&output)?;
+
+        let staged_pdf = root.join(format!("{}.pdf", STAGED_PDF_NAME));
+        fs::copy(pdf_path, &staged_pdf)?;
+
+        Ok(Some(Self {
+            root,
+            pdf_path: staged_pdf,
+            output_dir: output,
+        }))
+    }
+
+    /// 将转换结果移回原输出目录，并把文件名中的临时名称替换为原 PDF 名称
+    fn restore(&self, output_dir: &Path, pdf_name: &str) -> Result<()> {
+        let staged_result = self.output_dir.join(STAGED_PDF_NAME);
+        if !staged_result.exists() {
+            return Err(anyhow!("未找到临时目录中的转换结果"));
+        }
+
+        let target = output_dir.join(pdf_name);
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+
+        for entry in walkdir::WalkDir::new(&staged_result).into_iter().filter_map(|e| e.ok()) {
+            let relative = match entry.path().strip_prefix(&staged_result) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            let renamed: PathBuf = relative
+                .components()
+                .map(|c| {
+                    let name = c.as_os_str().to_string_lossy();
+                    match name.strip_prefix(STAGED_PDF_NAME) {
+                        Some(rest) if rest.is_empty() || rest.starts_with('.') || rest.starts_with('_') => {
+                            format!("{}{}", pdf_name, rest)
+                        }
+                        _ => name.to_string(),
+                    }
+                })
+                .collect();
+            let dest = target.join(renamed);
+
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&dest)?;
+            } else if fs::rename(entry.path(), &dest).is_err() {
+                // 跨磁盘时无法直接重命名，改为复制
+                fs::copy(entry.path(), &dest)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for StagedConversion {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// 取输出的最后几行，用于记录超时前的错误信息
+fn tail_lines(output: &str, count: usize) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    let start = lines.len().saturating_sub(count);
+    lines[start..].join("\n")
+}
+
+/// 逐行读取子进程输出
+/// tqdm 进度条使用 \r 刷新同一行，因此 \r 和 \n 都视为行结束
+fn read_output_lines<R: std::io::Read>(reader: R, mut on_line: impl FnMut(String)) {
+    use std::io::{BufReader, Read};
+
+    let mut buf = Vec::new();
+    for byte in BufReader::new(reader).bytes() {
+        let byte = match byte {
+            Ok(b) => b,
+            Err(_) => break,
+        };
+        if byte == b'\n' || byte == b'\r' {
+            if !buf.is_empty() {
+                on_line(String::from_utf8_lossy(&buf).trim_end().to_string());
+                buf.clear();
+            }
+        } else {
+            buf.push(byte);
+        }
+    }
+    if !buf.is_empty() {
+        on_line(String::from_utf8_lossy(&buf).trim_end().to_string());
+    }
+}
+
+/// 发送一行转换输出，如果能解析出进度则同时发送进度事件
+fn emit_convert_line(app_handle: Option<&AppHandle>, task_id: &str, msg_type: &str, line: &str) {
+    let app_handle = match app_handle {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    let _ = app_handle.emit_all("mineru-convert-output",
+        serde_json::json!({
+            "type": msg_type,
+            "file_id": task_id,
+            "message": format!("{}\n", line)
+        }));
+
+    if let Some((current, total)) = parse_page_progress(line) {
+        let percent = current as f32 / total as f32 * 100.0;
+        let _ = app_handle.emit_all("mineru-convert-progress",
+            serde_json::json!({
+                "file_id": task_id,
+                "current": current,
+                "total": total,
+                "progress": percent
+            }));
+    }
+}
+
+/// 从 magic-pdf 的 tqdm 进度条中解析页面进度
+/// 例如: "Predict: 45%|████▌     | 9/20 [00:12<00:15,  1.39s/it]"
+pub fn parse_page_progress(line: &str) -> Option<(u32, u32)> {
+    use regex::Regex;
+
+    static PROGRESS_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\|\s*(\d+)/(\d+)\s*\[").unwrap());
+
+    let caps = PROGRESS_RE.captures(line)?;
+    let current: u32 = caps[1].parse().ok()?;
+    let total: u32 = caps[2].parse().ok()?;
+    if total == 0 || current > total {
+        return None;
+    }
+    Some((current, total))
+}
+
+/// 下载进度条中解析出的一行进度
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadProgress {
+    pub file: Option<String>,
+    pub percent: f32,
+    pub downloaded: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// 从 ModelScope / huggingface_hub 的 tqdm 进度条中解析下载进度
+/// 例如: "Downloading [model.safetensors]:  45%|████▌     | 1.02G/2.27G [00:30<00:37, 35.1MB/s]"
+pub fn parse_download_progress(line: &str) -> Option<DownloadProgress> {
+    use regex::Regex;
+
+    static DOWNLOAD_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?:\[(?P<file>[^\]]+)\]|(?P<name>[^\s:|]+))?:?\s*(?P<pct>\d{1,3})%\|[^|]*\|\s*(?:(?P<done>[\d.]+)\s*(?P<du>[kKMGT]?)i?B?/(?P<total>[\d.]+)\s*(?P<tu>[kKMGT]?)i?B?)?",
+        )
+        .unwrap()
+    });
+
+    let caps = DOWNLOAD_RE.captures(line)?;
+    let percent: f32 = caps["pct"].parse().ok()?;
+    if percent > 100.0 {
+        return None;
+    }
+
+    let file = caps
+        .name("file")
+        .or_else(|| caps.name("name"))
+        .map(|m| m.as_str().trim().to_string());
+    let downloaded = match (caps.name("done"), caps.name("du")) {
+        (Some(n), Some(u)) => parse_size(n.as_str(), u.as_str()),
+        _ => None,
+    };
+    let total = match (caps.name("total"), caps.name("tu")) {
+        (Some(n), Some(u)) => parse_size(n.as_str(), u.as_str()),
+        _ => None,
+    };
+
+    Some(DownloadProgress {
+        file,
+        percent,
+        downloaded,
+        total,
+    })
+}
+
+/// 将 tqdm 的 "1.02G" 形式的数值转换为字节数
+fn parse_size(number: &str, unit: &str) -> Option<u64> {
+    let value: f64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "k" | "K" => 1024.0,
+        "M" => 1024.0 * 1024.0,
+        "G" => 1024.0 * 1024.0 * 1024.0,
+        "T" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    Some((value * multiplier) as u64)
+}
+
+/// 汇总各个文件的下载进度，得到整体进度
+#[derive(Default)]
+struct DownloadProgressTracker {
+    files: HashMap<String, (u64, u64)>, // 文件名 -> (已下载, 总大小)
+    last_percent: Option<f32>,
+    last_emitted: Option<u32>,
+}
+
+impl DownloadProgressTracker {
+    /// 记录一行进度，返回整体进度有变化时的新值
+    fn update(&mut self, progress: &DownloadProgress) -> Option<f32> {
+        if let (Some(file), Some(done), Some(total)) =
+            (&progress.file, progress.downloaded, progress.total)
+        {
+            self.files.insert(file.clone(), (done, total));
+        }
+        self.last_percent = Some(progress.percent);
+
+        let overall = self.overall_percent()?;
+        // 按整数百分比节流，避免事件过多
+        let rounded = overall.floor() as u32;
+        if self.last_emitted == Some(rounded) {
+            return None;
+        }
+        self.last_emitted = Some(rounded);
+        Some(overall)
+    }
+
+    /// 整体进度：优先按字节数汇总，没有大小信息时使用最近一行的百分比
+    fn overall_percent(&self) -> Option<f32> {
+        let (done, total) = self
+            .files
+            .values()
+            .fold((0u64, 0u64), |(d, t), (fd, ft)| (d + fd, t + ft));
+        if total > 0 {
+            Some((done as f64 / total as f64 * 100.0).min(100.0) as f32)
+        } else {
+            self.last_percent
+        }
+    }
+}
+
+/// 发送结构化的模型下载状态
+fn emit_model_status(
+    app_handle: &AppHandle,
+    model_type: &str,
+    status: &str,
+    progress: Option<f32>,
+    message: &str,
+) {
+    let _ = app_handle.emit_all("mineru-model-progress", ModelDownloadStatus {
+        model_type: model_type.to_string(),
+        status: status.to_string(),
+        progress,
+        message: message.to_string(),
+    });
+}
+
+/// 发送一行下载输出；进度条只转为进度事件，已完成的文件仍输出到日志
+fn emit_model_line(
+    app_handle: &AppHandle,
+    model_type: &str,
+    tracker: &Mutex<DownloadProgressTracker>,
+    msg_type: &str,
+    line: &str,
+) {
+    if let Some(progress) = parse_download_progress(line) {
+        let overall = tracker.lock().update(&progress);
+        if let Some(overall) = overall {
+            let message = progress.file.clone().unwrap_or_default();
+            emit_model_status(app_handle, model_type, "downloading", Some(overall), &message);
+        }
+        if progress.percent < 100.0 {
+            return;
+        }
+    }
+
+    let _ = app_handle.emit_all("mineru-model-output",
+        serde_json::json!({
+            "type": msg_type, 
+            "model_type": model_type,
+            "message": format!("{}\n", line)
+        }));
+}
+
+/// 生成模型下载脚本
+/// 脚本在下载中断时自动重试，ModelScope 与 huggingface_hub 都会跳过已完成的文件
+fn build_download_script(package: &ModelPackage, source: ModelSource, target_dir: &Path) -> String {
+    let repo_id = match source {
+        ModelSource::ModelScope => package.modelscope_id,
+        ModelSource::HuggingFace | ModelSource::HfMirror => package.huggingface_id,
+    };
+
+    format!(
+        r#"# -*- coding: utf-8 -*-
+import sys
+import os
+import time
+
+source = '{source}'
+repo_id = '{repo_id}'
+target_dir = r'{target_dir}'
+max_retries = 5
+
+if source == 'hf-mirror':
+    os.environ['HF_ENDPOINT'] = 'https://hf-mirror.com'
+
+print("正在初始化下载工具...", flush=True)
+try:
+    if source == 'modelscope':
+        from modelscope import snapshot_download
+    else:
+        from huggingface_hub import snapshot_download
+    print("下载工具已加载", flush=True)
+except ImportError as e:
+    print(f"错误: 无法导入下载工具: {{e}}", flush=True)
+    if source != 'modelscope':
+        print("请先执行 pip install -U huggingface_hub", flush=True)
+    sys.exit(1)
+
+print(f"下载源: {{source}}", flush=True)
+print(f"目标目录: {{target_dir}}", flush=True)
+
+for attempt in range(1, max_retries + 1):
+    try:
+        print(f"开始下载 {display_name} 模型（第 {{attempt}}/{{max_retries}} 次尝试），已下载的文件会自动跳过...", flush=True)
+        if source == 'modelscope':
+            model_dir = snapshot_download(repo_id, local_dir=target_dir, max_workers={max_workers})
+        else:
+            model_dir = snapshot_download(repo_id=repo_id, local_dir=target_dir, max_workers={max_workers})
+        print(f"模型下载成功，存放路径为: {{model_dir}}", flush=True)
+        sys.exit(0)
+    except Exception as e:
+        print(f"下载中断: {{e}}", flush=True)
+        if attempt < max_retries:
+            wait = min(60, 5 * attempt)
+            print(f"{{wait}} 秒后继续下载...", flush=True)
+            time.sleep(wait)
+
+print("下载失败: 已达到最大重试次数", flush=True)
+sys.exit(1)
+"#,
+        source = source.as_str(),
+        repo_id = repo_id,
+        target_dir = target_dir.to_string_lossy().replace("\\", "\\\\"),
+        display_name = package.display_name,
+        max_workers = package.max_workers,
+    )
+}
+
+/// 获取 magic-pdf.json 配置文件路径（位于用户主目录）
+fn get_magic_pdf_config_path() -> Option<PathBuf> {
+    let home_dir = if cfg!(target_os = "windows") {
+        std::env::var("USERPROFILE").ok()
+    } else {
+        std::env::var("HOME").ok()
+    };
+    home_dir.map(|dir| PathBuf::from(dir).join("magic-pdf.json"))
+}
+
+/// 计算目录占用的总字节数
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// 递归复制目录，并按已复制的字节数发送进度事件
+fn copy_dir_with_progress(app_handle: &AppHandle, from: &Path, to: &Path, total_bytes: u64) -> Result<()> {
+    let mut copied_bytes = 0u64;
+    let mut last_percent = 0u32;
+
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(from)?;
+        let dest = to.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&dest)?;
+            continue;
+        }
+
+        copied_bytes += fs::copy(entry.path(), &dest)?;
+        let percent = if total_bytes > 0 {
+            (copied_bytes as f64 / total_bytes as f64 * 100.0) as u32
+        } else {
+            100
+        };
+        if percent != last_percent {
+            last_percent = percent;
+            let _ = app_handle.emit_all("mineru-model-relocate", serde_json::json!({
+                "copied_bytes": copied_bytes,
+                "total_bytes": total_bytes,
+                "progress": percent as f32
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// 通过 pip show -f 统计已安装包的文件大小，未安装时返回 None
+fn get_package_files_size(package: &str) -> Option<u64> {
+    let output = python_env::pip_command()
+        .args(["show", "-f", package])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let location = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Location:"))
+        .map(|l| PathBuf::from(l.trim()))?;
+
+    // "Files:" 之后每行一个相对于 Location 的文件路径
+    let size = stdout
+        .lines()
+        .skip_while(|line| !line.starts_with("Files:"))
+        .skip(1)
+        .filter_map(|line| fs::metadata(location.join(line.trim())).ok())
+        .map(|m| m.len())
+        .sum();
+    Some(size)
+}
+
+/// 判断文件是否为未下载完成的临时文件
+/// ModelScope 使用 ._____temp 目录，huggingface_hub 使用 .incomplete 后缀
+fn is_partial_download(path: &Path) -> bool {
+    let path_str = path.to_string_lossy();
+    if path_str.contains("._____temp") {
+        return true;
+    }
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e, "incomplete" | "part" | "tmp" | "lock"))
+        .unwrap_or(false)
+}
+
+/// 查找目录下所有未下载完成的临时文件
+fn find_partial_downloads(dir: &Path) -> Vec<String> {
+    if !dir.exists() {
+        return Vec::new();
+    }
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_partial_download(e.path()))
+        .map(|e| e.path().to_string_lossy().to_string())
+        .collect()
+}
+
+/// 校验模型包：必需文件存在且大小合理，并且没有残留的临时文件
+fn verify_model_package(package: &ModelPackage, models_dir: &Path) -> ModelVerifyReport {
+    let package_dir = models_dir.join(package.target_subdir);
+    let mut missing = Vec::new();
+
+    for required in package.required_files {
+        let dir = if required.dir.is_empty() {
+            package_dir.clone()
+        } else {
+            package_dir.join(required.dir)
+        };
+        let found = fs::read_dir(&dir)
+            .map(|entries| {
+                entries.flatten().any(|entry| {
+                    let path = entry.path();
+                    path.extension().map(|e| e == required.extension).unwrap_or(false)
+                        && entry.metadata().map(|m| m.len() >= required.min_size).unwrap_or(false)
+                })
+            })
+            .unwrap_or(false);
+        if !found {
+            missing.push(format!("{}/*.{}", required.dir, required.extension).trim_start_matches('/').to_string());
+        }
+    }
+
+    let partial_files = find_partial_downloads(&package_dir);
+    let total_size = if package_dir.exists() {
+        dir_size(&package_dir)
+    } else {
+        0
+    };
+
+    ModelVerifyReport {
+        model_type: package.model_type.to_string(),
+        model_dir: package_dir.to_string_lossy().to_string(),
+        complete: missing.is_empty() && partial_files.is_empty(),
+        total_size,
+        missing,
+        partial_files,
+    }
+}
+
+/// 查找 MinerU 的结果目录
+/// magic-pdf 与 mineru 都会在 <output>/<pdf_name>/ 下按解析方式创建子目录
+pub fn find_output_dir(output_dir: &Path, pdf_name: &str) -> Option<PathBuf> {
+    let base = output_dir.join(pdf_name);
+    ["auto", "txt", "ocr", "vlm"]
+        .iter()
+        .map(|sub| base.join(sub))
+        .find(|dir| dir.join(format!("{}.md", pdf_name)).exists())
+}
+
+/// 查找 MinerU 生成的完整 Markdown 文件
+pub fn find_output_markdown(output_dir: &Path, pdf_name: &str) -> Option<PathBuf> {
+    find_output_dir(output_dir, pdf_name).map(|dir| dir.join(format!("{}.md", pdf_name)))
+}
+
 /// 按页面分割 Markdown 内容
 /// MinerU 生成的 Markdown 可能包含页面标记
 pub fn split_markdown_by_pages(content: &str) -> Vec<String> {
@@ -1469,6 +2752,84 @@ pub fn split_markdown_by_pages(content: &str) -> Vec<String> {
     pages
 }
 
+/// 将整本书的转换结果拆分到按页缓存（markdown/NNNN_page.md）
+/// 优先按 content_list / middle.json 中的页码拆分，没有时回退到 split_markdown_by_pages
+/// 没有内容的空白页也写入空缓存，total_pages 之内的页码都能读取
+/// overwrite 为 false 时保留已有的页面缓存，返回写入的页数
+pub fn populate_page_cache(
+    output_dir: &Path,
+    pdf_name: &str,
+    markdown_dir: &Path,
+    total_pages: u32,
+    overwrite: bool,
+) -> Result<usize> {
+    use crate::logger;
+
+    let mut pages = match crate::mineru_layout::load_page_markdowns(output_dir, pdf_name) {
+        Some(pages) if !pages.is_empty() => pages,
+        _ => {
+            let md_file = find_output_markdown(output_dir, pdf_name)
+                .ok_or_else(|| anyhow!("未找到 MinerU 转换结果"))?;
+            let content = fs::read_to_string(&md_file)?;
+            split_markdown_by_pages(&content)
+                .into_iter()
+                .enumerate()
+                .map(|(i, page)| (i as u32 + 1, page))
+                .collect()
+        }
+    };
+
+    let last_page = pages.iter().map(|(page, _)| *page).max().unwrap_or(0).max(total_pages);
+    for page in 1..=last_page {
+        if !pages.iter().any(|(p, _)| *p == page) {
+            pages.push((page, String::new()));
+        }
+    }
+    pages.sort_by_key(|(page, _)| *page);
+
+    fs::create_dir_all(markdown_dir)?;
+    let result_dir = find_output_dir(output_dir, pdf_name);
+    let mut written = 0;
+    for (page, content) in &pages {
+        let md_file = markdown_dir.join(format!("{:04}_page.md", page));
+        if !overwrite && md_file.exists() {
+            continue;
+        }
+        fs::write(&md_file, content)?;
+        if let Some(ref result_dir) = result_dir {
+            copy_linked_images(content, result_dir, markdown_dir);
+        }
+        written += 1;
+    }
+
+    logger::info("mineru", &format!("已将转换结果拆分为 {} 页，写入 {} 页缓存", pages.len(), written));
+    Ok(written)
+}
+
+/// 把 Markdown 中以相对路径引用的图片从转换结果目录复制到页面缓存目录，保持相对路径不变
+/// 页面缓存不在转换结果目录中，不复制的话按页显示时图片链接会失效
+pub fn copy_linked_images(markdown: &str, source_dir: &Path, dest_dir: &Path) {
+    use crate::logger;
+
+    for (_, link) in crate::question_images::image_links(markdown) {
+        let relative = Path::new(&link);
+        if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+            continue;
+        }
+        let (source, dest) = (source_dir.join(relative), dest_dir.join(relative));
+        if dest.exists() || !source.is_file() {
+            continue;
+        }
+        let copied = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(&source, &dest));
+        if let Err(e) = copied {
+            logger::warn("mineru", &format!("复制图片 {} 失败: {}", source.display(), e));
+        }
+    }
+}
+
 /// 获取 MinerU 输出目录
 pub fn get_mineru_output_dir(app_handle: &AppHandle, file_id: &str) -> PathBuf {
     let config = crate::config::get_config_sync(app_handle);
@@ -1483,3 +2844,53 @@ pub fn get_mineru_output_dir(app_handle: &AppHandle, file_id: &str) -> PathBuf {
     };
     base_path.join(file_id).join("mineru_output")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_args(cli: MineruCli, pdf_path: &str, output_dir: &str) -> (String, String) {
+        let args = cli.build_convert_args(pdf_path, output_dir, "auto");
+        let pdf_index = args.iter().position(|a| a == "-p").unwrap() + 1;
+        let output_index = args.iter().position(|a| a == "-o").unwrap() + 1;
+        (args[pdf_index].clone(), args[output_index].clone())
+    }
+
+    #[test]
+    fn convert_args_keep_paths_as_single_arguments() {
+        let cases = [
+            ("/home/user/My Docs/chapter 1.pdf", "/home/user/My Docs/out"),
+            ("/tmp/say \"hi\" 'there'.pdf", "/tmp/out \"quoted\""),
+            ("/home/张三/高等数学 第一章.pdf", "/home/张三/输出"),
+            (r"C:\Users\张三\My Documents\习题 1.pdf", r"C:\Users\张三\AppData\Local\booq\out"),
+            (r"\\server\share\a b\c.pdf", r"D:\out dir\"),
+        ];
+        for cli in [MineruCli::MagicPdf, MineruCli::Mineru] {
+            for (pdf_path, output_dir) in cases {
+                assert_eq!(path_args(cli, pdf_path, output_dir), (pdf_path.to_string(), output_dir.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn convert_args_per_cli() {
+        assert_eq!(
+            MineruCli::MagicPdf.build_convert_args("a.pdf", "out", "txt"),
+            vec!["-p", "a.pdf", "-o", "out", "-m", "txt"]
+        );
+        assert_eq!(
+            MineruCli::Mineru.build_convert_args("a.pdf", "out", "auto"),
+            vec!["-p", "a.pdf", "-o", "out", "-m", "auto", "-b", "pipeline"]
+        );
+    }
+
+    #[test]
+    fn unsafe_cli_paths_are_staged() {
+        assert!(is_safe_cli_path(Path::new("/tmp/booq_mineru/abc/input.pdf")));
+        assert!(is_safe_cli_path(Path::new(r"C:\booq_mineru\abc\input.pdf")));
+        assert!(!is_safe_cli_path(Path::new("/home/user/My Docs/a.pdf")));
+        assert!(!is_safe_cli_path(Path::new(r"C:\Users\张三\a.pdf")));
+        assert!(!is_safe_cli_path(Path::new("/tmp/高等数学.pdf")));
+        assert!(!is_safe_cli_path(&PathBuf::from(format!("/tmp/{}.pdf", "a".repeat(MAX_SAFE_PATH_LEN)))));
+    }
+}
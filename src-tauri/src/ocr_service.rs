@@ -93,7 +93,7 @@ impl PaddleOCRClient {
             .map_err(|_| anyhow!("未设置 PADDLE_OCR_TOKEN 环境变量"))?;
         
         Ok(Self {
-            client: Client::new(),
+            client: crate::http_client::client(),
             api_url,
             token,
         })
@@ -102,7 +102,7 @@ impl PaddleOCRClient {
     /// 使用指定的 URL 和 Token 创建客户端
     pub fn new(api_url: &str, token: &str) -> Self {
         Self {
-            client: Client::new(),
+            client: crate::http_client::client(),
             api_url: api_url.to_string(),
             token: token.to_string(),
         }
@@ -215,7 +215,7 @@ impl PaddleOCRClient {
 // ==================== 辅助函数 ====================
 
 /// 从 PDF 中提取单页，返回单页 PDF 的字节数据
-fn extract_pdf_single_page(file_path: &str, page_number: u32) -> Result<Vec<u8>> {
+pub(crate) fn extract_pdf_single_page(file_path: &str, page_number: u32) -> Result<Vec<u8>> {
     let doc = Document::load(file_path)?;
     let pages = doc.get_pages();
     let total_pages = pages.len() as u32;
@@ -354,6 +354,14 @@ fn get_file_storage_path(app_handle: &AppHandle, file_id: &str) -> PathBuf {
 }
 
 /// 将页面转换为 Markdown（使用 PaddleOCR-VL）
+/// 读取已缓存的页面 Markdown，不触发转换
+pub fn cached_page_markdown(app_handle: &AppHandle, file_id: &str, page_number: u32) -> Option<String> {
+    let md_file_path = get_file_storage_path(app_handle, file_id)
+        .join("markdown")
+        .join(format!("{:04}_page.md", page_number));
+    fs::read_to_string(md_file_path).ok()
+}
+
 pub async fn convert_page_to_markdown(
     app_handle: &AppHandle,
     file_id: &str,
@@ -395,7 +403,7 @@ pub async fn convert_page_to_markdown(
             // 其次使用 MinerU（如果命令可用，并传入存储路径检查模型）
             else if crate::mineru_service::MineruService::check_command_available_with_storage(storage_path) {
                 logger::info("ocr", "使用 MinerU 本地工具进行转换");
-                convert_pdf_with_mineru(app_handle, &file_info.path, &markdown_dir, page_number, storage_path).await?
+                convert_pdf_with_mineru(app_handle, file_id, &file_info, &markdown_dir, page_number, storage_path).await?
             }
             // 最后回退到简单文本提取
             else {
@@ -467,8 +475,9 @@ async fn convert_pdf_with_paddle_ocr_config(
 
 /// 使用 MinerU 转换 PDF 单页
 async fn convert_pdf_with_mineru(
-    _app_handle: &AppHandle,
-    file_path: &str,
+    app_handle: &AppHandle,
+    file_id: &str,
+    file_info: &crate::commands::FileInfo,
     output_dir: &PathBuf,
     page_number: u32,
     storage_path: Option<&str>,
@@ -476,7 +485,8 @@ async fn convert_pdf_with_mineru(
     use crate::mineru_service::MineruService;
     use crate::logger;
     
-    let service = MineruService::new();
+    let file_path = file_info.path.as_str();
+    let service = MineruService::with_events(app_handle);
     
     logger::info("ocr", &format!("使用 MinerU 转换 PDF 第 {} 页: {}", page_number, file_path));
     
@@ -491,47 +501,66 @@ async fn convert_pdf_with_mineru(
         .and_then(|s| s.to_str())
         .unwrap_or("output");
     
-    let cached_md = mineru_output.join(pdf_name).join("auto").join(format!("{}.md", pdf_name));
+    let cached_md = crate::mineru_service::find_output_markdown(&mineru_output, pdf_name);
+    
+    // 逐页模式：没有整本书的转换结果时，只转换当前页
+    let config = crate::config::get_config_sync(app_handle);
+    if config.mineru_page_mode && cached_md.is_none() {
+        logger::info("ocr", &format!("逐页模式，仅转换第 {} 页", page_number));
+        let page_content = service
+            .convert_single_page(file_id, file_path, page_number, &mineru_output, output_dir, storage_path)
+            .await?;
+        
+        fs::create_dir_all(output_dir)?;
+        let md_filename = output_dir.join(format!("{:04}_page.md", page_number));
+        fs::write(&md_filename, &page_content)?;
+        
+        return Ok(page_content);
+    }
     
-    let full_content = if cached_md.exists() {
+    if let Some(cached_md) = cached_md {
         logger::debug("ocr", &format!("使用缓存的 Markdown 文件: {}", cached_md.display()));
-        fs::read_to_string(&cached_md)?
     } else {
         logger::info("ocr", "缓存不存在，开始完整转换");
         // 需要先转换整个 PDF，传入存储路径
-        let result = service.convert_pdf_full_with_storage(file_path, &mineru_output, storage_path).await?;
-        if let Some(md_file) = result.first() {
-            logger::info("ocr", &format!("MinerU 转换成功: {}", md_file));
-            fs::read_to_string(md_file)?
-        } else {
-            logger::error("ocr", "MinerU 转换失败：未生成 Markdown 文件");
-            return Err(anyhow!("MinerU 转换失败：未生成 Markdown 文件"));
+        let result = service.convert_pdf_full_with_storage(file_id, file_path, &mineru_output, storage_path).await?;
+        match result.first() {
+            Some(md_file) => logger::info("ocr", &format!("MinerU 转换成功: {}", md_file)),
+            None => {
+                logger::error("ocr", "MinerU 转换失败：未生成 Markdown 文件");
+                return Err(anyhow!("MinerU 转换失败：未生成 Markdown 文件"));
+            }
         }
-    };
+    }
     
-    // 按页面分割内容
-    let pages = crate::mineru_service::split_markdown_by_pages(&full_content);
-    logger::debug("ocr", &format!("分割得到 {} 个页面", pages.len()));
+    // 将整本书的结果拆分到按页缓存（保留已有的页面缓存）
+    let written = crate::mineru_service::populate_page_cache(&mineru_output, pdf_name, output_dir, file_info.total_pages, false)?;
+    logger::debug("ocr", &format!("写入 {} 个页面缓存", written));
     
     // 获取指定页面
-    let page_content = if page_number as usize <= pages.len() {
-        pages[page_number as usize - 1].clone()
-    } else {
-        // 如果页码超出范围，返回完整内容（可能是单页或未正确分割）
-        if pages.len() == 1 {
-            pages[0].clone()
-        } else {
-            logger::error("ocr", &format!("页码 {} 超出范围，总共 {} 页", page_number, pages.len()));
-            return Err(anyhow!("页码 {} 超出范围", page_number));
-        }
-    };
-    
-    // 保存到缓存
-    fs::create_dir_all(output_dir)?;
     let md_filename = output_dir.join(format!("{:04}_page.md", page_number));
-    fs::write(&md_filename, &page_content)?;
+    if md_filename.exists() {
+        return fs::read_to_string(&md_filename).map_err(|e| anyhow!("读取缓存失败: {}", e));
+    }
+    
+    // 如果页码超出范围且只有一页，返回完整内容（可能是单页或未正确分割）
+    let first_page = output_dir.join(format!("{:04}_page.md", 1));
+    let page_count = fs::read_dir(output_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_name().to_string_lossy().ends_with("_page.md"))
+                .count()
+        })
+        .unwrap_or(0);
+    if page_count == 1 && first_page.exists() {
+        let page_content = fs::read_to_string(&first_page)?;
+        fs::write(&md_filename, &page_content)?;
+        return Ok(page_content);
+    }
     
-    Ok(page_content)
+    logger::error("ocr", &format!("页码 {} 超出范围，总共 {} 页", page_number, page_count));
+    Err(anyhow!("页码 {} 超出范围", page_number))
 }
 
 /// 使用 PaddleOCR-VL 转换 PDF 单页
@@ -571,7 +600,7 @@ async fn convert_pdf_with_paddle_ocr(
 }
 
 /// 规范化 LaTeX 代码
-fn normalize_latex(markdown: &str) -> String {
+pub fn normalize_latex(markdown: &str) -> String {
     use regex::Regex;
     
     let mut result = markdown.to_string();
@@ -782,7 +811,7 @@ pub struct OCRClient {
 impl OCRClient {
     pub fn new(api_url: &str) -> Self {
         Self {
-            client: Client::new(),
+            client: crate::http_client::client(),
             api_url: api_url.to_string(),
         }
     }
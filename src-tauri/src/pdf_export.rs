@@ -0,0 +1,229 @@
+// PDF 导出模块 - 将题目集或试卷排版为 Typst 文档，调用 typst 编译为 PDF
+// 可以只导出题目、题目附答案，或者单独导出答案（题号与题目文档一致），方便分发练习卷
+// 公式通过 Typst 的 mitex 包按 LaTeX 语法渲染（首次编译时 typst 会自动下载该包），插图随文档一起编译
+// 需要安装 typst 命令行工具，可在设置中指定 typst 可执行文件路径
+
+use crate::commands::Question;
+use crate::question_images::{image_files, strip_image_links};
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+const MITEX_IMPORT: &str = "#import \"@preview/mitex:0.2.4\": mi, mitex";
+
+// 行间公式 $$...$$ 和行内公式 $...$
+static MATH_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)\$\$(.+?)\$\$|\$([^$\n]+?)\$").unwrap());
+
+/// 要导出的文档
+pub struct PdfDocument<'a> {
+    pub title: String,
+    pub subtitle: String, // 标题下方的说明，例如试卷满分
+    pub sections: Vec<PdfSection<'a>>,
+    pub content: PdfContent,
+}
+
+/// 文档包含的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfContent {
+    Questions,            // 只有题目
+    QuestionsWithAnswers, // 题目，末尾附上答案和解析
+    AnswerKey,            // 只有答案和解析（与题目文档的题号一致）
+}
+
+/// 文档中的一个部分（试卷的大题；题目集只有一个部分，标题为空）
+pub struct PdfSection<'a> {
+    pub title: String,
+    pub questions: Vec<PdfQuestion<'a>>,
+}
+
+/// 文档中的一道题，base_dir 为题目插图路径的基准目录（文件存储目录）
+pub struct PdfQuestion<'a> {
+    pub number: u32,
+    pub score: Option<f64>,
+    pub question: &'a Question,
+    pub base_dir: PathBuf,
+}
+
+/// 生成 PDF，typst_path 为空时使用 PATH 中的 typst
+pub async fn write_pdf(typst_path: &str, dest_path: &Path, doc: &PdfDocument<'_>) -> Result<()> {
+    let temp_dir = std::env::temp_dir().join(format!("booq_pdf_{}", uuid::Uuid::new_v4().simple()));
+    fs::create_dir_all(&temp_dir)?;
+    let result = compile(typst_path, &temp_dir, dest_path, doc).await;
+    let _ = fs::remove_dir_all(&temp_dir);
+    result
+}
+
+async fn compile(typst_path: &str, temp_dir: &Path, dest_path: &Path, doc: &PdfDocument<'_>) -> Result<()> {
+    // typst 只能读取项目目录中的文件，插图先复制到临时目录
+    let mut image_count = 0;
+    let mut copy_image = |source: &Path| -> Option<String> {
+        let extension = source.extension()?.to_string_lossy().to_lowercase();
+        image_count += 1;
+        let name = format!("img_{}.{}", image_count, extension);
+        fs::copy(source, temp_dir.join(&name)).ok()?;
+        Some(name)
+    };
+    let source = to_typst(doc, &mut copy_image);
+    let main_file = temp_dir.join("main.typ");
+    fs::write(&main_file, source)?;
+
+    let program = if typst_path.trim().is_empty() { "typst" } else { typst_path.trim() };
+    let output = Command::new(program)
+        .arg("compile")
+        .arg(&main_file)
+        .arg(dest_path)
+        .output()
+        .await
+        .map_err(|e| anyhow!("无法运行 typst（{}），请安装 typst 或在设置中指定 typst 路径", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "typst 编译失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// 生成 Typst 源文件，copy_image 把插图复制到项目目录并返回文件名（失败时跳过该插图）
+pub fn to_typst(doc: &PdfDocument, copy_image: &mut dyn FnMut(&Path) -> Option<String>) -> String {
+    let mut typ = String::new();
+    typ.push_str(MITEX_IMPORT);
+    typ.push('\n');
+    typ.push_str("#set page(paper: \"a4\", margin: 2cm, numbering: \"1\")\n");
+    typ.push_str("#set text(font: (\"Noto Serif CJK SC\", \"Source Han Serif SC\", \"SimSun\", \"Songti SC\"), size: 11pt, lang: \"zh\")\n");
+    typ.push_str("#set par(justify: true, leading: 0.8em)\n\n");
+    let title = if doc.content == PdfContent::AnswerKey {
+        format!("{}（参考答案）", doc.title)
+    } else {
+        doc.title.clone()
+    };
+    typ.push_str(&format!(
+        "#align(center, text(size: 18pt, weight: \"bold\")[#{}])\n\n",
+        string_literal(&title)
+    ));
+    if !doc.subtitle.trim().is_empty() && doc.content != PdfContent::AnswerKey {
+        typ.push_str(&format!("#align(center)[#{}]\n\n", string_literal(&doc.subtitle)));
+    }
+
+    if doc.content != PdfContent::AnswerKey {
+        questions_typst(doc, copy_image, &mut typ);
+    }
+    if doc.content == PdfContent::QuestionsWithAnswers {
+        typ.push_str("#pagebreak()\n#heading(level: 2)[#\"参考答案\"]\n\n");
+    }
+    if doc.content != PdfContent::Questions {
+        answers_typst(doc, &mut typ);
+    }
+    typ
+}
+
+/// 题目部分
+fn questions_typst(doc: &PdfDocument, copy_image: &mut dyn FnMut(&Path) -> Option<String>, typ: &mut String) {
+    for section in &doc.sections {
+        if !section.title.trim().is_empty() {
+            typ.push_str(&format!("#heading(level: 2)[#{}]\n\n", string_literal(&section.title)));
+        }
+        for item in &section.questions {
+            let q = item.question;
+            let score = match item.score {
+                Some(score) => format!("#\"（{} 分）\"", crate::exam_paper::format_score(score)),
+                None => String::new(),
+            };
+            let text = strip_image_links(&q.question_text);
+            typ.push_str(&format!(
+                "#block(breakable: true)[#strong[#\"{}.\"] {}{}\n",
+                item.number,
+                score,
+                content(&text)
+            ));
+            for option in &q.options {
+                typ.push_str(&format!(
+                    "\n#pad(left: 2em)[#{}{}]\n",
+                    string_literal(&format!("{}. ", option.label)),
+                    content(&option.text)
+                ));
+            }
+            for source in image_files(q, &item.base_dir) {
+                if let Some(name) = copy_image(&source) {
+                    typ.push_str(&format!(
+                        "\n#align(center, image({}, width: 60%))\n",
+                        string_literal(&name)
+                    ));
+                }
+            }
+            typ.push_str("]\n\n");
+        }
+    }
+}
+
+/// 答案部分，试卷按大题分组
+fn answers_typst(doc: &PdfDocument, typ: &mut String) {
+    for section in &doc.sections {
+        if !section.title.trim().is_empty() {
+            typ.push_str(&format!("#heading(level: 3)[#{}]\n\n", string_literal(&section.title)));
+        }
+        for item in &section.questions {
+            let q = item.question;
+            let labels = q.correct_options.join("");
+            let mut answer = String::new();
+            if !labels.is_empty() && !q.answer.trim_start().starts_with(&labels) {
+                answer.push_str(&format!("#strong[#{}] ", string_literal(&labels)));
+            }
+            answer.push_str(&content(&q.answer));
+            typ.push_str(&format!("#strong[#\"{}.\"] {}\n\n", item.number, answer));
+            if !q.analysis.trim().is_empty() {
+                typ.push_str(&format!(
+                    "#pad(left: 2em)[#text(fill: rgb(\"#555555\"))[#\"解析：\"{}]]\n\n",
+                    content(&q.analysis)
+                ));
+            }
+        }
+    }
+}
+
+/// 题目文字转换为 Typst 内容：普通文字写成字符串（不受 Typst 标记语法影响），公式交给 mitex 渲染
+fn content(text: &str) -> String {
+    let text = text.trim();
+    let mut typ = String::new();
+    let mut last = 0;
+    for caps in MATH_RE.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        typ.push_str(&plain_text(&text[last..whole.start()]));
+        match (caps.get(1), caps.get(2)) {
+            (Some(display), _) => typ.push_str(&format!("#mitex({})", string_literal(display.as_str().trim()))),
+            (_, Some(inline)) => typ.push_str(&format!("#mi({})", string_literal(inline.as_str().trim()))),
+            _ => {}
+        }
+        last = whole.end();
+    }
+    typ.push_str(&plain_text(&text[last..]));
+    typ
+}
+
+/// 普通文字，换行保留为 Typst 的换行
+fn plain_text(text: &str) -> String {
+    text.split('\n')
+        .map(|line| if line.is_empty() { String::new() } else { format!("#{}", string_literal(line)) })
+        .collect::<Vec<_>>()
+        .join("#linebreak()")
+}
+
+/// Typst 字符串字面量，放在标记中时需要加上 # 前缀
+fn string_literal(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 3);
+    literal.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\r' => {}
+            '\t' => literal.push_str("\\t"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,216 @@
+// 练习会话模块 - 从题库中抽题练习，逐题提交答案，结束时生成成绩报告
+// 会话保存在文件目录（单个文件的练习）或应用数据目录（整个题库的练习）下的 practice 目录中
+
+use crate::commands::{Question, QuestionFilter};
+use crate::json_store;
+use anyhow::Result;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 时间格式，与其他记录一致
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 练习会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PracticeSession {
+    pub session_id: String,
+    #[serde(default)]
+    pub file_id: Option<String>, // 为空时从整个题库抽题
+    pub status: String,          // "active", "finished"
+    pub started_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    #[serde(default)]
+    pub filters: QuestionFilter,
+    pub questions: Vec<Question>, // 开始练习时的题目快照
+    #[serde(default)]
+    pub answers: Vec<SessionAnswer>,
+    #[serde(default)]
+    pub report: Option<SessionReport>,
+}
+
+/// 一道题的作答
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAnswer {
+    pub question_id: String,
+    pub user_answer: String,
+    pub correct: bool,
+    pub score: f64,      // 百分制
+    pub verdict: String, // "correct", "partially_correct", "incorrect"
+    #[serde(default)]
+    pub feedback: String,
+    pub answered_at: String,
+}
+
+/// 成绩报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionReport {
+    pub total: u32,
+    pub answered: u32,
+    pub correct: u32,
+    pub score: f64, // 全部题目的平均分（未作答按 0 分），百分制
+    pub duration_secs: i64,
+    pub by_type: Vec<ScoreGroup>,
+    pub by_knowledge_point: Vec<ScoreGroup>,
+    pub wrong_question_ids: Vec<String>, // 答错和未作答的题目
+}
+
+/// 按题型或知识点汇总的成绩
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreGroup {
+    pub name: String,
+    pub total: u32,
+    pub correct: u32,
+}
+
+/// 会话列表中的摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub file_id: Option<String>,
+    pub status: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub total: u32,
+    pub answered: u32,
+    pub score: Option<f64>,
+}
+
+impl PracticeSession {
+    pub fn new(file_id: Option<&str>, filters: QuestionFilter, questions: Vec<Question>) -> Self {
+        Self {
+            session_id: uuid::Uuid::new_v4().to_string(),
+            file_id: file_id.map(str::to_string),
+            status: "active".to_string(),
+            started_at: Local::now().format(TIME_FORMAT).to_string(),
+            finished_at: None,
+            filters,
+            questions,
+            answers: Vec::new(),
+            report: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.status == "finished"
+    }
+
+    /// 记录作答，同一道题再次提交时替换之前的答案
+    pub fn set_answer(&mut self, answer: SessionAnswer) {
+        self.answers.retain(|a| a.question_id != answer.question_id);
+        self.answers.push(answer);
+    }
+
+    /// 结束会话并生成成绩报告
+    pub fn finish(&mut self) -> SessionReport {
+        let now = Local::now();
+        let duration_secs = NaiveDateTime::parse_from_str(&self.started_at, TIME_FORMAT)
+            .map(|start| (now.naive_local() - start).num_seconds().max(0))
+            .unwrap_or(0);
+        let report = build_report(&self.questions, &self.answers, duration_secs);
+        self.status = "finished".to_string();
+        self.finished_at = Some(now.format(TIME_FORMAT).to_string());
+        self.report = Some(report.clone());
+        report
+    }
+
+    pub fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            session_id: self.session_id.clone(),
+            file_id: self.file_id.clone(),
+            status: self.status.clone(),
+            started_at: self.started_at.clone(),
+            finished_at: self.finished_at.clone(),
+            total: self.questions.len() as u32,
+            answered: self.answers.len() as u32,
+            score: self.report.as_ref().map(|r| r.score),
+        }
+    }
+}
+
+/// 汇总成绩：总分、按题型和知识点的正确数，以及需要复习的题目
+pub fn build_report(questions: &[Question], answers: &[SessionAnswer], duration_secs: i64) -> SessionReport {
+    let mut by_type: BTreeMap<String, ScoreGroup> = BTreeMap::new();
+    let mut by_knowledge_point: BTreeMap<String, ScoreGroup> = BTreeMap::new();
+    let mut report = SessionReport {
+        total: questions.len() as u32,
+        duration_secs,
+        ..Default::default()
+    };
+    let mut score_sum = 0.0;
+
+    for q in questions {
+        let answer = answers.iter().find(|a| a.question_id == q.id);
+        let correct = answer.is_some_and(|a| a.correct);
+        if let Some(answer) = answer {
+            report.answered += 1;
+            score_sum += answer.score;
+        }
+        if correct {
+            report.correct += 1;
+        } else {
+            report.wrong_question_ids.push(q.id.clone());
+        }
+
+        let add = |groups: &mut BTreeMap<String, ScoreGroup>, name: &str| {
+            let group = groups.entry(name.to_string()).or_insert_with(|| ScoreGroup {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            group.total += 1;
+            if correct {
+                group.correct += 1;
+            }
+        };
+        add(&mut by_type, &q.question_type);
+        for kp in &q.knowledge_points {
+            add(&mut by_knowledge_point, kp);
+        }
+    }
+
+    if report.total > 0 {
+        report.score = score_sum / report.total as f64;
+    }
+    report.by_type = by_type.into_values().collect();
+    report.by_knowledge_point = by_knowledge_point.into_values().collect();
+    report
+}
+
+/// 选择题按选项判分：选出的选项与正确选项完全一致才算正确
+pub fn judge_choice(question: &Question, user_answer: &str) -> Option<bool> {
+    if question.options.is_empty() || question.correct_options.is_empty() {
+        return None;
+    }
+    let mut chosen = crate::question_options::correct_labels(user_answer, &question.options);
+    let mut expected = question.correct_options.clone();
+    chosen.sort();
+    expected.sort();
+    Some(chosen == expected)
+}
+
+/// 打乱顺序（Fisher-Yates），随机数取自 UUID v4
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = (uuid::Uuid::new_v4().as_u128() % (i as u128 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// 读取会话
+pub fn load(dir: &Path, session_id: &str) -> Result<Option<PracticeSession>> {
+    Ok(json_store::load(&json_store::record_path(dir, session_id)?))
+}
+
+/// 保存会话
+pub fn save(dir: &Path, session: &PracticeSession) -> Result<()> {
+    json_store::save(dir, &session.session_id, session)
+}
+
+/// 列出目录中的会话，最近开始的排在前面
+pub fn list(dir: &Path) -> Vec<PracticeSession> {
+    let mut sessions: Vec<PracticeSession> = json_store::list(dir);
+    sessions.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    sessions
+}
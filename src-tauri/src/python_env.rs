@@ -0,0 +1,344 @@
+// Python 环境管理模块 - 为 MinerU 维护应用专用的虚拟环境
+// 将 magic-pdf 安装到独立的 venv 中，避免污染全局 Python 环境
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::Manager;
+
+/// 应用专用虚拟环境目录
+static VENV_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+/// 用户在设置中指定的可执行文件路径（优先于自动检测）
+static PATH_OVERRIDES: Lazy<RwLock<PathOverrides>> = Lazy::new(|| RwLock::new(PathOverrides::default()));
+
+/// 自定义可执行文件路径
+#[derive(Debug, Clone, Default)]
+pub struct PathOverrides {
+    pub python: Option<PathBuf>,
+    pub pip: Option<PathBuf>,
+    pub magic_pdf: Option<PathBuf>,
+}
+
+/// Python 环境信息
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PythonEnvInfo {
+    pub venv_dir: String,
+    pub venv_exists: bool,
+    pub python_path: Option<String>,
+    pub scripts_dir: Option<String>,
+}
+
+/// 初始化虚拟环境路径（在应用启动时调用）
+pub fn init_venv_dir(app_dir: &Path) {
+    let mut dir = VENV_DIR.write();
+    *dir = Some(app_dir.join("mineru_venv"));
+}
+
+/// 设置自定义可执行文件路径，空字符串表示使用自动检测
+pub fn set_path_overrides(python: &str, pip: &str, magic_pdf: &str) {
+    let to_path = |s: &str| {
+        let s = s.trim();
+        if s.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(s))
+        }
+    };
+    *PATH_OVERRIDES.write() = PathOverrides {
+        python: to_path(python),
+        pip: to_path(pip),
+        magic_pdf: to_path(magic_pdf),
+    };
+}
+
+/// 获取自定义的 magic-pdf（或 mineru）路径
+pub fn get_magic_pdf_override() -> Option<PathBuf> {
+    PATH_OVERRIDES.read().magic_pdf.clone()
+}
+
+/// 获取虚拟环境目录
+pub fn get_venv_dir() -> Option<PathBuf> {
+    VENV_DIR.read().clone()
+}
+
+/// 获取虚拟环境中的脚本目录（Windows 为 Scripts，其他平台为 bin）
+pub fn get_venv_scripts_dir() -> Option<PathBuf> {
+    let venv_dir = get_venv_dir()?;
+    let scripts_dir = if cfg!(target_os = "windows") {
+        venv_dir.join("Scripts")
+    } else {
+        venv_dir.join("bin")
+    };
+    if scripts_dir.exists() {
+        Some(scripts_dir)
+    } else {
+        None
+    }
+}
+
+/// 获取虚拟环境中的 Python 解释器路径（venv 不存在时返回 None）
+pub fn get_venv_python() -> Option<PathBuf> {
+    let scripts_dir = get_venv_scripts_dir()?;
+    let python = if cfg!(target_os = "windows") {
+        scripts_dir.join("python.exe")
+    } else {
+        scripts_dir.join("python")
+    };
+    if python.exists() {
+        Some(python)
+    } else {
+        None
+    }
+}
+
+/// 获取虚拟环境中某个脚本的路径（例如 magic-pdf）
+pub fn get_venv_script(name: &str) -> Option<PathBuf> {
+    let scripts_dir = get_venv_scripts_dir()?;
+    let script = if cfg!(target_os = "windows") {
+        scripts_dir.join(format!("{}.exe", name))
+    } else {
+        scripts_dir.join(name)
+    };
+    if script.exists() {
+        Some(script)
+    } else {
+        None
+    }
+}
+
+/// 检查虚拟环境是否可用
+pub fn venv_exists() -> bool {
+    get_venv_python().is_some()
+}
+
+/// 创建调用 Python 的命令
+/// 优先使用设置中指定的解释器，其次是虚拟环境中的解释器
+pub fn python_command() -> Command {
+    if let Some(python) = PATH_OVERRIDES.read().python.clone() {
+        return Command::new(python);
+    }
+    if let Some(python) = get_venv_python() {
+        return Command::new(python);
+    }
+    Command::new("python")
+}
+
+/// 创建调用基础 Python 的命令（用于创建虚拟环境，不使用 venv 中的解释器）
+fn base_python_command() -> Command {
+    match PATH_OVERRIDES.read().python.clone() {
+        Some(python) => Command::new(python),
+        None => Command::new("python"),
+    }
+}
+
+/// 创建调用 pip 的命令
+/// 优先使用设置中指定的 pip，其次是指定解释器或虚拟环境中的 python -m pip
+pub fn pip_command() -> Command {
+    let overrides = PATH_OVERRIDES.read().clone();
+    if let Some(pip) = overrides.pip {
+        return Command::new(pip);
+    }
+    if let Some(python) = overrides.python.or_else(get_venv_python) {
+        let mut cmd = Command::new(python);
+        cmd.args(["-m", "pip"]);
+        return cmd;
+    }
+    if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "pip"]);
+        cmd
+    } else {
+        Command::new("pip")
+    }
+}
+
+/// 列出本机的 conda 环境目录
+/// 优先使用 conda env list，conda 不在 PATH 中时扫描常见的安装目录
+pub fn list_conda_envs() -> Vec<PathBuf> {
+    let mut command = match std::env::var("CONDA_EXE") {
+        Ok(conda) if !conda.is_empty() => Command::new(conda),
+        _ if cfg!(target_os = "windows") => {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", "conda"]);
+            cmd
+        }
+        _ => Command::new("conda"),
+    };
+
+    if let Ok(output) = command.args(["env", "list", "--json"]).output() {
+        if output.status.success() {
+            let envs = serde_json::from_slice::<serde_json::Value>(&output.stdout)
+                .ok()
+                .and_then(|v| v.get("envs").and_then(|e| e.as_array()).cloned())
+                .unwrap_or_default();
+            let envs: Vec<PathBuf> = envs
+                .iter()
+                .filter_map(|e| e.as_str())
+                .map(PathBuf::from)
+                .filter(|p| p.exists())
+                .collect();
+            if !envs.is_empty() {
+                return envs;
+            }
+        }
+    }
+
+    // 回退：扫描常见的 conda 安装目录
+    let home = if cfg!(target_os = "windows") {
+        std::env::var("USERPROFILE").ok()
+    } else {
+        std::env::var("HOME").ok()
+    };
+    let home = match home {
+        Some(home) => PathBuf::from(home),
+        None => return Vec::new(),
+    };
+
+    let mut envs = Vec::new();
+    for base in ["miniconda3", "anaconda3", "miniforge3", "mambaforge"] {
+        let root = home.join(base);
+        if !root.exists() {
+            continue;
+        }
+        if let Ok(entries) = fs::read_dir(root.join("envs")) {
+            envs.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+        envs.push(root);
+    }
+    envs
+}
+
+/// 获取 conda 环境中某个脚本的路径
+pub fn get_conda_env_script(env_dir: &Path, name: &str) -> Option<PathBuf> {
+    let script = if cfg!(target_os = "windows") {
+        env_dir.join("Scripts").join(format!("{}.exe", name))
+    } else {
+        env_dir.join("bin").join(name)
+    };
+    if script.exists() {
+        Some(script)
+    } else {
+        None
+    }
+}
+
+/// 获取 Python 环境信息
+pub fn get_env_info() -> PythonEnvInfo {
+    PythonEnvInfo {
+        venv_dir: get_venv_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        venv_exists: venv_exists(),
+        python_path: get_venv_python().map(|p| p.to_string_lossy().to_string()),
+        scripts_dir: get_venv_scripts_dir().map(|p| p.to_string_lossy().to_string()),
+    }
+}
+
+/// 运行命令并把输出通过 mineru-install-output 事件发送到前端
+fn run_with_events(app_handle: &tauri::AppHandle, mut command: Command) -> Result<bool> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let app_handle_stdout = app_handle.clone();
+    let stdout_thread = std::thread::spawn(move || {
+        if let Some(stdout) = stdout {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines().map_while(Result::ok) {
+                let _ = app_handle_stdout.emit_all("mineru-install-output",
+                    serde_json::json!({"type": "info", "message": format!("{}\n", line)}));
+            }
+        }
+    });
+
+    let app_handle_stderr = app_handle.clone();
+    let stderr_thread = std::thread::spawn(move || {
+        if let Some(stderr) = stderr {
+            let reader = BufReader::new(stderr);
+            for line in reader.lines().map_while(Result::ok) {
+                let msg_type = if line.to_lowercase().contains("error") {
+                    "error"
+                } else {
+                    "info"
+                };
+                let _ = app_handle_stderr.emit_all("mineru-install-output",
+                    serde_json::json!({"type": msg_type, "message": format!("{}\n", line)}));
+            }
+        }
+    });
+
+    let _ = stdout_thread.join();
+    let _ = stderr_thread.join();
+
+    Ok(child.wait()?.success())
+}
+
+/// 创建虚拟环境并安装 magic-pdf 和 modelscope
+pub fn create_venv_with_events(app_handle: &tauri::AppHandle) -> Result<String> {
+    use crate::logger;
+
+    let venv_dir = get_venv_dir().ok_or_else(|| anyhow!("虚拟环境目录未初始化"))?;
+
+    if !venv_exists() {
+        logger::info("python", &format!("创建虚拟环境: {}", venv_dir.display()));
+        let _ = app_handle.emit_all("mineru-install-output",
+            serde_json::json!({"type": "cmd", "message": format!("> python -m venv {}\n", venv_dir.display())}));
+
+        let mut cmd = base_python_command();
+        cmd.args(["-m", "venv"]).arg(&venv_dir);
+        if !run_with_events(app_handle, cmd)? || !venv_exists() {
+            logger::error("python", "虚拟环境创建失败");
+            let _ = app_handle.emit_all("mineru-install-output",
+                serde_json::json!({"type": "error", "message": "\n✗ 虚拟环境创建失败，请确认已安装 Python 3.10+\n"}));
+            return Err(anyhow!("虚拟环境创建失败"));
+        }
+    }
+
+    let python = get_venv_python().ok_or_else(|| anyhow!("未找到虚拟环境中的 Python"))?;
+
+    // 升级 pip，然后安装依赖
+    let steps: [&[&str]; 2] = [
+        &["-m", "pip", "install", "-U", "pip"],
+        &["-m", "pip", "install", "-U", "magic-pdf[full]", "modelscope"],
+    ];
+    for args in steps {
+        let _ = app_handle.emit_all("mineru-install-output",
+            serde_json::json!({"type": "cmd", "message": format!("> python {}\n", args.join(" "))}));
+        let mut cmd = Command::new(&python);
+        cmd.args(args);
+        if !run_with_events(app_handle, cmd)? {
+            logger::error("python", &format!("命令执行失败: python {}", args.join(" ")));
+            let _ = app_handle.emit_all("mineru-install-output",
+                serde_json::json!({"type": "error", "message": "\n✗ 依赖安装失败，请检查错误信息\n"}));
+            return Err(anyhow!("依赖安装失败"));
+        }
+    }
+
+    logger::info("python", "虚拟环境创建完成");
+    let _ = app_handle.emit_all("mineru-install-output",
+        serde_json::json!({"type": "success", "message": "\n✓ MinerU 虚拟环境创建成功！\n"}));
+    Ok("MinerU 虚拟环境创建成功".to_string())
+}
+
+/// 删除虚拟环境
+pub fn remove_venv() -> Result<()> {
+    use crate::logger;
+
+    let venv_dir = get_venv_dir().ok_or_else(|| anyhow!("虚拟环境目录未初始化"))?;
+    if venv_dir.exists() {
+        fs::remove_dir_all(&venv_dir)?;
+        logger::info("python", &format!("已删除虚拟环境: {}", venv_dir.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+// QTI 导出模块 - 将题目打包为 IMS QTI 2.1 内容包（zip），可导入 Canvas、Blackboard 等平台
+// 包内包含 imsmanifest.xml、一份引用全部题目的测验 assessment.xml、
+// 每道题一个 items/*.xml 以及题目插图 media/*
+// 题型判断与 Moodle 导出一致：选择题、简答题（文本填空）和问答题
+
+use crate::anki_export::markdown_to_html;
+use crate::commands::Question;
+use crate::lms_export::{escape_xml, question_kind, question_name, ExportQuestion, QuestionKind};
+use anyhow::Result;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const QTI_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imsqti_v2p1";
+const QTI_SCHEMA_LOCATION: &str =
+    "http://www.imsglobal.org/xsd/imsqti_v2p1 http://www.imsglobal.org/xsd/qti/qtiv2p1/imsqti_v2p1.xsd";
+
+/// 包内的一道题
+struct PackageItem {
+    identifier: String,
+    href: String,
+    xml: String,
+    media: Vec<(String, PathBuf)>, // 包内路径、源文件
+}
+
+/// 生成 QTI 2.1 内容包，返回导出的题目数
+pub fn write_package(dest_path: &Path, title: &str, questions: &[ExportQuestion]) -> Result<usize> {
+    let items: Vec<PackageItem> = questions
+        .iter()
+        .enumerate()
+        .map(|(index, item)| build_item(index, item))
+        .collect();
+
+    let file = fs::File::create(dest_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("imsmanifest.xml", options)?;
+    zip.write_all(manifest_xml(&items).as_bytes())?;
+    zip.start_file("assessment.xml", options)?;
+    zip.write_all(assessment_test_xml(title, &items).as_bytes())?;
+    for item in &items {
+        zip.start_file(item.href.as_str(), options)?;
+        zip.write_all(item.xml.as_bytes())?;
+        for (href, source) in &item.media {
+            zip.start_file(href.as_str(), options)?;
+            zip.write_all(&fs::read(source)?)?;
+        }
+    }
+    zip.finish()?;
+    Ok(items.len())
+}
+
+fn build_item(index: usize, item: &ExportQuestion) -> PackageItem {
+    let q = item.question;
+    let identifier = format!("item_{}", index + 1);
+    let href = format!("items/{}.xml", identifier);
+
+    // 插图文件名加上题目序号，避免不同题目的同名图片冲突
+    let mut media = Vec::new();
+    let mut images_html = String::new();
+    for source in crate::question_images::image_files(q, &item.base_dir) {
+        let name = source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.png", media.len()));
+        let media_href = format!("media/{}_{}", index + 1, name);
+        images_html.push_str(&format!(
+            "<p><img src=\"../{}\" alt=\"{}\"/></p>",
+            escape_xml(&media_href),
+            escape_xml(&name)
+        ));
+        media.push((media_href, source));
+    }
+
+    let kind = question_kind(q);
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<assessmentItem xmlns=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{}\" identifier=\"{}\" label=\"{}\" title=\"{}\" adaptive=\"false\" timeDependent=\"false\">\n",
+        QTI_NAMESPACE,
+        QTI_SCHEMA_LOCATION,
+        identifier,
+        escape_xml(&q.id),
+        escape_xml(&question_name(q))
+    );
+    xml.push_str(&response_declaration(q, kind));
+    xml.push_str("  <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\">\n    <defaultValue><value>0</value></defaultValue>\n  </outcomeDeclaration>\n");
+    xml.push_str("  <outcomeDeclaration identifier=\"FEEDBACK\" cardinality=\"single\" baseType=\"identifier\"/>\n");
+
+    xml.push_str("  <itemBody>\n");
+    xml.push_str(&format!("    <div>{}{}</div>\n", xhtml(&q.question_text), images_html));
+    match kind {
+        QuestionKind::Choice => {
+            let max_choices = if q.correct_options.len() == 1 { 1 } else { 0 };
+            xml.push_str(&format!(
+                "    <choiceInteraction responseIdentifier=\"RESPONSE\" shuffle=\"false\" maxChoices=\"{}\">\n",
+                max_choices
+            ));
+            for option in &q.options {
+                xml.push_str(&format!(
+                    "      <simpleChoice identifier=\"{}\">{}</simpleChoice>\n",
+                    choice_identifier(&option.label),
+                    xhtml(&option.text)
+                ));
+            }
+            xml.push_str("    </choiceInteraction>\n");
+        }
+        QuestionKind::ShortAnswer => {
+            xml.push_str("    <p><textEntryInteraction responseIdentifier=\"RESPONSE\" expectedLength=\"20\"/></p>\n");
+        }
+        QuestionKind::Essay => {
+            xml.push_str("    <extendedTextInteraction responseIdentifier=\"RESPONSE\" expectedLines=\"8\"/>\n");
+        }
+    }
+    xml.push_str("  </itemBody>\n");
+
+    // 选择题和简答题与正确答案一致得 1 分；作答后都显示答案和解析
+    xml.push_str("  <responseProcessing>\n");
+    if kind != QuestionKind::Essay {
+        xml.push_str(concat!(
+            "    <responseCondition>\n",
+            "      <responseIf>\n",
+            "        <match><variable identifier=\"RESPONSE\"/><correct identifier=\"RESPONSE\"/></match>\n",
+            "        <setOutcomeValue identifier=\"SCORE\"><baseValue baseType=\"float\">1</baseValue></setOutcomeValue>\n",
+            "      </responseIf>\n",
+            "    </responseCondition>\n",
+        ));
+    }
+    xml.push_str("    <setOutcomeValue identifier=\"FEEDBACK\"><baseValue baseType=\"identifier\">SOLUTION</baseValue></setOutcomeValue>\n");
+    xml.push_str("  </responseProcessing>\n");
+    xml.push_str(&format!(
+        "  <modalFeedback outcomeIdentifier=\"FEEDBACK\" identifier=\"SOLUTION\" showHide=\"show\">{}</modalFeedback>\n",
+        solution_html(q)
+    ));
+    xml.push_str("</assessmentItem>\n");
+
+    PackageItem {
+        identifier,
+        href,
+        xml,
+        media,
+    }
+}
+
+/// 作答变量声明（含正确答案），问答题没有正确答案
+fn response_declaration(q: &Question, kind: QuestionKind) -> String {
+    match kind {
+        QuestionKind::Choice => {
+            let cardinality = if q.correct_options.len() == 1 { "single" } else { "multiple" };
+            let values: String = q
+                .correct_options
+                .iter()
+                .map(|label| format!("<value>{}</value>", choice_identifier(label)))
+                .collect();
+            format!(
+                "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"{}\" baseType=\"identifier\">\n    <correctResponse>{}</correctResponse>\n  </responseDeclaration>\n",
+                cardinality, values
+            )
+        }
+        QuestionKind::ShortAnswer => format!(
+            "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"single\" baseType=\"string\">\n    <correctResponse><value>{}</value></correctResponse>\n  </responseDeclaration>\n",
+            escape_xml(q.answer.trim())
+        ),
+        QuestionKind::Essay => {
+            "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"single\" baseType=\"string\"/>\n".to_string()
+        }
+    }
+}
+
+/// 答案和解析
+fn solution_html(q: &Question) -> String {
+    let mut html = format!("<div><p><strong>答案：</strong></p><div>{}</div>", xhtml(&q.answer));
+    if !q.analysis.trim().is_empty() {
+        html.push_str(&format!("<p><strong>解析：</strong></p><div>{}</div>", xhtml(&q.analysis)));
+    }
+    html.push_str("</div>");
+    html
+}
+
+/// 选项标签转换为 QTI 标识符（只能包含字母、数字、下划线等）
+fn choice_identifier(label: &str) -> String {
+    let label: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("choice_{}", label)
+}
+
+/// QTI 的题目内容必须是 XHTML，换行写成自闭合标签
+fn xhtml(text: &str) -> String {
+    markdown_to_html(text).replace("<br>", "<br/>")
+}
+
+/// 引用全部题目的测验
+fn assessment_test_xml(title: &str, items: &[PackageItem]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<assessmentTest xmlns=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{}\" identifier=\"test\" title=\"{}\">\n",
+        QTI_NAMESPACE,
+        QTI_SCHEMA_LOCATION,
+        escape_xml(title)
+    );
+    xml.push_str("  <testPart identifier=\"part_1\" navigationMode=\"nonlinear\" submissionMode=\"simultaneous\">\n");
+    xml.push_str("    <assessmentSection identifier=\"section_1\" title=\"Section 1\" visible=\"true\">\n");
+    for item in items {
+        xml.push_str(&format!(
+            "      <assessmentItemRef identifier=\"{}\" href=\"{}\"/>\n",
+            item.identifier,
+            escape_xml(&item.href)
+        ));
+    }
+    xml.push_str("    </assessmentSection>\n  </testPart>\n</assessmentTest>\n");
+    xml
+}
+
+/// 内容包清单：测验和每道题各是一个资源，题目资源列出其插图文件
+fn manifest_xml(items: &[PackageItem]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest xmlns=\"http://www.imsglobal.org/xsd/imscp_v1p1\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"http://www.imsglobal.org/xsd/imscp_v1p1 http://www.imsglobal.org/xsd/qti/qtiv2p1/qtiv2p1_imscpv1p2_v1p0.xsd\" identifier=\"MANIFEST-{}\">\n",
+        uuid::Uuid::new_v4().simple()
+    );
+    xml.push_str("  <metadata>\n    <schema>QTIv2.1 Package</schema>\n    <schemaversion>1.0.0</schemaversion>\n  </metadata>\n");
+    xml.push_str("  <organizations/>\n  <resources>\n");
+    xml.push_str("    <resource identifier=\"test\" type=\"imsqti_test_xmlv2p1\" href=\"assessment.xml\">\n");
+    xml.push_str("      <file href=\"assessment.xml\"/>\n");
+    for item in items {
+        xml.push_str(&format!("      <dependency identifierref=\"{}\"/>\n", item.identifier));
+    }
+    xml.push_str("    </resource>\n");
+    for item in items {
+        xml.push_str(&format!(
+            "    <resource identifier=\"{}\" type=\"imsqti_item_xmlv2p1\" href=\"{}\">\n",
+            item.identifier,
+            escape_xml(&item.href)
+        ));
+        xml.push_str(&format!("      <file href=\"{}\"/>\n", escape_xml(&item.href)));
+        for (href, _) in &item.media {
+            xml.push_str(&format!("      <file href=\"{}\"/>\n", escape_xml(href)));
+        }
+        xml.push_str("    </resource>\n");
+    }
+    xml.push_str("  </resources>\n</manifest>\n");
+    xml
+}
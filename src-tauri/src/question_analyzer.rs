@@ -1,24 +1,113 @@
 // 题目分析模块 - 核心业务逻辑
 
-use crate::{ai_service, config, ocr_service, rag_service};
-use crate::commands::{AnalysisProgress, Question};
+use crate::{
+    ai_service, anki_export, bank_exchange, chapter_structure, config, database, exam_paper, json_repair,
+    knowledge_graph, knowledge_points, latex_check, lms_export, markdown_export, mineru_layout, mineru_service,
+    ocr_service, pdf_export, practice_session, qti_export, question_banks, question_dedup, question_history,
+    question_images, question_index, question_options, question_translation, rag_service, spreadsheet_export,
+    study_records,
+};
+use crate::commands::{AnalysisCheckpoint, AnalysisProgress, AnalysisRun, FailedPage, QuestionFilter, QuestionPage, BatchJob, DocumentAnswer, GradeResult, Question, RubricItem};
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 use once_cell::sync::Lazy;
 
 // 分析状态存储
 static ANALYSIS_STATE: Lazy<Arc<Mutex<HashMap<String, AnalysisState>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
-#[derive(Debug, Clone)]
+// 题目编辑时串行读写题目文件，避免同时修改时丢失改动
+static QUESTION_STORE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 练习会话串行读写，避免同时提交答案时丢失作答
+static PRACTICE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 检索题目时每页最多返回的数量
+const MAX_QUESTION_PAGE_SIZE: usize = 200;
+
+// 计算知识点合并建议时参与向量比较的最大知识点数
+const MAX_EMBEDDED_KNOWLEDGE_POINTS: usize = 2000;
+
+// 建立章节结构时读取的前几页（目录通常在这里）
+const STRUCTURE_PREPASS_PAGES: u32 = 12;
+
+// 保留的题目版本快照数，超出时删除最早的快照（分析记录保留）
+const MAX_QUESTION_SNAPSHOTS: usize = 20;
+
+// RAG 参考上下文的最大 token 数
+const MAX_RAG_CONTEXT_TOKENS: usize = 4000;
+
+// 解答习题时参考上下文中各类文档的最大数量，保证有例题时至少带上一道
+const EXERCISE_CONTEXT_QUOTAS: &[(&str, usize)] = &[("example", 2), ("knowledge", 6)];
+
+// 每页最多发送的图片数
+const MAX_IMAGES_PER_PAGE: usize = 4;
+
+// 文档问答的参考上下文最大 token 数
+const MAX_ASK_CONTEXT_TOKENS: usize = 6000;
+
+// 批量分析任务状态的查询间隔
+const BATCH_POLL_INTERVAL_SECS: u64 = 60;
+
+// 批量分析任务连续查询失败的最大次数
+const MAX_BATCH_POLL_ERRORS: u32 = 10;
+
+// 正在后台查询状态的批量任务（按文件 ID）
+static BATCH_POLLERS: Lazy<Mutex<std::collections::HashSet<String>>> =
+    Lazy::new(|| Mutex::new(std::collections::HashSet::new()));
+
+#[derive(Clone)]
 struct AnalysisState {
     progress: AnalysisProgress,
     should_stop: bool,
+    cancel_token: ai_service::CancelToken, // 停止时中止正在进行的 AI 请求
+    app_handle: Option<AppHandle>,         // 用于推送进度事件
+}
+
+impl AnalysisState {
+    /// 向前端推送当前进度（analysis-progress 事件）
+    fn emit_progress(&self) {
+        if let Some(app_handle) = &self.app_handle {
+            let _ = app_handle.emit_all("analysis-progress", &self.progress);
+        }
+    }
+}
+
+// 待分析的页面
+struct PageJob {
+    page: u32,
+    markdown_content: String,
+    analysis_content: String,
+    sections: Vec<PageSection>, // 按模型上下文长度拆分的内容，页面不超长时只有一段
+    images: Vec<PathBuf>,
+    has_examples: bool,  // 阅读模型判断页面中有例题（未配置阅读模型时为 true）
+    has_exercises: bool, // 阅读模型判断页面中有习题
+}
+
+/// 页面内容的一段，例题和习题按段分别识别
+struct PageSection {
+    content: String,
+    context_budget: usize, // 习题参考上下文可用的 token 数
+}
+
+/// 阅读模型对页面的概括和分类
+#[derive(Debug, Clone, serde::Serialize, Deserialize)]
+struct PageReading {
+    #[serde(default)]
+    summary: String,
+    #[serde(default = "default_true")]
+    has_examples: bool,
+    #[serde(default = "default_true")]
+    has_exercises: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 /// 获取文件存储路径
@@ -36,8 +125,49 @@ fn get_file_storage_path(app_handle: &AppHandle, file_id: &str) -> PathBuf {
     base_path.join(file_id)
 }
 
-/// 开始分析
+/// 开始分析（清除之前未完成的分析进度）
 pub async fn start_analysis(app_handle: &AppHandle, file_id: &str) -> Result<()> {
+    run_analysis(app_handle, file_id, false, None, Vec::new()).await
+}
+
+/// 只分析指定页码范围（含两端），结果与该文件已有的题目合并，范围内原有的题目会被替换
+pub async fn start_analysis_range(app_handle: &AppHandle, file_id: &str, from: u32, to: u32) -> Result<()> {
+    if from == 0 || to < from {
+        return Err(anyhow!("页码范围无效：{} - {}", from, to));
+    }
+    run_analysis(app_handle, file_id, false, Some((from, to)), Vec::new()).await
+}
+
+/// 从上次中断的位置继续分析，跳过已完成的页面
+pub async fn resume_analysis(app_handle: &AppHandle, file_id: &str) -> Result<()> {
+    if get_analysis_checkpoint(app_handle, file_id)?.is_none() {
+        return Err(anyhow!("没有可恢复的分析进度"));
+    }
+    run_analysis(app_handle, file_id, true, None, Vec::new()).await
+}
+
+/// 只重新分析上次失败的页面
+pub async fn retry_failed_pages(app_handle: &AppHandle, file_id: &str) -> Result<()> {
+    let mut pages: Vec<u32> = get_failed_pages(app_handle, file_id)?.into_iter().map(|f| f.page).collect();
+    pages.sort_unstable();
+    pages.dedup();
+    if pages.is_empty() {
+        return Err(anyhow!("没有失败的页面"));
+    }
+    let range = (pages[0], pages[pages.len() - 1]);
+    run_analysis(app_handle, file_id, false, Some(range), pages).await
+}
+
+/// 分析页面并提取题目，range 为空时分析全部页面，pages 不为空时只分析范围内的这些页面
+/// 每组页面完成后保存该组各页的题目和检查点，中途退出后可以用 resume 继续（沿用原来的页码范围）
+/// 同一文件同时只能有一个分析任务；分析出错退出时状态设为 error，不会一直停留在分析中
+async fn run_analysis(
+    app_handle: &AppHandle,
+    file_id: &str,
+    resume: bool,
+    range: Option<(u32, u32)>,
+    pages: Vec<u32>,
+) -> Result<()> {
     let file_path = get_file_storage_path(app_handle, file_id);
     
     // 检查文件是否存在
@@ -50,62 +180,273 @@ pub async fn start_analysis(app_handle: &AppHandle, file_id: &str) -> Result<()>
     let meta_content = fs::read_to_string(&meta_path)?;
     let file_info: crate::commands::FileInfo = serde_json::from_str(&meta_content)?;
     
-    // 初始化分析状态
-    let initial_progress = AnalysisProgress {
-        file_id: file_id.to_string(),
-        status: "analyzing".to_string(),
-        current_page: 0,
-        total_pages: file_info.total_pages,
-        current_step: "初始化".to_string(),
-        questions_found: 0,
-        message: "正在准备分析...".to_string(),
-    };
-    
-    {
+    let active = ActiveAnalysis::claim(file_id, file_info.total_pages, Some(app_handle.clone()))?;
+    let result = analyze_pages(app_handle, file_id, &file_info, &active.cancel_token, resume, range, pages).await;
+    active.finish(&result);
+    result
+}
+
+/// 正在进行的分析：创建时登记分析状态，结束时（包括出错提前返回、任务被中止）检查状态不再停留在分析中
+struct ActiveAnalysis {
+    file_id: String,
+    cancel_token: ai_service::CancelToken,
+}
+
+impl ActiveAnalysis {
+    /// 登记分析状态，文件正在分析或正在停止时返回错误（检查和登记在同一次加锁中完成）
+    fn claim(file_id: &str, total_pages: u32, app_handle: Option<AppHandle>) -> Result<Self> {
         let mut states = ANALYSIS_STATE.lock().unwrap();
-        states.insert(
-            file_id.to_string(),
-            AnalysisState {
-                progress: initial_progress,
-                should_stop: false,
+        if states.get(file_id).is_some_and(|s| is_active_status(&s.progress.status)) {
+            return Err(anyhow!("该文件正在分析中"));
+        }
+        let cancel_token = ai_service::CancelToken::new();
+        let state = AnalysisState {
+            progress: AnalysisProgress {
+                file_id: file_id.to_string(),
+                status: "analyzing".to_string(),
+                current_page: 0,
+                total_pages,
+                current_step: "初始化".to_string(),
+                questions_found: 0,
+                message: "正在准备分析...".to_string(),
+                parse_failures: 0,
+                pages_completed: 0,
             },
-        );
+            should_stop: false,
+            cancel_token: cancel_token.clone(),
+            app_handle,
+        };
+        state.emit_progress();
+        states.insert(file_id.to_string(), state);
+        Ok(Self {
+            file_id: file_id.to_string(),
+            cancel_token,
+        })
+    }
+
+    /// 分析结束，出错时把错误信息记入状态
+    fn finish(self, result: &Result<()>) {
+        if let Err(e) = result {
+            fail_analysis(&self.file_id, &e.to_string());
+        }
+    }
+}
+
+impl Drop for ActiveAnalysis {
+    fn drop(&mut self) {
+        fail_analysis(&self.file_id, "分析意外中止");
+    }
+}
+
+/// 仍在分析中的文件设为 error 状态（已经结束的分析不受影响）
+fn fail_analysis(file_id: &str, message: &str) {
+    use crate::logger;
+
+    let mut states = ANALYSIS_STATE.lock().unwrap();
+    if let Some(state) = states.get_mut(file_id) {
+        if !is_active_status(&state.progress.status) {
+            return;
+        }
+        logger::error("ai", &format!("文件 {} 的分析失败: {}", file_id, message));
+        state.progress.status = "error".to_string();
+        state.progress.message = message.to_string();
+        state.emit_progress();
+        if let Err(e) = database::save_analysis_state(&state.progress) {
+            logger::warn("system", &format!("保存分析状态失败: {}", e));
+        }
     }
+}
+
+fn is_active_status(status: &str) -> bool {
+    status == "analyzing" || status == "stopping"
+}
+
+/// 分析页面（run_analysis 登记分析状态后调用）
+async fn analyze_pages(
+    app_handle: &AppHandle,
+    file_id: &str,
+    file_info: &crate::commands::FileInfo,
+    cancel_token: &ai_service::CancelToken,
+    resume: bool,
+    range: Option<(u32, u32)>,
+    pages: Vec<u32>,
+) -> Result<()> {
+    use crate::logger;
+    
+    let file_path = get_file_storage_path(app_handle, file_id);
     
     // 获取配置
     let app_config = config::get_config_sync(app_handle);
     
-    // 创建 RAG 存储
-    let rag_path = file_path.join("rag_index.json");
-    let mut rag_store = rag_service::RAGStore::new(rag_path);
-    
     // 创建问题存储目录
     let questions_dir = file_path.join("questions");
     fs::create_dir_all(&questions_dir)?;
     
+    // 继续分析时读取检查点和已完成页面的题目，重新开始时清除之前的进度
+    let checkpoint_path = questions_dir.join("checkpoint.json");
+    let pages_dir = questions_dir.join("pages");
+    let mut checkpoint = if resume {
+        get_analysis_checkpoint(app_handle, file_id)?.unwrap_or_default()
+    } else {
+        clear_checkpoint(&questions_dir);
+        AnalysisCheckpoint::default()
+    };
+    let mut all_questions: Vec<Question> = Vec::new();
+    for page in &checkpoint.completed_pages {
+        all_questions.extend(load_page_questions(&pages_dir, *page));
+    }
+    if resume {
+        logger::info(
+            "ai",
+            &format!(
+                "继续分析文件 {}：已完成 {} 页，{} 道题目",
+                file_id,
+                checkpoint.completed_pages.len(),
+                all_questions.len()
+            ),
+        );
+    }
+    
+    // 本次分析的批次 ID，用于统计 AI 用量；继续分析时沿用原来的批次
+    if checkpoint.run_id.is_empty() {
+        checkpoint.run_id = uuid::Uuid::new_v4().to_string();
+        checkpoint.total_pages = file_info.total_pages;
+        if let Some((from, to)) = range {
+            checkpoint.page_from = from;
+            checkpoint.page_to = to;
+        }
+        checkpoint.pages = pages;
+    }
+    let run_id = checkpoint.run_id.clone();
+    checkpoint.stopped_at_page = 0;
+    let started_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    
+    // 本次分析的页码范围
+    let (first_page, last_page) = if checkpoint.page_from > 0 {
+        (checkpoint.page_from, checkpoint.page_to.min(file_info.total_pages))
+    } else {
+        (1, file_info.total_pages)
+    };
+    if first_page > last_page {
+        update_progress(file_id, "error", 0, file_info.total_pages, "页码范围超出文件页数", 0);
+        return Err(anyhow!("页码范围超出文件页数（共 {} 页）", file_info.total_pages));
+    }
+    
+    // 创建 RAG 存储
+    let rag_path = file_path.join("rag_index.db");
+    let reranker = get_reranker(&app_config)?;
+    let query_expander = get_query_expander(&app_config)?;
+    let mut rag_store = rag_service::RAGStore::new(rag_path)
+        .with_embedder(get_embedder(&app_config))
+        .with_reranker(reranker.clone())
+        .with_query_expander(query_expander.clone());
+    
+    // 全局知识库（启用时）
+    let mut global_store = rag_service::open_global_store().map(|store| {
+        store
+            .with_embedder(get_embedder(&app_config))
+            .with_reranker(reranker.clone())
+            .with_query_expander(query_expander.clone())
+    });
+    
     // 文本分块器
     let chunker = rag_service::TextChunker::new(1000, 100);
     
+    // 如果已有 MinerU 整本转换结果，读取版面结构，按块切分题目
+    let pdf_name = Path::new(&file_info.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let layout_pages: HashMap<u32, mineru_layout::LayoutPage> = mineru_layout::load_layout(
+        &mineru_service::get_mineru_output_dir(app_handle, file_id),
+        pdf_name,
+    )
+    .map(|pages| pages.into_iter().map(|p| (p.page_number, p)).collect())
+    .unwrap_or_default();
+    
+    // 页面 Markdown 中图片链接的查找目录，用于复制题目插图
+    let image_dirs = image_search_dirs(app_handle, file_id, &file_path, pdf_name);
+    
+    // 页面中的插图，开启后随文本一起发送给支持图片输入的模型
+    let page_images: HashMap<u32, Vec<PathBuf>> = if app_config.ai_send_page_images {
+        mineru_layout::load_page_images(
+            &mineru_service::get_mineru_output_dir(app_handle, file_id),
+            pdf_name,
+        )
+    } else {
+        HashMap::new()
+    };
+    
     let total_pages = file_info.total_pages;
-    let batch_size = if total_pages > 400 { 20 } else { total_pages };
+    // 本次要分析的页面（重试失败页面时只包含失败的页面）
+    let selected_pages: Vec<u32> = (first_page..=last_page)
+        .filter(|page| checkpoint.pages.is_empty() || checkpoint.pages.contains(page))
+        .collect();
+    let page_count = selected_pages.len() as u32;
+    set_pages_completed(
+        file_id,
+        checkpoint
+            .completed_pages
+            .iter()
+            .filter(|page| selected_pages.contains(*page))
+            .count() as u32,
+    );
+    let batch_size = if page_count > 400 { 20 } else { page_count.max(1) };
     
-    let mut all_questions: Vec<Question> = Vec::new();
-    let mut current_batch_start = 1u32;
+    // 即将重新分析的页面从失败记录中移除，本次仍然失败时重新记录
+    remove_failed_pages(
+        &questions_dir,
+        &selected_pages
+            .iter()
+            .copied()
+            .filter(|page| !checkpoint.completed_pages.contains(page))
+            .collect::<Vec<u32>>(),
+    );
+    
+    // 分析模型（含备用模型）和并发请求执行器
+    let chain = ai_service::FallbackChain::new(
+        get_analysis_models(&app_config)
+            .into_iter()
+            .map(|model| analysis_service(model, &app_config, file_id, &run_id, cancel_token))
+            .collect::<Result<Vec<_>>>()?,
+    );
+    let pool = ai_service::AIRequestPool::new(app_config.ai_max_parallel as usize);
+    
+    // 阅读模型（配置后先概括页面并判断是否有题目，没有题目的页面不再调用分析模型）
+    let reader = match get_reading_model(&app_config) {
+        Some(model) => Some(analysis_service(model, &app_config, file_id, &run_id, cancel_token)?),
+        None => None,
+    };
+    let readings_path = questions_dir.join("readings.json");
+    let mut readings: HashMap<u32, PageReading> = fs::read_to_string(&readings_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    
+    // 章节结构：首次分析时读取目录建立章节树，之后重复使用
+    let structure_path = questions_dir.join("structure.json");
+    let mut structure = match chapter_structure::load(&structure_path) {
+        Some(tree) => tree,
+        None if !chain.is_empty() => {
+            update_step(file_id, "识别章节结构", all_questions.len() as u32);
+            build_chapter_structure(app_handle, file_id, &chain, total_pages, &structure_path).await
+        }
+        None => chapter_structure::ChapterTree::default(),
+    };
+    
+    // 请求停止时正在处理的页码
+    let mut stopped_at: Option<u32> = None;
     
     // 分批处理页面
-    while current_batch_start <= total_pages {
+    'batches: for batch in selected_pages.chunks(batch_size as usize) {
+        let (current_batch_start, batch_end) = (batch[0], batch[batch.len() - 1]);
+        
         // 检查是否需要停止
-        {
-            let states = ANALYSIS_STATE.lock().unwrap();
-            if let Some(state) = states.get(file_id) {
-                if state.should_stop {
-                    return Ok(());
-                }
-            }
+        if should_stop(file_id) {
+            stopped_at = Some(current_batch_start);
+            break 'batches;
         }
         
-        let batch_end = (current_batch_start + batch_size - 1).min(total_pages);
-        
         // 更新进度
         update_progress(
             file_id,
@@ -116,110 +457,418 @@ pub async fn start_analysis(app_handle: &AppHandle, file_id: &str) -> Result<()>
             all_questions.len() as u32,
         );
         
-        // 处理当前批次的页面
-        for page in current_batch_start..=batch_end {
-            // 检查是否需要停止
-            {
-                let states = ANALYSIS_STATE.lock().unwrap();
-                if let Some(state) = states.get(file_id) {
-                    if state.should_stop {
-                        return Ok(());
-                    }
-                }
-            }
-            
-            // 获取页面的 Markdown 内容
-            let markdown_content = ocr_service::convert_page_to_markdown(
-                app_handle,
-                file_id,
-                page,
-            )
-            .await
-            .unwrap_or_default();
+        // 按并发数分组处理当前批次的页面
+        for group in batch.chunks(pool.max_parallel()) {
+            let (group_start, group_end) = (group[0], group[group.len() - 1]);
             
-            if markdown_content.trim().is_empty() {
-                continue;
+            // 检查是否需要停止
+            if should_stop(file_id) {
+                stopped_at = Some(group_start);
+                break 'batches;
             }
             
-            // 将内容添加到 RAG
-            let chunks = chunker.chunk_by_paragraph(&markdown_content);
-            for (i, chunk) in chunks.iter().enumerate() {
-                let doc = rag_service::Document {
-                    id: format!("{}_{}_{}", file_id, page, i),
-                    content: chunk.clone(),
-                    metadata: rag_service::DocumentMetadata {
-                        file_id: file_id.to_string(),
-                        page_number: page,
-                        chunk_index: i as u32,
-                        doc_type: "knowledge".to_string(),
-                        chapter: String::new(),
-                        section: String::new(),
-                    },
-                    embedding: None,
+            // 依次读取页面内容并加入 RAG（已完成的页面在上次分析时已加入）
+            let mut jobs: Vec<PageJob> = Vec::new();
+            let mut failed_pages: Vec<u32> = Vec::new();
+            for &page in group {
+                if checkpoint.completed_pages.contains(&page) {
+                    continue;
+                }
+                
+                // 获取页面的 Markdown 内容，转换失败的页面记录下来留到重试
+                let markdown_content = match ocr_service::convert_page_to_markdown(app_handle, file_id, page).await {
+                    Ok(content) => content,
+                    Err(e) => {
+                        logger::warn("ocr", &format!("第 {} 页转换失败: {}", page, e));
+                        add_failed_page(&questions_dir, page, "ocr", "ocr_error", &e.to_string());
+                        failed_pages.push(page);
+                        mark_page_completed(file_id, page, page_count);
+                        continue;
+                    }
                 };
-                rag_store.add_document(doc);
+                
+                if markdown_content.trim().is_empty() {
+                    mark_page_completed(file_id, page, page_count);
+                    continue;
+                }
+                
+                // 将内容添加到 RAG，一页的内容在一个事务中写入
+                update_step(file_id, &format!("读取第 {} 页", page), all_questions.len() as u32);
+                structure.locate_in_page(page, &markdown_content);
+                begin_store_batch(&mut rag_store, global_store.as_mut());
+                for doc in knowledge_documents(&chunker, file_id, page, &markdown_content, &structure) {
+                    add_to_stores(&mut rag_store, global_store.as_mut(), doc);
+                }
+                commit_store_batch(&mut rag_store, global_store.as_mut());
+                
+                // 有版面结构时使用带分段标记的内容，帮助 AI 识别题目边界
+                let analysis_content = layout_pages
+                    .get(&page)
+                    .filter(|p| !p.blocks.is_empty())
+                    .map(|p| p.to_segmented_markdown())
+                    .unwrap_or_else(|| markdown_content.clone());
+                
+                jobs.push(PageJob {
+                    page,
+                    markdown_content,
+                    analysis_content,
+                    sections: Vec::new(),
+                    images: page_images
+                        .get(&page)
+                        .map(|images| images.iter().take(MAX_IMAGES_PER_PAGE).cloned().collect())
+                        .unwrap_or_default(),
+                    has_examples: true,
+                    has_exercises: true,
+                });
             }
             
-            // 更新进度
-            update_progress(
-                file_id,
-                "analyzing",
-                page,
-                total_pages,
-                &format!("正在识别第 {} 页的题目", page),
-                all_questions.len() as u32,
-            );
+            // 阅读页面，跳过没有例题或习题的部分（之前读过的页面沿用结果）
+            if let Some(reader) = &reader {
+                update_step(file_id, "阅读页面", all_questions.len() as u32);
+                let unread: Vec<(u32, String)> = jobs
+                    .iter()
+                    .filter(|job| !readings.contains_key(&job.page))
+                    .map(|job| (job.page, job.markdown_content.clone()))
+                    .collect();
+                if !unread.is_empty() {
+                    let results = pool
+                        .run_all(unread, |(page, content)| {
+                            let reader = reader.clone();
+                            async move { (page, reader.read_page(&content).await) }
+                        })
+                        .await;
+                    for (page, result) in results {
+                        // 阅读失败时按有题目处理，不影响后续分析
+                        match result.and_then(|text| parse_json_response::<PageReading>(&text)) {
+                            Ok(reading) => {
+                                readings.insert(page, reading);
+                            }
+                            Err(e) => logger::warn("ai", &format!("第 {} 页阅读失败: {}", page, e)),
+                        }
+                    }
+                    if let Err(e) = save_json(&readings_path, &readings) {
+                        logger::warn("ai", &format!("保存页面阅读结果失败: {}", e));
+                    }
+                }
+                for job in jobs.iter_mut() {
+                    if let Some(reading) = readings.get(&job.page) {
+                        job.has_examples = reading.has_examples;
+                        job.has_exercises = reading.has_exercises;
+                    }
+                }
+                let skipped = jobs.iter().filter(|job| !job.has_examples && !job.has_exercises).count();
+                if skipped > 0 {
+                    logger::info("ai", &format!("第 {} - {} 页中有 {} 页没有题目，已跳过", group_start, group_end, skipped));
+                }
+            }
             
             // 使用 AI 分析页面内容，提取题目
-            if let Some(model) = get_analysis_model(&app_config) {
-                let ai_service = ai_service::create_ai_service(
-                    &model.api_url,
-                    &model.api_key,
-                    &model.model_name,
+            if !chain.is_empty() {
+                // 更新进度
+                update_progress(
+                    file_id,
+                    "analyzing",
+                    group_start,
+                    total_pages,
+                    &if group_start == group_end {
+                        format!("正在识别第 {} 页的题目", group_start)
+                    } else {
+                        format!("正在识别第 {} - {} 页的题目", group_start, group_end)
+                    },
+                    all_questions.len() as u32,
                 );
                 
-                // 分析例题
-                if let Ok(examples_json) = ai_service.analyze_examples(&markdown_content).await {
-                    if let Ok(questions) = parse_examples_response(&examples_json, file_id, page) {
-                        for q in questions {
-                            // 添加例题到 RAG
-                            let doc = rag_service::Document {
-                                id: q.id.clone(),
-                                content: format!("题目：{}\n答案：{}", q.question_text, q.answer),
-                                metadata: rag_service::DocumentMetadata {
-                                    file_id: file_id.to_string(),
-                                    page_number: page,
-                                    chunk_index: 0,
-                                    doc_type: "example".to_string(),
-                                    chapter: q.chapter.clone(),
-                                    section: q.section.clone(),
-                                },
-                                embedding: None,
-                            };
-                            rag_store.add_document(doc);
-                            all_questions.push(q);
+                // 超出模型上下文长度的页面拆分为多段分别识别，剩余部分留给 RAG 上下文
+                let budget = chain.input_budget();
+                let language = ai_service::PromptLanguage::from_config(&app_config.prompt_language);
+                for job in jobs.iter_mut() {
+                    job.sections = page_sections(job.page, &job.analysis_content, &job.images, budget, language, |tokens| {
+                        chain.context_budget(tokens)
+                    });
+                }
+                
+                // 并发分析例题
+                update_step(file_id, "识别例题", all_questions.len() as u32);
+                let examples_results = pool
+                    .run_all(
+                        jobs.iter()
+                            .filter(|job| job.has_examples)
+                            .flat_map(|job| {
+                                job.sections
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(index, section)| (job.page, index, section.content.clone(), job.images.clone()))
+                            })
+                            .collect(),
+                        |(page, index, content, images)| {
+                            let chain = chain.clone();
+                            async move {
+                                let (result, model) = chain
+                                    .run(|service| {
+                                        let content = content.clone();
+                                        let images = images.clone();
+                                        async move { service.analyze_examples_with_images(&content, &images).await }
+                                    })
+                                    .await;
+                                (page, index, result, model)
+                            }
+                        },
+                    )
+                    .await;
+                
+                begin_store_batch(&mut rag_store, global_store.as_mut());
+                for (page, index, examples_result, model) in examples_results {
+                    if let Err(e) = &examples_result {
+                        failed_pages.push(page);
+                        record_request_failure(&questions_dir, page, "examples", e);
+                        handle_ai_error(file_id, page, total_pages, all_questions.len() as u32, e)?;
+                    }
+                    if let Ok(reply) = examples_result {
+                        let parsed = parse_examples_response(&reply.content, file_id, page);
+                        if let Err(e) = &parsed {
+                            failed_pages.push(page);
+                            record_parse_failure(&questions_dir, file_id, page, "examples", &reply.content, e);
+                        }
+                        if let Ok(questions) = parsed {
+                            for mut q in questions {
+                                q.model = model.clone();
+                                set_section_id(&mut q, index);
+                                apply_structure(&mut q, &structure);
+                                apply_reasoning(&mut q, &reply, app_config.store_reasoning_as_analysis);
+                                // 添加例题到 RAG
+                                add_to_stores(&mut rag_store, global_store.as_mut(), example_document(&q));
+                                all_questions.push(q);
+                            }
+                        }
+                    }
+                }
+                commit_store_batch(&mut rag_store, global_store.as_mut());
+                
+                if should_stop(file_id) {
+                    stopped_at = Some(group_start);
+                    break 'batches;
+                }
+                
+                // 为本组新加入的内容和例题计算向量
+                update_step(file_id, "计算向量", all_questions.len() as u32);
+                embed_pending(&mut rag_store).await;
+                if let Some(global) = global_store.as_mut() {
+                    embed_pending(global).await;
+                }
+                
+                // 并发分析课后习题（使用 RAG 上下文，包含本组页面的例题）
+                update_step(file_id, "识别习题", all_questions.len() as u32);
+                let mut exercise_inputs: Vec<(u32, usize, String, String, Vec<PathBuf>)> = Vec::new();
+                let mut page_references: HashMap<u32, Vec<rag_service::Citation>> = HashMap::new();
+                let mut pending_sections: HashMap<u32, usize> = HashMap::new();
+                for job in jobs.iter().filter(|job| !job.has_exercises) {
+                    mark_page_completed(file_id, job.page, page_count);
+                }
+                for job in jobs.iter().filter(|job| job.has_exercises) {
+                    pending_sections.insert(job.page, job.sections.len());
+                    for (index, section) in job.sections.iter().enumerate() {
+                        // 拆分的页面按各段内容检索参考上下文
+                        let query = if job.sections.len() > 1 { &section.content } else { &job.markdown_content };
+                        let (context, citations) = rag_service::build_context_with_global(
+                            &rag_store,
+                            global_store.as_ref(),
+                            file_id,
+                            query,
+                            section.context_budget,
+                            EXERCISE_CONTEXT_QUOTAS,
+                        )
+                        .await;
+                        let references = page_references.entry(job.page).or_default();
+                        for citation in citations {
+                            if !references.iter().any(|c| c.marker == citation.marker) {
+                                references.push(citation);
+                            }
                         }
+                        exercise_inputs.push((job.page, index, section.content.clone(), context, job.images.clone()));
                     }
                 }
+                // 习题是每页的最后一步，一页的各段都完成后立即更新进度
+                let exercises_results = pool
+                    .run_all_with_progress(
+                        exercise_inputs,
+                        |(page, index, content, context, images)| {
+                            let chain = chain.clone();
+                            async move {
+                                let (result, model) = chain
+                                    .run(|service| {
+                                        let content = content.clone();
+                                        let context = context.clone();
+                                        let images = images.clone();
+                                        async move {
+                                            service.analyze_exercises_with_images(&content, &context, &images).await
+                                        }
+                                    })
+                                    .await;
+                                (page, index, result, model)
+                            }
+                        },
+                        |(page, _, _, _)| {
+                            if let Some(pending) = pending_sections.get_mut(page) {
+                                *pending = pending.saturating_sub(1);
+                                if *pending == 0 {
+                                    mark_page_completed(file_id, *page, page_count);
+                                }
+                            }
+                        },
+                    )
+                    .await;
                 
-                // 分析课后习题（使用 RAG 上下文）
-                let context = rag_store.build_context(&markdown_content, 4000);
-                if let Ok(exercises_json) = ai_service.analyze_exercises(&markdown_content, &context).await {
-                    if let Ok(questions) = parse_exercises_response(&exercises_json, file_id, page) {
-                        for q in questions {
-                            all_questions.push(q);
+                for (page, index, exercises_result, model) in exercises_results {
+                    if let Err(e) = &exercises_result {
+                        failed_pages.push(page);
+                        record_request_failure(&questions_dir, page, "exercises", e);
+                        handle_ai_error(file_id, page, total_pages, all_questions.len() as u32, e)?;
+                    }
+                    if let Ok(reply) = exercises_result {
+                        let parsed = parse_exercises_response(&reply.content, file_id, page);
+                        if let Err(e) = &parsed {
+                            failed_pages.push(page);
+                            record_parse_failure(&questions_dir, file_id, page, "exercises", &reply.content, e);
+                        }
+                        if let Ok(questions) = parsed {
+                            for mut q in questions {
+                                q.model = model.clone();
+                                set_section_id(&mut q, index);
+                                q.references = page_references.get(&page).cloned().unwrap_or_default();
+                                apply_structure(&mut q, &structure);
+                                apply_reasoning(&mut q, &reply, app_config.store_reasoning_as_analysis);
+                                all_questions.push(q);
+                            }
                         }
                     }
                 }
+            } else {
+                for job in &jobs {
+                    mark_page_completed(file_id, job.page, page_count);
+                }
+            }
+            
+            update_step(file_id, "保存结果", all_questions.len() as u32);
+            
+            // 将题目附近的插图复制到题库
+            for job in &jobs {
+                let mut page_questions: Vec<&mut Question> =
+                    all_questions.iter_mut().filter(|q| q.page_number == job.page).collect();
+                question_images::attach_page_images(
+                    &mut page_questions,
+                    &job.markdown_content,
+                    &image_dirs,
+                    &questions_dir.join("images"),
+                    &file_path,
+                );
+            }
+            
+            // 保存本组各页的题目和检查点，请求失败的页面留到继续分析时重试
+            let finished: Vec<u32> = group
+                .iter()
+                .copied()
+                .filter(|page| !checkpoint.completed_pages.contains(page) && !failed_pages.contains(page))
+                .collect();
+            for page in &finished {
+                let page_questions: Vec<&Question> = all_questions.iter().filter(|q| q.page_number == *page).collect();
+                save_json(&pages_dir.join(format!("{}.json", page)), &page_questions)?;
             }
+            checkpoint.completed_pages.extend(finished);
+            checkpoint.updated_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            save_json(&checkpoint_path, &checkpoint)?;
         }
-        
-        current_batch_start = batch_end + 1;
     }
     
-    // 保存所有问题
-    let questions_json = serde_json::to_string_pretty(&all_questions)?;
-    fs::write(questions_dir.join("all_questions.json"), questions_json)?;
+    // 与已有的题目合并后保存，按页码排序（继续分析时重试的页面排在后面）
+    // 只替换本次处理完成或识别出题目的页面，失败、未选中和停止时尚未处理的页面保留原有题目；
+    // 失败页面中部分成功的结果不保存，重试时整页重新分析
+    let run_failed_pages: std::collections::HashSet<u32> = load_failed_pages(&questions_dir)
+        .iter()
+        .map(|f| f.page)
+        .filter(|page| selected_pages.contains(page) && !checkpoint.completed_pages.contains(page))
+        .collect();
+    all_questions.retain(|q| !run_failed_pages.contains(&q.page_number));
+    let processed_pages: std::collections::HashSet<u32> = checkpoint
+        .completed_pages
+        .iter()
+        .copied()
+        .chain(all_questions.iter().map(|q| q.page_number))
+        .collect();
+    knowledge_points::normalize_questions(&mut all_questions);
+    let run_question_count = all_questions.len() as u32;
+    let mut run_models: Vec<String> = Vec::new();
+    for q in &all_questions {
+        if !q.model.is_empty() && !run_models.contains(&q.model) {
+            run_models.push(q.model.clone());
+        }
+    }
+    // 在题目存储锁内读取已有题目并合并写入，分析期间用户的修改不会被覆盖
+    let editor = format!("ai:{}", run_models.join(","));
+    let all_questions = modify_questions_with(app_handle, file_id, &editor, "重新分析", false, |questions| {
+        all_questions.extend(questions.iter().filter(|q| !processed_pages.contains(&q.page_number)).cloned());
+        all_questions.sort_by_key(|q| q.page_number);
+        *questions = all_questions.clone();
+        Ok(all_questions)
+    })?;
+    
+    // 保存定位到的章节起始页
+    if !structure.is_empty() {
+        if let Err(e) = chapter_structure::save(&structure_path, &structure) {
+            logger::warn("ai", &format!("保存章节结构失败: {}", e));
+        }
+    }
+    
+    // 记录本次分析并保存题目快照
+    let usage = crate::ai_usage::summarize(Some(file_id), &crate::ai_usage::model_prices(&app_config.models))
+        .by_run
+        .into_iter()
+        .find(|r| r.run_id == run_id)
+        .map(|r| r.totals)
+        .unwrap_or_default();
+    let run = AnalysisRun {
+        run_id: run_id.clone(),
+        started_at,
+        finished_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        status: if stopped_at.is_some() { "stopped" } else { "completed" }.to_string(),
+        models: run_models,
+        page_from: first_page,
+        page_to: last_page,
+        pages_completed: checkpoint
+            .completed_pages
+            .iter()
+            .filter(|page| (first_page..=last_page).contains(*page))
+            .count() as u32,
+        question_count: run_question_count,
+        total_questions: all_questions.len() as u32,
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        cost: usage.cost,
+        has_snapshot: true,
+    };
+    if let Err(e) = record_analysis_run(&questions_dir, run, &all_questions) {
+        logger::warn("ai", &format!("保存分析记录失败: {}", e));
+    }
+    
+    // 中途停止：保留检查点以便继续分析，并记录停止的位置
+    if let Some(page) = stopped_at {
+        checkpoint.stopped_at_page = page;
+        checkpoint.updated_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        save_json(&checkpoint_path, &checkpoint)?;
+        logger::info(
+            "ai",
+            &format!("文件 {} 的分析已停止于第 {} 页，已保存 {} 道题目", file_id, page, all_questions.len()),
+        );
+        update_progress(
+            file_id,
+            "stopped",
+            page,
+            total_pages,
+            &format!("分析已停止于第 {} 页", page),
+            all_questions.len() as u32,
+        );
+        return Ok(());
+    }
+    clear_checkpoint(&questions_dir);
     
     // 更新最终进度
     update_progress(
@@ -234,13 +883,65 @@ pub async fn start_analysis(app_handle: &AppHandle, file_id: &str) -> Result<()>
     Ok(())
 }
 
+/// 获取文件未完成的分析进度，没有时返回 None
+pub fn get_analysis_checkpoint(app_handle: &AppHandle, file_id: &str) -> Result<Option<AnalysisCheckpoint>> {
+    let path = get_file_storage_path(app_handle, file_id)
+        .join("questions")
+        .join("checkpoint.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&fs::read_to_string(&path)?)?))
+}
+
+/// 删除检查点和各页的中间结果（含页面阅读结果）
+fn clear_checkpoint(questions_dir: &Path) {
+    let _ = fs::remove_file(questions_dir.join("checkpoint.json"));
+    let _ = fs::remove_file(questions_dir.join("readings.json"));
+    let _ = fs::remove_dir_all(questions_dir.join("pages"));
+}
+
+/// 读取检查点中保存的一页题目
+fn load_page_questions(pages_dir: &Path, page: u32) -> Vec<Question> {
+    fs::read_to_string(pages_dir.join(format!("{}.json", page)))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 先写入临时文件再替换，避免写到一半时退出留下损坏的文件
+fn save_json<T: serde::Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// 题目写入 all_questions.json 后同步到数据库，失败时只记录日志（下次访问时从 JSON 文件重新导入）
+fn sync_questions_to_database(app_handle: &AppHandle, file_id: &str, questions_file: &Path, questions: &[Question]) {
+    use crate::logger;
+
+    let storage_root = crate::file_manager::get_storage_root(app_handle);
+    if let Err(e) = database::replace_questions(&storage_root, file_id, questions, questions_file) {
+        logger::warn("system", &format!("题目写入数据库失败: {}", e));
+    }
+}
+
 /// 停止分析
 pub async fn stop_analysis(_app_handle: &AppHandle, file_id: &str) -> Result<()> {
     let mut states = ANALYSIS_STATE.lock().unwrap();
     if let Some(state) = states.get_mut(file_id) {
         state.should_stop = true;
-        state.progress.status = "stopped".to_string();
-        state.progress.message = "分析已停止".to_string();
+        state.cancel_token.cancel();
+        // 分析任务保存已识别的题目后再将状态设为 stopped
+        if state.progress.status == "analyzing" {
+            state.progress.status = "stopping".to_string();
+            state.progress.message = "正在停止，保存已识别的题目...".to_string();
+            state.emit_progress();
+        }
     }
     Ok(())
 }
@@ -250,6 +951,9 @@ pub async fn get_analysis_progress(_app_handle: &AppHandle, file_id: &str) -> Re
     let states = ANALYSIS_STATE.lock().unwrap();
     if let Some(state) = states.get(file_id) {
         Ok(state.progress.clone())
+    } else if let Some(progress) = database::load_analysis_state(file_id).ok().flatten() {
+        // 本次启动后没有分析过，返回上次分析结束时的进度
+        Ok(progress)
     } else {
         Ok(AnalysisProgress {
             file_id: file_id.to_string(),
@@ -259,12 +963,22 @@ pub async fn get_analysis_progress(_app_handle: &AppHandle, file_id: &str) -> Re
             current_step: "".to_string(),
             questions_found: 0,
             message: "未开始分析".to_string(),
+            parse_failures: 0,
+            pages_completed: 0,
         })
     }
 }
 
 /// 获取题目列表
+/// 优先从数据库读取，数据库不可用或没有该文件时读取 all_questions.json
 pub async fn get_questions(app_handle: &AppHandle, file_id: &str) -> Result<Vec<Question>> {
+    use crate::logger;
+
+    match database::file_questions(&crate::file_manager::get_storage_root(app_handle), file_id) {
+        Ok(Some(questions)) => return Ok(questions),
+        Ok(None) => {}
+        Err(e) => logger::warn("system", &format!("从数据库读取题目失败: {}", e)),
+    }
     let file_path = get_file_storage_path(app_handle, file_id);
     let questions_file = file_path.join("questions").join("all_questions.json");
     
@@ -277,6 +991,48 @@ pub async fn get_questions(app_handle: &AppHandle, file_id: &str) -> Result<Vec<
     }
 }
 
+/// 获取某一页的题目（阅读器中标出当前页的题目），使用页码索引，不必每次读取全部题目
+pub fn get_questions_by_page(app_handle: &AppHandle, file_id: &str, page: u32) -> Result<Vec<Question>> {
+    let questions_file = get_file_storage_path(app_handle, file_id)
+        .join("questions")
+        .join("all_questions.json");
+    question_index::questions_on_page(&questions_file, page)
+}
+
+/// 获取文件的知识图谱
+/// 保存的图谱比题目或 RAG 索引旧时重新构建
+pub async fn get_knowledge_graph(app_handle: &AppHandle, file_id: &str) -> Result<knowledge_graph::KnowledgeGraph> {
+    let file_path = get_file_storage_path(app_handle, file_id);
+    if !file_path.join("meta.json").exists() {
+        return Err(anyhow!("文件不存在"));
+    }
+    let graph_path = file_path.join("knowledge_graph.json");
+    let questions_path = file_path.join("questions").join("all_questions.json");
+    let rag_path = file_path.join("rag_index.db");
+
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let up_to_date = modified(&graph_path).is_some_and(|graph_time| {
+        [&questions_path, &rag_path]
+            .iter()
+            .all(|path| modified(path).map_or(true, |time| time <= graph_time))
+    });
+    if up_to_date {
+        if let Some(graph) = knowledge_graph::load(&graph_path) {
+            return Ok(graph);
+        }
+    }
+
+    let questions = get_questions(app_handle, file_id).await?;
+    let documents = if rag_path.exists() {
+        rag_service::RAGStore::new(rag_path).get_knowledge()
+    } else {
+        Vec::new()
+    };
+    let graph = knowledge_graph::build(file_id, &questions, &documents);
+    knowledge_graph::save(&graph_path, &graph)?;
+    Ok(graph)
+}
+
 /// 获取题目详情
 pub async fn get_question_detail(
     app_handle: &AppHandle,
@@ -290,36 +1046,2658 @@ pub async fn get_question_detail(
         .ok_or_else(|| anyhow!("题目不存在"))
 }
 
-/// 更新进度
-fn update_progress(
-    file_id: &str,
-    status: &str,
-    current_page: u32,
-    total_pages: u32,
-    message: &str,
-    questions_found: u32,
-) {
-    let mut states = ANALYSIS_STATE.lock().unwrap();
-    if let Some(state) = states.get_mut(file_id) {
-        state.progress.status = status.to_string();
-        state.progress.current_page = current_page;
-        state.progress.total_pages = total_pages;
-        state.progress.message = message.to_string();
-        state.progress.questions_found = questions_found;
-    }
+/// 获取文件的分析记录，最新的在前
+pub fn get_analysis_runs(app_handle: &AppHandle, file_id: &str) -> Result<Vec<AnalysisRun>> {
+    let questions_dir = get_file_storage_path(app_handle, file_id).join("questions");
+    let mut runs = load_analysis_runs(&questions_dir);
+    runs.reverse();
+    Ok(runs)
 }
 
-/// 获取分析模型配置
-fn get_analysis_model(config: &crate::commands::AppConfig) -> Option<&crate::commands::ModelConfig> {
-    config
-        .models
-        .iter()
-        .find(|m| m.id == config.analysis_model)
-        .or_else(|| config.models.first())
-}
+/// 将题目恢复为某次分析结束时的快照
+pub fn rollback_questions(app_handle: &AppHandle, file_id: &str, run_id: &str) -> Result<Vec<Question>> {
+    use crate::logger;
 
-/// 解析例题响应
-fn parse_examples_response(json_str: &str, file_id: &str, page: u32) -> Result<Vec<Question>> {
+    if is_analyzing(file_id) {
+        return Err(anyhow!("正在分析，无法回滚"));
+    }
+    let questions_dir = get_file_storage_path(app_handle, file_id).join("questions");
+    let snapshot_path = crate::json_store::record_path(&questions_dir.join("versions"), run_id)?;
+    if !snapshot_path.exists() {
+        return Err(anyhow!("该版本的题目快照不存在"));
+    }
+    let questions: Vec<Question> = serde_json::from_str(&fs::read_to_string(&snapshot_path)?)?;
+
+    let _guard = QUESTION_STORE_LOCK.lock().unwrap();
+    let existing: Vec<Question> = fs::read_to_string(questions_dir.join("all_questions.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    save_json(&questions_dir.join("all_questions.json"), &questions)?;
+    sync_questions_to_database(app_handle, file_id, &questions_dir.join("all_questions.json"), &questions);
+    record_history(
+        &questions_dir,
+        file_id,
+        &existing,
+        &questions,
+        "user",
+        &format!("回滚到分析 {}", run_id),
+        true,
+    );
+    logger::info("ai", &format!("文件 {} 的题目已回滚到分析 {}（{} 道题目）", file_id, run_id, questions.len()));
+    Ok(questions)
+}
+
+/// 读取分析记录（按时间顺序）
+fn load_analysis_runs(questions_dir: &Path) -> Vec<AnalysisRun> {
+    fs::read_to_string(questions_dir.join("runs.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存分析记录和题目快照；继续分析时沿用原来的批次 ID，会更新同一条记录
+fn record_analysis_run(questions_dir: &Path, run: AnalysisRun, questions: &[Question]) -> Result<()> {
+    let versions_dir = questions_dir.join("versions");
+    save_json(&versions_dir.join(format!("{}.json", run.run_id)), questions)?;
+
+    let mut runs = load_analysis_runs(questions_dir);
+    match runs.iter_mut().find(|r| r.run_id == run.run_id) {
+        Some(existing) => {
+            let started_at = std::mem::take(&mut existing.started_at);
+            *existing = AnalysisRun { started_at, ..run };
+        }
+        None => runs.push(run),
+    }
+
+    // 只保留最近的快照
+    let with_snapshot: Vec<usize> = (0..runs.len()).filter(|&i| runs[i].has_snapshot).collect();
+    if with_snapshot.len() > MAX_QUESTION_SNAPSHOTS {
+        for &i in &with_snapshot[..with_snapshot.len() - MAX_QUESTION_SNAPSHOTS] {
+            let _ = fs::remove_file(versions_dir.join(format!("{}.json", runs[i].run_id)));
+            runs[i].has_snapshot = false;
+        }
+    }
+    save_json(&questions_dir.join("runs.json"), &runs)
+}
+
+/// 检索题目：关键词按空白拆分，每个词都要出现在题干、答案或解析中（不区分大小写）
+/// 章节、知识点条件为包含匹配；file_id 为空时检索全部文件
+pub async fn search_questions(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    query: &str,
+    filter: &QuestionFilter,
+    page: usize,
+    page_size: usize,
+) -> Result<QuestionPage> {
+    use crate::logger;
+
+    let page = page.max(1);
+    let page_size = page_size.clamp(1, MAX_QUESTION_PAGE_SIZE);
+    let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+
+    // 在数据库中筛选和分页，数据库不可用时读取全部题目后筛选
+    let storage_root = crate::file_manager::get_storage_root(app_handle);
+    match database::query_questions(&storage_root, file_id, &terms, filter, page, page_size) {
+        Ok((items, total)) => {
+            return Ok(QuestionPage {
+                items,
+                total,
+                page,
+                page_size,
+            })
+        }
+        Err(e) => logger::warn("system", &format!("从数据库检索题目失败: {}", e)),
+    }
+
+    let questions = load_bank_questions(app_handle, file_id).await?;
+    let mut matched: Vec<Question> = questions
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .filter(|q| {
+            if terms.is_empty() {
+                return true;
+            }
+            let text = format!("{}\n{}\n{}", q.question_text, q.answer, q.analysis).to_lowercase();
+            terms.iter().all(|term| text.contains(term))
+        })
+        .collect();
+    if filter.max_confidence.is_some() {
+        // 置信度最低的排在前面，便于优先人工复核
+        matched.sort_by(|a, b| {
+            a.confidence
+                .unwrap_or(1.0)
+                .partial_cmp(&b.confidence.unwrap_or(1.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    let total = matched.len();
+    let items = matched.into_iter().skip((page - 1) * page_size).take(page_size).collect();
+    Ok(QuestionPage {
+        items,
+        total,
+        page,
+        page_size,
+    })
+}
+
+/// 题目是否符合筛选条件（章节、知识点按包含匹配，不区分大小写）
+fn matches_filter(q: &Question, filter: &QuestionFilter) -> bool {
+    let contains = |value: &str, pattern: &Option<String>| match pattern.as_deref().map(str::trim) {
+        Some(pattern) if !pattern.is_empty() => value.to_lowercase().contains(&pattern.to_lowercase()),
+        _ => true,
+    };
+    let type_matches = match filter.question_type.as_deref() {
+        Some(t) if !t.is_empty() => q.question_type == t,
+        _ => true,
+    };
+    let knowledge_point_matches = match filter.knowledge_point.as_deref().map(str::trim) {
+        Some(kp) if !kp.is_empty() => q.knowledge_points.iter().any(|k| contains(k, &filter.knowledge_point)),
+        _ => true,
+    };
+    let confidence_matches = match (filter.max_confidence, q.confidence) {
+        (Some(max), Some(confidence)) => confidence <= max,
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    let review_matches = match filter.review_status.as_deref() {
+        Some(status) if !status.is_empty() => q.review_status == status,
+        _ => true,
+    };
+    type_matches
+        && contains(&q.chapter, &filter.chapter)
+        && knowledge_point_matches
+        && confidence_matches
+        && review_matches
+}
+
+/// 设置中要求只使用审核通过的题目时，过滤掉未通过审核的题目（练习、组卷、合并题库）
+fn retain_approved(app_handle: &AppHandle, questions: &mut Vec<Question>) {
+    if config::get_config_sync(app_handle).approved_questions_only {
+        questions.retain(|q| q.review_status == "approved");
+    }
+}
+
+/// 读取文件的题目，file_id 为空时读取全部文件
+async fn load_bank_questions(app_handle: &AppHandle, file_id: Option<&str>) -> Result<Vec<Question>> {
+    match file_id {
+        Some(file_id) => get_questions(app_handle, file_id).await,
+        None => {
+            let mut questions = Vec::new();
+            for file in crate::file_manager::get_file_list(app_handle).await? {
+                questions.extend(get_questions(app_handle, &file.id).await.unwrap_or_default());
+            }
+            Ok(questions)
+        }
+    }
+}
+
+/// 章节目录：由章节结构生成，带各章节的页码范围和题目数
+pub async fn get_chapter_tree(
+    app_handle: &AppHandle,
+    file_id: &str,
+) -> Result<Vec<chapter_structure::ChapterOutlineNode>> {
+    let file_path = get_file_storage_path(app_handle, file_id);
+    if !file_path.join("meta.json").exists() {
+        return Err(anyhow!("文件不存在"));
+    }
+    let structure = chapter_structure::load(&file_path.join("questions").join("structure.json")).unwrap_or_default();
+    let total_pages = crate::file_manager::get_total_pages(app_handle, file_id).await.unwrap_or(0);
+    let questions = get_questions(app_handle, file_id).await?;
+    Ok(structure.outline(total_pages, &questions))
+}
+
+/// 统计知识点出现次数
+pub async fn get_knowledge_points(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+) -> Result<Vec<knowledge_points::KnowledgePointStat>> {
+    Ok(knowledge_points::collect_stats(&load_bank_questions(app_handle, file_id).await?))
+}
+
+/// 记录一次作答（题目 ID 在全部文件中查找）
+pub async fn record_attempt(
+    app_handle: &AppHandle,
+    question_id: &str,
+    correct: bool,
+    user_answer: &str,
+) -> Result<study_records::Attempt> {
+    let question = load_bank_questions(app_handle, None)
+        .await?
+        .into_iter()
+        .find(|q| q.id == question_id)
+        .ok_or_else(|| anyhow!("题目不存在"))?;
+    study_records::record(&question, correct, user_answer)
+}
+
+/// 错题本：最近一次作答错误的题目，file_id 为空时包含全部文件
+pub async fn get_wrong_questions(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+) -> Result<Vec<study_records::WrongQuestion>> {
+    Ok(study_records::wrong_questions(&load_bank_questions(app_handle, file_id).await?))
+}
+
+/// 练习会话的保存目录：单个文件的练习在文件目录下，整个题库的练习在应用数据目录下
+fn practice_dir(app_handle: &AppHandle, file_id: Option<&str>) -> PathBuf {
+    match file_id {
+        Some(file_id) => get_file_storage_path(app_handle, file_id).join("practice"),
+        None => app_handle.path_resolver().app_data_dir().unwrap().join("practice"),
+    }
+}
+
+/// 按 ID 查找练习会话，返回会话和所在目录
+async fn find_practice_session(
+    app_handle: &AppHandle,
+    session_id: &str,
+) -> Result<(PathBuf, practice_session::PracticeSession)> {
+    if !crate::utils::is_safe_id(session_id) {
+        return Err(anyhow!("无效的练习会话 ID"));
+    }
+    let mut dirs = vec![practice_dir(app_handle, None)];
+    for file in crate::file_manager::get_file_list(app_handle).await? {
+        dirs.push(practice_dir(app_handle, Some(&file.id)));
+    }
+    dirs.into_iter()
+        .find_map(|dir| {
+            let session = practice_session::load(&dir, session_id).ok()??;
+            Some((dir, session))
+        })
+        .ok_or_else(|| anyhow!("练习会话不存在"))
+}
+
+/// 开始练习：按条件抽取题目，count 为 0 时使用全部符合条件的题目
+pub async fn start_practice(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    count: usize,
+    shuffle: bool,
+) -> Result<practice_session::PracticeSession> {
+    let mut questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    retain_approved(app_handle, &mut questions);
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+    if shuffle {
+        practice_session::shuffle(&mut questions);
+    }
+    if count > 0 {
+        questions.truncate(count);
+    }
+
+    let session = practice_session::PracticeSession::new(file_id, filter.clone(), questions);
+    practice_session::save(&practice_dir(app_handle, file_id), &session)?;
+    Ok(session)
+}
+
+/// 提交一道题的答案：选择题按选项判分，其他题目使用 correct（自行判断）或由模型评分
+/// 作答同时记入练习记录（错题本和掌握程度统计）
+pub async fn submit_practice_answer(
+    app_handle: &AppHandle,
+    session_id: &str,
+    question_id: &str,
+    user_answer: &str,
+    correct: Option<bool>,
+) -> Result<practice_session::SessionAnswer> {
+    let (dir, session) = find_practice_session(app_handle, session_id).await?;
+    if session.is_finished() {
+        return Err(anyhow!("练习已结束"));
+    }
+    let question = session
+        .questions
+        .iter()
+        .find(|q| q.id == question_id)
+        .cloned()
+        .ok_or_else(|| anyhow!("题目不在本次练习中"))?;
+
+    let verdict = |correct: bool| if correct { "correct" } else { "incorrect" }.to_string();
+    let answer = match practice_session::judge_choice(&question, user_answer).or(correct) {
+        Some(correct) => practice_session::SessionAnswer {
+            question_id: question.id.clone(),
+            user_answer: user_answer.to_string(),
+            correct,
+            score: if correct { 100.0 } else { 0.0 },
+            verdict: verdict(correct),
+            feedback: String::new(),
+            answered_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+        None => {
+            let grade = grade_answer(app_handle, &question.file_id, &question.id, user_answer).await?;
+            practice_session::SessionAnswer {
+                question_id: question.id.clone(),
+                user_answer: user_answer.to_string(),
+                correct: grade.verdict == "correct",
+                score: grade.score,
+                verdict: grade.verdict,
+                feedback: grade.feedback,
+                answered_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            }
+        }
+    };
+
+    study_records::record(&question, answer.correct, user_answer)?;
+
+    // 评分期间可能提交了其他题目，重新读取后再保存
+    let _guard = PRACTICE_LOCK.lock().unwrap();
+    let mut session = practice_session::load(&dir, session_id)?.ok_or_else(|| anyhow!("练习会话不存在"))?;
+    session.set_answer(answer.clone());
+    practice_session::save(&dir, &session)?;
+    Ok(answer)
+}
+
+/// 结束练习并生成成绩报告（已结束的会话直接返回原来的报告）
+pub async fn finish_practice(app_handle: &AppHandle, session_id: &str) -> Result<practice_session::SessionReport> {
+    let (dir, _) = find_practice_session(app_handle, session_id).await?;
+    let _guard = PRACTICE_LOCK.lock().unwrap();
+    let mut session = practice_session::load(&dir, session_id)?.ok_or_else(|| anyhow!("练习会话不存在"))?;
+    if let Some(report) = session.report.clone().filter(|_| session.is_finished()) {
+        return Ok(report);
+    }
+    let report = session.finish();
+    practice_session::save(&dir, &session)?;
+    Ok(report)
+}
+
+/// 列出练习会话，file_id 为空时列出整个题库的练习
+pub fn get_practice_sessions(app_handle: &AppHandle, file_id: Option<&str>) -> Vec<practice_session::SessionSummary> {
+    practice_session::list(&practice_dir(app_handle, file_id))
+        .iter()
+        .map(|s| s.summary())
+        .collect()
+}
+
+/// 获取练习会话详情
+pub async fn get_practice_session(
+    app_handle: &AppHandle,
+    session_id: &str,
+) -> Result<practice_session::PracticeSession> {
+    Ok(find_practice_session(app_handle, session_id).await?.1)
+}
+
+/// 导出 Anki 卡组（.apkg），file_id 为空时从全部文件中按条件导出，返回导出的卡片数
+pub async fn export_anki(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+    deck_name: &str,
+) -> Result<usize> {
+    use crate::logger;
+
+    let mut questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    retain_approved(app_handle, &mut questions);
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+
+    let deck_name = export_title(app_handle, file_id, deck_name).await?;
+    let notes: Vec<anki_export::AnkiNote> = questions
+        .iter()
+        .map(|q| anki_export::AnkiNote {
+            question: q,
+            base_dir: get_file_storage_path(app_handle, &q.file_id),
+        })
+        .collect();
+    let count = anki_export::write_apkg(dest_path, &deck_name, &notes)?;
+    logger::info("ai", &format!("已导出 {} 张 Anki 卡片到 {}", count, dest_path.display()));
+    Ok(count)
+}
+
+/// 导出 Moodle XML 题库文件，返回导出的题目数
+pub async fn export_moodle_xml(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+) -> Result<usize> {
+    use crate::logger;
+
+    let questions = load_export_questions(app_handle, file_id, filter).await?;
+    let items = lms_export_items(app_handle, &questions);
+    fs::write(dest_path, lms_export::to_moodle_xml(&items))?;
+    logger::info("ai", &format!("已导出 {} 道题目（Moodle XML）到 {}", items.len(), dest_path.display()));
+    Ok(items.len())
+}
+
+/// 导出 GIFT 题库文件，返回导出的题目数
+pub async fn export_gift(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+) -> Result<usize> {
+    use crate::logger;
+
+    let questions = load_export_questions(app_handle, file_id, filter).await?;
+    let items = lms_export_items(app_handle, &questions);
+    fs::write(dest_path, lms_export::to_gift(&items))?;
+    logger::info("ai", &format!("已导出 {} 道题目（GIFT）到 {}", items.len(), dest_path.display()));
+    Ok(items.len())
+}
+
+/// 导出 QTI 2.1 内容包（zip），返回导出的题目数
+pub async fn export_qti(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+    title: &str,
+) -> Result<usize> {
+    use crate::logger;
+
+    let questions = load_export_questions(app_handle, file_id, filter).await?;
+    let title = export_title(app_handle, file_id, title).await?;
+    let items = lms_export_items(app_handle, &questions);
+    let count = qti_export::write_package(dest_path, &title, &items)?;
+    logger::info("ai", &format!("已导出 {} 道题目（QTI 2.1）到 {}", count, dest_path.display()));
+    Ok(count)
+}
+
+/// 导出的卡组、测验名称，未指定时使用文件名（全部文件时为 "BooQ"）
+async fn export_title(app_handle: &AppHandle, file_id: Option<&str>, name: &str) -> Result<String> {
+    Ok(match name.trim() {
+        "" => match file_id {
+            Some(file_id) => crate::file_manager::get_file_list(app_handle)
+                .await?
+                .into_iter()
+                .find(|f| f.id == file_id)
+                .map(|f| f.display_name)
+                .unwrap_or_else(|| "BooQ".to_string()),
+            None => "BooQ".to_string(),
+        },
+        name => name.to_string(),
+    })
+}
+
+/// 导出 CSV 或 Excel 表格，format 为 "csv" 或 "xlsx"，columns 为空时导出默认列；返回导出的题目数
+pub async fn export_questions_table(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+    columns: &[String],
+    format: &str,
+) -> Result<usize> {
+    use crate::logger;
+
+    let columns = spreadsheet_export::resolve_columns(columns)?;
+    let mut questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    retain_approved(app_handle, &mut questions);
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+
+    let count = match format {
+        "csv" => spreadsheet_export::write_csv(dest_path, &columns, &questions)?,
+        "xlsx" => spreadsheet_export::write_xlsx(dest_path, &columns, &questions)?,
+        other => return Err(anyhow!("不支持的表格格式: {}", other)),
+    };
+    logger::info("ai", &format!("已导出 {} 道题目到 {}", count, dest_path.display()));
+    Ok(count)
+}
+
+/// 导出为 Markdown 笔记（Obsidian 等），per_question 为 true 时每道题一个文件，否则每章一个文件；返回写入的文件数
+pub async fn export_markdown(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_dir: &Path,
+    per_question: bool,
+) -> Result<usize> {
+    use crate::logger;
+
+    let questions = load_export_questions(app_handle, file_id, filter).await?;
+    let items: Vec<markdown_export::MarkdownQuestion> = questions
+        .iter()
+        .map(|q| markdown_export::MarkdownQuestion {
+            question: q,
+            base_dir: get_file_storage_path(app_handle, &q.file_id),
+        })
+        .collect();
+    let files = markdown_export::write_vault(dest_dir, &items, per_question)?;
+    logger::info(
+        "ai",
+        &format!("已导出 {} 道题目（{} 个 Markdown 文件）到 {}", questions.len(), files, dest_dir.display()),
+    );
+    Ok(files)
+}
+
+/// 导出为带版本号的 BooQ 题库 JSON 文件（含插图），返回导出的题目数
+pub async fn export_bank(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+    name: &str,
+) -> Result<usize> {
+    use crate::logger;
+
+    let questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+    let name = export_title(app_handle, file_id, name).await?;
+    let bank = bank_exchange::build(&name, &questions, |q| get_file_storage_path(app_handle, &q.file_id));
+    save_json(dest_path, &bank)?;
+    logger::info("ai", &format!("已导出题库 {}（{} 道题目）到 {}", name, questions.len(), dest_path.display()));
+    Ok(questions.len())
+}
+
+/// 导入 BooQ 题库 JSON 文件到指定文件的题目中，旧版本的文件会先迁移到当前格式
+pub fn import_bank(app_handle: &AppHandle, file_id: &str, src_path: &Path) -> Result<bank_exchange::ImportSummary> {
+    use crate::logger;
+
+    let (bank, source_version) = bank_exchange::read(src_path)?;
+    let base_dir = get_file_storage_path(app_handle, file_id);
+    let images_dir = base_dir.join("questions").join("images");
+    let name = bank.name.clone();
+    let (imported, skipped_duplicates, renamed_ids) = modify_questions(app_handle, file_id, "导入题库", |questions| {
+        bank_exchange::merge_into(questions, bank.questions, file_id, &images_dir, &base_dir)
+    })?;
+    logger::info(
+        "ai",
+        &format!(
+            "已导入题库 {}（版本 {}）：新增 {} 道题目，跳过 {} 道重复题目",
+            name, source_version, imported, skipped_duplicates
+        ),
+    );
+    Ok(bank_exchange::ImportSummary {
+        name,
+        source_schema_version: source_version,
+        imported,
+        skipped_duplicates,
+        renamed_ids,
+    })
+}
+
+/// 读取要导出到学习平台（或按章节导出）的题目，按章节排列使同一章节的题目连续
+async fn load_export_questions(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+) -> Result<Vec<Question>> {
+    let mut questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    retain_approved(app_handle, &mut questions);
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+    // 稳定排序，同一章节内保持原来的页码顺序
+    questions.sort_by(|a, b| a.chapter.cmp(&b.chapter));
+    Ok(questions)
+}
+
+fn lms_export_items<'a>(app_handle: &AppHandle, questions: &'a [Question]) -> Vec<lms_export::ExportQuestion<'a>> {
+    questions
+        .iter()
+        .map(|q| lms_export::ExportQuestion {
+            question: q,
+            base_dir: get_file_storage_path(app_handle, &q.file_id),
+        })
+        .collect()
+}
+
+/// 试卷保存目录
+fn papers_dir(app_handle: &AppHandle) -> PathBuf {
+    app_handle.path_resolver().app_data_dir().unwrap().join("papers")
+}
+
+/// 按蓝图组卷并保存
+pub async fn generate_paper(app_handle: &AppHandle, spec: exam_paper::PaperSpec) -> Result<exam_paper::Paper> {
+    use crate::logger;
+
+    if spec.items.iter().all(|item| item.count == 0) {
+        return Err(anyhow!("组卷蓝图中没有需要抽取的题目"));
+    }
+    let mut questions = load_bank_questions(app_handle, spec.file_id.as_deref()).await?;
+    retain_approved(app_handle, &mut questions);
+    let dir = papers_dir(app_handle);
+    let recent_papers = exam_paper::list(&dir);
+    let paper = exam_paper::generate(spec, &questions, &recent_papers, |q, item| {
+        matches_filter(q, &item.filter) && exam_paper::matches_difficulty(q, item)
+    });
+    if paper.answer_key.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+    exam_paper::save(&dir, &paper)?;
+
+    logger::info(
+        "ai",
+        &format!(
+            "已生成试卷 {}：{} 道题，满分 {}",
+            paper.title,
+            paper.answer_key.len(),
+            paper.total_score
+        ),
+    );
+    if !paper.shortfalls.is_empty() {
+        logger::warn("ai", &format!("试卷 {} 有 {} 项题目不足", paper.title, paper.shortfalls.len()));
+    }
+    Ok(paper)
+}
+
+/// 列出已生成的试卷
+pub fn get_papers(app_handle: &AppHandle) -> Vec<exam_paper::PaperSummary> {
+    exam_paper::list(&papers_dir(app_handle)).iter().map(|p| p.summary()).collect()
+}
+
+/// 获取试卷详情
+pub fn get_paper(app_handle: &AppHandle, paper_id: &str) -> Result<exam_paper::Paper> {
+    exam_paper::load(&papers_dir(app_handle), paper_id)?.ok_or_else(|| anyhow!("试卷不存在"))
+}
+
+/// 删除试卷
+pub fn delete_paper(app_handle: &AppHandle, paper_id: &str) -> Result<()> {
+    if !exam_paper::remove(&papers_dir(app_handle), paper_id)? {
+        return Err(anyhow!("试卷不存在"));
+    }
+    Ok(())
+}
+
+/// 导出试卷为 Markdown，给出 answer_key_path 时答案单独导出到该文件（试卷中不附答案）
+pub fn export_paper(
+    app_handle: &AppHandle,
+    paper_id: &str,
+    dest_path: &Path,
+    include_answers: bool,
+    answer_key_path: Option<&Path>,
+) -> Result<()> {
+    use crate::logger;
+
+    let paper = get_paper(app_handle, paper_id)?;
+    match answer_key_path {
+        Some(key_path) => {
+            fs::write(dest_path, exam_paper::to_markdown(&paper, false))?;
+            fs::write(key_path, exam_paper::answer_key_markdown(&paper))?;
+            logger::info(
+                "ai",
+                &format!("已导出试卷 {} 到 {}，答案到 {}", paper.title, dest_path.display(), key_path.display()),
+            );
+        }
+        None => {
+            fs::write(dest_path, exam_paper::to_markdown(&paper, include_answers))?;
+            logger::info("ai", &format!("已导出试卷 {} 到 {}", paper.title, dest_path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// 将试卷导出为 PDF（公式和插图排版后输出），给出 answer_key_path 时答案单独导出为另一个 PDF
+pub async fn export_paper_pdf(
+    app_handle: &AppHandle,
+    paper_id: &str,
+    dest_path: &Path,
+    include_answers: bool,
+    answer_key_path: Option<&Path>,
+) -> Result<()> {
+    use crate::logger;
+
+    let paper = get_paper(app_handle, paper_id)?;
+    let sections = paper
+        .sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| pdf_export::PdfSection {
+            title: exam_paper::section_heading(index, section),
+            questions: section
+                .questions
+                .iter()
+                .map(|item| pdf_export::PdfQuestion {
+                    number: item.number,
+                    score: Some(item.score),
+                    question: &item.question,
+                    base_dir: get_file_storage_path(app_handle, &item.question.file_id),
+                })
+                .collect(),
+        })
+        .collect();
+    let doc = pdf_export::PdfDocument {
+        title: paper.title.clone(),
+        subtitle: format!("满分：{}", exam_paper::format_score(paper.total_score)),
+        sections,
+        content: pdf_export::PdfContent::Questions,
+    };
+    write_pdf_with_answer_key(app_handle, doc, dest_path, include_answers, answer_key_path).await?;
+    logger::info("ai", &format!("已导出试卷 {} 的 PDF 到 {}", paper.title, dest_path.display()));
+    Ok(())
+}
+
+/// 将符合条件的题目导出为 PDF 题目集，给出 answer_key_path 时答案单独导出为另一个 PDF；返回导出的题目数
+pub async fn export_questions_pdf(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    dest_path: &Path,
+    title: &str,
+    include_answers: bool,
+    answer_key_path: Option<&Path>,
+) -> Result<usize> {
+    use crate::logger;
+
+    let mut questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    retain_approved(app_handle, &mut questions);
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+
+    let doc = pdf_export::PdfDocument {
+        title: export_title(app_handle, file_id, title).await?,
+        subtitle: String::new(),
+        sections: vec![pdf_export::PdfSection {
+            title: String::new(),
+            questions: questions
+                .iter()
+                .enumerate()
+                .map(|(index, q)| pdf_export::PdfQuestion {
+                    number: index as u32 + 1,
+                    score: None,
+                    question: q,
+                    base_dir: get_file_storage_path(app_handle, &q.file_id),
+                })
+                .collect(),
+        }],
+        content: pdf_export::PdfContent::Questions,
+    };
+    write_pdf_with_answer_key(app_handle, doc, dest_path, include_answers, answer_key_path).await?;
+    logger::info("ai", &format!("已导出 {} 道题目的 PDF 到 {}", questions.len(), dest_path.display()));
+    Ok(questions.len())
+}
+
+/// 导出题目 PDF；给出答案文件路径时题目和答案分成两个文档，两者使用同一份题号
+async fn write_pdf_with_answer_key(
+    app_handle: &AppHandle,
+    mut doc: pdf_export::PdfDocument<'_>,
+    dest_path: &Path,
+    include_answers: bool,
+    answer_key_path: Option<&Path>,
+) -> Result<()> {
+    let typst_path = config::get_config_sync(app_handle).typst_path;
+    doc.content = match (answer_key_path, include_answers) {
+        (None, true) => pdf_export::PdfContent::QuestionsWithAnswers,
+        _ => pdf_export::PdfContent::Questions,
+    };
+    pdf_export::write_pdf(&typst_path, dest_path, &doc).await?;
+    if let Some(key_path) = answer_key_path {
+        doc.content = pdf_export::PdfContent::AnswerKey;
+        pdf_export::write_pdf(&typst_path, key_path, &doc).await?;
+    }
+    Ok(())
+}
+
+/// 按知识点统计掌握程度
+pub async fn get_knowledge_point_mastery(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+) -> Result<Vec<study_records::KnowledgePointMastery>> {
+    Ok(study_records::knowledge_point_mastery(&load_bank_questions(app_handle, file_id).await?))
+}
+
+/// 给出知识点合并建议：写法仅有空白、大小写、标点差异的直接归为一组，
+/// 配置了向量化模型时再按名称向量相似度聚类（只比较出现最多的一部分知识点）
+pub async fn suggest_knowledge_point_merges(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+) -> Result<Vec<knowledge_points::KnowledgePointCluster>> {
+    use crate::logger;
+
+    let stats = knowledge_points::collect_stats(&load_bank_questions(app_handle, file_id).await?);
+    let mut clusters = knowledge_points::exact_clusters(&stats);
+
+    let app_config = config::get_config_sync(app_handle);
+    if let Some(embedder) = get_embedder(&app_config) {
+        let candidates: Vec<knowledge_points::KnowledgePointStat> =
+            stats.into_iter().take(MAX_EMBEDDED_KNOWLEDGE_POINTS).collect();
+        let names: Vec<String> = candidates.iter().map(|s| s.name.clone()).collect();
+        match embedder.embed(&names).await {
+            Ok(embeddings) => clusters.extend(knowledge_points::embedding_clusters(
+                &candidates,
+                &embeddings,
+                knowledge_points::MERGE_SIMILARITY,
+            )),
+            Err(e) => logger::warn("ai", &format!("知识点向量化失败，只给出写法相同的合并建议: {}", e)),
+        }
+    }
+    Ok(clusters)
+}
+
+/// 将 sources 合并为 target：记入别名表后按别名表更新全部文件的题目，返回修改的题目数
+pub async fn merge_knowledge_points(app_handle: &AppHandle, sources: &[String], target: &str) -> Result<usize> {
+    use crate::logger;
+
+    let target = target.trim();
+    if target.is_empty() {
+        return Err(anyhow!("知识点名称不能为空"));
+    }
+    knowledge_points::add_aliases(sources, target)?;
+
+    let mut changed = 0;
+    for file in crate::file_manager::get_file_list(app_handle).await? {
+        changed += modify_questions(app_handle, &file.id, "合并知识点", |questions| {
+            let before: Vec<Vec<String>> = questions.iter().map(|q| q.knowledge_points.clone()).collect();
+            knowledge_points::normalize_questions(questions);
+            Ok(questions
+                .iter()
+                .zip(before)
+                .filter(|(q, before)| q.knowledge_points != *before)
+                .count())
+        })?;
+    }
+    logger::info("ai", &format!("知识点 {:?} 已合并为 {}，修改 {} 道题目", sources, target, changed));
+    Ok(changed)
+}
+
+/// 手动添加题目，id 为空时自动生成，按页码插入到同页题目之后
+pub async fn create_question(app_handle: &AppHandle, file_id: &str, mut question: Question) -> Result<Question> {
+    if question.question_text.trim().is_empty() {
+        return Err(anyhow!("题目内容不能为空"));
+    }
+    knowledge_points::normalize_questions(std::slice::from_mut(&mut question));
+    question_options::apply_options(&mut question);
+    // 手动添加的题目已经过人工确认
+    question.review_status = "approved".to_string();
+    modify_questions(app_handle, file_id, "手动添加", |questions| {
+        question.file_id = file_id.to_string();
+        if question.id.is_empty() {
+            let suffix = uuid::Uuid::new_v4().simple().to_string();
+            question.id = format!("{}_{}_manual_{}", file_id, question.page_number, &suffix[..8]);
+        } else if questions.iter().any(|q| q.id == question.id) {
+            return Err(anyhow!("题目 ID 已存在"));
+        }
+        let position = questions
+            .iter()
+            .position(|q| q.page_number > question.page_number)
+            .unwrap_or(questions.len());
+        questions.insert(position, question.clone());
+        Ok(question)
+    })
+}
+
+/// 修改题目（按 id 整体替换），用于修正答案、解析等
+pub async fn update_question(app_handle: &AppHandle, file_id: &str, mut question: Question) -> Result<Question> {
+    if question.question_text.trim().is_empty() {
+        return Err(anyhow!("题目内容不能为空"));
+    }
+    knowledge_points::normalize_questions(std::slice::from_mut(&mut question));
+    question_options::apply_options(&mut question);
+    modify_questions(app_handle, file_id, "手动修改", |questions| {
+        let existing = questions
+            .iter_mut()
+            .find(|q| q.id == question.id)
+            .ok_or_else(|| anyhow!("题目不存在"))?;
+        question.file_id = file_id.to_string();
+        *existing = question.clone();
+        Ok(question)
+    })
+}
+
+/// 批量设置审核状态（"pending", "approved", "rejected"），返回状态有变化的题目数
+pub async fn set_review_status(
+    app_handle: &AppHandle,
+    file_id: &str,
+    question_ids: &[String],
+    status: &str,
+) -> Result<usize> {
+    if !matches!(status, "pending" | "approved" | "rejected") {
+        return Err(anyhow!("无效的审核状态: {}", status));
+    }
+    modify_questions(app_handle, file_id, "审核", |questions| {
+        let mut changed = 0;
+        for q in questions.iter_mut().filter(|q| question_ids.contains(&q.id)) {
+            if q.review_status != status {
+                q.review_status = status.to_string();
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    })
+}
+
+/// 检查题目中的公式能否正常渲染，返回有问题的题目；file_id 为空时检查全部文件
+/// fix 为 true 时按 OCR 结果的规范化规则整理有问题的题目，问题减少时保存
+pub async fn validate_latex(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    fix: bool,
+) -> Result<Vec<latex_check::LatexReport>> {
+    use crate::logger;
+
+    let file_ids: Vec<String> = match file_id {
+        Some(file_id) => vec![file_id.to_string()],
+        None => crate::file_manager::get_file_list(app_handle)
+            .await?
+            .into_iter()
+            .map(|f| f.id)
+            .collect(),
+    };
+
+    let mut reports = Vec::new();
+    for file_id in file_ids {
+        let questions = get_questions(app_handle, &file_id).await?;
+        let mut file_reports: Vec<latex_check::LatexReport> = questions
+            .iter()
+            .filter_map(|q| {
+                let issues = latex_check::check_question(q);
+                (!issues.is_empty()).then(|| latex_check::LatexReport {
+                    question_id: q.id.clone(),
+                    file_id: file_id.clone(),
+                    page_number: q.page_number,
+                    issues,
+                    fixed: false,
+                })
+            })
+            .collect();
+
+        if fix && !file_reports.is_empty() && !is_analyzing(&file_id) {
+            modify_questions(app_handle, &file_id, "修复公式", |questions| {
+                for report in file_reports.iter_mut() {
+                    if let Some(q) = questions.iter_mut().find(|q| q.id == report.question_id) {
+                        let mut normalized = q.clone();
+                        for text in [
+                            &mut normalized.question_text,
+                            &mut normalized.answer,
+                            &mut normalized.analysis,
+                        ] {
+                            *text = ocr_service::normalize_latex(text).trim().to_string();
+                        }
+                        let remaining = latex_check::check_question(&normalized);
+                        if remaining.len() < report.issues.len() {
+                            *q = normalized;
+                            report.fixed = remaining.is_empty();
+                            report.issues = remaining;
+                        }
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        reports.extend(file_reports);
+    }
+
+    logger::info("ai", &format!("公式检查：{} 道题目存在问题", reports.iter().filter(|r| !r.fixed).count()));
+    Ok(reports)
+}
+
+/// 获取题目的修改记录（在全部文件中查找），最近的排在前面
+pub async fn get_question_history(
+    app_handle: &AppHandle,
+    question_id: &str,
+) -> Result<Vec<question_history::QuestionEdit>> {
+    let mut edits = Vec::new();
+    for file in crate::file_manager::get_file_list(app_handle).await? {
+        let path = get_file_storage_path(app_handle, &file.id).join("questions").join("history.jsonl");
+        edits.extend(question_history::load(&path, question_id));
+    }
+    edits.sort_by(|a, b| b.edited_at.cmp(&a.edited_at));
+    Ok(edits)
+}
+
+/// 把题目恢复为某次修改之前的内容：修改前不存在（新建）的题目会被删除，已删除的题目会重新加入
+pub async fn revert_question(app_handle: &AppHandle, question_id: &str, edit_id: &str) -> Result<Option<Question>> {
+    let edit = get_question_history(app_handle, question_id)
+        .await?
+        .into_iter()
+        .find(|e| e.edit_id == edit_id)
+        .ok_or_else(|| anyhow!("修改记录不存在"))?;
+    if is_analyzing(&edit.file_id) {
+        return Err(anyhow!("正在分析，无法恢复"));
+    }
+    let note = format!("恢复到 {} 的修改之前", edit.edited_at);
+    modify_questions(app_handle, &edit.file_id, &note, |questions| {
+        let position = questions.iter().position(|q| q.id == question_id);
+        match (edit.before, position) {
+            (Some(before), Some(i)) => questions[i] = before.clone(),
+            (Some(before), None) => {
+                let position = questions
+                    .iter()
+                    .position(|q| q.page_number > before.page_number)
+                    .unwrap_or(questions.len());
+                questions.insert(position, before.clone());
+            }
+            (None, Some(i)) => {
+                questions.remove(i);
+                return Ok(None);
+            }
+            (None, None) => return Ok(None),
+        }
+        Ok(questions.iter().find(|q| q.id == question_id).cloned())
+    })
+}
+
+/// 删除题目
+pub async fn delete_question(app_handle: &AppHandle, file_id: &str, question_id: &str) -> Result<()> {
+    modify_questions(app_handle, file_id, "手动删除", |questions| {
+        let before = questions.len();
+        questions.retain(|q| q.id != question_id);
+        if questions.len() == before {
+            return Err(anyhow!("题目不存在"));
+        }
+        Ok(())
+    })
+}
+
+/// 查找文件中近似重复的题目，include_other_files 为 true 时也与其他文件的题目比较
+/// 只返回包含该文件题目的重复组
+pub async fn find_duplicate_questions(
+    app_handle: &AppHandle,
+    file_id: &str,
+    include_other_files: bool,
+) -> Result<Vec<question_dedup::DuplicateGroup>> {
+    let mut questions = get_questions(app_handle, file_id).await?;
+    if include_other_files {
+        for file in crate::file_manager::get_file_list(app_handle).await? {
+            if file.id != file_id {
+                questions.extend(get_questions(app_handle, &file.id).await.unwrap_or_default());
+            }
+        }
+    }
+    Ok(question_dedup::find_duplicates(&questions, question_dedup::DUPLICATE_SIMILARITY)
+        .into_iter()
+        .filter(|g| g.keep.file_id == file_id || g.duplicates.iter().any(|d| d.file_id == file_id))
+        .collect())
+}
+
+/// 合并用户确认的重复题目：每组保留 keep 并补充其他副本的知识点，只删除组中列出的副本，返回删除的题目数
+/// 保留的题目已不存在的组跳过，避免两份都被删除
+pub async fn merge_duplicate_questions(app_handle: &AppHandle, groups: &[question_dedup::DuplicateGroup]) -> Result<usize> {
+    use crate::logger;
+
+    // 按文件整理要删除的题目，以及保留的题目要补充的知识点
+    let mut removals: HashMap<String, Vec<String>> = HashMap::new();
+    let mut merged_points: HashMap<(String, String), Vec<Question>> = HashMap::new();
+    let mut loaded: HashMap<String, Vec<Question>> = HashMap::new();
+    let mut merged_groups = 0;
+    for group in groups {
+        for file_id in std::iter::once(&group.keep.file_id).chain(group.duplicates.iter().map(|d| &d.file_id)) {
+            if !loaded.contains_key(file_id) {
+                loaded.insert(file_id.clone(), get_questions(app_handle, file_id).await?);
+            }
+        }
+        if !loaded[&group.keep.file_id].iter().any(|q| q.id == group.keep.question_id) {
+            logger::warn("ai", &format!("保留的题目 {} 不存在，跳过该组", group.keep.question_id));
+            continue;
+        }
+        let mut duplicates = Vec::new();
+        for member in &group.duplicates {
+            if member.file_id == group.keep.file_id && member.question_id == group.keep.question_id {
+                continue;
+            }
+            if let Some(q) = loaded[&member.file_id].iter().find(|q| q.id == member.question_id) {
+                duplicates.push(q.clone());
+                removals
+                    .entry(member.file_id.clone())
+                    .or_default()
+                    .push(member.question_id.clone());
+            }
+        }
+        if !duplicates.is_empty() {
+            merged_groups += 1;
+            merged_points
+                .entry((group.keep.file_id.clone(), group.keep.question_id.clone()))
+                .or_default()
+                .extend(duplicates);
+        }
+    }
+
+    let mut removed = 0;
+    let mut files: Vec<String> = removals.keys().cloned().collect();
+    for (file_id, _) in merged_points.keys() {
+        if !files.contains(file_id) {
+            files.push(file_id.clone());
+        }
+    }
+    for file in files {
+        let ids = removals.remove(&file).unwrap_or_default();
+        removed += modify_questions(app_handle, &file, "合并重复题目", |questions| {
+            for q in questions.iter_mut() {
+                if let Some(duplicates) = merged_points.get(&(file.clone(), q.id.clone())) {
+                    question_dedup::merge_knowledge_points(q, duplicates);
+                }
+            }
+            let before = questions.len();
+            questions.retain(|q| !ids.contains(&q.id));
+            Ok(before - questions.len())
+        })?;
+    }
+    logger::info("ai", &format!("合并重复题目：{} 组，删除 {} 道题目", merged_groups, removed));
+    Ok(removed)
+}
+
+/// 组合题库保存目录
+fn banks_dir(app_handle: &AppHandle) -> PathBuf {
+    app_handle.path_resolver().app_data_dir().unwrap().join("banks")
+}
+
+/// 把多个文件的题目合并为一个组合题库，各文件自己的题目不变
+/// dedupe 为 true 时近似重复的题目只保留一份，并记录全部来源
+pub async fn merge_banks(
+    app_handle: &AppHandle,
+    file_ids: &[String],
+    dedupe: bool,
+    name: &str,
+) -> Result<question_banks::QuestionBank> {
+    use crate::logger;
+
+    if file_ids.is_empty() {
+        return Err(anyhow!("请选择要合并的文件"));
+    }
+    let file_names: HashMap<String, String> = crate::file_manager::get_file_list(app_handle)
+        .await?
+        .into_iter()
+        .map(|f| (f.id, f.display_name))
+        .collect();
+    let mut unique_ids: Vec<String> = Vec::new();
+    for file_id in file_ids {
+        if !unique_ids.contains(file_id) {
+            unique_ids.push(file_id.clone());
+        }
+    }
+    let file_ids = unique_ids;
+    let mut questions = Vec::new();
+    for file_id in &file_ids {
+        if !file_names.contains_key(file_id) {
+            return Err(anyhow!("文件不存在: {}", file_id));
+        }
+        questions.extend(get_questions(app_handle, file_id).await?);
+    }
+    retain_approved(app_handle, &mut questions);
+
+    let bank = question_banks::merge(name, &file_ids, &file_names, questions, dedupe);
+    question_banks::save(&banks_dir(app_handle), &bank)?;
+    logger::info(
+        "ai",
+        &format!(
+            "已合并题库 {}：{} 个文件，{} 道题目，合并重复 {} 道",
+            bank.name,
+            file_ids.len(),
+            bank.questions.len(),
+            bank.merged_duplicates
+        ),
+    );
+    Ok(bank)
+}
+
+/// 列出组合题库
+pub fn get_banks(app_handle: &AppHandle) -> Vec<question_banks::BankSummary> {
+    question_banks::list(&banks_dir(app_handle)).iter().map(|b| b.summary()).collect()
+}
+
+/// 获取组合题库详情
+pub fn get_bank(app_handle: &AppHandle, bank_id: &str) -> Result<question_banks::QuestionBank> {
+    question_banks::load(&banks_dir(app_handle), bank_id)?.ok_or_else(|| anyhow!("题库不存在"))
+}
+
+/// 删除组合题库（不影响各文件的题目）
+pub fn delete_bank(app_handle: &AppHandle, bank_id: &str) -> Result<()> {
+    if !question_banks::remove(&banks_dir(app_handle), bank_id)? {
+        return Err(anyhow!("题库不存在"));
+    }
+    Ok(())
+}
+
+/// 用同步拉取的题目替换文件的全部题目，与编辑共用锁，并同步数据库、记录修改
+pub fn replace_synced_questions(app_handle: &AppHandle, file_id: &str, synced: Vec<Question>) -> Result<()> {
+    modify_questions_as(app_handle, file_id, "sync", "同步", |questions| {
+        *questions = synced;
+        Ok(())
+    })
+}
+
+/// 读取题目列表，修改后写回；操作返回错误时不写入
+/// note 为修改原因，记入每道有变化题目的修改记录
+fn modify_questions<T>(
+    app_handle: &AppHandle,
+    file_id: &str,
+    note: &str,
+    f: impl FnOnce(&mut Vec<Question>) -> Result<T>,
+) -> Result<T> {
+    modify_questions_as(app_handle, file_id, "user", note, f)
+}
+
+fn modify_questions_as<T>(
+    app_handle: &AppHandle,
+    file_id: &str,
+    editor: &str,
+    note: &str,
+    f: impl FnOnce(&mut Vec<Question>) -> Result<T>,
+) -> Result<T> {
+    modify_questions_with(app_handle, file_id, editor, note, true, f)
+}
+
+/// 修改题目，include_created 为 false 时不记录新出现的题目（见 question_history::diff）
+fn modify_questions_with<T>(
+    app_handle: &AppHandle,
+    file_id: &str,
+    editor: &str,
+    note: &str,
+    include_created: bool,
+    f: impl FnOnce(&mut Vec<Question>) -> Result<T>,
+) -> Result<T> {
+    let file_path = get_file_storage_path(app_handle, file_id);
+    if !file_path.join("meta.json").exists() {
+        return Err(anyhow!("文件不存在"));
+    }
+    let _guard = QUESTION_STORE_LOCK.lock().unwrap();
+    let questions_file = file_path.join("questions").join("all_questions.json");
+    let mut questions: Vec<Question> = if questions_file.exists() {
+        serde_json::from_str(&fs::read_to_string(&questions_file)?)?
+    } else {
+        Vec::new()
+    };
+    let before = questions.clone();
+    let result = f(&mut questions)?;
+    save_json(&questions_file, &questions)?;
+    sync_questions_to_database(app_handle, file_id, &questions_file, &questions);
+    record_history(&file_path.join("questions"), file_id, &before, &questions, editor, note, include_created);
+    Ok(result)
+}
+
+/// 记录题目修改，失败时只记录日志
+fn record_history(
+    questions_dir: &Path,
+    file_id: &str,
+    before: &[Question],
+    after: &[Question],
+    editor: &str,
+    note: &str,
+    include_created: bool,
+) {
+    use crate::logger;
+
+    let edits = question_history::diff(file_id, before, after, editor, note, include_created);
+    if let Err(e) = question_history::append(&questions_dir.join("history.jsonl"), &edits) {
+        logger::warn("ai", &format!("保存题目修改记录失败: {}", e));
+    }
+}
+
+/// 在文件的知识库中检索，返回的结果不包含向量数据
+pub async fn search_knowledge(
+    app_handle: &AppHandle,
+    file_id: &str,
+    query: &str,
+    top_k: usize,
+    filter: &rag_service::SearchFilter,
+) -> Result<Vec<rag_service::SearchResult>> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let app_config = config::get_config_sync(app_handle);
+    let rag_store = rag_service::RAGStore::new(get_file_storage_path(app_handle, file_id).join("rag_index.db"))
+        .with_embedder(get_embedder(&app_config))
+        .with_query_expander(get_query_expander(&app_config)?);
+
+    let mut results = rag_store.search_filtered(query, top_k.clamp(1, 50), filter).await;
+    for result in results.iter_mut() {
+        result.document.embedding = None;
+    }
+    Ok(results)
+}
+
+/// 重建文件的 RAG 索引
+/// 删除指定页面（为空时为全部页面）的索引内容，重新读取 Markdown 并加入已识别的例题，返回写入的文档数
+pub async fn rebuild_rag_index(app_handle: &AppHandle, file_id: &str, pages: Option<Vec<u32>>) -> Result<usize> {
+    use crate::logger;
+
+    let file_path = get_file_storage_path(app_handle, file_id);
+    let meta_path = file_path.join("meta.json");
+    if !meta_path.exists() {
+        return Err(anyhow!("文件不存在"));
+    }
+    let file_info: crate::commands::FileInfo = serde_json::from_str(&fs::read_to_string(&meta_path)?)?;
+
+    let app_config = config::get_config_sync(app_handle);
+    let mut rag_store =
+        rag_service::RAGStore::new(file_path.join("rag_index.db")).with_embedder(get_embedder(&app_config));
+    let mut global_store = rag_service::open_global_store().map(|store| store.with_embedder(get_embedder(&app_config)));
+
+    let pages = match pages {
+        Some(pages) => pages,
+        None => {
+            rag_store.remove_by_file(file_id)?;
+            if let Some(global) = global_store.as_mut() {
+                global.remove_by_file(file_id)?;
+            }
+            (1..=file_info.total_pages).collect()
+        }
+    };
+
+    let questions = get_questions(app_handle, file_id).await.unwrap_or_default();
+    let structure = chapter_structure::load(&file_path.join("questions").join("structure.json")).unwrap_or_default();
+    let chunker = rag_service::TextChunker::new(1000, 100);
+    let mut indexed = 0;
+    for page in pages {
+        rag_store.remove_by_page(file_id, page)?;
+        if let Some(global) = global_store.as_mut() {
+            global.remove_by_page(file_id, page)?;
+        }
+
+        let markdown_content = ocr_service::convert_page_to_markdown(app_handle, file_id, page)
+            .await
+            .unwrap_or_default();
+        let mut docs = if markdown_content.trim().is_empty() {
+            Vec::new()
+        } else {
+            knowledge_documents(&chunker, file_id, page, &markdown_content, &structure)
+        };
+        docs.extend(
+            questions
+                .iter()
+                .filter(|q| q.page_number == page && q.question_type == "example")
+                .map(example_document),
+        );
+
+        indexed += docs.len();
+        begin_store_batch(&mut rag_store, global_store.as_mut());
+        for doc in docs {
+            add_to_stores(&mut rag_store, global_store.as_mut(), doc);
+        }
+        commit_store_batch(&mut rag_store, global_store.as_mut());
+    }
+
+    embed_pending(&mut rag_store).await;
+    if let Some(global) = global_store.as_mut() {
+        embed_pending(global).await;
+    }
+
+    logger::info("ai", &format!("已重建文件 {} 的 RAG 索引，写入 {} 条文档", file_id, indexed));
+    Ok(indexed)
+}
+
+/// 打开文件的 RAG 索引，file_id 为空时打开全局知识库
+fn open_rag_store(app_handle: &AppHandle, file_id: Option<&str>) -> Result<rag_service::RAGStore> {
+    match file_id {
+        Some(file_id) => {
+            let file_path = get_file_storage_path(app_handle, file_id);
+            if !file_path.join("meta.json").exists() {
+                return Err(anyhow!("文件不存在"));
+            }
+            Ok(rag_service::RAGStore::new(file_path.join("rag_index.db")))
+        }
+        None => rag_service::open_global_store().ok_or_else(|| anyhow!("未启用全局知识库")),
+    }
+}
+
+/// 导出文件（file_id 为空时为全局知识库）的 RAG 索引，返回导出的文档数
+/// 向量连同生成它的模型一起导出，导入方使用同一模型时不需要重新计算
+pub fn export_rag_index(app_handle: &AppHandle, file_id: Option<&str>, dest_path: &Path) -> Result<usize> {
+    use crate::logger;
+
+    let export = open_rag_store(app_handle, file_id)?.export();
+    if export.documents.is_empty() {
+        return Err(anyhow!("索引为空，请先分析文档"));
+    }
+    export.write(dest_path)?;
+
+    logger::info(
+        "ai",
+        &format!("已导出 {} 条 RAG 文档到 {}", export.documents.len(), dest_path.display()),
+    );
+    Ok(export.documents.len())
+}
+
+/// 导入 RAG 索引到文件（file_id 为空时为全局知识库），返回新增的文档数
+/// 导入到文件时，文档归属改为该文件；向量模型与当前配置不同时丢弃向量并重新计算
+pub async fn import_rag_index(app_handle: &AppHandle, file_id: Option<&str>, src_path: &Path) -> Result<usize> {
+    use crate::logger;
+
+    let export = rag_service::RAGExport::read(src_path)?;
+    let app_config = config::get_config_sync(app_handle);
+    let embedder = get_embedder(&app_config);
+    let current_model = embedder.as_ref().map(|embedder| embedder.model_name()).unwrap_or_default();
+
+    let mut documents = export.documents;
+    for doc in documents.iter_mut() {
+        // 版本 1 的导出文件只在顶层记录了模型
+        if doc.embedding_model.is_empty() {
+            doc.embedding_model = export.embedding_model.clone();
+        }
+        if doc.embedding_model.is_empty() || doc.embedding_model != current_model {
+            doc.embedding = None;
+            doc.embedding_model.clear();
+        }
+        if let Some(file_id) = file_id {
+            if doc.metadata.file_id != file_id {
+                if let Some(rest) = doc.id.strip_prefix(&doc.metadata.file_id) {
+                    doc.id = format!("{}{}", file_id, rest);
+                }
+                doc.metadata.file_id = file_id.to_string();
+            }
+        }
+    }
+
+    let mut rag_store = open_rag_store(app_handle, file_id)?.with_embedder(embedder.clone());
+    let mut global_store = match file_id {
+        Some(_) => rag_service::open_global_store().map(|store| store.with_embedder(embedder.clone())),
+        None => None,
+    };
+
+    let before = rag_store.len();
+    if let Some(global) = global_store.as_mut() {
+        global.add_documents(documents.clone());
+    }
+    rag_store.add_documents(documents);
+    let imported = rag_store.len().saturating_sub(before);
+
+    embed_pending(&mut rag_store).await;
+    if let Some(global) = global_store.as_mut() {
+        embed_pending(global).await;
+    }
+
+    logger::info("ai", &format!("已从 {} 导入 {} 条 RAG 文档", src_path.display(), imported));
+    Ok(imported)
+}
+
+/// 压缩文件（file_id 为空时为全局知识库）的 RAG 索引，合并重复文档并回收空间
+pub fn compact_rag_index(app_handle: &AppHandle, file_id: Option<&str>) -> Result<rag_service::CompactResult> {
+    use crate::logger;
+
+    let result = open_rag_store(app_handle, file_id)?.compact()?;
+    logger::info(
+        "ai",
+        &format!(
+            "已压缩 RAG 索引：合并 {} 条重复文档，{} 字节 -> {} 字节",
+            result.duplicates_removed, result.size_before, result.size_after
+        ),
+    );
+    Ok(result)
+}
+
+/// 为用户的作答评分
+/// 使用解题模型对照题目的参考答案打分，返回各评分项的得分明细
+pub async fn grade_answer(
+    app_handle: &AppHandle,
+    file_id: &str,
+    question_id: &str,
+    user_answer: &str,
+) -> Result<GradeResult> {
+    #[derive(Deserialize)]
+    struct GradeResponse {
+        score: f64,
+        #[serde(default)]
+        verdict: String,
+        #[serde(default)]
+        rubric: Vec<RubricItem>,
+        #[serde(default)]
+        feedback: String,
+    }
+
+    if user_answer.trim().is_empty() {
+        return Err(anyhow!("作答内容不能为空"));
+    }
+
+    let question = get_question_detail(app_handle, file_id, question_id).await?;
+
+    let app_config = config::get_config_sync(app_handle);
+    let model = app_config
+        .models
+        .iter()
+        .find(|m| m.id == app_config.solving_model)
+        .or_else(|| get_analysis_model(&app_config))
+        .ok_or_else(|| anyhow!("未配置解题模型"))?;
+    let service = ai_service::create_ai_service(&model.api_url, &model.api_key, &model.model_name)
+        .with_headers(&model.headers)?
+        .with_language(ai_service::PromptLanguage::from_config(&app_config.prompt_language))
+        .with_max_retries(app_config.ai_max_retries)
+        .with_rate_limit(model.requests_per_minute)
+        .with_usage_scope(file_id, "grade");
+
+    let response = service
+        .grade_answer(&question.question_text, &question.answer, &question.analysis, user_answer)
+        .await?;
+    let grade: GradeResponse = parse_json_response(&response)?;
+
+    let score = grade.score.clamp(0.0, 100.0);
+    let verdict = match grade.verdict.as_str() {
+        "correct" | "partially_correct" | "incorrect" => grade.verdict,
+        _ if score >= 90.0 => "correct".to_string(),
+        _ if score > 0.0 => "partially_correct".to_string(),
+        _ => "incorrect".to_string(),
+    };
+
+    Ok(GradeResult {
+        question_id: question.id,
+        score,
+        verdict,
+        rubric: grade.rubric,
+        feedback: grade.feedback,
+        model: model.model_name.clone(),
+    })
+}
+
+/// 翻译符合条件的题目（题干、选项、答案和解析），译文保存在题目的 translations 中；
+/// 已有该语言译文的题目跳过，overwrite 为 true 时重新翻译
+pub async fn translate_questions(
+    app_handle: &AppHandle,
+    file_id: Option<&str>,
+    filter: &QuestionFilter,
+    target_lang: &str,
+    overwrite: bool,
+) -> Result<question_translation::TranslationSummary> {
+    use crate::logger;
+
+    let target_lang = target_lang.trim();
+    if target_lang.is_empty() {
+        return Err(anyhow!("未指定目标语言"));
+    }
+    let questions: Vec<Question> = load_bank_questions(app_handle, file_id)
+        .await?
+        .into_iter()
+        .filter(|q| matches_filter(q, filter))
+        .collect();
+    if questions.is_empty() {
+        return Err(anyhow!("没有符合条件的题目"));
+    }
+    let (pending, existing): (Vec<Question>, Vec<Question>) = questions
+        .into_iter()
+        .partition(|q| overwrite || !q.translations.contains_key(target_lang));
+
+    let app_config = config::get_config_sync(app_handle);
+    let model = app_config
+        .models
+        .iter()
+        .find(|m| m.id == app_config.solving_model)
+        .or_else(|| get_analysis_model(&app_config))
+        .ok_or_else(|| anyhow!("未配置翻译使用的模型"))?;
+    let service = ai_service::create_ai_service(&model.api_url, &model.api_key, &model.model_name)
+        .with_headers(&model.headers)?
+        .with_max_retries(app_config.ai_max_retries)
+        .with_rate_limit(model.requests_per_minute)
+        .with_usage_scope(file_id.unwrap_or_default(), "translate");
+    let language = question_translation::language_name(target_lang);
+    let model_name = model.model_name.clone();
+
+    let pool = ai_service::AIRequestPool::new(app_config.ai_max_parallel as usize);
+    let results = pool
+        .run_all(pending, |q| {
+            let service = service.clone();
+            let language = language.clone();
+            async move {
+                let (request, formulas) = question_translation::build_request(&q);
+                let result = async {
+                    let response = service.translate_question(&serde_json::to_string(&request)?, &language).await?;
+                    let translated = parse_json_response(&response)?;
+                    question_translation::parse_response(&q, translated, &formulas)
+                }
+                .await;
+                (q, result)
+            }
+        })
+        .await;
+
+    let mut summary = question_translation::TranslationSummary {
+        target_lang: target_lang.to_string(),
+        skipped: existing.len(),
+        ..Default::default()
+    };
+    // 按文件分组保存译文
+    let mut by_file: HashMap<String, Vec<(String, question_translation::QuestionTranslation)>> = HashMap::new();
+    for (q, result) in results {
+        match result {
+            Ok(mut translation) => {
+                translation.model = model_name.clone();
+                by_file.entry(q.file_id.clone()).or_default().push((q.id, translation));
+            }
+            Err(e) => {
+                logger::warn("ai", &format!("题目 {} 翻译失败: {}", q.id, e));
+                summary.failed.push(q.id);
+            }
+        }
+    }
+    for (file_id, translations) in by_file {
+        let saved = modify_questions(app_handle, &file_id, "翻译", |questions| {
+            let mut saved = 0;
+            for (question_id, translation) in translations {
+                if let Some(q) = questions.iter_mut().find(|q| q.id == question_id) {
+                    q.translations.insert(target_lang.to_string(), translation);
+                    saved += 1;
+                }
+            }
+            Ok(saved)
+        })?;
+        summary.translated += saved;
+    }
+    logger::info(
+        "ai",
+        &format!(
+            "题目翻译（{}）完成：翻译 {} 道，跳过 {} 道，失败 {} 道",
+            language,
+            summary.translated,
+            summary.skipped,
+            summary.failed.len()
+        ),
+    );
+    Ok(summary)
+}
+
+/// 针对文档提问：从该文件的 RAG 索引中检索相关内容，以流式方式生成回答并注明引用页码
+pub async fn ask_document<F>(app_handle: &AppHandle, file_id: &str, question: &str, on_delta: F) -> Result<DocumentAnswer>
+where
+    F: FnMut(&str) + Send,
+{
+    let question = question.trim();
+    if question.is_empty() {
+        return Err(anyhow!("问题不能为空"));
+    }
+
+    let app_config = config::get_config_sync(app_handle);
+    let mut rag_store = rag_service::RAGStore::new(get_file_storage_path(app_handle, file_id).join("rag_index.db"))
+        .with_embedder(get_embedder(&app_config))
+        .with_reranker(get_reranker(&app_config)?)
+        .with_query_expander(get_query_expander(&app_config)?);
+    if rag_store.is_empty() {
+        return Err(anyhow!("该文件还没有建立索引，请先分析文档"));
+    }
+    // 分析时未配置向量化模型的索引在这里补算向量
+    embed_pending(&mut rag_store).await;
+
+    let model = app_config
+        .models
+        .iter()
+        .find(|m| m.id == app_config.reading_model)
+        .or_else(|| get_analysis_model(&app_config))
+        .ok_or_else(|| anyhow!("未配置模型"))?;
+    let service = ai_service::create_ai_service(&model.api_url, &model.api_key, &model.model_name)
+        .with_headers(&model.headers)?
+        .with_rate_limit(model.requests_per_minute)
+        .with_usage_scope(file_id, "ask");
+
+    let language = ai_service::PromptLanguage::from_config(&app_config.prompt_language).resolve(question);
+    let build_messages = |context: &str| match language {
+        ai_service::PromptLanguage::English => vec![
+            ai_service::ChatMessage::system(
+                "You are a study assistant answering questions about a textbook. Answer using only the reference excerpts below. \
+                 Each excerpt starts with a citation marker such as [file:page]; when you use an excerpt, \
+                 copy its marker verbatim after the sentence that relies on it. \
+                 If the excerpts do not contain the answer, say so.",
+            ),
+            ai_service::ChatMessage::user(format!("Reference excerpts:\n{}\n\nQuestion: {}", context, question)),
+        ],
+        _ => vec![
+            ai_service::ChatMessage::system(
+                "你是一个学习助手，负责回答关于教材内容的问题。请只根据下面的参考内容作答，\
+                 每段参考内容开头都有 [文件:页码] 形式的引用标记，回答中用到某段内容时，\
+                 请在相应句子后原样附上它的引用标记。\
+                 如果参考内容中没有答案，请直接说明。",
+            ),
+            ai_service::ChatMessage::user(format!("参考内容：\n{}\n\n问题：{}", context, question)),
+        ],
+    };
+
+    // 扣除提示词和问题的实际长度，剩余部分留给参考内容
+    let budget = service
+        .context_budget(ai_service::count_message_tokens(&build_messages("")))
+        .min(MAX_ASK_CONTEXT_TOKENS);
+    let (context, sources) = rag_store.build_context_with_sources(question, budget).await;
+
+    let reply = service.chat_stream(build_messages(&context), on_delta).await?;
+
+    Ok(DocumentAnswer {
+        answer: reply.content,
+        reasoning: reply.reasoning,
+        sources,
+    })
+}
+
+/// 批量分析任务记录的保存路径
+fn batch_job_path(app_handle: &AppHandle, file_id: &str) -> PathBuf {
+    get_file_storage_path(app_handle, file_id)
+        .join("questions")
+        .join("batch_job.json")
+}
+
+fn save_batch_job(path: &Path, job: &BatchJob) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(job)?)?;
+    Ok(())
+}
+
+/// 获取文件的批量分析任务
+pub async fn get_batch_job(app_handle: &AppHandle, file_id: &str) -> Result<Option<BatchJob>> {
+    let path = batch_job_path(app_handle, file_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+/// 创建批量分析任务
+/// 把所有页面的例题、习题提取请求写入一个批量任务，由服务端在 24 小时内完成，费用约为实时分析的一半
+/// 批量模式下各页请求同时提交，习题的 RAG 上下文只包含教材内容，不包含其他页面识别出的例题
+pub async fn start_batch_analysis(app_handle: &AppHandle, file_id: &str) -> Result<BatchJob> {
+    use crate::logger;
+
+    if let Some(job) = get_batch_job(app_handle, file_id).await? {
+        if !is_batch_finished(&job.status) {
+            return Err(anyhow!("该文件已有未完成的批量分析任务"));
+        }
+    }
+
+    let file_path = get_file_storage_path(app_handle, file_id);
+    let meta_path = file_path.join("meta.json");
+    if !meta_path.exists() {
+        return Err(anyhow!("文件不存在"));
+    }
+    let meta_content = fs::read_to_string(&meta_path)?;
+    let file_info: crate::commands::FileInfo = serde_json::from_str(&meta_content)?;
+
+    let app_config = config::get_config_sync(app_handle);
+    let model = get_analysis_model(&app_config).ok_or_else(|| anyhow!("未配置分析模型"))?;
+    let client = ai_service::BatchClient::new(&model.api_url, &model.api_key)?.with_headers(&model.headers)?;
+    let service = ai_service::create_ai_service(&model.api_url, &model.api_key, &model.model_name)
+        .with_headers(&model.headers)?;
+
+    let pdf_name = Path::new(&file_info.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let output_dir = mineru_service::get_mineru_output_dir(app_handle, file_id);
+    let layout_pages: HashMap<u32, mineru_layout::LayoutPage> = mineru_layout::load_layout(&output_dir, pdf_name)
+        .map(|pages| pages.into_iter().map(|p| (p.page_number, p)).collect())
+        .unwrap_or_default();
+    let page_images: HashMap<u32, Vec<PathBuf>> = if app_config.ai_send_page_images {
+        mineru_layout::load_page_images(&output_dir, pdf_name)
+    } else {
+        HashMap::new()
+    };
+
+    // 读取所有页面内容并建立 RAG 索引
+    let reranker = get_reranker(&app_config)?;
+    let query_expander = get_query_expander(&app_config)?;
+    let mut rag_store = rag_service::RAGStore::new(file_path.join("rag_index.db"))
+        .with_embedder(get_embedder(&app_config))
+        .with_reranker(reranker.clone())
+        .with_query_expander(query_expander.clone());
+    let mut global_store = rag_service::open_global_store().map(|store| {
+        store
+            .with_embedder(get_embedder(&app_config))
+            .with_reranker(reranker.clone())
+            .with_query_expander(query_expander.clone())
+    });
+    let chunker = rag_service::TextChunker::new(1000, 100);
+    let structure = chapter_structure::load(&file_path.join("questions").join("structure.json")).unwrap_or_default();
+    let mut jobs: Vec<PageJob> = Vec::new();
+    for page in 1..=file_info.total_pages {
+        let markdown_content = ocr_service::convert_page_to_markdown(app_handle, file_id, page)
+            .await
+            .unwrap_or_default();
+        if markdown_content.trim().is_empty() {
+            continue;
+        }
+
+        begin_store_batch(&mut rag_store, global_store.as_mut());
+        for doc in knowledge_documents(&chunker, file_id, page, &markdown_content, &structure) {
+            add_to_stores(&mut rag_store, global_store.as_mut(), doc);
+        }
+        commit_store_batch(&mut rag_store, global_store.as_mut());
+
+        let analysis_content = layout_pages
+            .get(&page)
+            .filter(|p| !p.blocks.is_empty())
+            .map(|p| p.to_segmented_markdown())
+            .unwrap_or_else(|| markdown_content.clone());
+
+        jobs.push(PageJob {
+            page,
+            markdown_content,
+            analysis_content,
+            sections: Vec::new(),
+            images: page_images
+                .get(&page)
+                .map(|images| images.iter().take(MAX_IMAGES_PER_PAGE).cloned().collect())
+                .unwrap_or_default(),
+            has_examples: true,
+            has_exercises: true,
+        });
+    }
+
+    if jobs.is_empty() {
+        return Err(anyhow!("没有可分析的页面内容，请先转换文档"));
+    }
+
+    embed_pending(&mut rag_store).await;
+    if let Some(global) = global_store.as_mut() {
+        embed_pending(global).await;
+    }
+    
+    // 生成批量任务输入文件
+    let budget = service.input_budget();
+    let language = ai_service::PromptLanguage::from_config(&app_config.prompt_language);
+    let mut lines = Vec::new();
+    let mut references = HashMap::new();
+    for job in jobs.iter_mut() {
+        job.sections = page_sections(job.page, &job.analysis_content, &job.images, budget, language, |tokens| {
+            service.context_budget(tokens)
+        });
+
+        let mut page_references: Vec<rag_service::Citation> = Vec::new();
+        for (index, section) in job.sections.iter().enumerate() {
+            let query = if job.sections.len() > 1 { &section.content } else { &job.markdown_content };
+            let (context, citations) = rag_service::build_context_with_global(
+                &rag_store,
+                global_store.as_ref(),
+                file_id,
+                query,
+                section.context_budget,
+                EXERCISE_CONTEXT_QUOTAS,
+            )
+            .await;
+            for citation in citations {
+                if !page_references.iter().any(|c| c.marker == citation.marker) {
+                    page_references.push(citation);
+                }
+            }
+            // 拆分的页面在 custom_id 末尾加上段号
+            let suffix = if index > 0 { format!("-{}", index) } else { String::new() };
+            lines.push(client.request_line(
+                &format!("page-{}-examples{}", job.page, suffix),
+                &service.json_request(ai_service::examples_messages(&section.content, &job.images, language)),
+            )?);
+            lines.push(client.request_line(
+                &format!("page-{}-exercises{}", job.page, suffix),
+                &service.json_request(ai_service::exercises_messages(
+                    &section.content,
+                    &context,
+                    &job.images,
+                    language,
+                )),
+            )?);
+        }
+        references.insert(job.page, page_references);
+    }
+
+    logger::info(
+        "ai",
+        &format!("创建批量分析任务：{} 页，{} 个请求，模型 {}", jobs.len(), lines.len(), model.model_name),
+    );
+
+    let input_file_id = client.upload(lines.join("\n")).await?;
+    let status = client.create(&input_file_id).await?;
+
+    let job = BatchJob {
+        file_id: file_id.to_string(),
+        batch_id: status.id.clone(),
+        model_id: model.id.clone(),
+        model_name: model.model_name.clone(),
+        status: status.status.clone(),
+        total_requests: lines.len() as u64,
+        completed_requests: 0,
+        failed_requests: 0,
+        questions_found: 0,
+        created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        finished_at: None,
+        error: None,
+        references,
+    };
+    save_batch_job(&batch_job_path(app_handle, file_id), &job)?;
+
+    Ok(job)
+}
+
+/// 查询批量分析任务状态，任务完成后导入结果
+pub async fn poll_batch_analysis(app_handle: &AppHandle, file_id: &str) -> Result<BatchJob> {
+    use crate::logger;
+
+    let mut job = get_batch_job(app_handle, file_id)
+        .await?
+        .ok_or_else(|| anyhow!("没有批量分析任务"))?;
+    if is_batch_finished(&job.status) {
+        return Ok(job);
+    }
+
+    let client = batch_client_for(app_handle, &job)?;
+    let status = client.retrieve(&job.batch_id).await?;
+    if let Some(counts) = &status.request_counts {
+        job.total_requests = counts.total.max(job.total_requests);
+        job.completed_requests = counts.completed;
+        job.failed_requests = counts.failed;
+    }
+    job.status = status.status.clone();
+
+    if status.status == "completed" {
+        let mut results = match &status.output_file_id {
+            Some(file_id) => client.download_results(file_id).await?,
+            None => Vec::new(),
+        };
+        if let Some(error_file_id) = &status.error_file_id {
+            results.extend(client.download_results(error_file_id).await?);
+        }
+
+        let questions = import_batch_results(app_handle, &job, results)?;
+        job.questions_found = questions.len() as u32;
+        job.status = "imported".to_string();
+        logger::info(
+            "ai",
+            &format!("批量分析任务 {} 已完成，导入 {} 道题目", job.batch_id, job.questions_found),
+        );
+    } else if status.is_finished() {
+        job.error = Some(format!("批量任务状态: {}", status.status));
+        logger::warn("ai", &format!("批量分析任务 {} 未完成: {}", job.batch_id, status.status));
+    }
+
+    if is_batch_finished(&job.status) {
+        job.finished_at = Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+    save_batch_job(&batch_job_path(app_handle, file_id), &job)?;
+
+    Ok(job)
+}
+
+/// 在后台定期查询批量任务状态，直到任务结束；同一文件只会有一个查询任务
+/// 连续查询失败 MAX_BATCH_POLL_ERRORS 次后把任务标记为失败
+pub fn spawn_batch_polling(app_handle: &AppHandle, file_id: &str) {
+    use crate::logger;
+
+    if !BATCH_POLLERS.lock().unwrap().insert(file_id.to_string()) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    let file_id = file_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let mut errors = 0;
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(BATCH_POLL_INTERVAL_SECS)).await;
+            let job = match poll_batch_analysis(&app_handle, &file_id).await {
+                Ok(job) => {
+                    errors = 0;
+                    job
+                }
+                Err(e) => {
+                    errors += 1;
+                    logger::warn(
+                        "ai",
+                        &format!("查询批量分析任务失败（{}/{}）: {}", errors, MAX_BATCH_POLL_ERRORS, e),
+                    );
+                    if errors < MAX_BATCH_POLL_ERRORS {
+                        continue;
+                    }
+                    match fail_batch_job(&app_handle, &file_id, &format!("连续 {} 次查询任务状态失败: {}", errors, e)) {
+                        Ok(job) => job,
+                        Err(e) => {
+                            logger::warn("ai", &format!("保存批量分析任务失败: {}", e));
+                            break;
+                        }
+                    }
+                }
+            };
+            let _ = app_handle.emit_all("batch-analysis-progress", &job);
+            if is_batch_finished(&job.status) {
+                break;
+            }
+        }
+        BATCH_POLLERS.lock().unwrap().remove(&file_id);
+    });
+}
+
+/// 继续查询上次退出时还未结束的批量任务（在应用启动时调用）
+pub fn resume_batch_polling(app_handle: &AppHandle) {
+    let root = crate::file_manager::get_storage_root(app_handle);
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let content = match fs::read_to_string(entry.path().join("questions").join("batch_job.json")) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        if let Ok(job) = serde_json::from_str::<BatchJob>(&content) {
+            if !is_batch_finished(&job.status) {
+                spawn_batch_polling(app_handle, &job.file_id);
+            }
+        }
+    }
+}
+
+/// 把批量任务标记为失败
+fn fail_batch_job(app_handle: &AppHandle, file_id: &str, error: &str) -> Result<BatchJob> {
+    let path = batch_job_path(app_handle, file_id);
+    let mut job: BatchJob = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    job.status = "failed".to_string();
+    job.error = Some(error.to_string());
+    job.finished_at = Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+    save_batch_job(&path, &job)?;
+    Ok(job)
+}
+
+/// 取消批量分析任务
+pub async fn cancel_batch_analysis(app_handle: &AppHandle, file_id: &str) -> Result<BatchJob> {
+    let mut job = get_batch_job(app_handle, file_id)
+        .await?
+        .ok_or_else(|| anyhow!("没有批量分析任务"))?;
+    if is_batch_finished(&job.status) {
+        return Ok(job);
+    }
+
+    let client = batch_client_for(app_handle, &job)?;
+    let status = client.cancel(&job.batch_id).await?;
+    job.status = status.status;
+    save_batch_job(&batch_job_path(app_handle, file_id), &job)?;
+
+    Ok(job)
+}
+
+/// 批量任务是否已结束（不会再变化）
+pub fn is_batch_finished(status: &str) -> bool {
+    matches!(status, "imported" | "failed" | "expired" | "cancelled")
+}
+
+/// 使用创建任务时的模型配置连接批量接口
+fn batch_client_for(app_handle: &AppHandle, job: &BatchJob) -> Result<ai_service::BatchClient> {
+    let app_config = config::get_config_sync(app_handle);
+    let model = app_config
+        .models
+        .iter()
+        .find(|m| m.id == job.model_id)
+        .ok_or_else(|| anyhow!("创建批量任务的模型已被删除"))?;
+    ai_service::BatchClient::new(&model.api_url, &model.api_key)?.with_headers(&model.headers)
+}
+
+/// 解析批量任务结果并保存题目
+fn import_batch_results(
+    app_handle: &AppHandle,
+    job: &BatchJob,
+    results: Vec<ai_service::BatchResult>,
+) -> Result<Vec<Question>> {
+    use crate::{ai_usage, logger};
+
+    let app_config = config::get_config_sync(app_handle);
+    let questions_dir = get_file_storage_path(app_handle, &job.file_id).join("questions");
+    fs::create_dir_all(&questions_dir)?;
+    let structure = chapter_structure::load(&questions_dir.join("structure.json")).unwrap_or_default();
+    let scope = ai_usage::UsageScope {
+        file_id: job.file_id.clone(),
+        run_id: job.batch_id.clone(),
+    };
+
+    // custom_id 格式为 page-{页码}-{examples|exercises}[-{段号}]，按页码排序，同一页例题在前
+    let mut entries: Vec<(u32, String, usize, ai_service::BatchResult)> = results
+        .into_iter()
+        .filter_map(|result| {
+            let mut parts = result.custom_id.split('-');
+            let (_, page, kind) = (parts.next()?, parts.next()?.parse().ok()?, parts.next()?.to_string());
+            let index = parts.next().and_then(|i| i.parse().ok()).unwrap_or(0);
+            Some((page, kind, index, result))
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)).then_with(|| a.2.cmp(&b.2)));
+
+    let mut all_questions: Vec<Question> = Vec::new();
+    let mut failed_pages: std::collections::HashSet<u32> = std::collections::HashSet::new();
+    for (page, kind, index, result) in entries {
+        if let Some(usage) = result.usage {
+            ai_usage::record_batch(Some(&scope), &job.model_name, usage);
+        }
+        let reply = match result.result {
+            Ok(reply) => reply,
+            Err(e) => {
+                logger::warn("ai", &format!("第 {} 页{}批量请求失败: {}", page, kind, e));
+                add_failed_page(&questions_dir, page, &kind, "request_error", &e.to_string());
+                failed_pages.insert(page);
+                continue;
+            }
+        };
+
+        let parsed = if kind == "examples" {
+            parse_examples_response(&reply.content, &job.file_id, page)
+        } else {
+            parse_exercises_response(&reply.content, &job.file_id, page)
+        };
+        match parsed {
+            Ok(questions) => {
+                for mut q in questions {
+                    q.model = job.model_name.clone();
+                    set_section_id(&mut q, index);
+                    if kind == "exercises" {
+                        q.references = job.references.get(&page).cloned().unwrap_or_default();
+                    }
+                    apply_structure(&mut q, &structure);
+                    apply_reasoning(&mut q, &reply, app_config.store_reasoning_as_analysis);
+                    all_questions.push(q);
+                }
+            }
+            Err(e) => {
+                failed_pages.insert(page);
+                record_parse_failure(&questions_dir, &job.file_id, page, &kind, &reply.content, &e);
+            }
+        }
+    }
+
+    knowledge_points::normalize_questions(&mut all_questions);
+    attach_cached_page_images(app_handle, &job.file_id, &mut all_questions);
+    let existing: Vec<Question> = fs::read_to_string(questions_dir.join("all_questions.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    // 请求或解析失败的页面保留原有题目，可以在失败页面中重试
+    all_questions.retain(|q| !failed_pages.contains(&q.page_number));
+    all_questions.extend(existing.iter().filter(|q| failed_pages.contains(&q.page_number)).cloned());
+    all_questions.sort_by_key(|q| q.page_number);
+    fs::write(
+        questions_dir.join("all_questions.json"),
+        serde_json::to_string_pretty(&all_questions)?,
+    )?;
+    sync_questions_to_database(app_handle, &job.file_id, &questions_dir.join("all_questions.json"), &all_questions);
+    record_history(
+        &questions_dir,
+        &job.file_id,
+        &existing,
+        &all_questions,
+        &format!("ai:{}", job.model_name),
+        "批量分析",
+        false,
+    );
+
+    Ok(all_questions)
+}
+
+/// 更新进度
+fn update_progress(
+    file_id: &str,
+    status: &str,
+    current_page: u32,
+    total_pages: u32,
+    message: &str,
+    questions_found: u32,
+) {
+    let mut states = ANALYSIS_STATE.lock().unwrap();
+    if let Some(state) = states.get_mut(file_id) {
+        state.progress.status = status.to_string();
+        state.progress.current_page = current_page;
+        state.progress.total_pages = total_pages;
+        state.progress.message = message.to_string();
+        state.progress.questions_found = questions_found;
+        state.emit_progress();
+        // 分析结束时保存进度，重启后仍能看到上次分析的结果
+        if !matches!(status, "analyzing" | "stopping") {
+            if let Err(e) = database::save_analysis_state(&state.progress) {
+                crate::logger::warn("system", &format!("保存分析状态失败: {}", e));
+            }
+        }
+    }
+}
+
+/// 设置已处理完的页数（继续分析时从检查点恢复）
+fn set_pages_completed(file_id: &str, pages_completed: u32) {
+    let mut states = ANALYSIS_STATE.lock().unwrap();
+    if let Some(state) = states.get_mut(file_id) {
+        state.progress.pages_completed = pages_completed;
+        state.emit_progress();
+    }
+}
+
+/// 一页处理完成后更新进度
+fn mark_page_completed(file_id: &str, page: u32, page_count: u32) {
+    let mut states = ANALYSIS_STATE.lock().unwrap();
+    if let Some(state) = states.get_mut(file_id) {
+        state.progress.pages_completed = (state.progress.pages_completed + 1).min(page_count);
+        state.progress.current_page = page;
+        state.progress.message = format!("已完成 {} / {} 页", state.progress.pages_completed, page_count);
+        state.emit_progress();
+    }
+}
+
+/// 更新当前步骤和已发现的题目数
+fn update_step(file_id: &str, step: &str, questions_found: u32) {
+    let mut states = ANALYSIS_STATE.lock().unwrap();
+    if let Some(state) = states.get_mut(file_id) {
+        state.progress.current_step = step.to_string();
+        state.progress.questions_found = questions_found;
+        state.emit_progress();
+    }
+}
+
+/// 页面 Markdown 中图片相对路径的基准目录：OCR 输出目录和 MinerU 整本转换的输出目录
+fn image_search_dirs(app_handle: &AppHandle, file_id: &str, file_path: &Path, pdf_name: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![file_path.join("markdown")];
+    if let Some(dir) = mineru_service::find_output_dir(&mineru_service::get_mineru_output_dir(app_handle, file_id), pdf_name) {
+        dirs.push(dir);
+    }
+    dirs
+}
+
+/// 按已缓存的页面 Markdown 为题目附加插图（批量分析导入结果时使用）
+fn attach_cached_page_images(app_handle: &AppHandle, file_id: &str, questions: &mut [Question]) {
+    let file_path = get_file_storage_path(app_handle, file_id);
+    let file_info: crate::commands::FileInfo = match fs::read_to_string(file_path.join("meta.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        Some(info) => info,
+        None => return,
+    };
+    let pdf_name = Path::new(&file_info.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let image_dirs = image_search_dirs(app_handle, file_id, &file_path, pdf_name);
+
+    let mut pages: Vec<u32> = questions.iter().map(|q| q.page_number).collect();
+    pages.sort_unstable();
+    pages.dedup();
+    for page in pages {
+        if let Some(markdown) = ocr_service::cached_page_markdown(app_handle, file_id, page) {
+            let mut page_questions: Vec<&mut Question> = questions.iter_mut().filter(|q| q.page_number == page).collect();
+            question_images::attach_page_images(
+                &mut page_questions,
+                &markdown,
+                &image_dirs,
+                &file_path.join("questions").join("images"),
+                &file_path,
+            );
+        }
+    }
+}
+
+/// 使用章节结构中该页所属的章节和小节；结构中没有小节时保留模型给出的小节
+fn apply_structure(question: &mut Question, structure: &chapter_structure::ChapterTree) {
+    if let Some((chapter, section)) = structure.lookup(question.page_number) {
+        if chapter != question.chapter {
+            question.chapter = chapter;
+            if section.is_empty() {
+                question.section.clear();
+            }
+        }
+        if !section.is_empty() {
+            question.section = section;
+        }
+    }
+}
+
+/// 读取目录和前几页，用 AI 建立章节树，并在已转换的页面中定位各章节的起始页
+/// 请求或解析失败时返回空结构（不保存，下次分析时重试）
+async fn build_chapter_structure(
+    app_handle: &AppHandle,
+    file_id: &str,
+    chain: &ai_service::FallbackChain,
+    total_pages: u32,
+    structure_path: &Path,
+) -> chapter_structure::ChapterTree {
+    use crate::logger;
+
+    let mut text = String::new();
+    for page in 1..=total_pages.min(STRUCTURE_PREPASS_PAGES) {
+        let markdown = ocr_service::convert_page_to_markdown(app_handle, file_id, page)
+            .await
+            .unwrap_or_default();
+        if !markdown.trim().is_empty() {
+            text.push_str(&format!("--- 第 {} 页 ---\n{}\n\n", page, markdown));
+        }
+    }
+    if text.trim().is_empty() {
+        return chapter_structure::ChapterTree::default();
+    }
+    let text = ai_service::truncate_to_tokens(&text, chain.input_budget());
+
+    let (result, _) = chain
+        .run(|service| {
+            let text = text.clone();
+            async move { service.extract_structure(&text).await }
+        })
+        .await;
+    let mut structure = match result.and_then(|response| chapter_structure::ChapterTree::parse(&response)) {
+        Ok(structure) => structure,
+        Err(e) => {
+            logger::warn("ai", &format!("识别章节结构失败，使用模型逐页判断的章节: {}", e));
+            return chapter_structure::ChapterTree::default();
+        }
+    };
+
+    // 已转换的页面中定位章节起始页，其余页面在分析时定位
+    for page in 1..=total_pages {
+        if let Some(markdown) = ocr_service::cached_page_markdown(app_handle, file_id, page) {
+            structure.locate_in_page(page, &markdown);
+        }
+    }
+    logger::info("ai", &format!("识别到 {} 个章节", structure.chapters.len()));
+    if let Err(e) = chapter_structure::save(structure_path, &structure) {
+        logger::warn("ai", &format!("保存章节结构失败: {}", e));
+    }
+    structure
+}
+
+/// 推理模型的思考过程：开启设置后作为题目解析保存
+/// 思考过程针对整页内容，模型没有给出解析时也会用它补充
+fn apply_reasoning(question: &mut Question, reply: &ai_service::ChatReply, store_as_analysis: bool) {
+    let reasoning = match reply.reasoning.as_deref() {
+        Some(reasoning) if !reasoning.trim().is_empty() => reasoning,
+        _ => return,
+    };
+    if store_as_analysis || question.analysis.trim().is_empty() {
+        question.analysis = reasoning.to_string();
+    }
+}
+
+/// 是否已请求停止分析
+fn should_stop(file_id: &str) -> bool {
+    let states = ANALYSIS_STATE.lock().unwrap();
+    states.get(file_id).map(|s| s.should_stop).unwrap_or(false)
+}
+
+/// 文件是否正在分析
+pub fn is_analyzing(file_id: &str) -> bool {
+    let states = ANALYSIS_STATE.lock().unwrap();
+    states.get(file_id).is_some_and(|s| is_active_status(&s.progress.status))
+}
+
+/// 是否有文件正在分析
+pub fn any_analyzing() -> bool {
+    let states = ANALYSIS_STATE.lock().unwrap();
+    states.values().any(|s| is_active_status(&s.progress.status))
+}
+
+/// 处理 AI 请求错误
+/// 认证失败、额度不足等无法恢复的错误会中止分析，其他错误记录后跳过当前页
+fn handle_ai_error(
+    file_id: &str,
+    page: u32,
+    total_pages: u32,
+    questions_found: u32,
+    error: &anyhow::Error,
+) -> Result<()> {
+    use crate::logger;
+
+    match error.downcast_ref::<ai_service::AIError>() {
+        Some(ai_error) if ai_error.is_fatal() => {
+            logger::error("ai", &format!("第 {} 页分析失败，停止分析: {}", page, ai_error));
+            update_progress(file_id, "error", page, total_pages, &ai_error.to_string(), questions_found);
+            Err(anyhow!("{}", ai_error))
+        }
+        Some(ai_service::AIError::Cancelled) => Ok(()),
+        Some(ai_error @ ai_service::AIError::ContextTooLong(_)) => {
+            logger::warn("ai", &format!("第 {} 页内容过长，已跳过: {}", page, ai_error));
+            Ok(())
+        }
+        _ => {
+            logger::warn("ai", &format!("第 {} 页分析失败: {}", page, error));
+            Ok(())
+        }
+    }
+}
+
+/// 习题提示词（不含参考上下文）的 token 数，图片按估算值计入
+fn exercises_prompt_tokens(content: &str, images: &[PathBuf], language: ai_service::PromptLanguage) -> usize {
+    ai_service::count_message_tokens(&ai_service::exercises_messages(content, "", &[], language))
+        + images.len() * ai_service::IMAGE_TOKENS
+}
+
+/// 根据配置创建向量化服务，未配置时返回 None（使用关键词检索）
+fn get_embedder(config: &crate::commands::AppConfig) -> Option<ai_service::EmbeddingService> {
+    let embedding = &config.embedding;
+    if embedding.api_url.trim().is_empty() || embedding.model_name.trim().is_empty() {
+        return None;
+    }
+    Some(
+        ai_service::EmbeddingService::new(&embedding.api_url, &embedding.api_key, &embedding.model_name)
+            .with_batch_size(embedding.batch_size)
+            .with_max_retries(config.ai_max_retries),
+    )
+}
+
+/// 将页面内容切分为知识点文档
+fn knowledge_documents(
+    chunker: &rag_service::TextChunker,
+    file_id: &str,
+    page: u32,
+    markdown_content: &str,
+    structure: &chapter_structure::ChapterTree,
+) -> Vec<rag_service::Document> {
+    let (chapter, section) = structure.lookup(page).unwrap_or_default();
+    chunker
+        .chunk_by_paragraph(markdown_content)
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| rag_service::Document {
+            id: format!("{}_{}_{}", file_id, page, i),
+            content: chunk,
+            metadata: rag_service::DocumentMetadata {
+                file_id: file_id.to_string(),
+                page_number: page,
+                chunk_index: i as u32,
+                doc_type: "knowledge".to_string(),
+                chapter: chapter.clone(),
+                section: section.clone(),
+            },
+            embedding: None,
+            embedding_model: String::new(),
+        })
+        .collect()
+}
+
+/// 例题文档
+fn example_document(q: &Question) -> rag_service::Document {
+    rag_service::Document {
+        id: q.id.clone(),
+        content: format!("题目：{}\n答案：{}", q.question_text, q.answer),
+        metadata: rag_service::DocumentMetadata {
+            file_id: q.file_id.clone(),
+            page_number: q.page_number,
+            chunk_index: 0,
+            doc_type: "example".to_string(),
+            chapter: q.chapter.clone(),
+            section: q.section.clone(),
+        },
+        embedding: None,
+        embedding_model: String::new(),
+    }
+}
+
+/// 添加文档到本文件的 RAG 索引，启用全局知识库时同时加入全局知识库
+fn add_to_stores(
+    rag_store: &mut rag_service::RAGStore,
+    global_store: Option<&mut rag_service::RAGStore>,
+    doc: rag_service::Document,
+) {
+    if let Some(global) = global_store {
+        global.add_document(doc.clone());
+    }
+    rag_store.add_document(doc);
+}
+
+/// 本文件和全局知识库同时开始批量写入
+fn begin_store_batch(rag_store: &mut rag_service::RAGStore, global_store: Option<&mut rag_service::RAGStore>) {
+    if let Some(global) = global_store {
+        global.begin_batch();
+    }
+    rag_store.begin_batch();
+}
+
+/// 提交本文件和全局知识库的批量写入
+fn commit_store_batch(rag_store: &mut rag_service::RAGStore, global_store: Option<&mut rag_service::RAGStore>) {
+    if let Some(global) = global_store {
+        global.commit();
+    }
+    rag_store.commit();
+}
+
+/// 根据配置创建重排序模型，未配置时返回 None
+fn get_reranker(config: &crate::commands::AppConfig) -> Result<Option<ai_service::AIService>> {
+    get_retrieval_model(config, &config.rerank_model)
+}
+
+/// 根据配置创建查询扩展模型，未配置时返回 None
+fn get_query_expander(config: &crate::commands::AppConfig) -> Result<Option<ai_service::AIService>> {
+    get_retrieval_model(config, &config.query_expansion_model)
+}
+
+/// 创建检索辅助模型（重排序、查询扩展），只重试一次，失败时检索会跳过这一步
+fn get_retrieval_model(
+    config: &crate::commands::AppConfig,
+    model_id: &str,
+) -> Result<Option<ai_service::AIService>> {
+    if model_id.is_empty() {
+        return Ok(None);
+    }
+    let model = match config.models.iter().find(|m| m.id == model_id) {
+        Some(model) => model,
+        None => return Ok(None),
+    };
+    let service = ai_service::create_ai_service(&model.api_url, &model.api_key, &model.model_name)
+        .with_headers(&model.headers)?
+        .with_rate_limit(model.requests_per_minute)
+        .with_max_retries(1);
+    Ok(Some(service))
+}
+
+/// 为还没有向量的文档计算向量，失败时记录日志，检索会回退到关键词匹配
+async fn embed_pending(rag_store: &mut rag_service::RAGStore) {
+    use crate::logger;
+
+    if let Err(e) = rag_store.embed_pending().await {
+        logger::warn("ai", &format!("计算文档向量失败: {}", e));
+    }
+}
+
+/// 获取分析模型配置
+fn get_analysis_model(config: &crate::commands::AppConfig) -> Option<&crate::commands::ModelConfig> {
+    config
+        .models
+        .iter()
+        .find(|m| m.id == config.analysis_model)
+        .or_else(|| config.models.first())
+}
+
+/// 获取阅读模型配置，未配置时返回 None
+fn get_reading_model(config: &crate::commands::AppConfig) -> Option<&crate::commands::ModelConfig> {
+    if config.reading_model.is_empty() {
+        return None;
+    }
+    config.models.iter().find(|m| m.id == config.reading_model)
+}
+
+/// 创建分析用的 AI 服务：计入本次分析的用量，停止分析时中止请求
+fn analysis_service(
+    model: &crate::commands::ModelConfig,
+    app_config: &crate::commands::AppConfig,
+    file_id: &str,
+    run_id: &str,
+    cancel_token: &ai_service::CancelToken,
+) -> Result<ai_service::AIService> {
+    let service = ai_service::create_ai_service(&model.api_url, &model.api_key, &model.model_name)
+        .with_headers(&model.headers)?
+        .with_language(ai_service::PromptLanguage::from_config(&app_config.prompt_language))
+        .with_max_retries(app_config.ai_max_retries)
+        .with_rate_limit(model.requests_per_minute)
+        .with_usage_scope(file_id, run_id)
+        .with_cancel_token(cancel_token.clone());
+    Ok(if app_config.ai_request_timeout_secs > 0 {
+        service.with_timeout(std::time::Duration::from_secs(app_config.ai_request_timeout_secs))
+    } else {
+        service
+    })
+}
+
+/// 获取分析模型及其备用模型，按尝试顺序排列
+fn get_analysis_models(config: &crate::commands::AppConfig) -> Vec<&crate::commands::ModelConfig> {
+    let mut models: Vec<&crate::commands::ModelConfig> = get_analysis_model(config).into_iter().collect();
+    for id in &config.analysis_fallback_models {
+        if let Some(model) = config.models.iter().find(|m| &m.id == id) {
+            if !models.iter().any(|m| m.id == model.id) {
+                models.push(model);
+            }
+        }
+    }
+    models
+}
+
+/// 拆分页面内容并计算各段的参考上下文长度
+/// context_budget 根据提示词占用的 token 数返回剩余可用长度
+fn page_sections(
+    page: u32,
+    content: &str,
+    images: &[PathBuf],
+    budget: usize,
+    language: ai_service::PromptLanguage,
+    context_budget: impl Fn(usize) -> usize,
+) -> Vec<PageSection> {
+    use crate::logger;
+
+    let page_tokens = ai_service::count_tokens(content);
+    let parts = if page_tokens > budget {
+        // 每段预留参考上下文的长度，避免拆分后习题仍然没有上下文可用
+        let section_budget = budget.saturating_sub(MAX_RAG_CONTEXT_TOKENS.min(budget / 2)).max(1);
+        let parts = split_page_content(content, page_tokens, section_budget);
+        logger::info(
+            "ai",
+            &format!(
+                "第 {} 页内容约 {} tokens，超出模型可用长度 {}，拆分为 {} 段识别",
+                page,
+                page_tokens,
+                budget,
+                parts.len()
+            ),
+        );
+        parts
+    } else {
+        vec![content.to_string()]
+    };
+    parts
+        .into_iter()
+        .map(|content| PageSection {
+            // 扣除实际的习题提示词（含页面内容和图片）长度，剩余部分留给参考上下文
+            context_budget: context_budget(exercises_prompt_tokens(&content, images, language)).min(MAX_RAG_CONTEXT_TOKENS),
+            content,
+        })
+        .collect()
+}
+
+/// 按段落拆分超长的页面内容，每段不超过 budget 个 token
+/// 按实际 token 数换算每段的长度，单个段落仍然超长时再按字符切分
+fn split_page_content(content: &str, page_tokens: usize, budget: usize) -> Vec<String> {
+    let ratio = budget as f64 / page_tokens.max(1) as f64 * 0.9;
+    let paragraph_bytes = ((content.len() as f64 * ratio) as usize).max(1);
+    let chunk_chars = ((content.chars().count() as f64 * ratio) as usize).max(1);
+
+    let mut sections = Vec::new();
+    for chunk in rag_service::TextChunker::new(paragraph_bytes, 0).chunk_by_paragraph(content) {
+        if ai_service::count_tokens(&chunk) <= budget {
+            sections.push(chunk);
+            continue;
+        }
+        for piece in rag_service::TextChunker::new(chunk_chars, 0).chunk(&chunk) {
+            sections.push(ai_service::truncate_to_tokens(&piece, budget));
+        }
+    }
+    sections
+}
+
+/// 拆分页面中后续各段的题目 ID 加上段号，避免与第一段的题目重复
+fn set_section_id(question: &mut Question, index: usize) {
+    if index > 0 {
+        question.id = format!("{}_s{}", question.id, index);
+    }
+}
+
+/// 解析例题响应
+fn parse_examples_response(json_str: &str, file_id: &str, page: u32) -> Result<Vec<Question>> {
     #[derive(Deserialize)]
     struct ExamplesResponse {
         examples: Vec<ExampleItem>,
@@ -333,12 +3711,15 @@ fn parse_examples_response(json_str: &str, file_id: &str, page: u32) -> Result<V
         knowledge_points: Option<Vec<String>>,
         chapter: Option<String>,
         section: Option<String>,
+        confidence: Option<f32>,
+        #[serde(default)]
+        options: Vec<question_options::ChoiceOption>,
+        #[serde(default)]
+        correct_options: Vec<String>,
+        difficulty: Option<String>,
     }
     
-    // 尝试提取 JSON
-    let json_str = extract_json(json_str);
-    
-    let response: ExamplesResponse = serde_json::from_str(&json_str)?;
+    let response: ExamplesResponse = parse_json_response(json_str)?;
     
     let questions: Vec<Question> = response
         .examples
@@ -356,6 +3737,19 @@ fn parse_examples_response(json_str: &str, file_id: &str, page: u32) -> Result<V
             analysis: item.analysis.unwrap_or_default(),
             page_number: page,
             has_original_answer: true,
+            model: String::new(),
+            references: Vec::new(),
+            images: Vec::new(),
+            confidence: item.confidence.and_then(normalize_confidence),
+            options: item.options,
+            correct_options: item.correct_options,
+            difficulty: normalize_difficulty(item.difficulty.as_deref().unwrap_or_default()),
+            review_status: "pending".to_string(),
+            translations: BTreeMap::new(),
+        })
+        .map(|mut q| {
+            question_options::apply_options(&mut q);
+            q
         })
         .collect();
     
@@ -377,12 +3771,15 @@ fn parse_exercises_response(json_str: &str, file_id: &str, page: u32) -> Result<
         knowledge_points: Option<Vec<String>>,
         chapter: Option<String>,
         section: Option<String>,
+        confidence: Option<f32>,
+        #[serde(default)]
+        options: Vec<question_options::ChoiceOption>,
+        #[serde(default)]
+        correct_options: Vec<String>,
+        difficulty: Option<String>,
     }
     
-    // 尝试提取 JSON
-    let json_str = extract_json(json_str);
-    
-    let response: ExercisesResponse = serde_json::from_str(&json_str)?;
+    let response: ExercisesResponse = parse_json_response(json_str)?;
     
     let questions: Vec<Question> = response
         .exercises
@@ -400,19 +3797,151 @@ fn parse_exercises_response(json_str: &str, file_id: &str, page: u32) -> Result<
             analysis: item.analysis.unwrap_or_default(),
             page_number: page,
             has_original_answer: false,
+            model: String::new(),
+            references: Vec::new(),
+            images: Vec::new(),
+            confidence: item.confidence.and_then(normalize_confidence),
+            options: item.options,
+            correct_options: item.correct_options,
+            difficulty: normalize_difficulty(item.difficulty.as_deref().unwrap_or_default()),
+            review_status: "pending".to_string(),
+            translations: BTreeMap::new(),
+        })
+        .map(|mut q| {
+            question_options::apply_options(&mut q);
+            q
         })
         .collect();
     
     Ok(questions)
 }
 
-/// 从字符串中提取 JSON
-fn extract_json(text: &str) -> String {
-    // 尝试找到 JSON 对象
-    if let Some(start) = text.find('{') {
-        if let Some(end) = text.rfind('}') {
-            return text[start..=end].to_string();
-        }
+/// 规范化模型给出的置信度：按 0~1 处理，给出百分数（如 85）时换算，无效值视为未给出
+fn normalize_confidence(value: f32) -> Option<f32> {
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    let value = if value > 1.0 { value / 100.0 } else { value };
+    Some(value.min(1.0))
+}
+
+/// 规范化难度，只保留 "easy"、"medium"、"hard"（兼容中文写法）
+fn normalize_difficulty(value: &str) -> String {
+    match value.trim().to_lowercase().as_str() {
+        "easy" | "简单" | "容易" => "easy",
+        "medium" | "中等" | "一般" => "medium",
+        "hard" | "difficult" | "困难" | "难" => "hard",
+        _ => "",
+    }
+    .to_string()
+}
+
+/// 解析模型返回的 JSON
+/// JSON 模式下响应本身就是 JSON；否则去掉代码块等多余内容并修复常见格式错误后再解析
+fn parse_json_response<T: serde::de::DeserializeOwned>(text: &str) -> Result<T> {
+    json_repair::parse_relaxed(text)
+}
+
+/// 记录一次解析失败：保存原始响应以便排查，并累计本次分析的失败次数
+fn record_parse_failure(questions_dir: &Path, file_id: &str, page: u32, kind: &str, raw: &str, error: &anyhow::Error) {
+    use crate::logger;
+
+    logger::warn("ai", &format!("第 {} 页{}响应解析失败: {}", page, kind, error));
+    add_failed_page(questions_dir, page, kind, "parse_error", &error.to_string());
+
+    let failed_dir = questions_dir.join("failed_responses");
+    if fs::create_dir_all(&failed_dir).is_ok() {
+        let _ = fs::write(failed_dir.join(format!("{:04}_{}.txt", page, kind)), raw);
+    }
+
+    let mut states = ANALYSIS_STATE.lock().unwrap();
+    if let Some(state) = states.get_mut(file_id) {
+        state.progress.parse_failures += 1;
+        state.emit_progress();
+    }
+}
+
+/// 获取上次分析中失败的页面，按页码排序
+pub fn get_failed_pages(app_handle: &AppHandle, file_id: &str) -> Result<Vec<FailedPage>> {
+    let questions_dir = get_file_storage_path(app_handle, file_id).join("questions");
+    Ok(load_failed_pages(&questions_dir))
+}
+
+fn load_failed_pages(questions_dir: &Path) -> Vec<FailedPage> {
+    fs::read_to_string(questions_dir.join("failed_pages.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 记录失败的页面，同一页同一步骤只保留最近一次
+fn add_failed_page(questions_dir: &Path, page: u32, stage: &str, kind: &str, error: &str) {
+    let mut failed = load_failed_pages(questions_dir);
+    failed.retain(|f| !(f.page == page && f.stage == stage));
+    failed.push(FailedPage {
+        page,
+        stage: stage.to_string(),
+        kind: kind.to_string(),
+        error: error.to_string(),
+        failed_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    });
+    failed.sort_by_key(|f| f.page);
+    let _ = save_json(&questions_dir.join("failed_pages.json"), &failed);
+}
+
+/// 移除指定页面的失败记录
+fn remove_failed_pages(questions_dir: &Path, pages: &[u32]) {
+    let mut failed = load_failed_pages(questions_dir);
+    let before = failed.len();
+    failed.retain(|f| !pages.contains(&f.page));
+    if failed.len() != before {
+        let _ = save_json(&questions_dir.join("failed_pages.json"), &failed);
+    }
+}
+
+/// 记录 AI 请求失败的页面，停止分析时取消的请求不记录
+fn record_request_failure(questions_dir: &Path, page: u32, stage: &str, error: &anyhow::Error) {
+    if matches!(error.downcast_ref::<ai_service::AIError>(), Some(ai_service::AIError::Cancelled)) {
+        return;
+    }
+    add_failed_page(questions_dir, page, stage, "request_error", &error.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_analysis_does_not_stay_analyzing() {
+        // 请求头无效的重排序模型，创建时出错，分析在处理页面之前就退出
+        let mut config = crate::commands::AppConfig::default();
+        let reranker: crate::commands::ModelConfig = serde_json::from_value(serde_json::json!({
+            "id": "rerank",
+            "name": "rerank",
+            "provider": "openai",
+            "api_url": "http://127.0.0.1:1/v1/chat/completions",
+            "api_key": "",
+            "model_name": "rerank",
+            "headers": { "bad header": "x" },
+        }))
+        .unwrap();
+        config.models.push(reranker);
+        config.rerank_model = "rerank".to_string();
+
+        let file_id = "test_failed_reranker";
+        let active = ActiveAnalysis::claim(file_id, 3, None).unwrap();
+        assert!(is_analyzing(file_id));
+        assert!(ActiveAnalysis::claim(file_id, 3, None).is_err());
+
+        let result = get_reranker(&config).map(|_| ());
+        assert!(result.is_err());
+        active.finish(&result);
+        assert!(!is_analyzing(file_id));
+
+        // 出错后可以重新开始；任务中途被丢弃时同样不会停留在分析中
+        let active = ActiveAnalysis::claim(file_id, 3, None).unwrap();
+        assert!(is_analyzing(file_id));
+        drop(active);
+        assert!(!is_analyzing(file_id));
     }
-    text.to_string()
 }
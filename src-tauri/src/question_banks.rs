@@ -0,0 +1,161 @@
+// 组合题库模块 - 把多个文件（教材、历年真题等）的题目汇总为一个题库
+// 合并时不修改各文件自己的题目，每道题保留来源（文件、页码）；去重时重复的题目只保留一份并记录全部来源
+// 题库保存在应用数据目录下的 banks 目录中
+
+use crate::commands::Question;
+use crate::json_store;
+use crate::question_dedup;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 组合题库
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionBank {
+    pub bank_id: String,
+    pub name: String,
+    pub file_ids: Vec<String>,
+    pub dedupe: bool,
+    pub created_at: String,
+    pub questions: Vec<BankQuestion>,
+    #[serde(default)]
+    pub merged_duplicates: u32, // 去重时合并掉的题目数
+}
+
+/// 题库中的一道题和它的来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankQuestion {
+    pub question: Question,
+    pub sources: Vec<QuestionSource>, // 第一个是保留的题目本身，其余是合并掉的重复题目
+}
+
+/// 题目来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionSource {
+    pub file_id: String,
+    pub file_name: String,
+    pub question_id: String,
+    pub page_number: u32,
+}
+
+/// 题库列表中的摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankSummary {
+    pub bank_id: String,
+    pub name: String,
+    pub file_ids: Vec<String>,
+    pub dedupe: bool,
+    pub created_at: String,
+    pub question_count: u32,
+    pub merged_duplicates: u32,
+}
+
+impl QuestionBank {
+    pub fn summary(&self) -> BankSummary {
+        BankSummary {
+            bank_id: self.bank_id.clone(),
+            name: self.name.clone(),
+            file_ids: self.file_ids.clone(),
+            dedupe: self.dedupe,
+            created_at: self.created_at.clone(),
+            question_count: self.questions.len() as u32,
+            merged_duplicates: self.merged_duplicates,
+        }
+    }
+}
+
+/// 合并多个文件的题目，file_names 为文件 ID 到显示名称的映射
+/// dedupe 为 true 时近似重复的题目保留答案最完整的一份，并补充其他副本的知识点
+pub fn merge(
+    name: &str,
+    file_ids: &[String],
+    file_names: &HashMap<String, String>,
+    questions: Vec<Question>,
+    dedupe: bool,
+) -> QuestionBank {
+    let source = |q: &Question| QuestionSource {
+        file_id: q.file_id.clone(),
+        file_name: file_names.get(&q.file_id).cloned().unwrap_or_default(),
+        question_id: q.id.clone(),
+        page_number: q.page_number,
+    };
+
+    // 重复题目（文件 ID、题目 ID）-> 保留的题目
+    let mut merged_into: HashMap<(String, String), (String, String)> = HashMap::new();
+    if dedupe {
+        for group in question_dedup::find_duplicates(&questions, question_dedup::DUPLICATE_SIMILARITY) {
+            let keep = (group.keep.file_id, group.keep.question_id);
+            for member in group.duplicates {
+                merged_into.insert((member.file_id, member.question_id), keep.clone());
+            }
+        }
+    }
+
+    let mut bank_questions: Vec<BankQuestion> = Vec::new();
+    let mut positions: HashMap<(String, String), usize> = HashMap::new();
+    let mut duplicates: Vec<Question> = Vec::new();
+    for q in questions {
+        let key = (q.file_id.clone(), q.id.clone());
+        if merged_into.contains_key(&key) {
+            duplicates.push(q);
+            continue;
+        }
+        positions.insert(key, bank_questions.len());
+        bank_questions.push(BankQuestion {
+            sources: vec![source(&q)],
+            question: q,
+        });
+    }
+    let merged_duplicates = duplicates.len() as u32;
+    for q in duplicates {
+        let keep = &merged_into[&(q.file_id.clone(), q.id.clone())];
+        if let Some(&index) = positions.get(keep) {
+            let entry = &mut bank_questions[index];
+            question_dedup::merge_knowledge_points(&mut entry.question, std::slice::from_ref(&q));
+            entry.sources.push(source(&q));
+        }
+    }
+
+    let name = if name.trim().is_empty() {
+        file_ids
+            .iter()
+            .map(|id| file_names.get(id).cloned().unwrap_or_else(|| id.clone()))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    } else {
+        name.trim().to_string()
+    };
+    QuestionBank {
+        bank_id: uuid::Uuid::new_v4().to_string(),
+        name,
+        file_ids: file_ids.to_vec(),
+        dedupe,
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        questions: bank_questions,
+        merged_duplicates,
+    }
+}
+
+/// 读取题库
+pub fn load(dir: &Path, bank_id: &str) -> Result<Option<QuestionBank>> {
+    Ok(json_store::load(&json_store::record_path(dir, bank_id)?))
+}
+
+/// 保存题库
+pub fn save(dir: &Path, bank: &QuestionBank) -> Result<()> {
+    json_store::save(dir, &bank.bank_id, bank)
+}
+
+/// 删除题库，返回题库是否存在
+pub fn remove(dir: &Path, bank_id: &str) -> Result<bool> {
+    json_store::remove(dir, bank_id)
+}
+
+/// 列出目录中的题库，最近创建的排在前面
+pub fn list(dir: &Path) -> Vec<QuestionBank> {
+    let mut banks: Vec<QuestionBank> = json_store::list(dir);
+    banks.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    banks
+}
@@ -0,0 +1,168 @@
+// 重复题目检测模块 - 按题干文字相似度找出近似重复的题目
+// 同一道题常在例题、习题和不同教材中重复出现，合并时保留答案最完整的一份
+
+use crate::commands::Question;
+use crate::utils::{normalize_text, DisjointSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// 视为重复的最低相似度（题干字符二元组的 Jaccard 系数）
+pub const DUPLICATE_SIMILARITY: f32 = 0.85;
+
+/// 题干有效字符少于这个数时不参与比较，避免"证明"、"略"这类短题干互相匹配
+const MIN_QUESTION_CHARS: usize = 8;
+
+/// 出现在超过这个比例题目中的二元组不用于查找候选（常见词，几乎每题都有）
+const MAX_BIGRAM_FREQUENCY: f32 = 0.2;
+
+/// 题目较少时不跳过任何二元组
+const MIN_POSTINGS_LIMIT: usize = 50;
+
+/// 一组近似重复的题目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub keep: DuplicateMember, // 答案最完整的一份，合并时保留
+    pub duplicates: Vec<DuplicateMember>,
+    pub similarity: f32, // 各副本与保留题目的最低相似度
+}
+
+/// 重复组中的题目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateMember {
+    pub file_id: String,
+    pub question_id: String,
+    pub page_number: u32,
+    pub question_type: String,
+    pub question_text: String,
+}
+
+impl DuplicateMember {
+    fn from_question(q: &Question) -> Self {
+        Self {
+            file_id: q.file_id.clone(),
+            question_id: q.id.clone(),
+            page_number: q.page_number,
+            question_type: q.question_type.clone(),
+            question_text: q.question_text.clone(),
+        }
+    }
+}
+
+/// 查找近似重复的题目，threshold 为最低相似度
+pub fn find_duplicates(questions: &[Question], threshold: f32) -> Vec<DuplicateGroup> {
+    let bigrams: Vec<HashSet<(char, char)>> = questions.iter().map(|q| bigram_set(&q.question_text)).collect();
+
+    // 倒排索引：二元组 -> 包含它的题目
+    let mut postings: HashMap<(char, char), Vec<usize>> = HashMap::new();
+    for (i, set) in bigrams.iter().enumerate() {
+        for bigram in set {
+            postings.entry(*bigram).or_default().push(i);
+        }
+    }
+    let max_postings = ((questions.len() as f32 * MAX_BIGRAM_FREQUENCY) as usize).max(MIN_POSTINGS_LIMIT);
+
+    // 通过共同的二元组找候选对，再计算相似度
+    let mut sets = DisjointSet::new(questions.len());
+    for (i, set) in bigrams.iter().enumerate() {
+        if set.is_empty() {
+            continue;
+        }
+        let mut candidates: HashSet<usize> = HashSet::new();
+        for bigram in set {
+            let list = &postings[bigram];
+            if list.len() <= max_postings {
+                candidates.extend(list.iter().copied().filter(|&j| j > i));
+            }
+        }
+        for j in candidates {
+            if jaccard(set, &bigrams[j]) >= threshold {
+                sets.union(i, j);
+            }
+        }
+    }
+
+    let mut components: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..questions.len() {
+        let root = sets.find(i);
+        components.entry(root).or_default().push(i);
+    }
+
+    // 连通的题目两两之间不一定相似（A 像 B、B 像 C，A 和 C 可能差别很大）
+    // 每组只收入与保留题目直接相似的题目，其余题目在剩下的题目中重新分组
+    let mut result: Vec<DuplicateGroup> = Vec::new();
+    for mut members in components.into_values() {
+        while members.len() > 1 {
+            let keep = *members
+                .iter()
+                .max_by_key(|&&i| (completeness(&questions[i]), std::cmp::Reverse(i)))
+                .unwrap();
+            let mut duplicates = Vec::new();
+            let mut similarity: f32 = 1.0;
+            let mut rest = Vec::new();
+            for &i in members.iter().filter(|&&i| i != keep) {
+                let s = jaccard(&bigrams[keep], &bigrams[i]);
+                if s >= threshold {
+                    duplicates.push(DuplicateMember::from_question(&questions[i]));
+                    similarity = similarity.min(s);
+                } else {
+                    rest.push(i);
+                }
+            }
+            if !duplicates.is_empty() {
+                result.push(DuplicateGroup {
+                    keep: DuplicateMember::from_question(&questions[keep]),
+                    duplicates,
+                    similarity,
+                });
+            }
+            members = rest;
+        }
+    }
+    result.sort_by(|a, b| {
+        a.keep
+            .file_id
+            .cmp(&b.keep.file_id)
+            .then_with(|| a.keep.page_number.cmp(&b.keep.page_number))
+    });
+    result
+}
+
+/// 答案完整程度：教材原有答案优先，其次是有答案、有解析、有知识点和参考来源的
+fn completeness(q: &Question) -> (bool, bool, bool, usize) {
+    (
+        q.has_original_answer,
+        !q.answer.trim().is_empty(),
+        !q.analysis.trim().is_empty(),
+        q.answer.chars().count() + q.analysis.chars().count() + q.knowledge_points.len() + q.references.len(),
+    )
+}
+
+/// 合并时把重复题目的知识点并入保留的题目
+pub fn merge_knowledge_points(keep: &mut Question, duplicates: &[Question]) {
+    for q in duplicates {
+        for kp in &q.knowledge_points {
+            if !keep.knowledge_points.contains(kp) {
+                keep.knowledge_points.push(kp.clone());
+            }
+        }
+    }
+}
+
+/// 题干的字符二元组（去掉空白和标点，英文转小写）
+fn bigram_set(text: &str) -> HashSet<(char, char)> {
+    let chars: Vec<char> = normalize_text(text).chars().collect();
+    if chars.len() < MIN_QUESTION_CHARS {
+        return HashSet::new();
+    }
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+fn jaccard(a: &HashSet<(char, char)>, b: &HashSet<(char, char)>) -> f32 {
+    let shared = a.intersection(b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        0.0
+    } else {
+        shared as f32 / total as f32
+    }
+}
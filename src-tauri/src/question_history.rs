@@ -0,0 +1,137 @@
+// 题目修改记录模块 - 记录每道题的修改（谁、何时、改了哪些字段）并保存修改前后的内容
+// 手动修改和重新分析覆盖已有题目时都会记录，可以恢复到某次修改之前的内容
+// 记录保存在文件目录下的 questions/history.jsonl（每行一条 JSON 记录）
+// 每条记录带有修改前后的完整题目，文件超过大小上限时改名为 history.1.jsonl（替换更早的记录），只保留最近两份
+
+use crate::commands::Question;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 修改记录文件的大小上限（字节）
+const MAX_HISTORY_BYTES: u64 = 4 * 1024 * 1024;
+
+/// 一次修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionEdit {
+    pub edit_id: String,
+    pub question_id: String,
+    pub file_id: String,
+    pub edited_at: String,
+    pub editor: String, // "user"、"sync"（同步拉取），或生成题目的模型（"ai:模型名称"）
+    pub action: String, // "created", "updated", "deleted"
+    #[serde(default)]
+    pub note: String, // 修改原因，例如 "重新分析"、"恢复修改"
+    #[serde(default)]
+    pub changed_fields: Vec<String>,
+    #[serde(default)]
+    pub before: Option<Question>, // 新建的题目为空
+    #[serde(default)]
+    pub after: Option<Question>, // 删除的题目为空
+}
+
+/// 修改前后有变化的字段
+pub fn changed_fields(before: &Question, after: &Question) -> Vec<String> {
+    let (before, after) = match (serde_json::to_value(before), serde_json::to_value(after)) {
+        (Ok(serde_json::Value::Object(before)), Ok(serde_json::Value::Object(after))) => (before, after),
+        _ => return Vec::new(),
+    };
+    after
+        .iter()
+        .filter(|(key, value)| before.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// 比较修改前后的题目列表，生成修改记录（内容没有变化的题目不记录）
+/// include_created 为 false 时不记录新出现的题目（首次分析生成的题目已有分析记录）
+pub fn diff(
+    file_id: &str,
+    before: &[Question],
+    after: &[Question],
+    editor: &str,
+    note: &str,
+    include_created: bool,
+) -> Vec<QuestionEdit> {
+    let edited_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let edit = |question_id: &str, action: &str, before: Option<&Question>, after: Option<&Question>| QuestionEdit {
+        edit_id: uuid::Uuid::new_v4().to_string(),
+        question_id: question_id.to_string(),
+        file_id: file_id.to_string(),
+        edited_at: edited_at.clone(),
+        editor: editor.to_string(),
+        action: action.to_string(),
+        note: note.to_string(),
+        changed_fields: match (before, after) {
+            (Some(before), Some(after)) => changed_fields(before, after),
+            _ => Vec::new(),
+        },
+        before: before.cloned(),
+        after: after.cloned(),
+    };
+
+    let old: HashMap<&str, &Question> = before.iter().map(|q| (q.id.as_str(), q)).collect();
+    let new: HashMap<&str, &Question> = after.iter().map(|q| (q.id.as_str(), q)).collect();
+    let mut edits = Vec::new();
+    for q in after {
+        match old.get(q.id.as_str()) {
+            Some(previous) => {
+                let record = edit(&q.id, "updated", Some(previous), Some(q));
+                if !record.changed_fields.is_empty() {
+                    edits.push(record);
+                }
+            }
+            None if include_created => edits.push(edit(&q.id, "created", None, Some(q))),
+            None => {}
+        }
+    }
+    for q in before.iter().filter(|q| !new.contains_key(q.id.as_str())) {
+        edits.push(edit(&q.id, "deleted", Some(q), None));
+    }
+    edits
+}
+
+/// 追加修改记录
+pub fn append(path: &Path, edits: &[QuestionEdit]) -> Result<()> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(path).is_ok_and(|m| m.len() >= MAX_HISTORY_BYTES) {
+        fs::rename(path, rotated_path(path))?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for edit in edits {
+        writeln!(file, "{}", serde_json::to_string(edit)?)?;
+    }
+    Ok(())
+}
+
+/// 读取一道题的修改记录（包括改名保留的较早记录），最近的排在前面
+pub fn load(path: &Path, question_id: &str) -> Vec<QuestionEdit> {
+    let mut edits: Vec<QuestionEdit> = [rotated_path(path), path.to_path_buf()]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .filter_map(|l| serde_json::from_str::<QuestionEdit>(l).ok())
+                .filter(|e| e.question_id == question_id)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    edits.reverse();
+    edits
+}
+
+/// 超过大小上限后保留的较早记录：history.jsonl -> history.1.jsonl
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("1.jsonl")
+}
@@ -0,0 +1,130 @@
+// 题目插图模块 - 将页面 Markdown 中题目附近的图片复制到题库
+// 很多题目依赖插图（几何图形、电路图等），导出或练习时需要保留这些图片
+
+use crate::commands::Question;
+use crate::utils::normalized_chars;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 定位题目时使用的题干前缀长度（只计字母、数字和汉字）
+const LOCATE_PREFIX_CHARS: usize = 12;
+
+/// 每道题目最多保留的图片数
+const MAX_IMAGES_PER_QUESTION: usize = 6;
+
+// Markdown 图片和 HTML img 标签中的图片地址
+static IMAGE_LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"!\[[^\]]*\]\(\s*<?([^)\s>]+)>?[^)]*\)|<img[^>]*\ssrc\s*=\s*["']([^"']+)["']"#).unwrap()
+});
+
+/// 页面中的图片链接及其在正文中的位置（字节偏移）
+pub fn image_links(markdown: &str) -> Vec<(usize, String)> {
+    IMAGE_LINK_RE
+        .captures_iter(markdown)
+        .filter_map(|caps| {
+            let m = caps.get(1).or_else(|| caps.get(2))?;
+            Some((caps.get(0)?.start(), m.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// 去掉文本中的图片链接（导出时插图按题目的 images 单独处理）
+pub fn strip_image_links(text: &str) -> Cow<'_, str> {
+    IMAGE_LINK_RE.replace_all(text, "")
+}
+
+/// 题目插图的文件路径（images 中是相对于 base_dir 的路径），跳过不存在的文件
+pub fn image_files(question: &Question, base_dir: &Path) -> Vec<PathBuf> {
+    question
+        .images
+        .iter()
+        .map(|image| base_dir.join(image))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// 为同一页的题目附加插图：按题干在页面中的位置划分区间，区间内的图片归该题目
+/// 图片复制到 dest_dir，题目中记录相对于 base_dir 的路径；已有图片的题目不处理
+pub fn attach_page_images(
+    questions: &mut [&mut Question],
+    markdown: &str,
+    search_dirs: &[PathBuf],
+    dest_dir: &Path,
+    base_dir: &Path,
+) {
+    use crate::logger;
+
+    let links = image_links(markdown);
+    if links.is_empty() {
+        return;
+    }
+
+    // 定位各题目的起始位置，找不到的题目不分配图片
+    let mut starts: Vec<(usize, usize)> = questions
+        .iter()
+        .enumerate()
+        .filter_map(|(i, q)| locate(markdown, &q.question_text).map(|start| (start, i)))
+        .collect();
+    starts.sort_unstable();
+
+    for (k, &(start, i)) in starts.iter().enumerate() {
+        let question = &mut questions[i];
+        if !question.images.is_empty() {
+            continue;
+        }
+        let end = starts.get(k + 1).map(|(s, _)| *s).unwrap_or(markdown.len());
+        for (n, (_, link)) in links
+            .iter()
+            .filter(|(offset, _)| *offset >= start && *offset < end)
+            .take(MAX_IMAGES_PER_QUESTION)
+            .enumerate()
+        {
+            let source = match resolve(link, search_dirs) {
+                Some(path) => path,
+                None => continue,
+            };
+            let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("jpg");
+            let dest = dest_dir.join(format!("{}_{}.{}", question.id, n, ext));
+            let copied = fs::create_dir_all(dest_dir).and_then(|_| fs::copy(&source, &dest));
+            match copied {
+                Ok(_) => {
+                    let relative = dest.strip_prefix(base_dir).unwrap_or(&dest);
+                    question.images.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+                Err(e) => logger::warn("ai", &format!("复制题目插图 {} 失败: {}", source.display(), e)),
+            }
+        }
+    }
+}
+
+/// 在页面中查找题干开头的位置：只比较字母、数字和汉字，返回原文中的字节偏移
+fn locate(markdown: &str, question_text: &str) -> Option<usize> {
+    let prefix: Vec<char> = normalized_chars(question_text)
+        .map(|(_, c)| c)
+        .take(LOCATE_PREFIX_CHARS)
+        .collect();
+    if prefix.len() < LOCATE_PREFIX_CHARS / 2 {
+        return None;
+    }
+
+    let (offsets, chars): (Vec<usize>, Vec<char>) = normalized_chars(markdown).unzip();
+    chars
+        .windows(prefix.len())
+        .position(|w| w == prefix.as_slice())
+        .map(|pos| offsets[pos])
+}
+
+/// 解析图片地址：绝对路径直接使用，相对路径依次在各目录下查找，网络地址跳过
+fn resolve(link: &str, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if link.starts_with("http://") || link.starts_with("https://") || link.starts_with("data:") {
+        return None;
+    }
+    let path = Path::new(link);
+    if path.is_absolute() {
+        return path.exists().then(|| path.to_path_buf());
+    }
+    search_dirs.iter().map(|dir| dir.join(path)).find(|p| p.exists())
+}
@@ -0,0 +1,48 @@
+// 题目页码索引模块 - 按页码索引文件的题目，阅读器翻页时直接取当前页的题目
+// 索引保存在内存中，题目文件内容变化后重新建立（按内容哈希判断，修改时间和大小在快速连续写入时可能不变）
+
+use crate::commands::Question;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 一个文件的页码索引
+struct PageIndex {
+    hash: Vec<u8>,
+    pages: BTreeMap<u32, Vec<Question>>,
+}
+
+// 题目文件路径 -> 页码索引
+static PAGE_INDEX: Lazy<Mutex<HashMap<PathBuf, PageIndex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 取某一页的题目，题目文件不存在时返回空列表
+pub fn questions_on_page(questions_file: &Path, page: u32) -> Result<Vec<Question>> {
+    let content = match fs::read(questions_file) {
+        Ok(content) => content,
+        Err(_) => {
+            PAGE_INDEX.lock().remove(questions_file);
+            return Ok(Vec::new());
+        }
+    };
+    let hash = Sha256::digest(&content).to_vec();
+
+    let mut indexes = PAGE_INDEX.lock();
+    let up_to_date = indexes.get(questions_file).is_some_and(|index| index.hash == hash);
+    if !up_to_date {
+        let questions: Vec<Question> = serde_json::from_slice(&content)?;
+        let mut pages: BTreeMap<u32, Vec<Question>> = BTreeMap::new();
+        for q in questions {
+            pages.entry(q.page_number).or_default().push(q);
+        }
+        indexes.insert(questions_file.to_path_buf(), PageIndex { hash, pages });
+    }
+    Ok(indexes
+        .get(questions_file)
+        .and_then(|index| index.pages.get(&page))
+        .cloned()
+        .unwrap_or_default())
+}
@@ -0,0 +1,136 @@
+// 选择题选项模块 - 从题干中拆出 A/B/C/D 选项，从答案中识别正确选项
+// 选项单独保存后，导出和练习时可以按选择题展示和判分
+
+use crate::commands::Question;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// 选择题至少需要的选项数
+const MIN_OPTIONS: usize = 2;
+
+/// 选项标记："(A)" "（A）"，或 "A." "A、" "A："（前面须是行首、空白或右括号）
+static OPTION_MARKER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[(（]([A-H])[)）]|([A-H])[.．、:：]").unwrap());
+
+// 答案开头的选项字母，例如 "C"、"AC"、"A、C"、"答案：B"、"（B）"
+static ANSWER_LABELS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(?:答案\s*[:：]\s*)?[(（]?([A-H](?:\s*[、,，]?\s*[A-H])*)[)）]?").unwrap()
+});
+
+/// 选择题选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoiceOption {
+    pub label: String, // "A", "B", ...
+    pub text: String,
+}
+
+/// 从题干中拆分选项：选项标记须从 A 开始依次出现，且选项位于题干末尾，返回去掉选项后的题干和选项
+pub fn split_options(text: &str) -> Option<(String, Vec<ChoiceOption>)> {
+    // 标记在正文中的起止位置和字母
+    let markers: Vec<(usize, usize, char)> = OPTION_MARKER_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            if let Some(label) = caps.get(1) {
+                return Some((whole.start(), whole.end(), label.as_str().chars().next()?));
+            }
+            // "点A、B" 这类写法不是选项，字母标记前面必须是分隔符
+            let label = caps.get(2)?;
+            let separated = text[..whole.start()]
+                .chars()
+                .last()
+                .map_or(true, |c| c.is_whitespace() || c == ')' || c == '）');
+            if !separated {
+                return None;
+            }
+            Some((whole.start(), whole.end(), label.as_str().chars().next()?))
+        })
+        .collect();
+
+    // 依次取 A、B、C……，跳过不按顺序出现的标记
+    let mut chain: Vec<(usize, usize, char)> = Vec::new();
+    for marker in markers {
+        let expected = (b'A' + chain.len() as u8) as char;
+        if marker.2 == expected {
+            chain.push(marker);
+        }
+    }
+    if chain.len() < MIN_OPTIONS {
+        return None;
+    }
+
+    let stem = text[..chain[0].0].trim_end().to_string();
+    let options: Vec<ChoiceOption> = chain
+        .iter()
+        .enumerate()
+        .map(|(i, (_, content_start, label))| {
+            let content_end = chain.get(i + 1).map(|next| next.0).unwrap_or(text.len());
+            let content = text[*content_start..content_end].trim();
+            ChoiceOption {
+                label: label.to_string(),
+                text: content.to_string(),
+            }
+        })
+        .collect();
+    if options.iter().any(|o| o.text.is_empty()) {
+        return None;
+    }
+    // 最后一个选项后面还有其他段落时，这些标记是题干中间的列举（如 "(A) 组……(B) 组……"），不是选项
+    if options.last().is_some_and(|o| o.text.contains('\n')) {
+        return None;
+    }
+    Some((stem, options))
+}
+
+/// 从答案第一行中识别正确选项：只识别开头的选项字母（"AC"、"A、C"、"答案：B"），
+/// 字母后紧跟英文单词时（"A decreases ..."）视为普通文字；含有不在选项中的字母时返回空
+pub fn correct_labels(answer: &str, options: &[ChoiceOption]) -> Vec<String> {
+    let first_line = answer.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+    let caps = match ANSWER_LABELS_RE.captures(first_line) {
+        Some(caps) => caps,
+        None => return Vec::new(),
+    };
+    let rest = first_line[caps.get(0).map_or(0, |m| m.end())..].trim_start();
+    if rest.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Vec::new();
+    }
+    let mut labels: Vec<String> = Vec::new();
+    for c in caps[1].chars().filter(|c| c.is_ascii_uppercase()) {
+        let label = c.to_string();
+        if !options.iter().any(|o| o.label == label) {
+            return Vec::new();
+        }
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    labels
+}
+
+/// 整理选择题：题干中含有选项时拆分出来，正确选项未给出时从答案中识别
+/// 模型已单独给出选项时只从题干中去掉重复的选项文字
+pub fn apply_options(question: &mut Question) {
+    if let Some((stem, options)) = split_options(&question.question_text) {
+        let same_labels = question.options.is_empty()
+            || (question.options.len() == options.len()
+                && question.options.iter().zip(&options).all(|(a, b)| a.label == b.label));
+        if same_labels && !stem.is_empty() {
+            question.question_text = stem;
+            if question.options.is_empty() {
+                question.options = options;
+            }
+        }
+    }
+    if question.options.is_empty() {
+        question.correct_options.clear();
+        return;
+    }
+    let options = &question.options;
+    question
+        .correct_options
+        .retain(|label| options.iter().any(|o| &o.label == label));
+    if question.correct_options.is_empty() {
+        question.correct_options = correct_labels(&question.answer, &question.options);
+    }
+}
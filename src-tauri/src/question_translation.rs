@@ -0,0 +1,151 @@
+// 题目翻译模块 - 将题干、选项、答案和解析翻译为目标语言，译文按语言保存在题目中，原文保持不变（用于双语教学）
+// 翻译前把公式替换为占位符 [[F1]]、[[F2]]……，收到译文后再换回原公式，保证 LaTeX 不被模型改写；
+// 选项标签不参与翻译，题号和小问编号由提示词要求保持不变
+
+use crate::commands::Question;
+use crate::question_options::ChoiceOption;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// 行间公式 $$...$$、\[...\] 和行内公式 $...$、\(...\)
+static FORMULA_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)\$\$.+?\$\$|\$[^$\n]+?\$|\\\(.+?\\\)|\\\[.+?\\\]").unwrap());
+
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[F(\d+)\]\]").unwrap());
+
+/// 一道题的译文
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuestionTranslation {
+    pub question_text: String,
+    #[serde(default)]
+    pub options: Vec<ChoiceOption>,
+    #[serde(default)]
+    pub answer: String,
+    #[serde(default)]
+    pub analysis: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub translated_at: String,
+}
+
+/// 翻译结果统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranslationSummary {
+    pub target_lang: String,
+    pub translated: usize,
+    pub skipped: usize,       // 已有该语言译文的题目
+    pub failed: Vec<String>,  // 翻译失败的题目 ID
+}
+
+/// 发给模型的待翻译内容（公式已替换为占位符）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationRequest {
+    pub question_text: String,
+    #[serde(default)]
+    pub options: BTreeMap<String, String>, // 选项标签 -> 选项内容
+    #[serde(default)]
+    pub answer: String,
+    #[serde(default)]
+    pub analysis: String,
+}
+
+/// 语言代码对应的语言名称（写入提示词），未知的代码原样使用
+pub fn language_name(code: &str) -> String {
+    match code.trim().to_lowercase().as_str() {
+        "zh" | "zh-cn" | "zh-hans" => "简体中文".to_string(),
+        "zh-tw" | "zh-hant" => "繁體中文".to_string(),
+        "en" => "English".to_string(),
+        "ja" => "日本語".to_string(),
+        "ko" => "한국어".to_string(),
+        "fr" => "Français".to_string(),
+        "de" => "Deutsch".to_string(),
+        "es" => "Español".to_string(),
+        "ru" => "Русский".to_string(),
+        _ => code.trim().to_string(),
+    }
+}
+
+/// 生成待翻译内容，返回内容和被替换掉的公式（占位符编号从 1 开始，各字段共用一组编号）
+pub fn build_request(q: &Question) -> (TranslationRequest, Vec<String>) {
+    let mut formulas = Vec::new();
+    let request = TranslationRequest {
+        question_text: mask_formulas(&q.question_text, &mut formulas),
+        options: q
+            .options
+            .iter()
+            .map(|o| (o.label.clone(), mask_formulas(&o.text, &mut formulas)))
+            .collect(),
+        answer: mask_formulas(&q.answer, &mut formulas),
+        analysis: mask_formulas(&q.analysis, &mut formulas),
+    };
+    (request, formulas)
+}
+
+/// 把模型返回的译文还原为题目译文：换回公式并检查每个公式都保留了下来
+pub fn parse_response(q: &Question, translated: TranslationRequest, formulas: &[String]) -> Result<QuestionTranslation> {
+    let mut used = vec![false; formulas.len()];
+    let mut restore = |text: &str| restore_formulas(text, formulas, &mut used);
+
+    let question_text = restore(&translated.question_text)?;
+    if question_text.trim().is_empty() {
+        return Err(anyhow!("译文为空"));
+    }
+    let mut options = Vec::new();
+    for option in &q.options {
+        let text = match translated.options.get(&option.label) {
+            Some(text) => restore(text)?,
+            None => return Err(anyhow!("译文缺少选项 {}", option.label)),
+        };
+        options.push(ChoiceOption {
+            label: option.label.clone(),
+            text,
+        });
+    }
+    let answer = restore(&translated.answer)?;
+    let analysis = restore(&translated.analysis)?;
+    if let Some(missing) = used.iter().position(|u| !u) {
+        return Err(anyhow!("译文中缺少公式 {}", formulas[missing]));
+    }
+
+    Ok(QuestionTranslation {
+        question_text,
+        options,
+        answer,
+        analysis,
+        model: String::new(),
+        translated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+fn mask_formulas(text: &str, formulas: &mut Vec<String>) -> String {
+    FORMULA_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            formulas.push(caps[0].to_string());
+            format!("[[F{}]]", formulas.len())
+        })
+        .to_string()
+}
+
+fn restore_formulas(text: &str, formulas: &[String], used: &mut [bool]) -> Result<String> {
+    let mut unknown = None;
+    let restored = PLACEHOLDER_RE.replace_all(text, |caps: &regex::Captures| {
+        match caps[1].parse::<usize>().ok().filter(|n| *n >= 1 && *n <= formulas.len()) {
+            Some(n) => {
+                used[n - 1] = true;
+                formulas[n - 1].clone()
+            }
+            None => {
+                unknown = Some(caps[0].to_string());
+                String::new()
+            }
+        }
+    });
+    match unknown {
+        Some(placeholder) => Err(anyhow!("译文中出现未知的公式占位符 {}", placeholder)),
+        None => Ok(restored.to_string()),
+    }
+}
@@ -2,10 +2,17 @@
 
 #![allow(dead_code)]
 
+use crate::ai_service::{AIService, EmbeddingService};
 use anyhow::Result;
+use jieba_rs::Jieba;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
@@ -13,6 +20,8 @@ pub struct Document {
     pub content: String,
     pub metadata: DocumentMetadata,
     pub embedding: Option<Vec<f32>>,
+    #[serde(default)]
+    pub embedding_model: String, // 生成向量的模型，没有向量时为空
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,170 +40,1001 @@ pub struct SearchResult {
     pub score: f32,
 }
 
+/// 导出文件的格式标识和版本
+pub const RAG_EXPORT_FORMAT: &str = "booq-rag-index";
+/// 版本 2：生成向量的模型记录在每个文档中
+pub const RAG_EXPORT_VERSION: u32 = 2;
+
+/// 导出的 RAG 索引，用于在其他电脑上导入，不需要重新 OCR 和向量化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RAGExport {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    #[serde(default)]
+    pub embedding_model: String, // 版本 1 中全部向量共用的模型，版本 2 起为空（见 Document::embedding_model）
+    pub documents: Vec<Document>,
+}
+
+impl RAGExport {
+    /// 读取导出文件并检查格式
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let export: RAGExport = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("不是有效的索引导出文件: {}", e))?;
+        if export.format != RAG_EXPORT_FORMAT {
+            return Err(anyhow::anyhow!("不是有效的索引导出文件"));
+        }
+        if export.version > RAG_EXPORT_VERSION {
+            return Err(anyhow::anyhow!("索引导出文件版本 {} 过新，请升级应用", export.version));
+        }
+        Ok(export)
+    }
+    
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// 上下文中引用的来源
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub marker: String, // 上下文中的引用标记，格式为 [文件ID:页码]
+    pub file_id: String,
+    pub page_number: u32,
+    pub chapter: String,
+}
+
+/// 引用标记，例如 [3fa9c01b2e:12]
+pub fn citation_marker(file_id: &str, page_number: u32) -> String {
+    format!("[{}:{}]", file_id, page_number)
+}
+
+/// 压缩索引的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompactResult {
+    pub documents_before: usize,
+    pub duplicates_removed: usize,
+    pub size_before: u64, // 数据库文件大小（字节）
+    pub size_after: u64,
+}
+
+/// 检索条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchFilter {
+    #[serde(default)]
+    pub exclude_file_id: Option<String>, // 跳过该文件的内容（全局知识库中排除当前文件）
+    #[serde(default)]
+    pub doc_types: Vec<String>, // 为空时不限类型
+    #[serde(default)]
+    pub chapter: Option<String>,
+    #[serde(default)]
+    pub page_from: Option<u32>,
+    #[serde(default)]
+    pub page_to: Option<u32>,
+}
+
+impl SearchFilter {
+    fn matches(&self, metadata: &DocumentMetadata) -> bool {
+        if self.exclude_file_id.as_deref() == Some(metadata.file_id.as_str()) {
+            return false;
+        }
+        if !self.doc_types.is_empty() && !self.doc_types.contains(&metadata.doc_type) {
+            return false;
+        }
+        if let Some(chapter) = self.chapter.as_deref().filter(|c| !c.is_empty()) {
+            if metadata.chapter != chapter {
+                return false;
+            }
+        }
+        self.page_from.map_or(true, |from| metadata.page_number >= from)
+            && self.page_to.map_or(true, |to| metadata.page_number <= to)
+    }
+}
+
+// 全局知识库路径
+static GLOBAL_STORE_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 是否启用全局知识库
+static GLOBAL_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 初始化全局知识库路径（在应用启动时调用）
+pub fn init_global_store(app_dir: &Path) {
+    *GLOBAL_STORE_PATH.write() = Some(app_dir.join("global_rag_index.db"));
+}
+
+/// 设置是否启用全局知识库
+pub fn set_global_enabled(enabled: bool) {
+    GLOBAL_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_global_enabled() -> bool {
+    GLOBAL_ENABLED.load(Ordering::SeqCst)
+}
+
+/// 打开全局知识库，未启用时返回 None
+/// 全局知识库收录所有已分析文件的内容，分析一本书时可以引用其他书中的定义和例题
+pub fn open_global_store() -> Option<RAGStore> {
+    if !is_global_enabled() {
+        return None;
+    }
+    GLOBAL_STORE_PATH.read().clone().map(RAGStore::new)
+}
+
+/// 从全局知识库中删除文件的内容（删除文件时调用，未启用时也会清理）
+pub fn remove_file_from_global_store(file_id: &str) -> Result<usize> {
+    let path = match GLOBAL_STORE_PATH.read().clone() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(0),
+    };
+    RAGStore::new(path).remove_by_file(file_id)
+}
+
+/// 构建上下文：优先使用本文件的内容，启用全局知识库时用其他文件的内容补充，最多占三分之一
+/// quotas 不为空时本文件的内容按文档类型配额检索（见 build_context_with_quotas），同时返回引用的来源（本文件在前）
+pub async fn build_context_with_global(
+    local: &RAGStore,
+    global: Option<&RAGStore>,
+    file_id: &str,
+    query: &str,
+    max_tokens: usize,
+    quotas: &[(&str, usize)],
+) -> (String, Vec<Citation>) {
+    let local_budget = if global.is_some() { max_tokens - max_tokens / 3 } else { max_tokens };
+    let (local_context, mut citations) = if quotas.is_empty() {
+        local.build_context_with_sources(query, local_budget).await
+    } else {
+        local
+            .build_context_with_quotas(query, local_budget, quotas, &SearchFilter::default())
+            .await
+    };
+    let global = match global {
+        Some(global) => global,
+        None => return (local_context, citations),
+    };
+    
+    let remaining = max_tokens.saturating_sub(crate::ai_service::count_tokens(&local_context));
+    let filter = SearchFilter {
+        exclude_file_id: Some(file_id.to_string()),
+        ..Default::default()
+    };
+    let (global_context, global_citations) = global
+        .build_context_filtered(query, remaining.min(max_tokens / 3), &filter)
+        .await;
+    
+    if global_context.is_empty() {
+        (local_context, citations)
+    } else {
+        citations.extend(global_citations);
+        (format!("{}其他教材中的相关内容：\n{}", local_context, global_context), citations)
+    }
+}
+
 /// RAG 知识库
+/// 文档保存在 SQLite 中（documents 表 + 分词后的倒排索引），每次添加只写入新文档，
+/// 旧版本的 rag_index.json 会在首次打开时导入
 pub struct RAGStore {
-    documents: Vec<Document>,
+    conn: Mutex<Connection>,
     index_path: PathBuf,
+    embedder: Option<EmbeddingService>, // 未配置向量化模型时使用关键词检索
+    reranker: Option<AIService>,        // 对检索结果重新排序的模型
+    query_expander: Option<AIService>,  // 检索前扩展查询词的模型
+    staged: Option<Vec<Document>>,      // begin_batch 之后暂存、等待 commit 写入的文档
 }
 
+// 表结构：postings 为 jieba 分词后的倒排索引，用于 BM25 检索
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS documents (
+    rowid INTEGER PRIMARY KEY,
+    id TEXT NOT NULL UNIQUE,
+    content TEXT NOT NULL,
+    file_id TEXT NOT NULL,
+    page_number INTEGER NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    doc_type TEXT NOT NULL,
+    chapter TEXT NOT NULL,
+    section TEXT NOT NULL,
+    embedding BLOB,
+    token_count INTEGER,
+    content_hash TEXT,
+    embedding_model TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_documents_type ON documents(doc_type);
+CREATE TABLE IF NOT EXISTS postings (
+    term TEXT NOT NULL,
+    doc INTEGER NOT NULL,
+    tf INTEGER NOT NULL,
+    PRIMARY KEY (term, doc)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_postings_doc ON postings(doc);
+CREATE TRIGGER IF NOT EXISTS documents_postings_ad AFTER DELETE ON documents BEGIN
+    DELETE FROM postings WHERE doc = old.rowid;
+END;
+"#;
+
+// 查询扩展结果缓存
+static EXPANSION_CACHE: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 缓存的查询扩展结果数，超过时清空
+const MAX_EXPANSION_CACHE: usize = 256;
+
+// 构建上下文时取的检索结果数
+const CONTEXT_RESULTS: usize = 10;
+
+// 启用重排序时取的候选数
+const RERANK_CANDIDATES: usize = 20;
+
+// BM25 参数
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+// 中文分词器，首次使用时加载词典
+static JIEBA: Lazy<Jieba> = Lazy::new(Jieba::new);
+
+const DOCUMENT_COLUMNS: &str =
+    "id, content, file_id, page_number, chunk_index, doc_type, chapter, section, embedding, embedding_model";
+
 impl RAGStore {
-    /// 创建新的 RAG 存储
+    /// 创建新的 RAG 存储，index_path 为 SQLite 数据库路径
+    /// 同目录下存在旧版本的同名 .json 索引时导入数据库，导入后重命名为 .json.bak
     pub fn new(index_path: PathBuf) -> Self {
-        let documents = if index_path.exists() {
-            let content = fs::read_to_string(&index_path).unwrap_or_default();
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            Vec::new()
-        };
+        use crate::logger;
         
-        Self {
-            documents,
+        if let Some(parent) = index_path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        // 全局知识库可能被多个分析任务同时写入
+        let conn = Connection::open(&index_path)
+            .and_then(|conn| conn.busy_timeout(std::time::Duration::from_secs(10)).map(|_| conn))
+            .and_then(|conn| conn.execute_batch(SCHEMA).map(|_| conn))
+            .and_then(|conn| migrate(&conn).map(|_| conn))
+            .unwrap_or_else(|e| {
+                logger::warn("ai", &format!("打开 RAG 索引失败，本次使用内存索引: {}", e));
+                let conn = Connection::open_in_memory().expect("无法创建内存数据库");
+                conn.execute_batch(SCHEMA).ok();
+                migrate(&conn).ok();
+                conn
+            });
+        
+        let mut store = Self {
+            conn: Mutex::new(conn),
             index_path,
+            embedder: None,
+            reranker: None,
+            query_expander: None,
+            staged: None,
+        };
+        if let Err(e) = store.import_json() {
+            logger::warn("ai", &format!("导入旧版 RAG 索引失败: {}", e));
+        }
+        if let Err(e) = store.index_unindexed() {
+            logger::warn("ai", &format!("建立 RAG 倒排索引失败: {}", e));
         }
+        store
     }
     
-    /// 添加文档
-    pub fn add_document(&mut self, doc: Document) {
-        // 检查是否已存在相同 ID 的文档
-        if !self.documents.iter().any(|d| d.id == doc.id) {
-            self.documents.push(doc);
-            self.save().ok();
+    /// 导入旧版本的 JSON 索引
+    fn import_json(&mut self) -> Result<()> {
+        use crate::logger;
+        
+        let json_path = self.index_path.with_extension("json");
+        if !json_path.exists() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(&json_path)?;
+        let documents: Vec<Document> = serde_json::from_str(&content)?;
+        let count = documents.len();
+        self.add_documents(documents);
+        fs::rename(&json_path, json_path.with_extension("json.bak"))?;
+        
+        logger::info("ai", &format!("已将 {} 条文档从 {} 导入 SQLite 索引", count, json_path.display()));
+        Ok(())
+    }
+    
+    /// 为还没有倒排索引的文档（早期版本写入的）建立索引
+    fn index_unindexed(&mut self) -> Result<()> {
+        let mut conn = self.conn.lock();
+        let pending: Vec<(i64, String)> = {
+            let mut stmt = conn.prepare("SELECT rowid, content FROM documents WHERE token_count IS NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+        
+        let tx = conn.transaction()?;
+        for (rowid, content) in &pending {
+            index_terms(&tx, *rowid, content)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    
+    /// 设置向量化服务，设置后新加入的文档会计算向量，检索时按余弦相似度排序
+    pub fn with_embedder(mut self, embedder: Option<EmbeddingService>) -> Self {
+        self.embedder = embedder;
+        self
+    }
+    
+    /// 设置重排序模型，设置后构建上下文时先多取一些候选，由模型按相关性重新排序
+    pub fn with_reranker(mut self, reranker: Option<AIService>) -> Self {
+        self.reranker = reranker;
+        self
+    }
+    
+    /// 设置查询扩展模型，设置后检索前先由模型补充同义词、公式名称和中英文术语
+    pub fn with_query_expander(mut self, query_expander: Option<AIService>) -> Self {
+        self.query_expander = query_expander;
+        self
+    }
+    
+    /// 为还没有向量的文档计算向量，返回处理的文档数
+    pub async fn embed_pending(&mut self) -> Result<usize> {
+        let embedder = match &self.embedder {
+            Some(embedder) => embedder.clone(),
+            None => return Ok(0),
+        };
+        
+        let pending: Vec<(i64, String)> = {
+            let conn = self.conn.lock();
+            let mut stmt = conn.prepare("SELECT rowid, content FROM documents WHERE embedding IS NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        if pending.is_empty() {
+            return Ok(0);
         }
+        
+        let texts: Vec<String> = pending.iter().map(|(_, content)| content.clone()).collect();
+        let embeddings = embedder.embed(&texts).await?;
+        
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        for ((rowid, _), embedding) in pending.iter().zip(embeddings) {
+            tx.execute(
+                "UPDATE documents SET embedding = ?1, embedding_model = ?2 WHERE rowid = ?3",
+                params![encode_embedding(&embedding), embedder.model_name(), rowid],
+            )?;
+        }
+        tx.commit()?;
+        
+        Ok(pending.len())
+    }
+    
+    /// 添加文档（已存在相同 ID 的文档时跳过）
+    pub fn add_document(&mut self, doc: Document) {
+        self.add_documents(vec![doc]);
     }
     
-    /// 批量添加文档
+    /// 批量添加文档，处于批量写入中时先暂存，commit 时统一写入
     pub fn add_documents(&mut self, docs: Vec<Document>) {
-        for doc in docs {
-            self.add_document(doc);
+        match self.staged.as_mut() {
+            Some(staged) => staged.extend(docs),
+            None => self.write_documents(&docs),
+        }
+    }
+    
+    /// 开始批量写入：之后添加的文档暂存在内存中，commit 时在一个事务中写入
+    /// 暂存的文档在提交前检索不到；未提交的文档在存储释放时自动提交
+    pub fn begin_batch(&mut self) {
+        if self.staged.is_none() {
+            self.staged = Some(Vec::new());
+        }
+    }
+    
+    /// 提交批量写入，返回提交的文档数
+    pub fn commit(&mut self) -> usize {
+        let docs = self.staged.take().unwrap_or_default();
+        if !docs.is_empty() {
+            self.write_documents(&docs);
         }
+        docs.len()
     }
     
-    /// 搜索相关文档（基于关键词匹配的简单实现）
-    pub fn search(&self, query: &str, top_k: usize) -> Vec<SearchResult> {
-        let query_lower = query.to_lowercase();
-        let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+    /// 在一个事务中写入文档
+    fn write_documents(&self, docs: &[Document]) {
+        use crate::logger;
         
-        let mut results: Vec<SearchResult> = self
-            .documents
-            .iter()
-            .map(|doc| {
-                let content_lower = doc.content.to_lowercase();
-                
-                // 计算匹配分数
-                let mut score = 0.0f32;
-                for word in &query_words {
-                    if content_lower.contains(word) {
-                        score += 1.0;
+        let mut conn = self.conn.lock();
+        let result = conn.transaction().and_then(|tx| {
+            for doc in docs {
+                // 同一页已有内容相同的文档时跳过（重复分析时分块 ID 可能变化）
+                let hash = content_hash(&doc.content);
+                let duplicate: bool = tx.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM documents WHERE file_id = ?1 AND page_number = ?2 AND doc_type = ?3 AND content_hash = ?4)",
+                    params![doc.metadata.file_id, doc.metadata.page_number, doc.metadata.doc_type, hash],
+                    |row| row.get(0),
+                )?;
+                if duplicate {
+                    continue;
+                }
+                let inserted = tx.execute(
+                    &format!(
+                        "INSERT OR IGNORE INTO documents ({}, content_hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        DOCUMENT_COLUMNS
+                    ),
+                    params![
+                        doc.id,
+                        doc.content,
+                        doc.metadata.file_id,
+                        doc.metadata.page_number,
+                        doc.metadata.chunk_index,
+                        doc.metadata.doc_type,
+                        doc.metadata.chapter,
+                        doc.metadata.section,
+                        doc.embedding.as_deref().map(encode_embedding),
+                        doc.embedding.as_ref().map(|_| doc.embedding_model.as_str()),
+                        hash,
+                    ],
+                )?;
+                if inserted > 0 {
+                    index_terms(&tx, tx.last_insert_rowid(), &doc.content)?;
+                }
+            }
+            tx.commit()
+        });
+        if let Err(e) = result {
+            logger::warn("ai", &format!("写入 RAG 索引失败: {}", e));
+        }
+    }
+    
+    /// 搜索相关文档
+    /// 配置了向量化模型且文档已有向量时按余弦相似度检索，否则使用关键词匹配
+    pub async fn search(&self, query: &str, top_k: usize) -> Vec<SearchResult> {
+        self.search_filtered(query, top_k, &SearchFilter::default()).await
+    }
+    
+    /// 按条件搜索相关文档
+    pub async fn search_filtered(&self, query: &str, top_k: usize, filter: &SearchFilter) -> Vec<SearchResult> {
+        use crate::logger;
+        
+        let expanded = self.expand_query(query).await;
+        let query = expanded.as_str();
+        
+        if let Some(embedder) = &self.embedder {
+            if self.has_embeddings() {
+                match embedder.embed_one(query).await {
+                    Ok(query_embedding) => {
+                        let results = self.vector_search(&query_embedding, top_k, filter);
+                        if !results.is_empty() {
+                            return results;
+                        }
                     }
+                    Err(e) => logger::warn("ai", &format!("查询向量化失败，改用关键词检索: {}", e)),
                 }
-                
-                // 考虑文档类型权重
-                let type_weight = match doc.metadata.doc_type.as_str() {
-                    "example" => 1.5,
-                    "knowledge" => 1.2,
-                    "exercise" => 1.0,
-                    _ => 0.8,
-                };
-                
-                SearchResult {
-                    document: doc.clone(),
-                    score: score * type_weight,
+            }
+        }
+        self.keyword_search(query, top_k, filter)
+    }
+    
+    /// 是否有已计算向量的文档
+    /// 扩展查询，未设置扩展模型或调用失败时返回原查询
+    /// 同一查询的扩展结果会缓存，本文件和全局知识库检索时只调用一次模型
+    async fn expand_query(&self, query: &str) -> String {
+        use crate::logger;
+        
+        let expander = match &self.query_expander {
+            Some(expander) => expander,
+            None => return query.to_string(),
+        };
+        let key = format!("{}\0{}", expander.model_name(), query);
+        if let Some(expanded) = EXPANSION_CACHE.lock().get(&key) {
+            return expanded.clone();
+        }
+        
+        match expander.expand_query(query).await {
+            Ok(terms) if !terms.is_empty() => {
+                let expanded = format!("{}\n{}", query, terms.join(" "));
+                let mut cache = EXPANSION_CACHE.lock();
+                if cache.len() >= MAX_EXPANSION_CACHE {
+                    cache.clear();
                 }
+                cache.insert(key, expanded.clone());
+                expanded
+            }
+            Ok(_) => query.to_string(),
+            Err(e) => {
+                logger::warn("ai", &format!("查询扩展失败，使用原查询: {}", e));
+                query.to_string()
+            }
+        }
+    }
+    
+    fn has_embeddings(&self) -> bool {
+        self.conn
+            .lock()
+            .query_row("SELECT EXISTS(SELECT 1 FROM documents WHERE embedding IS NOT NULL)", [], |row| row.get(0))
+            .unwrap_or(false)
+    }
+    
+    /// 按余弦相似度检索，跳过没有向量或维度不一致的文档
+    fn vector_search(&self, query_embedding: &[f32], top_k: usize, filter: &SearchFilter) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = self
+            .query_documents("embedding IS NOT NULL", [])
+            .into_iter()
+            .filter(|doc| filter.matches(&doc.metadata))
+            .filter_map(|doc| {
+                let embedding = doc.embedding.as_ref()?;
+                if embedding.len() != query_embedding.len() {
+                    return None;
+                }
+                let score = cosine_similarity(query_embedding, embedding);
+                Some(SearchResult { document: doc, score })
             })
             .filter(|r| r.score > 0.0)
             .collect();
         
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        results.into_iter().take(top_k).collect()
+    }
+    
+    /// 基于关键词的 BM25 检索
+    /// 查询和文档都经过 jieba 分词，按词频、逆文档频率和文档长度打分
+    pub fn keyword_search(&self, query: &str, top_k: usize, filter: &SearchFilter) -> Vec<SearchResult> {
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+        
+        let mut scores: HashMap<i64, f32> = HashMap::new();
+        {
+            let conn = self.conn.lock();
+            let (doc_count, avg_len): (i64, f64) = conn
+                .query_row(
+                    "SELECT COUNT(*), COALESCE(AVG(token_count), 0) FROM documents WHERE token_count IS NOT NULL",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap_or((0, 0.0));
+            if doc_count == 0 {
+                return Vec::new();
+            }
+            let avg_len = (avg_len as f32).max(1.0);
+            
+            for term in &query_terms {
+                let postings: rusqlite::Result<Vec<(i64, u32, u32)>> = conn
+                    .prepare_cached(
+                        "SELECT p.doc, p.tf, d.token_count FROM postings p \
+                         JOIN documents d ON d.rowid = p.doc WHERE p.term = ?1",
+                    )
+                    .and_then(|mut stmt| stmt.query_map([term], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?.collect());
+                let postings = postings.unwrap_or_default();
+                if postings.is_empty() {
+                    continue;
+                }
+                
+                let df = postings.len() as f32;
+                let idf = ((doc_count as f32 - df + 0.5) / (df + 0.5) + 1.0).ln();
+                for (doc, tf, doc_len) in postings {
+                    let tf = tf as f32;
+                    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * doc_len as f32 / avg_len);
+                    *scores.entry(doc).or_insert(0.0) += idf * tf * (BM25_K1 + 1.0) / (tf + norm);
+                }
+            }
+        }
+        
+        // 先按 BM25 分数取符合条件的候选，再考虑文档类型权重
+        let mut candidates: Vec<(i64, f32)> = scores.into_iter().collect();
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        
+        let mut results: Vec<SearchResult> = Vec::new();
+        for (rowid, score) in candidates {
+            if results.len() >= top_k * 3 {
+                break;
+            }
+            let doc = match self.query_documents("rowid = ?1", [rowid]).into_iter().next() {
+                Some(doc) if filter.matches(&doc.metadata) => doc,
+                _ => continue,
+            };
+            let type_weight = match doc.metadata.doc_type.as_str() {
+                "example" => 1.5,
+                "knowledge" => 1.2,
+                "exercise" => 1.0,
+                _ => 0.8,
+            };
+            results.push(SearchResult {
+                document: doc,
+                score: score * type_weight,
+            });
+        }
+        
         // 按分数排序
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
         
         // 返回 top_k 结果
         results.into_iter().take(top_k).collect()
     }
     
-    /// 按类型获取文档
-    pub fn get_by_type(&self, doc_type: &str) -> Vec<&Document> {
-        self.documents
-            .iter()
-            .filter(|d| d.metadata.doc_type == doc_type)
+    /// 按条件查询文档
+    fn query_documents<P: rusqlite::Params>(&self, condition: &str, params: P) -> Vec<Document> {
+        let conn = self.conn.lock();
+        let sql = format!("SELECT {} FROM documents WHERE {} ORDER BY rowid", DOCUMENT_COLUMNS, condition);
+        let result = conn.prepare_cached(&sql).and_then(|mut stmt| {
+            stmt.query_map(params, |row| {
+                Ok(Document {
+                    id: row.get(0)?,
+                    content: row.get(1)?,
+                    metadata: DocumentMetadata {
+                        file_id: row.get(2)?,
+                        page_number: row.get(3)?,
+                        chunk_index: row.get(4)?,
+                        doc_type: row.get(5)?,
+                        chapter: row.get(6)?,
+                        section: row.get(7)?,
+                    },
+                    embedding: row.get::<_, Option<Vec<u8>>>(8)?.map(|bytes| decode_embedding(&bytes)),
+                    embedding_model: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+                })
+            })?
             .collect()
+        });
+        result.unwrap_or_default()
+    }
+    
+    /// 获取全部文档
+    pub fn all_documents(&self) -> Vec<Document> {
+        self.query_documents("1", [])
+    }
+    
+    /// 导出全部文档，向量连同生成它的模型一起导出；不知道模型的向量（早期版本写入）不导出
+    pub fn export(&self) -> RAGExport {
+        let mut documents = self.all_documents();
+        for doc in documents.iter_mut() {
+            if doc.embedding_model.is_empty() {
+                doc.embedding = None;
+            }
+        }
+        RAGExport {
+            format: RAG_EXPORT_FORMAT.to_string(),
+            version: RAG_EXPORT_VERSION,
+            exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            embedding_model: String::new(),
+            documents,
+        }
+    }
+    
+    /// 按类型获取文档
+    pub fn get_by_type(&self, doc_type: &str) -> Vec<Document> {
+        self.query_documents("doc_type = ?1", [doc_type])
     }
     
     /// 按章节获取文档
-    pub fn get_by_chapter(&self, chapter: &str) -> Vec<&Document> {
-        self.documents
-            .iter()
-            .filter(|d| d.metadata.chapter == chapter)
-            .collect()
+    pub fn get_by_chapter(&self, chapter: &str) -> Vec<Document> {
+        self.query_documents("chapter = ?1", [chapter])
     }
     
     /// 获取所有例题
-    pub fn get_examples(&self) -> Vec<&Document> {
+    pub fn get_examples(&self) -> Vec<Document> {
         self.get_by_type("example")
     }
     
     /// 获取所有知识点
-    pub fn get_knowledge(&self) -> Vec<&Document> {
+    pub fn get_knowledge(&self) -> Vec<Document> {
         self.get_by_type("knowledge")
     }
     
     /// 构建上下文
-    pub fn build_context(&self, query: &str, max_tokens: usize) -> String {
-        let results = self.search(query, 10);
-        
-        let mut context = String::new();
-        let mut token_count = 0;
-        
-        for result in results {
-            let doc_text = format!(
-                "【{}】{}\n{}\n\n",
-                result.document.metadata.doc_type,
-                if !result.document.metadata.chapter.is_empty() {
-                    format!("（{}）", result.document.metadata.chapter)
-                } else {
-                    String::new()
-                },
-                result.document.content
-            );
-            
-            let doc_tokens = doc_text.len() / 4; // 粗略估计
-            if token_count + doc_tokens > max_tokens {
-                break;
+    pub async fn build_context(&self, query: &str, max_tokens: usize) -> String {
+        self.build_context_with_sources(query, max_tokens).await.0
+    }
+    
+    /// 构建上下文，同时返回引用的来源（按文件和页码去重、按出现顺序）
+    pub async fn build_context_with_sources(&self, query: &str, max_tokens: usize) -> (String, Vec<Citation>) {
+        self.build_context_filtered(query, max_tokens, &SearchFilter::default()).await
+    }
+    
+    /// 按条件检索并构建上下文，同时返回引用的来源
+    /// 每段内容开头标有 [文件ID:页码] 引用标记，生成的答案和解析可以据此指回教材原页
+    pub async fn build_context_filtered(
+        &self,
+        query: &str,
+        max_tokens: usize,
+        filter: &SearchFilter,
+    ) -> (String, Vec<Citation>) {
+        let results = match &self.reranker {
+            Some(reranker) => {
+                let candidates = self.search_filtered(query, RERANK_CANDIDATES, filter).await;
+                rerank_results(reranker, query, candidates, CONTEXT_RESULTS).await
             }
+            None => self.search_filtered(query, CONTEXT_RESULTS, filter).await,
+        };
+        
+        format_context(results, max_tokens)
+    }
+    
+    /// 按文档类型配额构建上下文，例如 [("example", 2), ("knowledge", 3)] 表示最多 2 道例题、3 段知识内容
+    /// 每种类型的最佳结果排在最前，只要有对应内容，上下文中每种类型至少会有一条
+    pub async fn build_context_with_quotas(
+        &self,
+        query: &str,
+        max_tokens: usize,
+        quotas: &[(&str, usize)],
+        filter: &SearchFilter,
+    ) -> (String, Vec<Citation>) {
+        let mut best = Vec::new();
+        let mut rest = Vec::new();
+        for &(doc_type, quota) in quotas {
+            if quota == 0 || (!filter.doc_types.is_empty() && !filter.doc_types.iter().any(|t| t == doc_type)) {
+                continue;
+            }
+            let type_filter = SearchFilter {
+                doc_types: vec![doc_type.to_string()],
+                ..filter.clone()
+            };
+            let results = match &self.reranker {
+                Some(reranker) => {
+                    let candidates = self.search_filtered(query, RERANK_CANDIDATES, &type_filter).await;
+                    rerank_results(reranker, query, candidates, quota).await
+                }
+                None => self.search_filtered(query, quota, &type_filter).await,
+            };
             
-            context.push_str(&doc_text);
-            token_count += doc_tokens;
+            let mut results = results.into_iter();
+            best.extend(results.next());
+            rest.extend(results);
         }
         
-        context
+        best.extend(rest);
+        format_context(best, max_tokens)
+    }
+    
+    /// 删除某个文件的全部文档（删除文件或整个文件重新导入时使用），返回删除的文档数
+    pub fn remove_by_file(&mut self, file_id: &str) -> Result<usize> {
+        Ok(self.conn.lock().execute("DELETE FROM documents WHERE file_id = ?1", [file_id])?)
+    }
+    
+    /// 删除某个文件一页的全部文档（重新导入修正后的页面时使用），返回删除的文档数
+    pub fn remove_by_page(&mut self, file_id: &str, page: u32) -> Result<usize> {
+        Ok(self.conn.lock().execute(
+            "DELETE FROM documents WHERE file_id = ?1 AND page_number = ?2",
+            params![file_id, page],
+        )?)
     }
     
-    /// 保存到文件
-    fn save(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self.documents)?;
+    /// 压缩索引：合并同一页内容相同的重复文档（优先保留已有向量的），清理倒排索引并回收空间
+    pub fn compact(&mut self) -> Result<CompactResult> {
+        let file_size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let documents_before = self.len();
+        let size_before = file_size(&self.index_path);
         
-        if let Some(parent) = self.index_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+        let duplicates_removed = {
+            let conn = self.conn.lock();
+            let removed = conn.execute(
+                "DELETE FROM documents WHERE rowid NOT IN (
+                    SELECT COALESCE(MIN(CASE WHEN embedding IS NOT NULL THEN rowid END), MIN(rowid))
+                    FROM documents GROUP BY file_id, page_number, doc_type, content_hash
+                )",
+                [],
+            )?;
+            conn.execute("DELETE FROM postings WHERE doc NOT IN (SELECT rowid FROM documents)", [])?;
+            conn.execute_batch("VACUUM;")?;
+            removed
+        };
         
-        fs::write(&self.index_path, content)?;
-        Ok(())
+        Ok(CompactResult {
+            documents_before,
+            duplicates_removed,
+            size_before,
+            size_after: file_size(&self.index_path),
+        })
     }
     
     /// 清空存储
     pub fn clear(&mut self) {
-        self.documents.clear();
-        self.save().ok();
+        self.conn.lock().execute_batch("DELETE FROM postings; DELETE FROM documents;").ok();
     }
     
     /// 获取文档数量
     pub fn len(&self) -> usize {
-        self.documents.len()
+        self.conn
+            .lock()
+            .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get::<_, i64>(0))
+            .unwrap_or(0) as usize
     }
     
     /// 是否为空
     pub fn is_empty(&self) -> bool {
-        self.documents.is_empty()
+        self.len() == 0
+    }
+}
+
+impl Drop for RAGStore {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// 按顺序拼接检索结果，超出 token 上限时停止；每段内容以 [文件ID:页码] 引用标记开头
+fn format_context(results: Vec<SearchResult>, max_tokens: usize) -> (String, Vec<Citation>) {
+    let mut context = String::new();
+    let mut citations: Vec<Citation> = Vec::new();
+    let mut token_count = 0;
+    
+    for result in results {
+        let metadata = &result.document.metadata;
+        let marker = citation_marker(&metadata.file_id, metadata.page_number);
+        let doc_text = format!(
+            "{} 【{}】第 {} 页{}\n{}\n\n",
+            marker,
+            metadata.doc_type,
+            metadata.page_number,
+            if !metadata.chapter.is_empty() {
+                format!("（{}）", metadata.chapter)
+            } else {
+                String::new()
+            },
+            result.document.content
+        );
+        
+        let doc_tokens = crate::ai_service::count_tokens(&doc_text);
+        if token_count + doc_tokens > max_tokens {
+            break;
+        }
+        
+        context.push_str(&doc_text);
+        token_count += doc_tokens;
+        if !citations.iter().any(|c| c.marker == marker) {
+            citations.push(Citation {
+                marker,
+                file_id: metadata.file_id.clone(),
+                page_number: metadata.page_number,
+                chapter: metadata.chapter.clone(),
+            });
+        }
+    }
+    
+    (context, citations)
+}
+
+/// 使用模型为候选结果打分并重新排序，去掉评为不相关的内容，返回前 top_k 条；失败时保持原有顺序
+async fn rerank_results(
+    reranker: &AIService,
+    query: &str,
+    mut candidates: Vec<SearchResult>,
+    top_k: usize,
+) -> Vec<SearchResult> {
+    use crate::logger;
+    
+    if candidates.len() < 2 {
+        return candidates;
+    }
+    let passages: Vec<String> = candidates.iter().map(|r| r.document.content.clone()).collect();
+    let scores = match reranker.rerank(query, &passages).await {
+        Ok(scores) => scores,
+        Err(e) => {
+            logger::warn("ai", &format!("检索结果重排序失败，使用原始顺序: {}", e));
+            candidates.truncate(top_k);
+            return candidates;
+        }
+    };
+    
+    let mut results: Vec<SearchResult> = candidates
+        .into_iter()
+        .zip(scores)
+        .filter(|(_, score)| *score > 0.0)
+        .map(|(result, score)| SearchResult { score, ..result })
+        .collect();
+    // 分数相同时保持原有顺序
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(top_k);
+    results
+}
+
+/// 向量以小端 f32 序列保存
+pub fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// 余弦相似度
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// 分词：英文转为小写，中文使用 jieba 搜索引擎模式切分，去掉空白和标点
+pub fn tokenize(text: &str) -> Vec<String> {
+    let text = text.to_lowercase();
+    JIEBA
+        .cut_for_search(&text, true)
+        .into_iter()
+        .map(|term| term.trim())
+        .filter(|term| term.chars().any(|c| c.is_alphanumeric()))
+        .map(|term| term.to_string())
+        .collect()
+}
+
+/// 写入文档的倒排索引和词数
+fn index_terms(conn: &Connection, rowid: i64, content: &str) -> rusqlite::Result<()> {
+    let terms = tokenize(content);
+    let mut tf: HashMap<&str, u32> = HashMap::new();
+    for term in &terms {
+        *tf.entry(term.as_str()).or_insert(0) += 1;
+    }
+    
+    conn.execute("DELETE FROM postings WHERE doc = ?1", [rowid])?;
+    let mut stmt = conn.prepare_cached("INSERT INTO postings (term, doc, tf) VALUES (?1, ?2, ?3)")?;
+    for (term, count) in tf {
+        stmt.execute(params![term, rowid, count])?;
+    }
+    conn.execute(
+        "UPDATE documents SET token_count = ?1 WHERE rowid = ?2",
+        params![terms.len() as i64, rowid],
+    )?;
+    Ok(())
+}
+
+/// 升级早期版本的表结构
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let has_content_hash: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info('documents') WHERE name = 'content_hash')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_content_hash {
+        conn.execute_batch("ALTER TABLE documents ADD COLUMN content_hash TEXT;")?;
+    }
+    
+    let has_embedding_model: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info('documents') WHERE name = 'embedding_model')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_embedding_model {
+        conn.execute_batch("ALTER TABLE documents ADD COLUMN embedding_model TEXT;")?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_documents_hash ON documents(file_id, page_number, content_hash);",
+    )?;
+    
+    // 为早期版本写入的文档补算内容哈希
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT rowid, content FROM documents WHERE content_hash IS NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    if !pending.is_empty() {
+        let tx = conn.unchecked_transaction()?;
+        for (rowid, content) in &pending {
+            tx.execute(
+                "UPDATE documents SET content_hash = ?1 WHERE rowid = ?2",
+                params![content_hash(content), rowid],
+            )?;
+        }
+        tx.commit()?;
     }
+    Ok(())
+}
+
+/// 内容哈希，忽略空白和大小写差异（OCR 重新识别时排版可能略有不同）
+fn content_hash(content: &str) -> String {
+    use sha2::{Digest, Sha256};
+    
+    let normalized: String = content
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
 }
 
 /// 文本分块器
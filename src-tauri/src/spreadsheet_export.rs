@@ -0,0 +1,237 @@
+// 表格导出模块 - 将题目导出为 CSV 或 Excel（.xlsx）表格，每道题一行，导出的列可以自定义
+// CSV 带 UTF-8 BOM，Excel 直接打开时中文不会乱码；.xlsx 按 Office Open XML 格式直接生成
+
+use crate::commands::Question;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// 未指定列时导出的列
+pub const DEFAULT_COLUMNS: &[&str] = &[
+    "question",
+    "answer",
+    "analysis",
+    "chapter",
+    "knowledge_points",
+    "difficulty",
+    "page",
+];
+
+/// 可以导出的列及表头
+const COLUMNS: &[(&str, &str)] = &[
+    ("id", "题目ID"),
+    ("question_type", "类型"),
+    ("question", "题目"),
+    ("options", "选项"),
+    ("correct_options", "正确选项"),
+    ("answer", "答案"),
+    ("analysis", "解析"),
+    ("chapter", "章节"),
+    ("section", "小节"),
+    ("knowledge_points", "知识点"),
+    ("difficulty", "难度"),
+    ("page", "页码"),
+    ("review_status", "审核状态"),
+];
+
+/// 检查列名，未指定时使用默认列
+pub fn resolve_columns(columns: &[String]) -> Result<Vec<String>> {
+    if columns.is_empty() {
+        return Ok(DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect());
+    }
+    for column in columns {
+        if header(column).is_none() {
+            return Err(anyhow!(
+                "不支持的列: {}（可选: {}）",
+                column,
+                COLUMNS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+    Ok(columns.to_vec())
+}
+
+fn header(column: &str) -> Option<&'static str> {
+    COLUMNS.iter().find(|(name, _)| *name == column).map(|(_, header)| *header)
+}
+
+/// 单元格内容
+fn cell(q: &Question, column: &str) -> String {
+    match column {
+        "id" => q.id.clone(),
+        "question_type" => match q.question_type.as_str() {
+            "example" => "例题".to_string(),
+            "exercise" => "习题".to_string(),
+            other => other.to_string(),
+        },
+        "question" => q.question_text.trim().to_string(),
+        "options" => q
+            .options
+            .iter()
+            .map(|o| format!("{}. {}", o.label, o.text))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "correct_options" => q.correct_options.join(""),
+        "answer" => q.answer.trim().to_string(),
+        "analysis" => q.analysis.trim().to_string(),
+        "chapter" => q.chapter.clone(),
+        "section" => q.section.clone(),
+        "knowledge_points" => q.knowledge_points.join("; "),
+        "difficulty" => match q.difficulty.as_str() {
+            "easy" => "简单".to_string(),
+            "medium" => "中等".to_string(),
+            "hard" => "困难".to_string(),
+            other => other.to_string(),
+        },
+        "page" => q.page_number.to_string(),
+        "review_status" => match q.review_status.as_str() {
+            "pending" => "待审核".to_string(),
+            "approved" => "已通过".to_string(),
+            "rejected" => "未通过".to_string(),
+            other => other.to_string(),
+        },
+        _ => String::new(),
+    }
+}
+
+/// 导出 CSV，返回导出的题目数
+pub fn write_csv(dest_path: &Path, columns: &[String], questions: &[Question]) -> Result<usize> {
+    let mut csv = String::from("\u{feff}");
+    let headers: Vec<&str> = columns.iter().map(|c| header(c).unwrap_or(c.as_str())).collect();
+    csv.push_str(&csv_row(&headers));
+    for q in questions {
+        let cells: Vec<String> = columns.iter().map(|c| cell(q, c)).collect();
+        csv.push_str(&csv_row(&cells));
+    }
+    fs::write(dest_path, csv)?;
+    Ok(questions.len())
+}
+
+fn csv_row<S: AsRef<str>>(cells: &[S]) -> String {
+    let mut row = cells
+        .iter()
+        .map(|cell| {
+            let cell = cell.as_ref();
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// 导出 Excel 工作簿（单个工作表，首行为表头），返回导出的题目数
+pub fn write_xlsx(dest_path: &Path, columns: &[String], questions: &[Question]) -> Result<usize> {
+    let file = fs::File::create(dest_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES_XML.to_string()),
+        ("_rels/.rels", ROOT_RELS_XML.to_string()),
+        ("xl/workbook.xml", WORKBOOK_XML.to_string()),
+        ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS_XML.to_string()),
+        ("xl/styles.xml", STYLES_XML.to_string()),
+        ("xl/worksheets/sheet1.xml", sheet_xml(columns, questions)),
+    ];
+    for (name, content) in parts {
+        zip.start_file(name, options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(questions.len())
+}
+
+fn sheet_xml(columns: &[String], questions: &[Question]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">",
+    );
+    // 冻结表头行
+    xml.push_str("<sheetViews><sheetView workbookViewId=\"0\"><pane ySplit=\"1\" topLeftCell=\"A2\" activePane=\"bottomLeft\" state=\"frozen\"/></sheetView></sheetViews>");
+    xml.push_str("<cols>");
+    for (index, column) in columns.iter().enumerate() {
+        let width = match column.as_str() {
+            "question" | "answer" | "analysis" | "options" => 60,
+            "chapter" | "section" | "knowledge_points" => 24,
+            "id" => 38,
+            _ => 10,
+        };
+        xml.push_str(&format!("<col min=\"{0}\" max=\"{0}\" width=\"{1}\" customWidth=\"1\"/>", index + 1, width));
+    }
+    xml.push_str("</cols><sheetData>");
+
+    xml.push_str("<row r=\"1\">");
+    for (index, column) in columns.iter().enumerate() {
+        xml.push_str(&string_cell(index, 1, header(column).unwrap_or(column.as_str()), 1));
+    }
+    xml.push_str("</row>");
+    for (row_index, q) in questions.iter().enumerate() {
+        let row = row_index + 2;
+        xml.push_str(&format!("<row r=\"{}\">", row));
+        for (index, column) in columns.iter().enumerate() {
+            if column == "page" {
+                xml.push_str(&format!("<c r=\"{}{}\"><v>{}</v></c>", column_name(index), row, q.page_number));
+            } else {
+                xml.push_str(&string_cell(index, row, &cell(q, column), 2));
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// 文本单元格，style 为 styles.xml 中的格式序号（1 表头加粗，2 自动换行）
+fn string_cell(column: usize, row: usize, text: &str, style: u32) -> String {
+    format!(
+        "<c r=\"{}{}\" s=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+        column_name(column),
+        row,
+        style,
+        escape_xml(text)
+    )
+}
+
+/// 列序号（从 0 开始）转换为列名：A, B, ..., Z, AA, ...
+fn column_name(index: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// 转义 XML，并去掉 XML 中不允许出现的控制字符
+fn escape_xml(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const CONTENT_TYPES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="题目" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+// 格式 0 默认，1 表头加粗，2 自动换行、顶端对齐
+const STYLES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="3"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0" applyAlignment="1"><alignment vertical="top" wrapText="1"/></xf></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
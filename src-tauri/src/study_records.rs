@@ -0,0 +1,198 @@
+// 练习记录模块 - 记录每次作答，整理错题本并统计知识点掌握程度
+// 作答记录保存在应用数据目录（每行一条 JSON 记录），跨文件汇总
+
+use crate::commands::Question;
+use anyhow::Result;
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 计算掌握程度时每道题参考的最近作答次数
+const MASTERY_RECENT_ATTEMPTS: usize = 3;
+
+// 作答记录文件路径
+static ATTEMPTS_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+// 已加载的作答记录，按时间先后排列
+static ATTEMPTS: Lazy<RwLock<Vec<Attempt>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 一次作答
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    pub question_id: String,
+    pub file_id: String,
+    pub correct: bool,
+    #[serde(default)]
+    pub user_answer: String,
+    pub answered_at: String,
+}
+
+/// 错题本中的一道题：最近一次作答错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrongQuestion {
+    pub question: Question,
+    pub attempts: u32,
+    pub wrong_count: u32,
+    pub last_user_answer: String,
+    pub last_answered_at: String,
+}
+
+/// 知识点掌握程度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgePointMastery {
+    pub name: String,
+    pub questions: u32,           // 包含该知识点的题目数
+    pub attempted_questions: u32, // 作答过的题目数
+    pub attempts: u32,
+    pub correct: u32,
+    pub mastery: f32, // 0~1，作答过的题目最近几次作答的平均正确率
+}
+
+/// 初始化作答记录路径并加载（在应用启动时调用）
+pub fn init_store(app_dir: &Path) {
+    use crate::logger;
+
+    let path = app_dir.join("practice_attempts.jsonl");
+    let attempts: Vec<Attempt> = fs::read_to_string(&path)
+        .map(|content| {
+            content
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .filter_map(|l| serde_json::from_str(l).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !attempts.is_empty() {
+        logger::debug("system", &format!("已加载 {} 条作答记录", attempts.len()));
+    }
+
+    *ATTEMPTS.write() = attempts;
+    *ATTEMPTS_PATH.write() = Some(path);
+}
+
+/// 记录一次作答
+pub fn record(question: &Question, correct: bool, user_answer: &str) -> Result<Attempt> {
+    let attempt = Attempt {
+        question_id: question.id.clone(),
+        file_id: question.file_id.clone(),
+        correct,
+        user_answer: user_answer.to_string(),
+        answered_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    if let Some(path) = ATTEMPTS_PATH.read().as_ref() {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(&attempt)?)?;
+    }
+    ATTEMPTS.write().push(attempt.clone());
+    Ok(attempt)
+}
+
+/// 各题目的作答记录（按时间先后）
+fn attempts_by_question() -> HashMap<String, Vec<Attempt>> {
+    let mut grouped: HashMap<String, Vec<Attempt>> = HashMap::new();
+    for attempt in ATTEMPTS.read().iter() {
+        grouped.entry(attempt.question_id.clone()).or_default().push(attempt.clone());
+    }
+    grouped
+}
+
+/// 错题本：最近一次作答错误的题目，最近答错的排在前面
+pub fn wrong_questions(questions: &[Question]) -> Vec<WrongQuestion> {
+    let grouped = attempts_by_question();
+    let mut wrong: Vec<WrongQuestion> = questions
+        .iter()
+        .filter_map(|q| {
+            let attempts = grouped.get(&q.id)?;
+            let last = attempts.last()?;
+            if last.correct {
+                return None;
+            }
+            Some(WrongQuestion {
+                question: q.clone(),
+                attempts: attempts.len() as u32,
+                wrong_count: attempts.iter().filter(|a| !a.correct).count() as u32,
+                last_user_answer: last.user_answer.clone(),
+                last_answered_at: last.answered_at.clone(),
+            })
+        })
+        .collect();
+    wrong.sort_by(|a, b| b.last_answered_at.cmp(&a.last_answered_at));
+    wrong
+}
+
+/// 按知识点统计掌握程度，掌握程度低的排在前面（没有作答过的排在最后）
+pub fn knowledge_point_mastery(questions: &[Question]) -> Vec<KnowledgePointMastery> {
+    let grouped = attempts_by_question();
+    let mut stats: HashMap<String, (KnowledgePointMastery, f32)> = HashMap::new();
+    for q in questions {
+        let attempts = grouped.get(&q.id);
+        for kp in &q.knowledge_points {
+            let (stat, score_sum) = stats.entry(kp.clone()).or_insert_with(|| {
+                (
+                    KnowledgePointMastery {
+                        name: kp.clone(),
+                        questions: 0,
+                        attempted_questions: 0,
+                        attempts: 0,
+                        correct: 0,
+                        mastery: 0.0,
+                    },
+                    0.0,
+                )
+            });
+            stat.questions += 1;
+            if let Some(attempts) = attempts.filter(|a| !a.is_empty()) {
+                stat.attempted_questions += 1;
+                stat.attempts += attempts.len() as u32;
+                stat.correct += attempts.iter().filter(|a| a.correct).count() as u32;
+                let recent: Vec<&Attempt> = attempts.iter().rev().take(MASTERY_RECENT_ATTEMPTS).collect();
+                *score_sum += recent.iter().filter(|a| a.correct).count() as f32 / recent.len() as f32;
+            }
+        }
+    }
+
+    let mut result: Vec<KnowledgePointMastery> = stats
+        .into_values()
+        .map(|(mut stat, score_sum)| {
+            if stat.attempted_questions > 0 {
+                stat.mastery = score_sum / stat.attempted_questions as f32;
+            }
+            stat
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        (a.attempted_questions == 0)
+            .cmp(&(b.attempted_questions == 0))
+            .then_with(|| a.mastery.partial_cmp(&b.mastery).unwrap_or(std::cmp::Ordering::Equal))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    result
+}
+
+/// 清除作答记录，question_id 为空时清除全部
+pub fn clear(question_id: Option<&str>) -> Result<()> {
+    let remaining = {
+        let mut attempts = ATTEMPTS.write();
+        match question_id {
+            Some(id) => attempts.retain(|a| a.question_id != id),
+            None => attempts.clear(),
+        }
+        attempts.clone()
+    };
+    if let Some(path) = ATTEMPTS_PATH.read().as_ref() {
+        let mut content = String::new();
+        for attempt in &remaining {
+            content.push_str(&serde_json::to_string(attempt)?);
+            content.push('\n');
+        }
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
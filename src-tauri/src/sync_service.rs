@@ -0,0 +1,536 @@
+// 同步模块 - 把题目、组合题库和配置推送到 WebDAV 或 S3（兼容 MinIO 等）存储，或从远端拉取，在多台电脑之间使用同一份题库
+// 远端目录中的 manifest.json 记录每个条目的内容哈希；本地的 sync_state.json 记录上次同步时的哈希
+// 只有一边有变化时推送或拉取；两边都有变化且内容不同时记为冲突，不覆盖任何一边，可以指定以本地或远端为准再次同步
+// 同步的内容：各文件的元数据和题目（源文件和转换缓存不同步）、组合题库、知识点别名表，以及可选的配置（存储路径和同步设置保留本机的）
+// 不同步删除：一边删除的条目在另一边保留，下次同步时会重新出现
+
+use crate::commands::{AppConfig, FileInfo, SyncConfig};
+use crate::utils::is_safe_id;
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const MANIFEST_KEY: &str = "manifest.json";
+const STATE_FILE: &str = "sync_state.json";
+const DEFAULT_REMOTE_DIR: &str = "booq";
+
+/// 远端 manifest.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncManifest {
+    #[serde(default)]
+    pub entries: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub hash: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub device: String,
+}
+
+/// 本地的同步状态：上次同步时各条目的内容哈希，remote 为远端的标识，更换远端后重新开始
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SyncState {
+    #[serde(default)]
+    remote: String,
+    #[serde(default)]
+    entries: BTreeMap<String, String>,
+}
+
+/// 同步结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncSummary {
+    pub pushed: Vec<String>,
+    pub pulled: Vec<String>,
+    pub conflicts: Vec<String>, // 两边都有修改的条目，未同步
+    pub skipped: Vec<String>,   // 正在分析等原因暂未同步的条目
+    pub unchanged: usize,
+}
+
+/// 发生冲突时以哪一边为准
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    None,
+    Local,
+    Remote,
+}
+
+impl ConflictResolution {
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.map(str::trim).unwrap_or("") {
+            "" => Ok(Self::None),
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            other => Err(anyhow!("不支持的冲突处理方式: {}（可选: local、remote）", other)),
+        }
+    }
+}
+
+/// 同步一次：推送本地的修改，拉取远端的修改
+pub async fn sync_now(app_handle: &AppHandle, resolution: ConflictResolution) -> Result<SyncSummary> {
+    use crate::logger;
+
+    let config = crate::config::get_config_sync(app_handle);
+    let mut remote = Remote::new(&config.sync)?;
+    let app_dir = app_data_dir(app_handle)?;
+    let storage_root = crate::file_manager::get_storage_root(app_handle);
+
+    let state_path = app_dir.join(STATE_FILE);
+    let mut state: SyncState = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if state.remote != remote.identity() {
+        state = SyncState {
+            remote: remote.identity(),
+            entries: BTreeMap::new(),
+        };
+    }
+    let mut manifest: SyncManifest = match remote.get(MANIFEST_KEY).await? {
+        Some(bytes) => serde_json::from_slice(&bytes).map_err(|e| anyhow!("远端的 manifest.json 无法解析: {}", e))?,
+        None => SyncManifest::default(),
+    };
+
+    let local = local_entries(&config, &app_dir, &storage_root);
+    let keys: BTreeSet<String> = local.keys().chain(manifest.entries.keys()).cloned().collect();
+    let device = device_name();
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut summary = SyncSummary::default();
+    let mut pulled_any = false;
+
+    for key in keys {
+        let local_content = local.get(&key);
+        let local_hash = local_content.map(|content| content_hash(content));
+        let remote_hash = manifest.entries.get(&key).map(|e| e.hash.clone());
+        let base = state.entries.get(&key).cloned();
+
+        let action = match (&local_hash, &remote_hash) {
+            (Some(l), Some(r)) if l == r => Action::None,
+            (Some(_), None) => Action::Push,
+            (None, Some(_)) => Action::Pull,
+            (None, None) => Action::None,
+            (Some(l), Some(r)) => {
+                let local_changed = base.as_ref() != Some(l);
+                let remote_changed = base.as_ref() != Some(r);
+                match (local_changed, remote_changed, resolution) {
+                    (true, false, _) => Action::Push,
+                    (false, true, _) => Action::Pull,
+                    (_, _, ConflictResolution::Local) => Action::Push,
+                    (_, _, ConflictResolution::Remote) => Action::Pull,
+                    (_, _, ConflictResolution::None) => Action::Conflict,
+                }
+            }
+        };
+
+        match action {
+            Action::None => {
+                if let Some(hash) = local_hash.or(remote_hash) {
+                    state.entries.insert(key.clone(), hash);
+                }
+                summary.unchanged += 1;
+            }
+            Action::Push => {
+                let content = local_content.cloned().unwrap_or_default();
+                remote.put(&key, content.clone()).await?;
+                let hash = content_hash(&content);
+                manifest.entries.insert(
+                    key.clone(),
+                    ManifestEntry {
+                        hash: hash.clone(),
+                        updated_at: now.clone(),
+                        device: device.clone(),
+                    },
+                );
+                state.entries.insert(key.clone(), hash);
+                summary.pushed.push(key);
+            }
+            Action::Pull => {
+                let content = match remote.get(&key).await? {
+                    Some(content) => content,
+                    None => {
+                        logger::warn("system", &format!("远端缺少 {}，跳过", key));
+                        summary.skipped.push(key);
+                        continue;
+                    }
+                };
+                if !apply_remote(app_handle, &app_dir, &storage_root, &key, &content).await? {
+                    summary.skipped.push(key);
+                    continue;
+                }
+                pulled_any = true;
+                state.entries.insert(key.clone(), content_hash(&content));
+                summary.pulled.push(key);
+            }
+            Action::Conflict => summary.conflicts.push(key),
+        }
+    }
+
+    if !summary.pushed.is_empty() {
+        remote.put(MANIFEST_KEY, serde_json::to_vec_pretty(&manifest)?).await?;
+    }
+    fs::write(&state_path, serde_json::to_string_pretty(&state)?)?;
+    if pulled_any {
+        crate::database::reload();
+        crate::knowledge_points::init_alias_store(&app_dir);
+    }
+
+    logger::info(
+        "system",
+        &format!(
+            "同步完成：推送 {}，拉取 {}，冲突 {}",
+            summary.pushed.len(),
+            summary.pulled.len(),
+            summary.conflicts.len()
+        ),
+    );
+    Ok(summary)
+}
+
+/// 测试同步设置：读取远端的 manifest.json，返回远端已有的条目数
+pub async fn test_connection(config: &SyncConfig) -> Result<usize> {
+    let remote = Remote::new(config)?;
+    match remote.get(MANIFEST_KEY).await? {
+        Some(bytes) => {
+            let manifest: SyncManifest =
+                serde_json::from_slice(&bytes).map_err(|e| anyhow!("远端的 manifest.json 无法解析: {}", e))?;
+            Ok(manifest.entries.len())
+        }
+        None => Ok(0),
+    }
+}
+
+enum Action {
+    None,
+    Push,
+    Pull,
+    Conflict,
+}
+
+/// 本地需要同步的条目：远端键 -> 内容
+/// 文件元数据中的源文件路径只保留文件名，配置中去掉存储路径和同步设置，避免不同电脑上的路径被当作修改
+fn local_entries(config: &AppConfig, app_dir: &Path, storage_root: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut entries = BTreeMap::new();
+
+    let dirs = fs::read_dir(storage_root)
+        .map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for dir in dirs {
+        let info: FileInfo = match fs::read_to_string(dir.join("meta.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+        {
+            Some(info) => info,
+            None => continue,
+        };
+        let mut shared = info.clone();
+        shared.path = source_file_name(&info.path);
+        if let Ok(content) = serde_json::to_vec_pretty(&shared) {
+            entries.insert(format!("files/{}/meta.json", info.id), content);
+        }
+        if let Ok(content) = fs::read(dir.join("questions").join("all_questions.json")) {
+            entries.insert(format!("files/{}/questions/all_questions.json", info.id), content);
+        }
+    }
+
+    if let Ok(banks) = fs::read_dir(app_dir.join("banks")) {
+        for path in banks.filter_map(|e| e.ok().map(|e| e.path())) {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if name.ends_with(".json") && !name.starts_with('.') {
+                if let Ok(content) = fs::read(&path) {
+                    entries.insert(format!("app/banks/{}", name), content);
+                }
+            }
+        }
+    }
+    if let Ok(content) = fs::read(app_dir.join("knowledge_point_aliases.json")) {
+        entries.insert("app/knowledge_point_aliases.json".to_string(), content);
+    }
+    if config.sync.include_config {
+        let mut shared = config.clone();
+        shared.storage_path = String::new();
+        shared.sync = SyncConfig::default();
+        if let Ok(content) = serde_json::to_vec_pretty(&shared) {
+            entries.insert("app/config.json".to_string(), content);
+        }
+    }
+    entries
+}
+
+/// 把拉取的内容写入本地，返回 false 表示暂不写入（正在分析的文件）
+async fn apply_remote(
+    app_handle: &AppHandle,
+    app_dir: &Path,
+    storage_root: &Path,
+    key: &str,
+    content: &[u8],
+) -> Result<bool> {
+    let parts: Vec<&str> = key.split('/').collect();
+    match parts.as_slice() {
+        ["files", id, rest @ ..] if is_safe_id(id) => {
+            if crate::question_analyzer::is_analyzing(id) {
+                return Ok(false);
+            }
+            let file_dir = storage_root.join(id);
+            match rest {
+                ["meta.json"] => {
+                    let mut info: FileInfo = serde_json::from_slice(content)?;
+                    info.path = file_dir.join(source_file_name(&info.path)).to_string_lossy().to_string();
+                    fs::create_dir_all(file_dir.join("markdown"))?;
+                    fs::create_dir_all(file_dir.join("questions"))?;
+                    fs::write(file_dir.join("meta.json"), serde_json::to_string_pretty(&info)?)?;
+                }
+                ["questions", "all_questions.json"] => {
+                    let questions = serde_json::from_slice::<Vec<crate::commands::Question>>(content)
+                        .map_err(|e| anyhow!("{} 不是有效的题目文件: {}", key, e))?;
+                    crate::question_analyzer::replace_synced_questions(app_handle, id, questions)?;
+                }
+                _ => return Ok(false),
+            }
+        }
+        ["app", "banks", name] if is_safe_id(name.trim_end_matches(".json")) => {
+            write_file(&app_dir.join("banks").join(name), content)?;
+        }
+        ["app", "knowledge_point_aliases.json"] => {
+            write_file(&app_dir.join("knowledge_point_aliases.json"), content)?;
+        }
+        ["app", "config.json"] => {
+            let current = crate::config::get_config(app_handle).await?;
+            let mut config = crate::config::parse_config(serde_json::from_slice(content)?)?;
+            config.storage_path = current.storage_path.clone();
+            config.sync = current.sync.clone();
+            crate::config::save_config(app_handle, config).await?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// 远端存储
+struct Remote {
+    config: SyncConfig,
+    client: Client,
+    created_dirs: HashSet<String>, // 本次同步中已创建的 WebDAV 目录
+}
+
+impl Remote {
+    fn new(config: &SyncConfig) -> Result<Self> {
+        match config.provider.as_str() {
+            "webdav" => {
+                if config.endpoint.trim().is_empty() {
+                    return Err(anyhow!("请填写 WebDAV 地址"));
+                }
+            }
+            "s3" => {
+                if config.endpoint.trim().is_empty() || config.bucket.trim().is_empty() {
+                    return Err(anyhow!("请填写 S3 服务地址和存储桶"));
+                }
+                if config.access_key.is_empty() || config.secret_key.is_empty() {
+                    return Err(anyhow!("请填写 S3 访问密钥"));
+                }
+            }
+            "" => return Err(anyhow!("未启用同步，请先在设置中选择 WebDAV 或 S3")),
+            other => return Err(anyhow!("不支持的同步方式: {}", other)),
+        }
+        Ok(Self {
+            config: config.clone(),
+            client: crate::http_client::client(),
+            created_dirs: HashSet::new(),
+        })
+    }
+
+    /// 远端的标识，更换地址、存储桶或目录后重新开始比较
+    fn identity(&self) -> String {
+        format!(
+            "{}:{}/{}/{}",
+            self.config.provider,
+            self.config.endpoint.trim_end_matches('/'),
+            self.config.bucket,
+            self.remote_dir()
+        )
+    }
+
+    fn remote_dir(&self) -> String {
+        match self.config.remote_dir.trim().trim_matches('/') {
+            "" => DEFAULT_REMOTE_DIR.to_string(),
+            dir => dir.to_string(),
+        }
+    }
+
+    /// 读取条目，不存在时返回 None
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.request(Method::GET, key, Vec::new())?.send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            status => Err(anyhow!("读取远端 {} 失败: HTTP {}", key, status)),
+        }
+    }
+
+    async fn put(&mut self, key: &str, content: Vec<u8>) -> Result<()> {
+        if self.config.provider == "webdav" {
+            self.ensure_webdav_dirs(key).await?;
+        }
+        let response = self.request(Method::PUT, key, content)?.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("写入远端 {} 失败: HTTP {}", key, response.status()));
+        }
+        Ok(())
+    }
+
+    /// WebDAV 写入前逐级创建目录（MKCOL），目录已存在时服务器返回 405
+    async fn ensure_webdav_dirs(&mut self, key: &str) -> Result<()> {
+        let mut path = String::new();
+        let segments: Vec<String> = self.object_path(key).split('/').map(str::to_string).collect();
+        for segment in &segments[..segments.len().saturating_sub(1)] {
+            path = if path.is_empty() { segment.clone() } else { format!("{}/{}", path, segment) };
+            if self.created_dirs.contains(&path) {
+                continue;
+            }
+            let url = format!("{}/{}/", self.config.endpoint.trim_end_matches('/'), encode_path(&path));
+            let method = Method::from_bytes(b"MKCOL").map_err(|e| anyhow!(e.to_string()))?;
+            let response = self
+                .client
+                .request(method, &url)
+                .basic_auth(&self.config.username, Some(&self.config.password))
+                .send()
+                .await?;
+            let status = response.status();
+            if !(status.is_success() || status == StatusCode::METHOD_NOT_ALLOWED) {
+                return Err(anyhow!("创建远端目录 {} 失败: HTTP {}", path, status));
+            }
+            self.created_dirs.insert(path.clone());
+        }
+        Ok(())
+    }
+
+    fn object_path(&self, key: &str) -> String {
+        format!("{}/{}", self.remote_dir(), key)
+    }
+
+    fn request(&self, method: Method, key: &str, body: Vec<u8>) -> Result<reqwest::RequestBuilder> {
+        let path = encode_path(&self.object_path(key));
+        let endpoint = self.config.endpoint.trim_end_matches('/');
+        if self.config.provider == "webdav" {
+            let url = format!("{}/{}", endpoint, path);
+            return Ok(self
+                .client
+                .request(method, &url)
+                .basic_auth(&self.config.username, Some(&self.config.password))
+                .body(body));
+        }
+
+        // S3 按路径方式访问（https://endpoint/bucket/key），请求使用 AWS Signature Version 4 签名
+        let url = reqwest::Url::parse(&format!("{}/{}/{}", endpoint, encode_path(&self.config.bucket), path))
+            .map_err(|e| anyhow!("S3 服务地址格式错误: {}", e))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => return Err(anyhow!("S3 服务地址格式错误")),
+        };
+        let region = if self.config.region.trim().is_empty() { "us-east-1" } else { self.config.region.trim() };
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method.as_str(),
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac_sha256(format!("AWS4{}", self.config.secret_key).as_bytes(), date.as_bytes());
+        for part in [region, "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.config.access_key, scope, signature
+        );
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization)
+            .body(body))
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC 可以使用任意长度的密钥");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// 按路径段进行 URI 编码（保留 "/" 和 RFC 3986 的非保留字符）
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            segment
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+                    _ => format!("%{:02X}", b),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn content_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// 源文件路径中的文件名（兼容 Windows 路径）
+fn source_file_name(path: &str) -> String {
+    Path::new(&path.replace('\\', "/"))
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// 先写入临时文件再替换
+fn write_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("sync.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn device_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| std::env::consts::OS.to_string())
+}
+
+fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+    app_handle
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| anyhow!("无法获取应用数据目录"))
+}
@@ -33,6 +33,11 @@ fn rand_bytes() -> [u8; 8] {
     result
 }
 
+/// 判断 ID 能否安全地用作文件名（文件、题库、试卷等的 ID 只包含字母、数字、下划线和连字符）
+pub fn is_safe_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
 /// 格式化文件大小
 pub fn format_file_size(size: u64) -> String {
     const KB: u64 = 1024;
@@ -73,3 +78,53 @@ pub fn is_valid_extension(filename: &str) -> bool {
         false
     }
 }
+
+/// 用于比较的字符：去掉空白和标点，英文转小写，同时给出每个字符在原文中的字节偏移
+pub fn normalized_chars(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    text.char_indices()
+        .filter(|(_, c)| c.is_alphanumeric())
+        .flat_map(|(i, c)| c.to_lowercase().map(move |lc| (i, lc)))
+}
+
+/// 用于比较的文本：去掉空白和标点，英文转小写
+pub fn normalize_text(text: &str) -> String {
+    normalized_chars(text).map(|(_, c)| c).collect()
+}
+
+/// 并查集，按相似关系把元素分组时使用；根节点总是组内最小的下标
+pub struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    pub fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut node = i;
+        while self.parent[node] != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    /// 合并两个元素所在的组，返回（合并后的根，被并入的根）；已在同一组时返回 None
+    pub fn union(&mut self, a: usize, b: usize) -> Option<(usize, usize)> {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return None;
+        }
+        let (root, child) = (ra.min(rb), ra.max(rb));
+        self.parent[child] = root;
+        Some((root, child))
+    }
+}
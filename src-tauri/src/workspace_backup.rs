@@ -0,0 +1,432 @@
+// 工作区备份模块 - 把配置、数据库、各文件的元数据和题库打包为一个 .zip 备份文件，重装或换电脑后可以恢复
+// 备份文件结构：backup.json（格式和版本）、app/（应用数据目录中的配置和数据）、files/<file_id>/（各文件目录）
+// 可重新生成的缓存（AI 响应缓存、向量缓存、RAG 索引、MinerU 输出）只在选择包含缓存时备份；Python 虚拟环境不备份
+
+use crate::commands::{AppConfig, FileInfo};
+use crate::utils::is_safe_id;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+pub const FORMAT_NAME: &str = "booq-workspace-backup";
+
+/// 当前的备份格式版本
+pub const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "backup.json";
+const APP_PREFIX: &str = "app/";
+const FILES_PREFIX: &str = "files/";
+const DATABASE_NAME: &str = "booq.db";
+
+/// 应用数据目录中需要备份的文件和目录，第二项为 true 的是缓存
+const APP_ENTRIES: &[(&str, bool)] = &[
+    ("config.json", false),
+    ("knowledge_point_aliases.json", false),
+    ("practice_attempts.jsonl", false),
+    ("ai_usage.jsonl", false),
+    ("banks", false),
+    ("profiles", false),
+    ("papers", false),
+    ("practice", false),
+    ("ai_cache", true),
+    ("embedding_cache.db", true),
+    ("global_rag_index.db", true),
+];
+
+/// 文件目录中的缓存
+const FILE_CACHE_ENTRIES: &[&str] = &["mineru_output", "rag_index.db", "failed_responses"];
+
+/// 已经压缩过的文件类型，打包时不再压缩
+const STORED_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "gif", "webp", "zip", "docx", "pptx", "xlsx", "apkg"];
+
+/// 备份文件中的 backup.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: String,
+    pub version: u32,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub include_caches: bool,
+    #[serde(default)]
+    pub file_count: usize,
+}
+
+/// 备份结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub path: String,
+    pub file_count: usize,  // 备份的文件（书籍、讲义）数
+    pub entry_count: usize, // 备份文件中的条目数
+    pub size: u64,
+}
+
+/// 恢复结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub files_restored: usize,
+    pub files_skipped: Vec<String>, // 已存在（未选择覆盖）或正在分析的文件 ID
+    pub app_entries_restored: usize,
+    pub app_entries_skipped: usize, // 已存在且未选择覆盖的应用数据文件
+    pub config_restored: bool,
+    pub analysis_states_restored: usize,
+}
+
+/// 备份工作区到 dest_path，include_caches 为 true 时同时备份可重新生成的缓存
+pub async fn backup_workspace(app_handle: &AppHandle, dest_path: &Path, include_caches: bool) -> Result<BackupSummary> {
+    use crate::logger;
+
+    let app_dir = app_data_dir(app_handle)?;
+    let storage_root = crate::file_manager::get_storage_root(app_handle);
+    let files = crate::file_manager::get_file_list(app_handle).await?;
+
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = dest_path.with_extension("zip.tmp");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&tmp_path)?);
+    let result = (|| -> Result<usize> {
+        let mut entries = 0;
+        let manifest = BackupManifest {
+            format: FORMAT_NAME.to_string(),
+            version: BACKUP_VERSION,
+            created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            include_caches,
+            file_count: files.len(),
+        };
+        zip.start_file(MANIFEST_NAME, file_options(Path::new(MANIFEST_NAME)))?;
+        zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+        entries += 1;
+
+        // 数据库可能正在写入，先导出一致的快照
+        let snapshot = app_dir.join(format!("{}.backup", DATABASE_NAME));
+        let _ = fs::remove_file(&snapshot);
+        crate::database::snapshot(&snapshot)?;
+        let added = add_path(&mut zip, &snapshot, &format!("{}{}", APP_PREFIX, DATABASE_NAME), &|_| true);
+        let _ = fs::remove_file(&snapshot);
+        entries += added?;
+
+        for (name, is_cache) in APP_ENTRIES {
+            let path = app_dir.join(name);
+            if (*is_cache && !include_caches) || !path.exists() {
+                continue;
+            }
+            entries += add_path(&mut zip, &path, &format!("{}{}", APP_PREFIX, name), &|_| true)?;
+        }
+
+        for file in &files {
+            let file_dir = storage_root.join(&file.id);
+            let keep = |relative: &Path| {
+                include_caches
+                    || !relative
+                        .components()
+                        .next()
+                        .is_some_and(|c| FILE_CACHE_ENTRIES.iter().any(|name| c.as_os_str() == *name))
+            };
+            entries += add_dir(&mut zip, &file_dir, &file_dir, &format!("{}{}", FILES_PREFIX, file.id), &keep)?;
+        }
+        Ok(entries)
+    })();
+    let entry_count = match result.and_then(|entries| zip.finish().map(|_| entries).map_err(anyhow::Error::from)) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    };
+    fs::rename(&tmp_path, dest_path)?;
+
+    let size = fs::metadata(dest_path).map(|m| m.len()).unwrap_or(0);
+    logger::info(
+        "system",
+        &format!("工作区已备份到 {}（{} 个文件，{} 个条目）", dest_path.display(), files.len(), entry_count),
+    );
+    Ok(BackupSummary {
+        path: dest_path.to_string_lossy().to_string(),
+        file_count: files.len(),
+        entry_count,
+        size,
+    })
+}
+
+/// 从备份文件恢复工作区
+/// overwrite 为 false 时保留已有的文件和数据，只恢复不存在的部分；为 true 时用备份覆盖（正在分析的文件除外）
+/// 配置只在覆盖或当前仍是默认配置时恢复，并保留当前的存储路径；数据库在恢复文件后从各文件的元数据重新同步
+pub async fn restore_workspace(app_handle: &AppHandle, src_path: &Path, overwrite: bool) -> Result<RestoreSummary> {
+    use crate::logger;
+
+    let mut archive =
+        zip::ZipArchive::new(fs::File::open(src_path)?).map_err(|e| anyhow!("不是有效的备份文件: {}", e))?;
+    let manifest: BackupManifest = {
+        let mut entry = archive.by_name(MANIFEST_NAME).map_err(|_| anyhow!("不是 BooQ 工作区备份文件"))?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        serde_json::from_str(&content).map_err(|e| anyhow!("备份文件信息无法解析: {}", e))?
+    };
+    if manifest.format != FORMAT_NAME {
+        return Err(anyhow!("不是 BooQ 工作区备份文件"));
+    }
+    if manifest.version > BACKUP_VERSION {
+        return Err(anyhow!(
+            "备份文件版本为 {}，当前程序只支持到版本 {}，请升级 BooQ",
+            manifest.version,
+            BACKUP_VERSION
+        ));
+    }
+
+    let app_dir = app_data_dir(app_handle)?;
+    let storage_root = crate::file_manager::get_storage_root(app_handle);
+    let mut summary = RestoreSummary::default();
+
+    // 先决定恢复哪些文件目录，需要覆盖的目录在全部解压成功后整个替换
+    let file_ids: BTreeSet<String> = archive
+        .file_names()
+        .filter_map(|name| name.strip_prefix(FILES_PREFIX)?.split('/').next().map(str::to_string))
+        .filter(|id| is_safe_id(id))
+        .collect();
+    let mut restore_ids = BTreeSet::new();
+    for id in file_ids {
+        let file_dir = storage_root.join(&id);
+        if file_dir.exists() && (!overwrite || crate::question_analyzer::is_analyzing(&id)) {
+            summary.files_skipped.push(id);
+            continue;
+        }
+        restore_ids.insert(id);
+    }
+
+    // 文件目录和应用数据先解压到临时目录，解压失败时删除临时目录，已有的数据不受影响
+    let staging_name = format!(".restore-{}", uuid::Uuid::new_v4().simple());
+    let files_staging = storage_root.join(&staging_name);
+    let app_staging = app_dir.join(&staging_name);
+    let mut restored_config = None;
+    let mut app_files = Vec::new();
+    let restore_db = app_dir.join(format!("{}.restore", DATABASE_NAME));
+    let extracted = (|| -> Result<()> {
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry.name().replace('\\', "/");
+
+            if let Some(rest) = name.strip_prefix(FILES_PREFIX) {
+                let relative = match safe_relative_path(rest) {
+                    Some(relative) => relative,
+                    None => continue,
+                };
+                let id = relative.components().next().map(|c| c.as_os_str().to_string_lossy().to_string());
+                if id.is_some_and(|id| restore_ids.contains(&id)) {
+                    extract(&mut entry, &files_staging.join(relative))?;
+                }
+            } else if let Some(rest) = name.strip_prefix(APP_PREFIX) {
+                let relative = match safe_relative_path(rest) {
+                    Some(relative) => relative,
+                    None => continue,
+                };
+                if rest == DATABASE_NAME {
+                    extract(&mut entry, &app_staging.join(&relative))?;
+                } else if rest == "config.json" {
+                    let mut content = String::new();
+                    entry.read_to_string(&mut content)?;
+                    restored_config = Some(content);
+                } else {
+                    let first = relative.components().next().map(|c| c.as_os_str().to_string_lossy().to_string());
+                    if APP_ENTRIES.iter().any(|(entry_name, _)| first.as_deref() == Some(*entry_name)) {
+                        extract(&mut entry, &app_staging.join(&relative))?;
+                        app_files.push(relative);
+                    }
+                }
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = extracted {
+        let _ = fs::remove_dir_all(&files_staging);
+        let _ = fs::remove_dir_all(&app_staging);
+        return Err(e);
+    }
+
+    // 全部解压成功后再替换：应用数据逐个移动到位，缓存数据库可能已被打开，只在不存在时恢复
+    for relative in &app_files {
+        let dest = app_dir.join(relative);
+        let is_database = relative.extension().is_some_and(|e| e == "db");
+        if dest.exists() && (!overwrite || is_database) {
+            summary.app_entries_skipped += 1;
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(app_staging.join(relative), &dest)?;
+        summary.app_entries_restored += 1;
+    }
+    if app_staging.join(DATABASE_NAME).exists() {
+        fs::rename(app_staging.join(DATABASE_NAME), &restore_db)?;
+    }
+    let _ = fs::remove_dir_all(&app_staging);
+
+    // 文件目录：元数据中的源文件路径指向备份时的存储目录，改为恢复后的位置，再替换原目录
+    for id in &restore_ids {
+        let staged_dir = files_staging.join(id);
+        let meta_path = staged_dir.join("meta.json");
+        let mut info: FileInfo = match fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+        {
+            Some(info) => info,
+            None => {
+                logger::warn("system", &format!("恢复的文件 {} 缺少元数据，已跳过", id));
+                summary.files_skipped.push(id.clone());
+                continue;
+            }
+        };
+        let file_dir = storage_root.join(id);
+        if let Some(source_name) = Path::new(&info.path.replace('\\', "/")).file_name() {
+            info.path = file_dir.join(source_name).to_string_lossy().to_string();
+        }
+        fs::write(&meta_path, serde_json::to_string_pretty(&info)?)?;
+
+        // 原目录先移到临时目录，新目录移动到位后再删除
+        let old_dir = files_staging.join(format!("{}.old", id));
+        if file_dir.exists() {
+            fs::rename(&file_dir, &old_dir)?;
+        }
+        if let Err(e) = fs::rename(&staged_dir, &file_dir) {
+            if old_dir.exists() {
+                let _ = fs::rename(&old_dir, &file_dir);
+            }
+            let _ = fs::remove_dir_all(&files_staging);
+            return Err(e.into());
+        }
+        summary.files_restored += 1;
+    }
+    let _ = fs::remove_dir_all(&files_staging);
+
+    if let Some(content) = restored_config {
+        let current = crate::config::get_config(app_handle).await?;
+        let is_default = serde_json::to_value(&current).ok() == serde_json::to_value(AppConfig::default()).ok();
+        if overwrite || is_default {
+            let mut config = serde_json::from_str(&content)
+                .map_err(anyhow::Error::from)
+                .and_then(crate::config::parse_config)
+                .map_err(|e| anyhow!("备份中的配置无法解析: {}", e))?;
+            config.storage_path = current.storage_path.clone();
+            crate::config::save_config(app_handle, config).await?;
+            summary.config_restored = true;
+        }
+    }
+
+    // 数据库从恢复的元数据和题目重新同步，分析状态从备份的数据库中导入
+    crate::database::reload();
+    if restore_db.exists() {
+        match crate::database::import_analysis_states(&restore_db, overwrite) {
+            Ok(count) => summary.analysis_states_restored = count,
+            Err(e) => logger::warn("system", &format!("恢复分析状态失败: {}", e)),
+        }
+        let _ = fs::remove_file(&restore_db);
+    }
+
+    // 重新加载已恢复的知识点别名、作答记录和用量记录
+    crate::knowledge_points::init_alias_store(&app_dir);
+    crate::study_records::init_store(&app_dir);
+    crate::ai_usage::init_usage_store(&app_dir);
+
+    logger::info(
+        "system",
+        &format!(
+            "已从 {} 恢复工作区：{} 个文件，跳过 {} 个已存在的文件",
+            src_path.display(),
+            summary.files_restored,
+            summary.files_skipped.len()
+        ),
+    );
+    Ok(summary)
+}
+
+fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+    app_handle
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| anyhow!("无法获取应用数据目录"))
+}
+
+fn file_options(path: &Path) -> zip::write::FileOptions {
+    let stored = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| STORED_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+    let method = if stored {
+        zip::CompressionMethod::Stored
+    } else {
+        zip::CompressionMethod::Deflated
+    };
+    zip::write::FileOptions::default()
+        .compression_method(method)
+        .large_file(true)
+}
+
+/// 把文件或目录加入备份，返回加入的条目数
+fn add_path(
+    zip: &mut zip::ZipWriter<fs::File>,
+    path: &Path,
+    name: &str,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<usize> {
+    if path.is_dir() {
+        return add_dir(zip, path, path, name, keep);
+    }
+    zip.start_file(name, file_options(path))?;
+    std::io::copy(&mut fs::File::open(path)?, zip)?;
+    Ok(1)
+}
+
+/// 递归加入目录，keep 按相对于 base 的路径判断是否备份，跳过写到一半的临时文件
+fn add_dir(
+    zip: &mut zip::ZipWriter<fs::File>,
+    base: &Path,
+    dir: &Path,
+    prefix: &str,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<usize> {
+    let mut count = 0;
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    entries.sort();
+    for path in entries {
+        let relative = path.strip_prefix(base).unwrap_or(&path);
+        if !keep(relative) || path.extension().is_some_and(|e| e == "tmp") {
+            continue;
+        }
+        let name = format!("{}/{}", prefix, path.file_name().unwrap_or_default().to_string_lossy());
+        if path.is_dir() {
+            count += add_dir(zip, base, &path, &name, keep)?;
+        } else {
+            zip.start_file(name, file_options(&path))?;
+            std::io::copy(&mut fs::File::open(&path)?, zip)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// 备份中的相对路径，只允许普通的路径段（不能包含 ..、根目录或盘符），否则返回 None
+fn safe_relative_path(path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path);
+    let safe = relative.components().next().is_some()
+        && relative.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    safe.then(|| relative.to_path_buf())
+}
+
+fn extract(entry: &mut zip::read::ZipFile, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    std::io::copy(entry, &mut fs::File::create(dest)?)?;
+    Ok(())
+}
@@ -179,6 +179,12 @@ function bindPreviewEvents() {
     document.getElementById('btn-next-question')?.addEventListener('click', () => {
         questionManager.nextQuestion();
     });
+
+    // 复核顺序
+    document.getElementById('btn-review-order')?.addEventListener('click', (e) => {
+        const enabled = questionManager.toggleReviewOrder();
+        (e.currentTarget as HTMLElement).classList.toggle('active', enabled);
+    });
 }
 
 // 绑定设置事件
@@ -1,6 +1,9 @@
 // 题目管理模块
 
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+
+type UnlistenFn = () => void;
 
 // 引入 marked 和 katex
 declare const marked: any;
@@ -18,6 +21,67 @@ export interface Question {
     analysis: string;
     page_number: number;
     has_original_answer: boolean;
+    model?: string;
+    references?: Citation[];
+    images?: string[];
+    confidence?: number;
+    options?: ChoiceOption[];
+    correct_options?: string[];
+    difficulty?: '' | 'easy' | 'medium' | 'hard';
+    review_status?: ReviewStatus;
+    translations?: Record<string, QuestionTranslation>;
+}
+
+export type ReviewStatus = 'pending' | 'approved' | 'rejected';
+
+export interface ChoiceOption {
+    label: string;
+    text: string;
+}
+
+export interface QuestionTranslation {
+    question_text: string;
+    options: ChoiceOption[];
+    answer: string;
+    analysis: string;
+    model: string;
+    translated_at: string;
+}
+
+export interface TranslationSummary {
+    target_lang: string;
+    translated: number;
+    skipped: number;
+    failed: string[];
+}
+
+export interface Citation {
+    marker: string;
+    file_id: string;
+    page_number: number;
+    chapter: string;
+}
+
+export interface QuestionFilter {
+    question_type?: string;
+    chapter?: string;
+    knowledge_point?: string;
+    max_confidence?: number;
+    review_status?: ReviewStatus;
+}
+
+export interface QuestionPage {
+    items: Question[];
+    total: number;
+    page: number;
+    page_size: number;
+}
+
+export interface DocumentHit {
+    file_id: string;
+    file_name: string;
+    page_number: number;
+    snippet: string;
 }
 
 export interface AnalysisProgress {
@@ -28,14 +92,409 @@ export interface AnalysisProgress {
     current_step: string;
     questions_found: number;
     message: string;
+    parse_failures?: number;
+    pages_completed?: number;
+}
+
+export interface AnalysisCheckpoint {
+    run_id: string;
+    total_pages: number;
+    completed_pages: number[];
+    updated_at: string;
+    page_from?: number;
+    page_to?: number;
+    stopped_at_page?: number;
+    pages?: number[];
+}
+
+export interface KnowledgePointStat {
+    name: string;
+    count: number;
+    files: string[];
+}
+
+export interface KnowledgePointCluster {
+    canonical: string;
+    members: string[];
+    reason: string;
+    similarity: number;
+}
+
+export interface KnowledgePointAlias {
+    alias: string;
+    canonical: string;
+}
+
+export interface Attempt {
+    question_id: string;
+    file_id: string;
+    correct: boolean;
+    user_answer: string;
+    answered_at: string;
+}
+
+export interface WrongQuestion {
+    question: Question;
+    attempts: number;
+    wrong_count: number;
+    last_user_answer: string;
+    last_answered_at: string;
+}
+
+export interface SessionAnswer {
+    question_id: string;
+    user_answer: string;
+    correct: boolean;
+    score: number;
+    verdict: 'correct' | 'partially_correct' | 'incorrect';
+    feedback: string;
+    answered_at: string;
+}
+
+export interface ScoreGroup {
+    name: string;
+    total: number;
+    correct: number;
+}
+
+export interface SessionReport {
+    total: number;
+    answered: number;
+    correct: number;
+    score: number;
+    duration_secs: number;
+    by_type: ScoreGroup[];
+    by_knowledge_point: ScoreGroup[];
+    wrong_question_ids: string[];
+}
+
+export interface PracticeSession {
+    session_id: string;
+    file_id?: string;
+    status: 'active' | 'finished';
+    started_at: string;
+    finished_at?: string;
+    filters: QuestionFilter;
+    questions: Question[];
+    answers: SessionAnswer[];
+    report?: SessionReport;
+}
+
+export interface SessionSummary {
+    session_id: string;
+    file_id?: string;
+    status: 'active' | 'finished';
+    started_at: string;
+    finished_at?: string;
+    total: number;
+    answered: number;
+    score?: number;
+}
+
+export interface ChapterOutlineNode {
+    name: string;
+    level: 'chapter' | 'section';
+    start_page?: number;
+    end_page?: number;
+    question_count: number;
+    example_count: number;
+    exercise_count: number;
+    children: ChapterOutlineNode[];
+}
+
+export interface LatexIssue {
+    field: string;
+    kind: 'unclosed_math' | 'unbalanced_braces' | 'environment' | 'unknown_command' | 'left_right';
+    message: string;
+    snippet: string;
+}
+
+export interface LatexReport {
+    question_id: string;
+    file_id: string;
+    page_number: number;
+    issues: LatexIssue[];
+    fixed: boolean;
+}
+
+export interface QuestionEdit {
+    edit_id: string;
+    question_id: string;
+    file_id: string;
+    edited_at: string;
+    editor: string;
+    action: 'created' | 'updated' | 'deleted';
+    note: string;
+    changed_fields: string[];
+    before?: Question;
+    after?: Question;
+}
+
+export interface QuestionSource {
+    file_id: string;
+    file_name: string;
+    question_id: string;
+    page_number: number;
+}
+
+export interface BankQuestion {
+    question: Question;
+    sources: QuestionSource[];
+}
+
+export interface QuestionBank {
+    bank_id: string;
+    name: string;
+    file_ids: string[];
+    dedupe: boolean;
+    created_at: string;
+    questions: BankQuestion[];
+    merged_duplicates: number;
+}
+
+export interface BankSummary {
+    bank_id: string;
+    name: string;
+    file_ids: string[];
+    dedupe: boolean;
+    created_at: string;
+    question_count: number;
+    merged_duplicates: number;
+}
+
+export interface ImportSummary {
+    name: string;
+    source_schema_version: number;
+    imported: number;
+    skipped_duplicates: number;
+    renamed_ids: number;
+}
+
+export interface BlueprintItem extends QuestionFilter {
+    difficulty?: 'easy' | 'medium' | 'hard';
+    count: number;
+    score?: number;
+}
+
+export interface PaperSpec {
+    title?: string;
+    file_id?: string;
+    items: BlueprintItem[];
+    total_score?: number;
+    avoid_recent_papers?: number;
+}
+
+export interface PaperQuestion {
+    number: number;
+    score: number;
+    question: Question;
+}
+
+export interface PaperSection {
+    title: string;
+    questions: PaperQuestion[];
+}
+
+export interface AnswerKeyItem {
+    number: number;
+    question_id: string;
+    answer: string;
+    correct_options: string[];
+    analysis: string;
+}
+
+export interface Paper {
+    paper_id: string;
+    title: string;
+    created_at: string;
+    spec: PaperSpec;
+    sections: PaperSection[];
+    total_score: number;
+    answer_key: AnswerKeyItem[];
+    shortfalls: { item_index: number; requested: number; selected: number }[];
 }
 
+export interface PaperSummary {
+    paper_id: string;
+    title: string;
+    created_at: string;
+    question_count: number;
+    total_score: number;
+}
+
+export interface KnowledgePointMastery {
+    name: string;
+    questions: number;
+    attempted_questions: number;
+    attempts: number;
+    correct: number;
+    mastery: number;
+}
+
+export interface DuplicateMember {
+    file_id: string;
+    question_id: string;
+    page_number: number;
+    question_type: string;
+    question_text: string;
+}
+
+export interface DuplicateGroup {
+    keep: DuplicateMember;
+    duplicates: DuplicateMember[];
+    similarity: number;
+}
+
+export interface FailedPage {
+    page: number;
+    stage: string;
+    kind: string;
+    error: string;
+    failed_at: string;
+}
+
+export interface AnalysisQueueJob {
+    job_id: string;
+    file_id: string;
+    file_name: string;
+    status: 'queued' | 'running' | 'completed' | 'stopped' | 'failed' | 'cancelled';
+    enqueued_at: string;
+    started_at?: string;
+    finished_at?: string;
+    error: string;
+    resume: boolean;
+}
+
+export interface AnalysisQueueStatus {
+    paused: boolean;
+    jobs: AnalysisQueueJob[];
+}
+
+export interface AnalysisRun {
+    run_id: string;
+    started_at: string;
+    finished_at: string;
+    status: string;
+    models: string[];
+    page_from: number;
+    page_to: number;
+    pages_completed: number;
+    question_count: number;
+    total_questions: number;
+    prompt_tokens: number;
+    completion_tokens: number;
+    cost: number;
+    has_snapshot: boolean;
+}
+
+export interface KnowledgeDocument {
+    id: string;
+    content: string;
+    metadata: {
+        file_id: string;
+        page_number: number;
+        chunk_index: number;
+        doc_type: string;
+        chapter: string;
+        section: string;
+    };
+}
+
+export interface KnowledgeSearchResult {
+    document: KnowledgeDocument;
+    score: number;
+}
+
+export interface KnowledgeSearchFilter {
+    doc_types?: string[];
+    chapter?: string;
+    page_from?: number;
+    page_to?: number;
+}
+
+export interface RubricItem {
+    criterion: string;
+    points: number;
+    max_points: number;
+    comment: string;
+}
+
+export interface GradeResult {
+    question_id: string;
+    score: number;
+    verdict: string;
+    rubric: RubricItem[];
+    feedback: string;
+    model: string;
+}
+
+export interface DocumentAnswer {
+    answer: string;
+    reasoning?: string;
+    sources: Citation[];
+}
+
+export interface BatchJob {
+    file_id: string;
+    batch_id: string;
+    model_id: string;
+    model_name: string;
+    status: string;
+    total_requests: number;
+    completed_requests: number;
+    failed_requests: number;
+    questions_found: number;
+    created_at: string;
+    finished_at?: string;
+    error?: string;
+    references?: Record<string, Citation[]>;
+}
+
+export interface GraphNode {
+    id: string;
+    label: string;
+    node_type: string;
+    weight: number;
+    pages: number[];
+}
+
+export interface GraphEdge {
+    source: string;
+    target: string;
+    edge_type: string;
+    weight: number;
+}
+
+export interface KnowledgeGraph {
+    file_id: string;
+    generated_at: string;
+    nodes: GraphNode[];
+    edges: GraphEdge[];
+}
+
+export interface CompactResult {
+    documents_before: number;
+    duplicates_removed: number;
+    size_before: number;
+    size_after: number;
+}
+
+// 置信度低于该值的题目标记为待复核
+const LOW_CONFIDENCE = 0.6;
+
+const REVIEW_STATUS_LABELS: Record<ReviewStatus, string> = {
+    pending: '待审核',
+    approved: '已通过',
+    rejected: '已驳回',
+};
+
 export class QuestionManager {
     private questions: Question[] = [];
     private currentIndex: number = 0;
     private currentFileId: string | null = null;
-    private analysisTimer: number | null = null;
+    private progressUnlisten: UnlistenFn | null = null;
     private showingAnswer: boolean = false;
+    private reviewOrder: boolean = false;
 
     async init() {
         // 初始化
@@ -46,6 +505,7 @@ export class QuestionManager {
         
         try {
             this.questions = await invoke<Question[]>('get_questions', { fileId });
+            this.applyOrder();
             this.currentIndex = 0;
             this.renderQuestions();
         } catch (error) {
@@ -55,19 +515,36 @@ export class QuestionManager {
         }
     }
 
+    // 切换复核顺序：开启时置信度低的题目排在前面，关闭时恢复页码顺序
+    toggleReviewOrder(): boolean {
+        this.reviewOrder = !this.reviewOrder;
+        this.applyOrder();
+        this.currentIndex = 0;
+        this.renderQuestions();
+        return this.reviewOrder;
+    }
+
+    private applyOrder() {
+        if (this.reviewOrder) {
+            // 没有置信度的题目（手动添加或旧数据）排在最后
+            this.questions.sort((a, b) => (a.confidence ?? 1) - (b.confidence ?? 1));
+        } else {
+            this.questions.sort((a, b) => a.page_number - b.page_number);
+        }
+    }
+
     async startAnalysis(fileId: string) {
         this.currentFileId = fileId;
 
         try {
-            await invoke('start_analysis', { fileId });
-            
-            // 显示进度条
+            // 显示进度条，先订阅进度事件再开始分析（分析命令在完成后才返回）
             this.showProgress();
+            await this.listenProgress();
             
-            // 开始轮询进度
-            this.startProgressPolling();
+            await invoke('start_analysis', { fileId });
         } catch (error) {
             console.error('开始分析失败:', error);
+            this.stopListeningProgress();
         }
     }
 
@@ -76,40 +553,33 @@ export class QuestionManager {
 
         try {
             await invoke('stop_analysis', { fileId: this.currentFileId });
-            this.stopProgressPolling();
         } catch (error) {
             console.error('停止分析失败:', error);
         }
     }
 
-    private startProgressPolling() {
-        this.stopProgressPolling();
-        
-        this.analysisTimer = window.setInterval(async () => {
-            if (!this.currentFileId) return;
-
-            try {
-                const progress = await invoke<AnalysisProgress>('get_analysis_progress', {
-                    fileId: this.currentFileId
-                });
-
-                this.updateProgress(progress);
-
-                // 如果完成或出错，停止轮询
-                if (progress.status === 'completed' || progress.status === 'error' || progress.status === 'stopped') {
-                    this.stopProgressPolling();
-                    await this.loadQuestions(this.currentFileId);
-                }
-            } catch (error) {
-                console.error('获取进度失败:', error);
+    // 后端在每页、每个步骤变化时推送 analysis-progress 事件
+    private async listenProgress() {
+        this.stopListeningProgress();
+
+        this.progressUnlisten = await listen<AnalysisProgress>('analysis-progress', async (event) => {
+            const progress = event.payload;
+            if (!this.currentFileId || progress.file_id !== this.currentFileId) return;
+
+            this.updateProgress(progress);
+
+            // 如果完成、出错或停止，取消订阅并刷新题目列表
+            if (progress.status === 'completed' || progress.status === 'error' || progress.status === 'stopped') {
+                this.stopListeningProgress();
+                await this.loadQuestions(this.currentFileId);
             }
-        }, 1000);
+        });
     }
 
-    private stopProgressPolling() {
-        if (this.analysisTimer !== null) {
-            window.clearInterval(this.analysisTimer);
-            this.analysisTimer = null;
+    private stopListeningProgress() {
+        if (this.progressUnlisten) {
+            this.progressUnlisten();
+            this.progressUnlisten = null;
         }
     }
 
@@ -187,12 +657,31 @@ export class QuestionManager {
                     ${question.chapter ? `<span class="question-tag">${question.chapter}</span>` : ''}
                     ${question.section ? `<span class="question-tag">${question.section}</span>` : ''}
                     <span class="question-tag">第 ${question.page_number} 页</span>
+                    ${question.confidence !== undefined && question.confidence !== null ? `
+                        <span class="question-tag ${question.confidence < LOW_CONFIDENCE ? 'low-confidence' : ''}">
+                            置信度 ${Math.round(question.confidence * 100)}%
+                        </span>
+                    ` : ''}
+                    <span class="question-tag review-${question.review_status || 'pending'}">
+                        ${REVIEW_STATUS_LABELS[question.review_status || 'pending']}
+                    </span>
                 </div>
                 
                 <div class="question-content" id="question-text">
                     ${marked.parse(question.question_text)}
                 </div>
                 
+                ${question.options && question.options.length > 0 ? `
+                    <ol class="question-options">
+                        ${question.options.map(option => `
+                            <li class="question-option" data-label="${option.label}">
+                                <span class="question-option-label">${option.label}.</span>
+                                ${marked.parseInline(option.text)}
+                            </li>
+                        `).join('')}
+                    </ol>
+                ` : ''}
+                
                 ${question.knowledge_points.length > 0 ? `
                     <div class="question-meta">
                         <strong>知识点：</strong>
@@ -205,6 +694,12 @@ export class QuestionManager {
                 <button class="btn btn-outline-success btn-show-answer" id="btn-show-answer">
                     <i class="bi bi-eye"></i> 显示解析
                 </button>
+                <button class="btn btn-outline-primary btn-show-answer" id="btn-approve-question">
+                    <i class="bi bi-check-lg"></i> 通过
+                </button>
+                <button class="btn btn-outline-danger btn-show-answer" id="btn-reject-question">
+                    <i class="bi bi-x-lg"></i> 驳回
+                </button>
                 
                 <div class="question-answer" id="question-answer" style="display: none;">
                     <div class="question-answer-header">
@@ -230,10 +725,33 @@ export class QuestionManager {
             this.toggleAnswer();
         });
 
+        // 绑定审核按钮
+        document.getElementById('btn-approve-question')?.addEventListener('click', () => {
+            this.setReviewStatus('approved');
+        });
+        document.getElementById('btn-reject-question')?.addEventListener('click', () => {
+            this.setReviewStatus('rejected');
+        });
+
         // 渲染数学公式
         this.renderMath(container);
     }
 
+    // 审核当前题目
+    private async setReviewStatus(status: 'approved' | 'rejected') {
+        const question = this.questions[this.currentIndex];
+        if (!question || !this.currentFileId) return;
+
+        try {
+            const command = status === 'approved' ? 'approve_questions' : 'reject_questions';
+            await invoke(command, { fileId: this.currentFileId, questionIds: [question.id] });
+            question.review_status = status;
+            this.renderCurrentQuestion();
+        } catch (error) {
+            console.error('审核题目失败:', error);
+        }
+    }
+
     private toggleAnswer() {
         const answerDiv = document.getElementById('question-answer');
         const analysisDiv = document.getElementById('question-analysis');
@@ -254,6 +772,12 @@ export class QuestionManager {
         }
 
         this.showingAnswer = !this.showingAnswer;
+
+        // 显示解析时标出正确选项
+        const correct = this.questions[this.currentIndex]?.correct_options || [];
+        document.querySelectorAll<HTMLElement>('.question-option').forEach(option => {
+            option.classList.toggle('correct', this.showingAnswer && correct.includes(option.dataset.label || ''));
+        });
     }
 
     private renderMath(container: HTMLElement) {
@@ -34,9 +34,89 @@ export interface ModelConfig {
     api_url: string;
     api_key: string;
     model_name: string;
+    input_price?: number;
+    output_price?: number;
+    requests_per_minute?: number;
+    headers?: Record<string, string>;
+}
+
+export interface ModelTestReport {
+    reply: string;
+    latency_ms: number;
+    available_models: string[];
+    model_listed: boolean | null;
+    max_context: number;
+    context_detected: boolean;
+    json_mode: boolean;
+    vision: boolean;
+}
+
+export interface BackupSummary {
+    path: string;
+    file_count: number;
+    entry_count: number;
+    size: number;
+}
+
+export interface RestoreSummary {
+    files_restored: number;
+    files_skipped: string[];
+    app_entries_restored: number;
+    app_entries_skipped: number;
+    config_restored: boolean;
+    analysis_states_restored: number;
+}
+
+export interface ConfigImportSummary {
+    models: number;
+    secrets_kept: number;
+    warnings: string[];
+}
+
+export interface ProfileSummary {
+    name: string;
+    active: boolean;
+    storage_path: string;
+    model_count: number;
+    updated_at: string;
+}
+
+export interface SyncConfig {
+    provider: string; // 'webdav' | 's3'，为空时不启用
+    endpoint: string;
+    username: string;
+    password: string;
+    bucket: string;
+    region: string;
+    access_key: string;
+    secret_key: string;
+    remote_dir: string;
+    include_config: boolean;
+}
+
+export interface SyncSummary {
+    pushed: string[];
+    pulled: string[];
+    conflicts: string[];
+    skipped: string[];
+    unchanged: number;
+}
+
+export interface ApiServerConfig {
+    enabled: boolean;
+    port: number;
+    token: string;
+    allow_write: boolean;
+}
+
+export interface ApiServerStatus {
+    running: boolean;
+    address: string;
+    allow_write: boolean;
 }
 
 export interface AppConfig {
+    config_version?: number;
     storage_path: string;
     theme: string;
     models: ModelConfig[];
@@ -48,6 +128,8 @@ export interface AppConfig {
     mineru_installed: boolean;
     paddle_ocr_url: string;
     paddle_ocr_token: string;
+    sync?: SyncConfig;
+    api_server?: ApiServerConfig;
 }
 
 // 供应商配置信息
@@ -627,18 +709,18 @@ export class SettingsManager {
         testButton.disabled = true;
 
         try {
-            const response = await invoke<string>('test_model', {
+            const report = await invoke<ModelTestReport>('test_model', {
                 apiUrl,
                 apiKey,
                 modelName
             });
             
             // 测试成功
-            testButton.innerHTML = '<i class="bi bi-check-circle"></i> 连接成功';
+            testButton.innerHTML = `<i class="bi bi-check-circle"></i> 连接成功 (${report.latency_ms} ms)`;
             testButton.classList.remove('btn-outline-primary', 'btn-outline-danger');
             testButton.classList.add('btn-outline-success');
             
-            console.log('模型响应:', response);
+            console.log('模型测试结果:', report);
             
             // 3秒后恢复按钮
             setTimeout(() => {